@@ -688,7 +688,7 @@ export const TLS_HS_MESSAGE_TYPE = {
     0: "HelloRequest",
     1: "ClientHello",
     2: "ServerHello",
-    3: "HELLO_VERIFY_REQUEST",
+    3: "HelloVerifyRequest",
     4: "NewSessionTicket",
     5: "END_OF_EARLY_DATA",
     6: "HELLO_RETRY_REQUEST",
@@ -1,4 +1,4 @@
-use pcap::common::{concept::{ConversationCriteria, Criteria, DNSRecord, DNSResponse, FrameIndex, FrameInfo, HttpCriteria, HttpMessageDetail, ListResult, ProgressStatus, TLSConversation, TLSItem, UDPConversation, VConnection, VConversation, VHttpConnection}, file::Metadata};
+use pcap::common::{concept::{ConversationCriteria, Criteria, DNSRecord, DNSResponse, FrameIndex, FrameInfo, HttpCriteria, HttpMessageDetail, ListResult, ProgressStatus, TLSConversation, TLSItem, UDPConversation, VConnection, VConversation, VHttpConnection}, dhcp::DhcpTransaction, export::HttpObject, file::Metadata, graph::ConnectionGraph, stream::StreamChunk};
 use serde::Serialize;
 use util::{PFile, core::FrameResult};
 use crate::GUIContext;
@@ -66,10 +66,11 @@ pub async fn tcp_list(state: tauri::State<'_, GUIContext>, start: usize, size: u
 }
 
 #[tauri::command]
-pub async fn tcp_conv_list(state: tauri::State<'_, GUIContext>, start: usize, size: usize, index: usize) -> Result<ListResult<VConnection>, String> {
+pub async fn tcp_conv_list(state: tauri::State<'_, GUIContext>, start: usize, size: usize, index: usize, order: Option<String>) -> Result<ListResult<VConnection>, String> {
     let context = state.inner();
     let cri = Criteria { start, size };
-    Ok(context.engine().connections(index, cri).await)
+    let order = order.and_then(|o| o.parse().ok()).unwrap_or_default();
+    Ok(context.engine().connections(index, cri, order).await)
 }
 
 #[tauri::command]
@@ -79,6 +80,50 @@ pub async fn udp_list(state: tauri::State<'_, GUIContext>, start: usize, size: u
     Ok(context.engine().udp_list(cri, ip, asc).await)
 }
 
+#[tauri::command]
+pub async fn follow_tcp(state: tauri::State<'_, GUIContext>, index: usize, connection: usize, start: usize, size: usize) -> Result<ListResult<StreamChunk>, String> {
+    let context = state.inner();
+    let cri = Criteria { start, size };
+    Ok(context.engine().follow_tcp(index, connection, cri).await)
+}
+
+#[tauri::command]
+pub async fn tcp_graph(state: tauri::State<'_, GUIContext>, index: usize, connection: usize) -> Result<ConnectionGraph, String> {
+    let context = state.inner();
+    Ok(context.engine().connection_graph(index, connection).await)
+}
+
+#[tauri::command]
+pub async fn set_decode_as(state: tauri::State<'_, GUIContext>, content: String) -> Result<usize, String> {
+    let context = state.inner();
+    context.engine().set_decode_as(content).await
+}
+
+#[tauri::command]
+pub async fn set_esp_sa(state: tauri::State<'_, GUIContext>, content: String) -> Result<usize, String> {
+    let context = state.inner();
+    context.engine().set_esp_sa(content).await
+}
+
+#[tauri::command]
+pub async fn load_geoip(state: tauri::State<'_, GUIContext>, path: String) -> Result<String, String> {
+    let context = state.inner();
+    context.engine().load_geoip(path).await
+}
+
+#[tauri::command]
+pub async fn set_verify_checksum(state: tauri::State<'_, GUIContext>, verify: bool) -> Result<(), String> {
+    let context = state.inner();
+    context.engine().set_verify_checksum(verify).await
+}
+
+#[tauri::command]
+pub async fn follow_udp(state: tauri::State<'_, GUIContext>, index: usize, start: usize, size: usize) -> Result<ListResult<StreamChunk>, String> {
+    let context = state.inner();
+    let cri = Criteria { start, size };
+    Ok(context.engine().follow_udp(index, cri).await)
+}
+
 #[tauri::command]
 pub async fn http_list(state: tauri::State<'_, GUIContext>, start: usize, size: usize, host: String, asc: bool) -> Result<ListResult<VHttpConnection>, String> {
     let context = state.inner();
@@ -101,6 +146,13 @@ pub async fn dns_record(state: tauri::State<'_, GUIContext>, index: usize, start
     Ok(context.engine().dns_record(index, cri).await)
 }
 
+#[tauri::command]
+pub async fn dhcp_failures(state: tauri::State<'_, GUIContext>, start: usize, size: usize) -> Result<ListResult<DhcpTransaction>, String> {
+    let cri = Criteria { start, size };
+    let context = state.inner();
+    Ok(context.engine().dhcp_failures(cri).await)
+}
+
 #[tauri::command]
 pub async fn tls_list(state: tauri::State<'_, GUIContext>, start: usize, size: usize) -> Result<ListResult<TLSConversation>, String> {
     let cri = Criteria { start, size };
@@ -122,3 +174,16 @@ pub async fn http_detail(state: tauri::State<'_, GUIContext>, index: usize) -> R
     let rs2: Option<Vec<HttpD>> = rs.map(|f| f.iter().map(|e| e.into()).collect());
     Ok(rs2)
 }
+
+#[tauri::command]
+pub async fn http_objects(state: tauri::State<'_, GUIContext>) -> Result<Vec<HttpObject>, String> {
+    let context = state.inner();
+    Ok(context.engine().http_objects().await)
+}
+
+// writes every object when no index is given
+#[tauri::command]
+pub async fn export_http_objects(state: tauri::State<'_, GUIContext>, dir: String, indexes: Option<Vec<usize>>) -> Result<Vec<String>, String> {
+    let context = state.inner();
+    context.engine().export_http_objects(dir.into(), indexes).await
+}
@@ -127,10 +127,20 @@ pub fn run() {
             tcp_list,
             tcp_conv_list,
             udp_list,
+            follow_tcp,
+            follow_udp,
+            tcp_graph,
+            set_decode_as,
+            set_verify_checksum,
+            set_esp_sa,
+            load_geoip,
             http_list,
             http_detail,
+            http_objects,
+            export_http_objects,
             dns_records,
             dns_record,
+            dhcp_failures,
             tls_list,
             tls_conv_list,
             open_file_dialog,
@@ -0,0 +1,249 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    net::Ipv4Addr,
+};
+
+use serde::Serialize;
+
+use crate::common::{
+    concept::{FrameIndex, Timestamp},
+    FastHashMap,
+};
+
+pub const ARP_REQUEST: u16 = 1;
+pub const ARP_REPLY: u16 = 2;
+
+// more gratuitous arp than this from one mac within the window is reported as a storm
+pub const GRATUITOUS_STORM_THRESHOLD: usize = 10;
+pub const GRATUITOUS_STORM_WINDOW: Timestamp = 1_000_000;
+// another mac seen for the ip within this window is a conflict, an older one only a change of binding
+pub const ARP_CONFLICT_WINDOW: Timestamp = 60_000_000;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ArpBinding {
+    pub ip: Ipv4Addr,
+    pub mac: String,
+    pub first_index: FrameIndex,
+    pub first: Timestamp,
+    pub last: Timestamp,
+    pub packets: u32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ArpConflict {
+    pub ip: Ipv4Addr,
+    pub macs: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ArpMacChange {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub ip: Ipv4Addr,
+    pub previous: String,
+    pub current: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ArpStorm {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub last: Timestamp,
+    pub mac: String,
+    pub count: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ArpRequest {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub sender_ip: Ipv4Addr,
+    pub sender_mac: String,
+    pub target_ip: Ipv4Addr,
+    pub answered: bool,
+}
+
+#[derive(Serialize, Default, Clone, Debug)]
+pub struct ArpReport {
+    pub bindings: Vec<ArpBinding>,
+    pub conflicts: Vec<ArpConflict>,
+    pub changes: Vec<ArpMacChange>,
+    pub storms: Vec<ArpStorm>,
+    pub unanswered: Vec<ArpRequest>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArpWarning {
+    // ip already bound to another mac, with the frame that claimed it first
+    DuplicateAddress(Ipv4Addr, String, FrameIndex),
+    MacChanged(Ipv4Addr, String, String),
+    GratuitousStorm(String, usize),
+    Unanswered(Ipv4Addr),
+}
+
+impl fmt::Display for ArpWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArpWarning::DuplicateAddress(ip, mac, index) => write!(f, "Duplicate IP address {ip} configured, also in use by {mac} (frame {})", index + 1),
+            ArpWarning::MacChanged(ip, previous, current) => write!(f, "MAC address of {ip} changed from {previous} to {current}"),
+            ArpWarning::GratuitousStorm(mac, count) => write!(f, "Gratuitous ARP storm from {mac}, {count} packets within a second"),
+            ArpWarning::Unanswered(ip) => write!(f, "ARP request for {ip} was not answered"),
+        }
+    }
+}
+
+/**
+ * arp traffic correlated across the capture. Every mac claiming an ip gets a binding, the
+ * latest one is the current binding of the ip
+ */
+#[derive(Default)]
+pub struct ArpTable {
+    bindings: Vec<ArpBinding>,
+    by_ip: FastHashMap<Ipv4Addr, Vec<usize>>,
+    current: FastHashMap<Ipv4Addr, usize>,
+    changes: Vec<ArpMacChange>,
+    storms: Vec<ArpStorm>,
+    gratuitous: FastHashMap<String, (VecDeque<Timestamp>, Option<usize>)>,
+    requests: Vec<ArpRequest>,
+    pending: FastHashMap<(Ipv4Addr, Ipv4Addr), Vec<usize>>,
+    frame_requests: FastHashMap<FrameIndex, usize>,
+    warnings: FastHashMap<FrameIndex, Vec<ArpWarning>>,
+}
+
+impl ArpTable {
+    /**
+     * returns true when the frame claims an ip another mac has recently claimed
+     */
+    pub fn add(&mut self, index: FrameIndex, ts: Timestamp, operation: u16, sender_mac: String, sender_ip: Ipv4Addr, target_ip: Ipv4Addr) -> bool {
+        let mut duplicate = false;
+        // probes (rfc 5227) carry no sender address and bind nothing
+        if !sender_ip.is_unspecified() {
+            duplicate = self.bind(index, ts, &sender_mac, sender_ip);
+        }
+        let gratuitous = sender_ip == target_ip && !sender_ip.is_unspecified();
+        if gratuitous {
+            self.gratuitous(index, ts, &sender_mac);
+        }
+        match operation {
+            ARP_REQUEST if !gratuitous && !sender_ip.is_unspecified() => {
+                let inx = self.requests.len();
+                self.requests.push(ArpRequest { index, ts, sender_ip, sender_mac, target_ip, answered: false });
+                self.pending.entry((sender_ip, target_ip)).or_default().push(inx);
+                self.frame_requests.insert(index, inx);
+            }
+            ARP_REPLY => {
+                for inx in self.pending.remove(&(target_ip, sender_ip)).unwrap_or_default() {
+                    self.requests[inx].answered = true;
+                }
+            }
+            _ => {}
+        }
+        duplicate
+    }
+
+    fn warn(&mut self, index: FrameIndex, warning: ArpWarning) {
+        self.warnings.entry(index).or_default().push(warning);
+    }
+
+    fn bind(&mut self, index: FrameIndex, ts: Timestamp, mac: &str, ip: Ipv4Addr) -> bool {
+        let list = self.by_ip.entry(ip).or_default();
+        let found = list.iter().copied().find(|inx| self.bindings[*inx].mac == mac);
+        let others: Vec<usize> = list
+            .iter()
+            .copied()
+            .filter(|inx| self.bindings[*inx].mac != mac && ts.saturating_sub(self.bindings[*inx].last) <= ARP_CONFLICT_WINDOW)
+            .collect();
+        let inx = match found {
+            Some(inx) => inx,
+            None => {
+                let inx = self.bindings.len();
+                self.bindings.push(ArpBinding { ip, mac: mac.to_string(), first_index: index, first: ts, last: ts, packets: 0 });
+                list.push(inx);
+                inx
+            }
+        };
+        let binding = &mut self.bindings[inx];
+        binding.last = ts;
+        binding.packets += 1;
+
+        if let Some(other) = others.first() {
+            let other = &self.bindings[*other];
+            let warning = ArpWarning::DuplicateAddress(ip, other.mac.clone(), other.first_index);
+            self.warn(index, warning);
+        }
+        if let Some(previous) = self.current.insert(ip, inx) {
+            if previous != inx {
+                let previous = self.bindings[previous].mac.clone();
+                self.warn(index, ArpWarning::MacChanged(ip, previous.clone(), mac.to_string()));
+                self.changes.push(ArpMacChange { index, ts, ip, previous, current: mac.to_string() });
+            }
+        }
+        !others.is_empty()
+    }
+
+    fn gratuitous(&mut self, index: FrameIndex, ts: Timestamp, mac: &str) {
+        let (window, storm) = self.gratuitous.entry(mac.to_string()).or_default();
+        window.push_back(ts);
+        while window.front().is_some_and(|first| ts.saturating_sub(*first) > GRATUITOUS_STORM_WINDOW) {
+            window.pop_front();
+        }
+        if window.len() <= GRATUITOUS_STORM_THRESHOLD {
+            return;
+        }
+        let count = window.len();
+        match storm {
+            Some(inx) if ts.saturating_sub(self.storms[*inx].last) <= GRATUITOUS_STORM_WINDOW => {
+                let storm = &mut self.storms[*inx];
+                storm.last = ts;
+                storm.count += 1;
+            }
+            _ => {
+                *storm = Some(self.storms.len());
+                self.storms.push(ArpStorm { index, ts, last: ts, mac: mac.to_string(), count });
+            }
+        }
+        self.warn(index, ArpWarning::GratuitousStorm(mac.to_string(), count));
+    }
+
+    /**
+     * requests are only known to be unanswered once the whole capture is parsed
+     */
+    pub fn warnings(&self, index: FrameIndex) -> Vec<ArpWarning> {
+        let mut list = self.warnings.get(&index).cloned().unwrap_or_default();
+        if let Some(request) = self.frame_requests.get(&index).map(|inx| &self.requests[*inx]) {
+            if !request.answered {
+                list.push(ArpWarning::Unanswered(request.target_ip));
+            }
+        }
+        list
+    }
+
+    pub fn conflicts(&self) -> Vec<ArpConflict> {
+        let mut list: Vec<ArpConflict> = self
+            .by_ip
+            .iter()
+            .filter(|(_, bindings)| bindings.len() > 1)
+            .map(|(ip, bindings)| ArpConflict { ip: *ip, macs: bindings.iter().map(|inx| self.bindings[*inx].mac.clone()).collect() })
+            .collect();
+        list.sort_by_key(|c| c.ip);
+        list
+    }
+
+    pub fn report(&self) -> ArpReport {
+        let mut bindings = self.bindings.clone();
+        bindings.sort_by(|a, b| a.ip.cmp(&b.ip).then(a.first.cmp(&b.first)));
+        ArpReport {
+            bindings,
+            conflicts: self.conflicts(),
+            changes: self.changes.clone(),
+            storms: self.storms.clone(),
+            unanswered: self.requests.iter().filter(|r| !r.answered).cloned().collect(),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use crate::common::{core::Context, enum_def::AddressField, io::Reader, Frame};
+
+pub const IP_PROTO_ICMP: u8 = 1;
+pub const IP_PROTO_IGMP: u8 = 2;
+pub const IP_PROTO_TCP: u8 = 6;
+pub const IP_PROTO_UDP: u8 = 17;
+pub const IP_PROTO_ICMP6: u8 = 58;
+pub const IP_PROTO_VRRP: u8 = 112;
+pub const IP_PROTO_SCTP: u8 = 132;
+
+// castagnoli polynomial, reflected
+const CRC32C_POLY: u32 = 0x82f6_3b78;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ChecksumStatus {
+    Unverified,
+    Correct,
+    Incorrect(u32),
+    // zero or pseudo-header-only value written by a NIC doing TX checksum offload
+    Offloaded,
+    // UDP over IPv4 may carry no checksum at all
+    Absent,
+}
+
+impl ChecksumStatus {
+    pub fn is_bad(&self) -> bool {
+        matches!(self, ChecksumStatus::Incorrect(_))
+    }
+}
+
+/**
+ * ones' complement sum over 16-bit words (RFC 1071), not folded
+ */
+pub fn inet_sum(data: &[u8], init: u64) -> u64 {
+    let mut sum = init;
+    let mut chunks = data.chunks_exact(2);
+    for word in &mut chunks {
+        sum += u16::from_be_bytes([word[0], word[1]]) as u64;
+    }
+    if let [last] = chunks.remainder() {
+        sum += (*last as u64) << 8;
+    }
+    sum
+}
+
+pub fn inet_fold(sum: u64) -> u16 {
+    let mut sum = sum;
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+pub fn inet_checksum(data: &[u8]) -> u16 {
+    !inet_fold(inet_sum(data, 0))
+}
+
+fn pseudo_header_sum(ctx: &Context, frame: &Frame, protocol: u8, len: usize) -> Option<u64> {
+    let sum = (protocol as u64) + (len as u64);
+    match &frame.address_field {
+        AddressField::IPv4(source, target) => {
+            let sum = inet_sum(&source.octets(), sum);
+            Some(inet_sum(&target.octets(), sum))
+        }
+        AddressField::IPv6(key) => {
+            let (_, source, target) = ctx.ipv6map.get(key)?;
+            let sum = inet_sum(&source.octets(), sum);
+            Some(inet_sum(&target.octets(), sum))
+        }
+        _ => None,
+    }
+}
+
+fn judge(data: &[u8], offset: usize, init: u64) -> ChecksumStatus {
+    if data.len() < offset + 2 {
+        return ChecksumStatus::Unverified;
+    }
+    let stored = u16::from_be_bytes([data[offset], data[offset + 1]]);
+    let total = inet_sum(data, init);
+    if inet_fold(total) == 0xffff {
+        return ChecksumStatus::Correct;
+    }
+    if stored == 0 {
+        return ChecksumStatus::Offloaded;
+    }
+    // partial checksum: the driver only filled in the pseudo header sum
+    if init > 0 && stored == inet_fold(init) {
+        return ChecksumStatus::Offloaded;
+    }
+    let expected = !inet_fold(total.wrapping_sub(stored as u64));
+    ChecksumStatus::Incorrect(expected as u32)
+}
+
+/**
+ * header starts at cursor and spans head_len bytes
+ */
+pub fn verify_ip4(reader: &Reader, head_len: usize) -> ChecksumStatus {
+    if head_len < 20 {
+        return ChecksumStatus::Unverified;
+    }
+    match reader.preview(head_len) {
+        Ok(data) => judge(data, 10, 0),
+        Err(_) => ChecksumStatus::Unverified,
+    }
+}
+
+/**
+ * transport segment starts at cursor; offset is the position of the checksum inside it
+ */
+pub fn verify_transport(ctx: &Context, frame: &Frame, reader: &Reader, protocol: u8, len: usize, offset: usize) -> ChecksumStatus {
+    if len == 0 {
+        return ChecksumStatus::Unverified;
+    }
+    let Ok(data) = reader.preview(len) else {
+        return ChecksumStatus::Unverified;
+    };
+    if protocol == IP_PROTO_ICMP || protocol == IP_PROTO_IGMP {
+        return judge(data, offset, 0);
+    }
+    if protocol == IP_PROTO_UDP && data.len() > offset + 1 && data[offset] == 0 && data[offset + 1] == 0 {
+        if let AddressField::IPv4(_, _) = &frame.address_field {
+            return ChecksumStatus::Absent;
+        }
+    }
+    match pseudo_header_sum(ctx, frame, protocol, len) {
+        Some(init) => judge(data, offset, init),
+        None => ChecksumStatus::Unverified,
+    }
+}
+
+/**
+ * checksum over the message alone, without pseudo header
+ */
+pub fn verify_plain(reader: &Reader, len: usize, offset: usize) -> ChecksumStatus {
+    match reader.preview(len) {
+        Ok(data) if len > 0 => judge(data, offset, 0),
+        _ => ChecksumStatus::Unverified,
+    }
+}
+
+pub fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ CRC32C_POLY,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+/**
+ * sctp packet starts at cursor, the crc32c is computed with its own field zeroed and stored little endian (RFC 9260 appendix A).
+ * Values are given as read in network order
+ */
+pub fn verify_sctp(reader: &Reader, len: usize) -> ChecksumStatus {
+    let Ok(data) = reader.preview(len) else {
+        return ChecksumStatus::Unverified;
+    };
+    if len < 12 {
+        return ChecksumStatus::Unverified;
+    }
+    let stored = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+    let mut packet = data.to_vec();
+    packet[8..12].fill(0);
+    let expected = crc32c(&packet).swap_bytes();
+    match stored {
+        _ if stored == expected => ChecksumStatus::Correct,
+        0 => ChecksumStatus::Offloaded,
+        _ => ChecksumStatus::Incorrect(expected),
+    }
+}
+
+pub fn t_checksum(name: &str, value: u16, status: ChecksumStatus) -> String {
+    match status {
+        ChecksumStatus::Unverified => format!("{name}: {value:#06x} [unverified]"),
+        ChecksumStatus::Correct => format!("{name}: {value:#06x} [correct]"),
+        ChecksumStatus::Incorrect(expected) => format!("{name}: {value:#06x} [incorrect, should be {expected:#06x}]"),
+        ChecksumStatus::Offloaded => format!("{name}: {value:#06x} [unverified, maybe caused by checksum offload]"),
+        ChecksumStatus::Absent => format!("{name}: {value:#06x} [missing]"),
+    }
+}
+
+pub fn t_checksum32(name: &str, value: u32, status: ChecksumStatus) -> String {
+    match status {
+        ChecksumStatus::Unverified => format!("{name}: {value:#010x} [unverified]"),
+        ChecksumStatus::Correct => format!("{name}: {value:#010x} [correct]"),
+        ChecksumStatus::Incorrect(expected) => format!("{name}: {value:#010x} [incorrect, should be {expected:#010x}]"),
+        ChecksumStatus::Offloaded => format!("{name}: {value:#010x} [unverified, maybe caused by checksum offload]"),
+        ChecksumStatus::Absent => format!("{name}: {value:#010x} [missing]"),
+    }
+}
@@ -3,16 +3,18 @@
 // This file is part of the pcapview project.
 // Licensed under the MIT License - see https://opensource.org/licenses/MIT
 
-use std::hash::Hash;
+use std::{cmp, hash::Hash};
 
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    common::{connection::Connection, enum_def::Protocol, util::date_str, FastHashMap, Instance, NString},
+    common::{connection::Connection, enum_def::Protocol, geoip::{GeoInfo, GeoIp}, util::date_str, FastHashMap, Instance, NString},
     protocol::transport::tls::tls_version_map,
 };
 
-use super::enum_def::PacketStatus;
+use strum_macros::EnumString;
+
+use super::enum_def::{PacketStatus, TCPCloseReason, TCPExpert};
 
 pub type FrameIndex = u32;
 pub type MessageIndex = u64;
@@ -38,6 +40,18 @@ pub struct ConversationCriteria {
     pub ip: Option<String>,
 }
 
+#[derive(Deserialize, EnumString, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ConnectionOrder {
+    #[default]
+    Index,
+    // slowest handshake first
+    Handshake,
+    // reset connections first
+    Reset,
+}
+
 impl ConversationCriteria {
     pub fn ip(ip: String) -> Self {
         Self { ip: Some(ip) }
@@ -53,6 +67,7 @@ impl HttpCriteria {
 #[derive(Default, Copy, Clone)]
 pub struct InstanceConfig {
     pub batch_size: usize,
+    pub verify_checksum: bool,
 }
 
 #[derive(Serialize, Default)]
@@ -72,11 +87,20 @@ impl LineChartData {
 pub struct CounterItem {
     pub key: String,
     pub count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo: Option<GeoInfo>,
 }
 
 impl CounterItem {
     pub fn new(key: String, count: usize) -> Self {
-        Self { key, count }
+        Self { key, count, geo: None }
+    }
+    /**
+     * annotates an item keyed by an ip address
+     */
+    pub fn geo(mut self, geoip: &GeoIp) -> Self {
+        self.geo = geoip.lookup_str(&self.key);
+        self
     }
 }
 
@@ -113,8 +137,12 @@ pub struct FrameInternInfo {
     pub index: FrameIndex,
     pub time: u64,
     pub len: u32,
+    // handshake round trip of the tcp connection, in milliseconds
     pub irtt: u16,
     pub status: PacketStatus,
+    pub reason: Option<NString>,
+    // well formed but worth a look, the status stays normal
+    pub warning: Option<NString>,
 }
 
 #[derive(Serialize, Default, Clone)]
@@ -128,6 +156,8 @@ pub struct FrameInfo {
     pub irtt: u16,
     pub info: String,
     pub status: PacketStatus,
+    pub reason: Option<NString>,
+    pub warning: Option<NString>,
 }
 
 impl From<&FrameInternInfo> for FrameInfo {
@@ -138,6 +168,8 @@ impl From<&FrameInternInfo> for FrameInfo {
             len: value.len,
             irtt: value.irtt,
             status: value.status,
+            reason: value.reason,
+            warning: value.warning,
             ..Default::default()
         }
     }
@@ -270,6 +302,8 @@ impl From<&Conversation> for VConversation {
             sender_bytes,
             receiver_bytes,
             connects,
+            sender_geo: None,
+            receiver_geo: None,
         }
     }
 }
@@ -284,6 +318,10 @@ pub struct VConversation {
     pub sender_bytes: u64,
     pub receiver_bytes: u64,
     pub connects: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender_geo: Option<GeoInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receiver_geo: Option<GeoInfo>,
 }
 
 #[derive(Serialize, Clone, Default)]
@@ -298,8 +336,13 @@ pub struct UDPConversation {
     pub packets: u32,
     pub bytes: usize,
     pub records: Vec<(u64, usize)>,
+    pub icmp_errors: Vec<FrameIndex>,
     pub ts_str: String,
     pub offset_str: (f64, NString),
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender_geo: Option<GeoInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receiver_geo: Option<GeoInfo>,
 }
 impl UDPConversation {
     pub fn new(index: usize, ts: Timestamp, sender: String, receiver: String, sender_port: u16, receiver_port: u16) -> Self {
@@ -331,6 +374,14 @@ pub struct TCPStatistic {
     pub clean_throughput: u64,
     pub retransmission: u32,
     pub invalid: u32,
+    pub dup_ack: u32,
+    pub fast_retransmission: u32,
+    pub spurious_retransmission: u32,
+    pub zero_window: u32,
+    pub window_update: u32,
+    pub zero_window_probe: u32,
+    pub window_full: u32,
+    pub out_of_order: u32,
 }
 
 impl TCPStatistic {
@@ -340,27 +391,151 @@ impl TCPStatistic {
         self.clean_throughput += other.clean_throughput;
         self.retransmission += other.retransmission;
         self.invalid += other.invalid;
+        self.dup_ack += other.dup_ack;
+        self.fast_retransmission += other.fast_retransmission;
+        self.spurious_retransmission += other.spurious_retransmission;
+        self.zero_window += other.zero_window;
+        self.window_update += other.window_update;
+        self.zero_window_probe += other.zero_window_probe;
+        self.window_full += other.window_full;
+        self.out_of_order += other.out_of_order;
+    }
+    pub fn count_expert(&mut self, expert: TCPExpert) {
+        match expert {
+            TCPExpert::DupAck => self.dup_ack += 1,
+            TCPExpert::FastRetransmission => self.fast_retransmission += 1,
+            TCPExpert::SpuriousRetransmission => self.spurious_retransmission += 1,
+            TCPExpert::Retransmission => {}
+            TCPExpert::ZeroWindow => self.zero_window += 1,
+            TCPExpert::WindowUpdate => self.window_update += 1,
+            TCPExpert::ZeroWindowProbe => self.zero_window_probe += 1,
+            TCPExpert::WindowFull => self.window_full += 1,
+            TCPExpert::OutOfOrder => self.out_of_order += 1,
+        }
+    }
+}
+
+/**
+ * handshake, round trip and lifetime of a tcp connection, durations in microseconds
+ */
+#[derive(Serialize, Default, Clone)]
+pub struct TCPMetrics {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syn: Option<Timestamp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syn_ack: Option<Timestamp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ack: Option<Timestamp>,
+    // syn to syn/ack
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syn_ack_time: Option<u64>,
+    // syn/ack to ack
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ack_time: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub irtt: Option<u64>,
+    pub rtt_samples: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rtt_min: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rtt_max: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rtt_avg: Option<u64>,
+    #[serde(skip)]
+    rtt_total: u64,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub duration: u64,
+    pub close: TCPCloseReason,
+    #[serde(skip)]
+    primary_fin: bool,
+    #[serde(skip)]
+    second_fin: bool,
+}
+
+impl TCPMetrics {
+    pub fn touch(&mut self, ts: Timestamp) {
+        if self.start == 0 {
+            self.start = ts;
+        }
+        self.end = cmp::max(self.end, ts);
+        self.duration = self.end - self.start;
+    }
+    pub fn syn(&mut self, ts: Timestamp) {
+        // a retransmitted syn keeps the first one
+        if self.syn.is_none() {
+            self.syn = Some(ts);
+        }
+    }
+    pub fn syn_ack(&mut self, ts: Timestamp) {
+        if self.syn_ack.is_none() {
+            self.syn_ack = Some(ts);
+            self.syn_ack_time = self.syn.map(|syn| ts.saturating_sub(syn));
+        }
+    }
+    pub fn established(&mut self, ts: Timestamp) {
+        if self.ack.is_none() {
+            self.ack = Some(ts);
+            self.ack_time = self.syn_ack.map(|syn_ack| ts.saturating_sub(syn_ack));
+            self.irtt = self.syn_ack_time.zip(self.ack_time).map(|(a, b)| a + b);
+        }
+    }
+    pub fn rtt(&mut self, sample: u64) {
+        self.rtt_samples += 1;
+        self.rtt_total += sample;
+        self.rtt_min = Some(self.rtt_min.map_or(sample, |v| cmp::min(v, sample)));
+        self.rtt_max = Some(self.rtt_max.map_or(sample, |v| cmp::max(v, sample)));
+        self.rtt_avg = Some(self.rtt_total / self.rtt_samples as u64);
+    }
+    pub fn fin(&mut self, primary: bool) {
+        match primary {
+            true => self.primary_fin = true,
+            false => self.second_fin = true,
+        }
+        if self.primary_fin && self.second_fin && self.close == TCPCloseReason::Open {
+            self.close = TCPCloseReason::Fin;
+        }
+    }
+    pub fn reset(&mut self, primary: bool) {
+        if self.close == TCPCloseReason::Open || self.close == TCPCloseReason::Fin {
+            self.close = match primary {
+                true => TCPCloseReason::PrimaryReset,
+                false => TCPCloseReason::SecondReset,
+            };
+        }
+    }
+    pub fn is_reset(&self) -> bool {
+        matches!(self.close, TCPCloseReason::PrimaryReset | TCPCloseReason::SecondReset)
     }
 }
 
 #[derive(Serialize, Default, Clone)]
 pub struct VConnection {
+    // position in the conversation, the key for follow stream
+    pub index: usize,
     pub primary: VEndpoint,
     pub second: VEndpoint,
     pub protocol: String,
+    pub icmp_errors: Vec<FrameIndex>,
+    pub metrics: TCPMetrics,
 }
 
 impl From<&Connection> for VConnection {
     fn from(value: &Connection) -> Self {
         let protocol = match value.protocol {
             Protocol::HTTP => "http".into(),
+            Protocol::HTTP2 => "http2".into(),
+            Protocol::WEBSOCKET => "websocket".into(),
             Protocol::TLS => "tls".into(),
             _ => "".into(),
         };
         Self {
+            index: 0,
             primary: value.primary().into(),
             second: value.second().into(),
             protocol,
+            icmp_errors: value.icmp_errors.clone(),
+            metrics: value.metrics.clone(),
         }
     }
 }
@@ -591,6 +766,12 @@ impl HttpMessageDetail {
     pub fn raw_content(&self) -> &[u8] {
         &self.content
     }
+    /**
+     * the body as the server meant it, chunks joined and content encoding undone
+     */
+    pub fn decoded_content(&self) -> Vec<u8> {
+        decode_content(self.raw_content(), self.text_encoding())
+    }
     pub fn get_text_content(&self) -> Option<String> {
         let len = self.content.len();
         if len == 0 {
@@ -656,15 +837,21 @@ pub struct TLSConversation {
     pub index: usize,
     pub primary: String,
     pub second: String,
+    // tcp or quic
+    pub transport: NString,
     pub list: Vec<TLSItem>,
 }
 
+// index of the first quic conversation in the tls view, tcp conversations keep their own index below it
+pub const QUIC_TLS_CONVERSATION: usize = 1 << 30;
+
 impl TLSConversation {
     pub fn new(index: usize, primary: String, second: String) -> Self {
         Self {
             index,
             primary,
             second,
+            transport: "tcp",
             list: vec![],
         }
     }
@@ -766,7 +953,14 @@ pub fn security_level(version: u16, ciphersuite: u16) -> SecurityLevel {
     }
 }
 fn decode_bytes(body_raw: &[u8], encoding: HttpEncoding) -> String {
-    let decoded_data = match encoding {
+    String::from_utf8(decode_content(body_raw, encoding)).unwrap_or_default()
+}
+
+/**
+ * undoes the content encoding of a body, the raw bytes are kept when it does not decode
+ */
+pub fn decode_content(body_raw: &[u8], encoding: HttpEncoding) -> Vec<u8> {
+    match encoding {
         HttpEncoding::None => body_raw.to_vec(),
         HttpEncoding::Gzip => {
             use flate2::read::GzDecoder;
@@ -801,7 +995,7 @@ fn decode_bytes(body_raw: &[u8], encoding: HttpEncoding) -> String {
             use std::io::Read;
             use zstd::stream::read::Decoder;
             let Ok(mut decoder) = Decoder::new(body_raw) else {
-                return String::from_utf8_lossy(body_raw).to_string();
+                return body_raw.to_vec();
             };
             let mut decoded = Vec::new();
             match decoder.read_to_end(&mut decoded) {
@@ -809,8 +1003,7 @@ fn decode_bytes(body_raw: &[u8], encoding: HttpEncoding) -> String {
                 Err(_) => body_raw.to_vec(),
             }
         }
-    };
-    String::from_utf8(decoded_data).unwrap_or_default()
+    }
 }
 
 #[derive(Default)]
@@ -855,9 +1048,12 @@ pub struct DNSRecord {
     pub rtype: String,
     pub class: String,
     pub info: Option<String>,
+    // location of the address of an A or AAAA answer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo: Option<GeoInfo>,
 }
 impl DNSRecord {
     pub fn new(host: String, rtype: String, class: String, info: Option<String>) -> Self {
-        Self { host, rtype, class, info }
+        Self { host, rtype, class, info, geo: None }
     }
 }
@@ -6,14 +6,16 @@
 // use crate::cache::intern;
 
 use std::{
+    cmp,
+    collections::VecDeque,
     fmt::{Display, Write},
     ops::Range,
 };
 
-use crate::{common::{ResourceLoader, concept::{ConnectionIndex, Conversation, FrameIndex, TCPStatistic, TLSInfo, VEndpoint}}, protocol};
+use crate::{common::{ResourceLoader, decode::Transport, graph::SegmentPoint, stream::{StreamDirection, StreamSegment}, concept::{ConnectionIndex, Conversation, FrameIndex, TCPMetrics, TCPStatistic, TLSInfo, Timestamp, VEndpoint}}, protocol::heuristic::heuristic};
 
 use super::{
-    enum_def::{Protocol, SegmentStatus, TCPConnectStatus, TCPDetail, TCPFLAG},
+    enum_def::{Protocol, SegmentStatus, TCPConnectStatus, TCPDetail, TCPExpert, TCPFLAG},
     io::{DataSource, Reader},
 };
 
@@ -111,6 +113,12 @@ pub struct ConnectState {
     pub connect_finished: bool,
     pub next_protocol: Protocol,
     pub connection: Option<(ConnectionIndex, bool)>,
+    pub experts: Vec<TCPExpert>,
+    // receive window advertised by the sender, scaled
+    pub window: u32,
+    // data sent and not yet acknowledged, 0 when unknown
+    pub in_flight: u32,
+    pub irtt: Option<u64>,
 }
 
 impl ConnectState {
@@ -125,6 +133,10 @@ impl ConnectState {
             connect_finished: false,
             next_protocol: Protocol::None,
             connection: None,
+            experts: vec![],
+            window: 0,
+            in_flight: 0,
+            irtt: None,
         }
     }
 }
@@ -235,6 +247,31 @@ impl TCPSegment {
     }
 }
 
+// out of order segments wait this long for the gap before it is taken as lost
+const HOLD_TIMEOUT: Timestamp = 3_000_000;
+const HOLD_MAX_SEGMENTS: usize = 256;
+const HOLD_MAX_BYTES: usize = 4 * 1024 * 1024;
+// segments waiting for an ack to take a round trip sample
+const RTT_MAX_PENDING: usize = 256;
+
+/**
+ * a segment ahead of the expected sequence, kept with a copy of its payload since the
+ * data source drops parsed bytes. Released segments are passed to the upper layer in order
+ */
+pub struct HeldSegment {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    sequence: u32,
+    // sequence space taken, fin included
+    len: u32,
+    fin: bool,
+    pub data: Vec<u8>,
+    pub range: Range<usize>,
+    pub protocol: Protocol,
+    // the bytes before this segment were never captured
+    pub gap: bool,
+}
+
 #[derive(Default)]
 pub struct Endpoint {
     pub host: String,
@@ -249,6 +286,16 @@ pub struct Endpoint {
     pub statistic: TCPStatistic,
     pub segment_status: SegmentStatus,
     _segments: Option<Vec<TCPSegment>>,
+    // out of order segments sorted by sequence
+    held: Vec<HeldSegment>,
+    // shift announced in the SYN, applied once both sides announced one
+    window_scale: Option<u8>,
+    // last advertised receive window, scaled
+    window: Option<u32>,
+    // duplicate acks received for the data of this endpoint
+    dup_acks: u32,
+    // end sequence and time of segments sent and not acked yet
+    unacked: VecDeque<(u32, Timestamp)>,
 }
 
 impl From<&Endpoint> for VEndpoint {
@@ -300,14 +347,15 @@ impl Endpoint {
         let sequence = stat.sequence;
         let mut statistic = TCPStatistic { count: 1, throughput: stat.payload_len as u64, ..Default::default() };
 
-        if self.seq == sequence && stat.payload_len == 0 {
-            return (TCPDetail::NEXT, statistic);
-        }
+        // a reset ends the connection whatever sequence it carries
         if stat.state.contain(TCPFLAG::RESET) {
             self.clear_segment();
             self.status = TCPConnectStatus::CLOSED;
             return (TCPDetail::RESET, statistic);
         }
+        if self.seq == sequence && stat.payload_len == 0 {
+            return (TCPDetail::NEXT, statistic);
+        }
         let mut _tcp_len = 0;
         if stat.state.contain(TCPFLAG::SYNC) || stat.state.contain(TCPFLAG::FIN) {
             _tcp_len = 1;
@@ -329,6 +377,17 @@ impl Endpoint {
             return (TCPDetail::NEXT, statistic);
         }
         if sequence > self.next {
+            if _tcp_len > 0 && !stat.state.contain(TCPFLAG::SYNC) {
+                if self.held.iter().any(|h| h.sequence == sequence) {
+                    statistic.retransmission = 1;
+                    return (TCPDetail::RETRANSMISSION, statistic);
+                }
+                return (TCPDetail::OUTOFORDER, statistic);
+            }
+            if !self.held.is_empty() {
+                // an ack carrying the sequence of data still waiting for the gap
+                return (TCPDetail::NEXT, statistic);
+            }
             self.seq = sequence;
             self.next = sequence + _tcp_len;
             self._checksum = stat.crc;
@@ -358,6 +417,102 @@ impl Endpoint {
         }
     }
 
+    fn hold(&mut self, stat: &TCPStat, data: Vec<u8>, range: Range<usize>) {
+        let fin = stat.state.contain(TCPFLAG::FIN);
+        let len = data.len() as u32 + fin as u32;
+        let segment = HeldSegment { index: stat.index, ts: stat.ts, sequence: stat.sequence, len, fin, data, range, protocol: Protocol::None, gap: false };
+        let pos = self.held.partition_point(|h| h.sequence < segment.sequence);
+        self.held.insert(pos, segment);
+    }
+
+    /**
+     * the held segments waited too long or fill the buffer
+     */
+    fn hold_expired(&self, ts: Timestamp) -> bool {
+        let Some(first) = self.held.iter().map(|h| h.ts).min() else {
+            return false;
+        };
+        let bytes: usize = self.held.iter().map(|h| h.data.len()).sum();
+        ts.saturating_sub(first) > HOLD_TIMEOUT || self.held.len() >= HOLD_MAX_SEGMENTS || bytes >= HOLD_MAX_BYTES
+    }
+
+    /**
+     * takes the held segments that continue the stream, with skip_gap the missing bytes are
+     * given up and every held segment is released
+     */
+    fn release(&mut self, skip_gap: bool) -> Vec<HeldSegment> {
+        let mut list = vec![];
+        while !self.held.is_empty() {
+            let first = &self.held[0];
+            let end = first.sequence.wrapping_add(first.len);
+            if first.sequence > self.next {
+                if !skip_gap {
+                    break;
+                }
+                self.held[0].gap = true;
+                self.next = self.held[0].sequence;
+                continue;
+            }
+            let mut segment = self.held.remove(0);
+            if end <= self.next {
+                // the stream already covers it
+                continue;
+            }
+            let overlap = cmp::min((self.next - segment.sequence) as usize, segment.data.len());
+            if overlap > 0 {
+                segment.data.drain(..overlap);
+                segment.range.start += overlap;
+            }
+            self.seq = segment.sequence;
+            self.next = end;
+            list.push(segment);
+        }
+        list
+    }
+    fn clear_held(&mut self) {
+        self.held.clear();
+    }
+
+    fn track(&mut self, ts: Timestamp) {
+        if self.unacked.back().is_some_and(|(end, _)| *end >= self.next) {
+            return;
+        }
+        if self.unacked.len() >= RTT_MAX_PENDING {
+            self.unacked.pop_front();
+        }
+        self.unacked.push_back((self.next, ts));
+    }
+    // a segment sent twice gives no sample for the data it covers
+    fn untrack(&mut self, sequence: u32) {
+        self.unacked.retain(|(end, _)| *end <= sequence);
+    }
+    /**
+     * round trip of the latest segment the ack covers
+     */
+    fn acked(&mut self, ack: u32, ts: Timestamp) -> Option<u64> {
+        let mut sent = None;
+        while let Some((end, time)) = self.unacked.front() {
+            if *end > ack {
+                break;
+            }
+            sent = Some(*time);
+            self.unacked.pop_front();
+        }
+        sent.map(|sent| ts.saturating_sub(sent))
+    }
+
+    fn scaled_window(&self, stat: &TCPStat, peer_scale: Option<u8>) -> u32 {
+        // the window of a SYN is never scaled
+        if stat.state.contain(TCPFLAG::SYNC) {
+            return stat.window as u32;
+        }
+        let shift = match (self.window_scale, peer_scale) {
+            (Some(shift), Some(_)) => cmp::min(shift, 14),
+            _ => 0,
+        };
+        (stat.window as u32) << shift
+    }
+
     pub fn ack(&self) -> u32 {
         if self._ack >= self.ack {
             return 0;
@@ -370,6 +525,9 @@ impl Endpoint {
         }
         self.seq - self._seq
     }
+    fn relative(&self, sequence: u32) -> u32 {
+        sequence.saturating_sub(self._seq)
+    }
     pub fn next(&self) -> u32 {
         if self.next > self._seq {
             return self.next - self._seq;
@@ -385,7 +543,16 @@ pub struct Connection {
     pub primary: Endpoint,
     pub second: Endpoint,
     pub protocol: Protocol,
+    // the heuristics already saw the first payload
+    pub probed: bool,
     pub tls_meta: TLSInfo,
+    // icmp errors quoting a segment of this connection
+    pub icmp_errors: Vec<FrameIndex>,
+    // payload of both directions in stream order
+    pub stream: Vec<StreamSegment>,
+    pub metrics: TCPMetrics,
+    // every segment in capture order, for the throughput and sequence graphs
+    pub points: Vec<SegmentPoint>,
 }
 impl Connection {
     pub fn new(primary: Endpoint, second: Endpoint) -> Self {
@@ -393,7 +560,12 @@ impl Connection {
             primary,
             second,
             protocol: Protocol::None,
+            probed: false,
             tls_meta: TLSInfo::default(),
+            icmp_errors: Vec::new(),
+            stream: Vec::new(),
+            metrics: TCPMetrics::default(),
+            points: Vec::new(),
         }
     }
     pub fn primary(&self) -> &Endpoint {
@@ -405,17 +577,177 @@ impl Connection {
     pub fn do_match(&self) -> bool{
         true
     }
+
+    /**
+     * gives up on the gaps of both endpoints, the capture has no more data
+     */
+    pub fn flush(&mut self) -> Vec<HeldSegment> {
+        let mut list = flush_held(&mut self.protocol, &mut self.probed, &mut self.stream, &mut self.second, false);
+        list.extend(flush_held(&mut self.protocol, &mut self.probed, &mut self.stream, &mut self.primary, true));
+        list
+    }
 }
 
 pub struct TmpConnection<'a> {
     connection_index: usize,
     conversation: &'a mut Conversation,
     reverse: bool,
+    // held segments given up on before this segment, they precede its data
+    pub flushed: Vec<HeldSegment>,
+    // held segments this segment made contiguous, they follow its data
+    pub released: Vec<HeldSegment>,
+}
+
+fn follow(stream: &mut Vec<StreamSegment>, index: FrameIndex, reverse: bool, range: Range<usize>) {
+    // the primary endpoint sends in the reverse direction
+    let direction = match reverse {
+        true => StreamDirection::Forward,
+        false => StreamDirection::Backward,
+    };
+    if !range.is_empty() {
+        stream.push(StreamSegment { index, direction, range });
+    }
+}
+
+/**
+ * expert analysis of a segment sent by main. It runs after main took the segment and before
+ * rev takes its ack, so rev.ack still holds the previous ack of main
+ */
+fn analyse(main: &mut Endpoint, rev: &mut Endpoint, stat: &TCPStat, expected: u32, window: u32, status: &mut TCPDetail, statistic: &mut TCPStatistic) -> Vec<TCPExpert> {
+    let mut list = vec![];
+    let len = stat.payload_len as u32;
+    let control = stat.state.contain(TCPFLAG::SYNC) || stat.state.contain(TCPFLAG::FIN) || stat.state.contain(TCPFLAG::RESET);
+    match status {
+        TCPDetail::ZEROWINDOWPROBE => list.push(TCPExpert::ZeroWindowProbe),
+        TCPDetail::OUTOFORDER => list.push(TCPExpert::OutOfOrder),
+        TCPDetail::RETRANSMISSION | TCPDetail::DUMP if len > 0 && stat.sequence < expected => {
+            let acked = main.ack != 0 && stat.sequence.wrapping_add(len) <= main.ack;
+            let expert = if acked {
+                TCPExpert::SpuriousRetransmission
+            } else if main.dup_acks >= 2 && stat.sequence == main.ack {
+                TCPExpert::FastRetransmission
+            } else {
+                TCPExpert::Retransmission
+            };
+            // data behind the stream is sent again, not garbage
+            *status = TCPDetail::RETRANSMISSION;
+            statistic.invalid = 0;
+            statistic.retransmission = 1;
+            list.push(expert);
+        }
+        _ => {}
+    }
+    if len > 0 && main.ack != 0 && !matches!(status, TCPDetail::ZEROWINDOWPROBE) {
+        if let Some(peer_window) = rev.window.filter(|w| *w > 0) {
+            if stat.sequence.wrapping_add(len).wrapping_sub(main.ack) >= peer_window {
+                list.push(TCPExpert::WindowFull);
+            }
+        }
+    }
+    if stat.state.contain(TCPFLAG::ACK) && !control {
+        if stat.window == 0 {
+            list.push(TCPExpert::ZeroWindow);
+        }
+        let same_ack = rev.ack != 0 && stat.ack == rev.ack;
+        if len == 0 && same_ack && stat.sequence == expected {
+            match main.window {
+                Some(last) if last == window && window > 0 => {
+                    rev.dup_acks += 1;
+                    list.push(TCPExpert::DupAck);
+                }
+                Some(last) if last != window && window > 0 => list.push(TCPExpert::WindowUpdate),
+                _ => {}
+            }
+        }
+        if !same_ack {
+            rev.dup_acks = 0;
+        }
+    }
+    for expert in &list {
+        statistic.count_expert(*expert);
+    }
+    list
+}
+
+/**
+ * handshake states and times, round trip samples and how the connection ends
+ */
+fn handshake(main: &mut Endpoint, rev: &mut Endpoint, metrics: &mut TCPMetrics, primary: bool, stat: &TCPStat, status: &TCPDetail) {
+    let flags = &stat.state;
+    metrics.touch(stat.ts);
+    if flags.contain(TCPFLAG::SYNC) {
+        if flags.contain(TCPFLAG::ACK) {
+            main.status = TCPConnectStatus::SYN_RECEIVED;
+            metrics.syn_ack(stat.ts);
+        } else {
+            main.status = TCPConnectStatus::SYN_SENT;
+            metrics.syn(stat.ts);
+        }
+    } else if flags.contain(TCPFLAG::ACK) && main.status == TCPConnectStatus::SYN_SENT && rev.status == TCPConnectStatus::SYN_RECEIVED {
+        main.status = TCPConnectStatus::ESTABLISHED;
+        rev.status = TCPConnectStatus::ESTABLISHED;
+        metrics.established(stat.ts);
+    }
+    match status {
+        TCPDetail::NEXT if stat.payload_len > 0 || flags.contain(TCPFLAG::SYNC) || flags.contain(TCPFLAG::FIN) => main.track(stat.ts),
+        TCPDetail::RETRANSMISSION | TCPDetail::DUMP | TCPDetail::NOPREVCAPTURE => main.untrack(stat.sequence),
+        _ => {}
+    }
+    if flags.contain(TCPFLAG::ACK) {
+        if let Some(sample) = rev.acked(stat.ack, stat.ts) {
+            metrics.rtt(sample);
+        }
+    }
+    if flags.contain(TCPFLAG::RESET) {
+        metrics.reset(primary);
+    } else if flags.contain(TCPFLAG::FIN) {
+        metrics.fin(primary);
+    }
+}
+// consulted once, on the first payload of the connection
+fn detect_protocol(conn: &mut Protocol, probed: &mut bool, endpoint: &mut Endpoint, reader: &Reader) {
+    if *probed {
+        return;
+    }
+    *probed = true;
+    if let Protocol::None = conn {
+        if let Some(protocol) = heuristic(Transport::TCP, reader) {
+            *conn = protocol;
+            endpoint.segment_status = SegmentStatus::Init;
+        }
+    }
+}
+
+// released segments carry the protocol known when they are passed on
+fn tag_released(conn: &mut Protocol, probed: &mut bool, endpoint: &mut Endpoint, list: &mut [HeldSegment]) {
+    for segment in list.iter_mut() {
+        if segment.fin {
+            endpoint.status = TCPConnectStatus::CLOSE_WAIT;
+        }
+        if segment.data.is_empty() {
+            continue;
+        }
+        if !*probed {
+            let ds = DataSource::create(segment.data.clone(), segment.range.clone());
+            detect_protocol(conn, probed, endpoint, &Reader::new(&ds));
+        }
+        segment.protocol = *conn;
+    }
+}
+
+// gives up on the gap of an endpoint, the segments it held are followed in its direction
+fn flush_held(conn: &mut Protocol, probed: &mut bool, stream: &mut Vec<StreamSegment>, endpoint: &mut Endpoint, reverse: bool) -> Vec<HeldSegment> {
+    let mut list = endpoint.release(true);
+    tag_released(conn, probed, endpoint, &mut list);
+    for segment in &list {
+        follow(stream, segment.index, reverse, segment.range.clone());
+    }
+    list
 }
 
 impl<'a> TmpConnection<'a> {
     pub fn new(conversation: &'a mut Conversation, connection_index: usize, reverse: bool) -> Self {
-        Self { connection_index, conversation, reverse }
+        Self { connection_index, conversation, reverse, flushed: vec![], released: vec![] }
     }
 
     pub fn source_endpoint(&mut self) -> &mut Endpoint {
@@ -444,33 +776,78 @@ impl<'a> TmpConnection<'a> {
             rev = &mut conn.second;
         }
 
-        let (status, statistic) = main.update(stat);
+        let reset = stat.state.contain(TCPFLAG::RESET);
+        if main.hold_expired(stat.ts) || reset {
+            self.flushed = flush_held(&mut conn.protocol, &mut conn.probed, &mut conn.stream, main, self.reverse);
+        }
+        // the peer may never send again, its gap is checked on this segment too
+        if rev.hold_expired(stat.ts) || reset {
+            let list = flush_held(&mut conn.protocol, &mut conn.probed, &mut conn.stream, rev, !self.reverse);
+            self.flushed.extend(list);
+        }
+        let expected = main.next;
+        let window = main.scaled_window(stat, rev.window_scale);
+        let probe = stat.payload_len == 1 && expected != 0 && stat.sequence == expected && rev.window == Some(0);
+        let (mut status, mut statistic) = match probe {
+            true => (TCPDetail::ZEROWINDOWPROBE, TCPStatistic { count: 1, throughput: 1, ..Default::default() }),
+            false => main.update(stat),
+        };
+        let experts = analyse(main, rev, stat, expected, window, &mut status, &mut statistic);
+        if stat.state.contain(TCPFLAG::SYNC) {
+            main.window_scale = stat.window_scale;
+        }
+        main.window = Some(window);
         main.statistic.append(&statistic);
         rev.confirm(stat);
         let mut rs = ConnectState::new(main.seq(), rev.ack(), main.next(), stat.payload_len, status);
+        rs.experts = experts;
+        rs.window = window;
+        if main.ack != 0 && stat.payload_len > 0 {
+            rs.in_flight = main.next.wrapping_sub(main.ack);
+        }
+        handshake(main, rev, &mut conn.metrics, self.reverse, stat, &rs.status);
+        rs.irtt = conn.metrics.irtt;
+        conn.points.push(SegmentPoint {
+            index: stat.index,
+            ts: stat.ts,
+            primary: self.reverse,
+            seq: main.relative(stat.sequence),
+            len: stat.payload_len,
+            ack: match stat.state.contain(TCPFLAG::ACK) && rev.seq != 0 {
+                true => rev.relative(stat.ack),
+                false => 0,
+            },
+            window,
+            retransmission: rs.status == TCPDetail::RETRANSMISSION,
+        });
         match &rs.status {
             TCPDetail::RESET => {
+                main.clear_held();
                 main.status = TCPConnectStatus::CLOSED;
                 rev.status = TCPConnectStatus::CLOSED;
                 rs.connect_finished = true;
             }
-            TCPDetail::RETRANSMISSION | TCPDetail::NOPREVCAPTURE | TCPDetail::DUMP => {
+            TCPDetail::OUTOFORDER => {
+                rs.seq = main.relative(stat.sequence);
+                let data = data_source.slice(range.clone())?.to_vec();
+                main.hold(stat, data, range);
+            }
+            TCPDetail::RETRANSMISSION | TCPDetail::NOPREVCAPTURE | TCPDetail::DUMP | TCPDetail::ZEROWINDOWPROBE => {
                 // TODO
             }
             _ => {
                 if rs.status == TCPDetail::NEXT && rs.len > 0 {
                     let reader = Reader::new_sub(data_source, range.clone())?;
-                    if let Protocol::None = conn.protocol {
-                        if protocol::application::http::detect(&reader).0 {
-                            conn.protocol = Protocol::HTTP;
-                            main.segment_status = SegmentStatus::Init;
-                        }
-                         else if protocol::transport::tls::detect(&reader) {
-                            conn.protocol = Protocol::TLS;
-                            main.segment_status = SegmentStatus::Init;
-                        }
-                    }
+                    detect_protocol(&mut conn.protocol, &mut conn.probed, main, &reader);
                     rs.next_protocol = conn.protocol;
+                    follow(&mut conn.stream, stat.index, self.reverse, range);
+                }
+                if rs.status == TCPDetail::NEXT {
+                    self.released = main.release(false);
+                    tag_released(&mut conn.protocol, &mut conn.probed, main, &mut self.released);
+                    for segment in &self.released {
+                        follow(&mut conn.stream, segment.index, self.reverse, segment.range.clone());
+                    }
                 }
                 // // process
                 if stat.state.contain(TCPFLAG::FIN) {
@@ -491,26 +868,36 @@ impl<'a> TmpConnection<'a> {
 
 pub struct TCPStat {
     pub index: FrameIndex,
+    ts: Timestamp,
     sequence: u32,
     ack: u32,
     crc: u16,
     state: TcpFlagField,
-    // window: u16,
+    window: u16,
+    window_scale: Option<u8>,
     // urgent: u16,
     payload_len: u16,
     // pub data_range: Range<usize>,
 }
 
 impl TCPStat {
-    pub fn new(index: FrameIndex, sequence: u32, ack: u32, crc: u16, state: TcpFlagField, payload_len: u16) -> Self {
+    pub fn new(index: FrameIndex, ts: Timestamp, sequence: u32, ack: u32, crc: u16, state: TcpFlagField, payload_len: u16) -> Self {
         Self {
             index,
+            ts,
             sequence,
             ack,
             crc,
             state,
+            window: 0,
+            window_scale: None,
             payload_len,
             // data_range,
         }
     }
+    pub fn with_window(mut self, window: u16, window_scale: Option<u8>) -> Self {
+        self.window = window;
+        self.window_scale = window_scale;
+        self
+    }
 }
@@ -4,23 +4,24 @@
 // Licensed under the MIT License - see https://opensource.org/licenses/MIT
 
 use std::{
-    collections::HashMap,
-    net::{Ipv4Addr, Ipv6Addr},
+    collections::{HashMap, VecDeque},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     ops::{AddAssign, Range},
 };
 
 use anyhow::{bail, Result};
 
 use crate::common::{
-    ResourceLoader, concept::{
-        ConnectionIndex, Conversation, ConversationKey, CounterItem, FrameIndex, HttpConnectIndex, HttpCriteria, HttpMessageDetail, LineChartData, MessageIndex, Timestamp,
+    ResourceLoader, arp::ArpTable, dhcp::DhcpTable, concept::{
+        ConnectionIndex, Conversation, ConversationKey, CounterItem, FrameIndex, HttpConnectIndex, HttpCriteria, HttpMessageDetail, InstanceConfig, LineChartData, MessageIndex, Timestamp,
         VHttpConnection, period,
-    }, enum_def::{AddressField, Protocol}, file::{FileMetadata, Metadata}, util::date_str
+    }, enum_def::{AddressField, Protocol}, file::{FileMetadata, Metadata}, icmp::{IcmpError, IcmpErrorTable, IP_PROTO_TCP}, ipsec::{EspSa, IpsecTable}, multicast::MulticastTable, ospf::OspfTable, fhrp::FhrpTable, sctp::SctpTable, quic::QuicTable, dtls::DtlsTable, mptcp::MptcpTable, http2::Http2Table, websocket::WebSocketTable, geoip::GeoIp, decode::{decode_as, DecodeRule, Transport}, util::date_str
 };
 
 use super::{
-    connection::{ConnectState, Connection, Endpoint, TCPStat, TmpConnection},
+    connection::{ConnectState, Connection, Endpoint, HeldSegment, TCPStat, TmpConnection},
     io::DataSource,
+    stream::StreamSegment,
     quick_hash, EthernetCache, FastHashMap, Frame, NString,
 };
 
@@ -70,9 +71,25 @@ pub struct HttpMessage {
     pub length: Option<usize>,
     pub chunked: bool,
     pub content_type: Option<String>,
+    // Content-Disposition, the name a body is saved under
+    pub disposition: Option<String>,
     pub headers: SegmentData,
     pub content: SegmentData,
     pub http_connect_index: Option<HttpConnectIndex>,
+    // header lines decoded while parsing, http/2 compresses them on the wire
+    pub decoded_headers: Vec<String>,
+    // a response to HEAD, or a 1xx, 204 or 304, ends with its headers whatever they announce
+    pub bodiless: bool,
+}
+
+/**
+ * status code of a response start line
+ */
+pub fn http_status(line: &str) -> Option<u16> {
+    match line.starts_with("HTTP/") {
+        true => line.split_whitespace().nth(1)?.parse().ok(),
+        false => None,
+    }
 }
 
 impl HttpMessage {
@@ -160,10 +177,15 @@ impl HttpConntect {
     fn to_detail(&self, ctx: &Context, loader: &dyn ResourceLoader, index: &Option<MessageIndex>, is_request: bool) -> Result<HttpMessageDetail> {
         if let Some(request_index) = index {
             if let Some(message) = ctx.http_messages.get(*request_index as usize) {
-                let header_range = message.headers.to_range();
-                let header_data = loader.loads(&header_range)?;
-                let text = String::from_utf8_lossy(&header_data);
-                let mut headers: Vec<String> = text.split("\r\n").map(|f| f.to_string()).filter(|f| !f.is_empty()).collect();
+                let mut headers: Vec<String> = match message.decoded_headers.is_empty() {
+                    true => {
+                        let header_range = message.headers.to_range();
+                        let header_data = loader.loads(&header_range)?;
+                        let text = String::from_utf8_lossy(&header_data);
+                        text.split("\r\n").map(|f| f.to_string()).filter(|f| !f.is_empty()).collect()
+                    }
+                    false => message.decoded_headers.clone(),
+                };
                 headers.insert(0, message.host.clone());
                 let body_range = message.content.to_range();
                 let content = loader.loads(&body_range)?;
@@ -235,20 +257,29 @@ impl HttpConntect {
     }
 }
 
+// both endpoints of a udp flow, the lower one first
+pub type UdpEndpoint = (Option<IpAddr>, u16);
+pub type UdpFlow = (UdpEndpoint, UdpEndpoint);
+
 #[derive(Default)]
 pub struct Context {
     // pub file_type: FileType,
     // pub link_type: u32,
     pub metadata: FileMetadata,
+    pub config: InstanceConfig,
     pub list: Vec<Frame>,
     pub counter: FrameIndex,
     // tcp
     pub active_connection: FastHashMap<(u64, u16, u64, u16), usize>,
     pub conversation_map: FastHashMap<ConversationKey, usize>,
     pub conversation_list: Vec<Conversation>,
+    // out of order segments the current frame lets through, the flushed ones precede its data, the released ones follow it
+    pub tcp_flushed: Vec<HeldSegment>,
+    pub tcp_released: Vec<HeldSegment>,
     // pub connections: Vec<Connection>,
     // http
-    pub http_connections_map: FastHashMap<ConnectionIndex, (HttpConnectIndex, Timestamp)>,
+    // requests still waiting for their response, answered in order on a keep-alive connection
+    pub http_connections_map: FastHashMap<ConnectionIndex, VecDeque<(HttpConnectIndex, Timestamp)>>,
     pub http_connections: Vec<HttpConntect>,
     pub http_messages: Vec<HttpMessage>,
     pub http_hostnames: FastHashMap<String, u16>,
@@ -259,8 +290,48 @@ pub struct Context {
     pub ipv6map: FastHashMap<u64, (u8, Ipv6Addr, Ipv6Addr)>,
     pub string_map: FastHashMap<u64, NString>,
 
+    // udp payload of each datagram, for following a udp stream
+    pub udp_payloads: FastHashMap<FrameIndex, Range<usize>>,
+    // heuristic choice per udp flow without a port match
+    pub udp_flows: FastHashMap<UdpFlow, Protocol>,
+    // first frame of every udp flow with the endpoint that sent it
+    pub udp_streams: FastHashMap<UdpFlow, (FrameIndex, UdpEndpoint)>,
+    // datagrams with payload by the first frame of their flow, forward is the sender of that frame
+    pub udp_segments: FastHashMap<FrameIndex, Vec<StreamSegment>>,
     pub stat_ip4: FastHashMap<Ipv4Addr, usize>,
     pub stat_ip6: FastHashMap<Ipv6Addr, usize>,
+
+    // igmp
+    pub multicast: MulticastTable,
+    // ipsec
+    pub esp_sa: Vec<EspSa>,
+    pub ipsec: IpsecTable,
+    // icmp errors
+    pub icmp_errors: IcmpErrorTable,
+    // arp
+    pub arp: ArpTable,
+    // dhcp
+    pub dhcp: DhcpTable,
+    // ospf
+    pub ospf: OspfTable,
+    // vrrp, hsrp
+    pub fhrp: FhrpTable,
+    // sctp associations
+    pub sctp: SctpTable,
+    // quic connections
+    pub quic: QuicTable,
+    // dtls handshake reassembly
+    pub dtls: DtlsTable,
+    // mptcp connections by subflow
+    pub mptcp: MptcpTable,
+    // http/2 frames and streams per tcp connection
+    pub http2: Http2Table,
+    // websocket frames and messages per upgraded tcp connection
+    pub websocket: WebSocketTable,
+    // local mmdb databases, kept across reloads
+    pub geoip: GeoIp,
+    // user decode as rules, kept across reloads
+    pub decode_as: Vec<DecodeRule>,
 }
 
 impl Context {
@@ -292,9 +363,11 @@ impl Context {
     }
     pub fn init_segment_message(&mut self, frame_index: FrameIndex, host: String, is_request: bool, connect_index: ConnectionIndex, timestamp: Timestamp) -> MessageIndex {
         let message_index = self.http_messages.len() as MessageIndex;
+        let status = http_status(&host);
         let mut sg = HttpMessage {
             frame_index,
             host,
+            bodiless: matches!(status, Some(100..=199 | 204 | 304)),
             ..Default::default()
         };
         // self.http_messages.push(sg);
@@ -303,14 +376,17 @@ impl Context {
             let http_connect_index = self.http_connections.len() as HttpConnectIndex;
             let connect = HttpConntect::request(connect_index, message_index);
             self.http_connections.push(connect);
-            self.http_connections_map.insert(connect_index, (http_connect_index, timestamp));
+            self.http_connections_map.entry(connect_index).or_default().push_back((http_connect_index, timestamp));
             sg.http_connect_index = Some(http_connect_index);
-        } else if let Some((http_connect_index, ts)) = self.http_connections_map.get(&connect_index) {
-            if let Some(connect) = self.http_connections.get_mut(*http_connect_index as usize) {
-                let fd = timestamp.saturating_sub(*ts);
+        } else if matches!(status, Some(100 | 102..=199)) {
+            // an interim response, the request still waits for its final one
+        } else if let Some((http_connect_index, ts)) = self.http_connections_map.get_mut(&connect_index).and_then(|queue| queue.pop_front()) {
+            if let Some(connect) = self.http_connections.get_mut(http_connect_index as usize) {
+                let fd = timestamp.saturating_sub(ts);
                 let latency = super::concept::period(timestamp, fd);
                 connect.add_response(message_index, latency);
-                self.http_connections_map.remove(&connect_index);
+                let head = connect.request.and_then(|request| self.http_messages.get(request as usize)).map(|request| request.host.starts_with("HEAD ")).unwrap_or(false);
+                sg.bodiless |= head;
             }
         } else {
             self.http_connections.push(HttpConntect::response(connect_index, message_index));
@@ -318,6 +394,45 @@ impl Context {
         self.http_messages.push(sg);
         message_index
     }
+    /**
+     * the request a response on the connection would be paired with
+     */
+    pub fn pending_request(&self, connect_index: &ConnectionIndex) -> Option<(HttpConnectIndex, Timestamp)> {
+        self.http_connections_map.get(connect_index)?.front().copied()
+    }
+    /**
+     * an http/2 message, the response pairs with the request of its stream rather than the last one on the connection
+     */
+    pub fn init_stream_message(&mut self, frame_index: FrameIndex, host: String, is_request: bool, connect_index: ConnectionIndex, request: Option<(HttpConnectIndex, Timestamp)>, timestamp: Timestamp) -> (MessageIndex, HttpConnectIndex) {
+        let message_index = self.http_messages.len() as MessageIndex;
+        let mut sg = HttpMessage {
+            frame_index,
+            host,
+            ..Default::default()
+        };
+        let http_connect_index = match (is_request, request) {
+            (false, Some((http_connect_index, ts))) => {
+                if let Some(connect) = self.http_connections.get_mut(http_connect_index as usize) {
+                    let latency = super::concept::period(timestamp, timestamp.saturating_sub(ts));
+                    connect.add_response(message_index, latency);
+                }
+                http_connect_index
+            }
+            _ => {
+                let http_connect_index = self.http_connections.len() as HttpConnectIndex;
+                match is_request {
+                    true => {
+                        self.http_connections.push(HttpConntect::request(connect_index, message_index));
+                        sg.http_connect_index = Some(http_connect_index);
+                    }
+                    false => self.http_connections.push(HttpConntect::response(connect_index, message_index)),
+                }
+                http_connect_index
+            }
+        };
+        self.http_messages.push(sg);
+        (message_index, http_connect_index)
+    }
     pub fn get_http_message(&mut self, message_index: MessageIndex) -> Option<&mut HttpMessage> {
         self.http_messages.get_mut(message_index as usize)
     }
@@ -424,7 +539,7 @@ impl Context {
         Self::default()
     }
 
-    pub fn _get_connect<T>(&mut self, _: &mut Frame, source: T, target: T, stat: TCPStat, data_source: &DataSource, range: Range<usize>) -> Result<ConnectState>
+    pub fn _get_connect<T>(&mut self, frame: &mut Frame, source: T, target: T, stat: TCPStat, data_source: &DataSource, range: Range<usize>) -> Result<ConnectState>
     where
         T: Into<Endpoint> + PartialOrd + Factor,
     {
@@ -443,6 +558,10 @@ impl Context {
             true => (source, target),
             false => (target, source),
         };
+        let decode = match self.active_connection.contains_key(&key) {
+            true => None,
+            false => decode_as(self, frame, Transport::TCP, (s.1, t.1)),
+        };
         let conversation_index = self.conversation_map.entry(conversation_key).or_insert_with(|| -> usize {
             let index = self.conversation_list.len();
             self.conversation_list.push(Conversation::new(index, eps.0.host(), eps.1.host()));
@@ -455,12 +574,17 @@ impl Context {
         if let Some(index) = self.active_connection.get(&key) {
             _index = *index;
         } else {
-            let connection = Connection::new(eps.0.into(), eps.1.into());
+            let mut connection = Connection::new(eps.0.into(), eps.1.into());
+            if let Some(protocol) = decode {
+                connection.protocol = protocol;
+            }
             _index = conversation.add_connection(connection);
             self.active_connection.insert(key, _index);
         }
         let mut tmp_conn = TmpConnection::new(conversation, _index, reverse);
         let mut rs = tmp_conn.update(&stat, data_source, range)?;
+        self.tcp_flushed = std::mem::take(&mut tmp_conn.flushed);
+        self.tcp_released = std::mem::take(&mut tmp_conn.released);
         rs.connection = Some(((*conversation_index, _index), reverse));
         // remove
         if rs.connect_finished {
@@ -488,6 +612,12 @@ impl Context {
         }
     }
 
+    /**
+     * takes the held segments of every connection, their gaps are given up on
+     */
+    pub fn flush_held(&mut self) -> Vec<HeldSegment> {
+        self.conversation_list.iter_mut().flat_map(|conversation| conversation.connections.iter_mut()).flat_map(|conn| conn.flush()).collect()
+    }
     pub fn connection(&mut self, frame: &Frame) -> Option<(ConnectionIndex, &mut Endpoint)> {
         if let Some(tcp_info) = &frame.tcp_info {
             if let Some(((conversation_index, connect_index), reverse)) = tcp_info.connection {
@@ -503,6 +633,56 @@ impl Context {
         }
         None
     }
+    /**
+     * hands the later segments of the frame's tcp connection to another protocol, after an upgrade
+     */
+    pub fn upgrade_connection(&mut self, frame: &Frame, protocol: Protocol) {
+        if let Some(((conversation_index, connect_index), _)) = frame.tcp_info.as_ref().and_then(|info| info.connection) {
+            if let Some(conn) = self.conversation_list.get_mut(conversation_index).and_then(|c| c.connection(connect_index)) {
+                conn.protocol = protocol;
+                conn.primary.segment_status = Default::default();
+                conn.second.segment_status = Default::default();
+            }
+        }
+    }
+    /**
+     * read only lookup of the latest tcp connection between two endpoints, in either direction
+     */
+    pub fn find_connection(&self, source: &IpAddr, source_port: u16, target: &IpAddr, target_port: u16) -> Option<ConnectionIndex> {
+        // endpoints are keyed by the hash of the plain v4/v6 address
+        let ip_hash = |ip: &IpAddr| match ip {
+            IpAddr::V4(ip) => quick_hash(ip),
+            IpAddr::V6(ip) => quick_hash(ip),
+        };
+        let (s, t) = (ip_hash(source), ip_hash(target));
+        let conversation_index = self.conversation_map.get(&(s, t)).or_else(|| self.conversation_map.get(&(t, s)))?;
+        let conversation = self.conversation_list.get(*conversation_index)?;
+        let (source, target) = (source.to_string(), target.to_string());
+        let matches = |ep: &Endpoint, host: &str, port: u16| ep.host == host && ep.port == port;
+        conversation
+            .connections
+            .iter()
+            .rposition(|conn| {
+                (matches(&conn.primary, &source, source_port) && matches(&conn.second, &target, target_port))
+                    || (matches(&conn.primary, &target, target_port) && matches(&conn.second, &source, source_port))
+            })
+            .map(|connect_index| (*conversation_index, connect_index))
+    }
+    /**
+     * errors quoting a tcp segment are linked to the connection, the quoted header never opens one
+     */
+    pub fn add_icmp_error(&mut self, mut error: IcmpError) {
+        let quoted = &error.quoted;
+        if quoted.protocol == IP_PROTO_TCP {
+            error.connection = self.find_connection(&quoted.source, quoted.source_port, &quoted.target, quoted.target_port);
+        }
+        if let Some((conversation_index, connect_index)) = error.connection {
+            if let Some(conn) = self.conversation_list.get_mut(conversation_index).and_then(|c| c.connection(connect_index)) {
+                conn.icmp_errors.push(error.index);
+            }
+        }
+        self.icmp_errors.add(error);
+    }
     pub fn _connection(&mut self, frame: &Frame) -> Option<&mut Connection> {
         if let Some(tcp_info) = &frame.tcp_info {
             if let Some(((conversation_index, connect_index), _)) = tcp_info.connection {
@@ -682,7 +862,7 @@ impl Context {
                 Protocol::UDP => {
                     incr(&mut udp, index, mount);
                 }
-                Protocol::HTTP => {
+                Protocol::HTTP | Protocol::HTTP2 | Protocol::WEBSOCKET => {
                     incr(&mut http, index, mount);
                 }
                 Protocol::TLS => {
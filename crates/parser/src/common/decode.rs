@@ -0,0 +1,120 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::{net::IpAddr, str::FromStr};
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use strum_macros::{Display, EnumString};
+
+use crate::common::{core::Context, enum_def::Protocol, ipsec::frame_ips, Frame};
+
+#[derive(Display, EnumString, Debug, Clone, Copy, PartialEq, Serialize)]
+#[strum(ascii_case_insensitive)]
+pub enum Transport {
+    TCP,
+    UDP,
+}
+
+/**
+ * dissect traffic on a port, or of one 5-tuple, as the given protocol instead of the detected one
+ */
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodeRule {
+    pub transport: Transport,
+    pub port: u16,
+    pub protocol: Protocol,
+    // source, target and source port, the rule then only matches that flow in either direction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tuple: Option<(IpAddr, IpAddr, u16)>,
+}
+
+impl DecodeRule {
+    fn matches(&self, transport: Transport, ips: (Option<IpAddr>, Option<IpAddr>), ports: (u16, u16)) -> bool {
+        if self.transport != transport {
+            return false;
+        }
+        match self.tuple {
+            Some((source, target, source_port)) => {
+                let (Some(s), Some(t)) = ips else {
+                    return false;
+                };
+                (s == source && t == target && ports == (source_port, self.port)) || (s == target && t == source && ports == (self.port, source_port))
+            }
+            None => ports.0 == self.port || ports.1 == self.port,
+        }
+    }
+}
+
+fn allowed(transport: Transport, protocol: Protocol) -> bool {
+    match transport {
+        // a tcp stream is only handed to the stream dissectors
+        Transport::TCP => matches!(protocol, Protocol::HTTP | Protocol::HTTP2 | Protocol::WEBSOCKET | Protocol::TLS),
+        Transport::UDP => matches!(protocol, Protocol::None | Protocol::DNS | Protocol::MDNS | Protocol::NBNS | Protocol::DHCP | Protocol::DHCP6 | Protocol::HSRP | Protocol::QUIC | Protocol::DTLS),
+    }
+}
+
+fn endpoint(text: &str) -> Result<(IpAddr, u16)> {
+    let Some((ip, port)) = text.rsplit_once(':') else {
+        bail!("invalid endpoint: {text}");
+    };
+    let ip = ip.trim_start_matches('[').trim_end_matches(']');
+    Ok((ip.parse()?, port.parse()?))
+}
+
+/**
+ * one rule per line, "none" dissects the udp payload as plain data:
+ * udp 5300 dns
+ * tcp 10.0.0.1:40000 10.0.0.2:8081 http
+ */
+pub fn parse_decode_as(content: &str) -> Result<Vec<DecodeRule>> {
+    let mut list = vec![];
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        let (transport, port, tuple, protocol) = match cols.as_slice() {
+            [transport, port, protocol] => (*transport, port.parse::<u16>()?, None, *protocol),
+            [transport, source, target, protocol] => {
+                let (source, source_port) = endpoint(source)?;
+                let (target, target_port) = endpoint(target)?;
+                (*transport, target_port, Some((source, target, source_port)), *protocol)
+            }
+            _ => bail!("invalid decode as line: {line}"),
+        };
+        let Ok(transport) = Transport::from_str(transport) else {
+            bail!("unknown transport: {transport}");
+        };
+        let Ok(protocol) = Protocol::from_str(protocol) else {
+            bail!("unknown protocol: {protocol}");
+        };
+        if !allowed(transport, protocol) {
+            bail!("{protocol} can not be decoded over {transport}");
+        }
+        list.push(DecodeRule { transport, port, protocol, tuple });
+    }
+    Ok(list)
+}
+
+/**
+ * rule for the flow of the frame, 5-tuple rules take precedence over port rules
+ */
+pub fn decode_as(ctx: &Context, frame: &Frame, transport: Transport, ports: (u16, u16)) -> Option<Protocol> {
+    if ctx.decode_as.is_empty() {
+        return None;
+    }
+    let ips = frame_ips(ctx, frame);
+    let rules = ctx.decode_as.iter().filter(|r| r.matches(transport, ips, ports));
+    let mut port_rule = None;
+    for rule in rules {
+        if rule.tuple.is_some() {
+            return Some(rule.protocol);
+        }
+        port_rule.get_or_insert(rule.protocol);
+    }
+    port_rule
+}
@@ -0,0 +1,214 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::net::IpAddr;
+
+use serde::Serialize;
+
+use crate::common::{
+    concept::{FrameIndex, Timestamp},
+    FastHashMap,
+};
+
+// dhcpv4 message types (rfc 2132 9.6)
+pub const DHCP_DISCOVER: u8 = 1;
+pub const DHCP_OFFER: u8 = 2;
+pub const DHCP_REQUEST: u8 = 3;
+pub const DHCP_DECLINE: u8 = 4;
+pub const DHCP_ACK: u8 = 5;
+pub const DHCP_NAK: u8 = 6;
+pub const DHCP_RELEASE: u8 = 7;
+
+// dhcpv6 message types (rfc 8415 7.3)
+pub const DHCP6_SOLICIT: u8 = 1;
+pub const DHCP6_ADVERTISE: u8 = 2;
+pub const DHCP6_REQUEST: u8 = 3;
+pub const DHCP6_REPLY: u8 = 7;
+
+/**
+ * what one dhcp/dhcpv6 message says about the lease, collected while parsing
+ */
+#[derive(Default, Clone, Debug)]
+pub struct DhcpMessage {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub version: u8,
+    pub msg_type: u8,
+    pub xid: u32,
+    // client mac for dhcpv4, client duid for dhcpv6
+    pub client: String,
+    pub server: Option<IpAddr>,
+    pub address: Option<IpAddr>,
+    pub lease_time: Option<u32>,
+    pub routers: Vec<IpAddr>,
+    pub dns: Vec<IpAddr>,
+    // dhcpv6 status code, 0 is success
+    pub status: Option<u16>,
+}
+
+impl DhcpMessage {
+    fn is_reply(&self) -> bool {
+        match self.version {
+            4 => matches!(self.msg_type, DHCP_OFFER | DHCP_ACK | DHCP_NAK),
+            _ => matches!(self.msg_type, DHCP6_ADVERTISE | DHCP6_REPLY),
+        }
+    }
+    // a client message that starts a new exchange
+    fn initial(&self) -> bool {
+        !self.is_reply() && !matches!((self.version, self.msg_type), (4, DHCP_REQUEST) | (6, DHCP6_REQUEST))
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum DhcpStatus {
+    Completed,
+    Nak,
+    // dhcpv6 reply carrying a non-success status code
+    Failed(u16),
+    Stalled,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DhcpTransaction {
+    pub version: u8,
+    pub xid: u32,
+    pub client: String,
+    pub frames: Vec<FrameIndex>,
+    pub messages: Vec<u8>,
+    pub start: Timestamp,
+    pub status: DhcpStatus,
+    // message the exchange stopped waiting for
+    pub waiting_for: Option<&'static str>,
+    pub server: Option<IpAddr>,
+    pub address: Option<IpAddr>,
+    pub lease_time: Option<u32>,
+    pub routers: Vec<IpAddr>,
+    pub dns: Vec<IpAddr>,
+    // first client message to the final server answer, microseconds
+    pub latency: Option<Timestamp>,
+}
+
+impl DhcpTransaction {
+    fn new(message: &DhcpMessage) -> Self {
+        Self {
+            version: message.version,
+            xid: message.xid,
+            client: message.client.clone(),
+            frames: vec![],
+            messages: vec![],
+            start: message.ts,
+            status: DhcpStatus::Stalled,
+            waiting_for: None,
+            server: None,
+            address: None,
+            lease_time: None,
+            routers: vec![],
+            dns: vec![],
+            latency: None,
+        }
+    }
+
+    fn add(&mut self, message: DhcpMessage) {
+        self.frames.push(message.index);
+        self.messages.push(message.msg_type);
+        if message.is_reply() {
+            self.server = message.server.or(self.server);
+            self.address = message.address.or(self.address);
+            self.lease_time = message.lease_time.or(self.lease_time);
+            if !message.routers.is_empty() {
+                self.routers = message.routers;
+            }
+            if !message.dns.is_empty() {
+                self.dns = message.dns;
+            }
+            self.latency = Some(message.ts.saturating_sub(self.start));
+        }
+        (self.status, self.waiting_for) = self.resolve(message.msg_type, message.status);
+    }
+
+    fn resolve(&self, msg_type: u8, status: Option<u16>) -> (DhcpStatus, Option<&'static str>) {
+        match (self.version, msg_type) {
+            (4, DHCP_ACK) => (DhcpStatus::Completed, None),
+            (4, DHCP_NAK) => (DhcpStatus::Nak, None),
+            (4, DHCP_DISCOVER) => (DhcpStatus::Stalled, Some("OFFER")),
+            (4, DHCP_OFFER) => (DhcpStatus::Stalled, Some("REQUEST")),
+            (4, DHCP_REQUEST) => (DhcpStatus::Stalled, Some("ACK")),
+            // no answer is expected for a release or decline
+            (4, DHCP_RELEASE | DHCP_DECLINE) => (DhcpStatus::Completed, None),
+            (4, _) => (DhcpStatus::Stalled, Some("ACK")),
+            (_, DHCP6_REPLY) => match status {
+                Some(code) if code != 0 => (DhcpStatus::Failed(code), None),
+                _ => (DhcpStatus::Completed, None),
+            },
+            (_, DHCP6_SOLICIT) => (DhcpStatus::Stalled, Some("ADVERTISE")),
+            (_, DHCP6_ADVERTISE) => (DhcpStatus::Stalled, Some("REQUEST")),
+            (_, _) => (DhcpStatus::Stalled, Some("REPLY")),
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.status != DhcpStatus::Stalled
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.status != DhcpStatus::Completed
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DhcpClient {
+    pub version: u8,
+    pub client: String,
+    // exchanges of the client in capture order, completed ones carry the lease
+    pub transactions: Vec<DhcpTransaction>,
+}
+
+/**
+ * dhcp exchanges correlated by transaction id, a message matches the open exchange of the
+ * same client and xid
+ */
+#[derive(Default)]
+pub struct DhcpTable {
+    transactions: Vec<DhcpTransaction>,
+    open: FastHashMap<(u8, u32, String), usize>,
+}
+
+impl DhcpTable {
+    pub fn add(&mut self, message: DhcpMessage) {
+        let key = (message.version, message.xid, message.client.clone());
+        let current = self.open.get(&key).copied().filter(|inx| !(self.transactions[*inx].finished() && message.initial()));
+        let inx = match current {
+            Some(inx) => inx,
+            None => {
+                let inx = self.transactions.len();
+                self.transactions.push(DhcpTransaction::new(&message));
+                self.open.insert(key, inx);
+                inx
+            }
+        };
+        self.transactions[inx].add(message);
+    }
+
+    pub fn transactions(&self) -> &[DhcpTransaction] {
+        &self.transactions
+    }
+
+    pub fn failures(&self) -> Vec<DhcpTransaction> {
+        self.transactions.iter().filter(|t| t.is_failed()).cloned().collect()
+    }
+
+    pub fn clients(&self) -> Vec<DhcpClient> {
+        let mut map: FastHashMap<(u8, &str), usize> = FastHashMap::default();
+        let mut list: Vec<DhcpClient> = vec![];
+        for transaction in &self.transactions {
+            let inx = *map.entry((transaction.version, &transaction.client)).or_insert_with(|| {
+                list.push(DhcpClient { version: transaction.version, client: transaction.client.clone(), transactions: vec![] });
+                list.len() - 1
+            });
+            list[inx].transactions.push(transaction.clone());
+        }
+        list
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::{net::IpAddr, ops::Range};
+
+use crate::common::{concept::FrameIndex, FastHashMap};
+
+pub const DTLS_CLIENT_HELLO: u8 = 1;
+pub const DTLS_HELLO_VERIFY_REQUEST: u8 = 3;
+
+// size of the dtls handshake header: type, length, message_seq, fragment_offset, fragment_length
+pub const DTLS_HANDSHAKE_HEADER: usize = 12;
+
+// handshake messages longer than this are not reassembled
+const MESSAGE_LIMIT: usize = 64 * 1024;
+
+pub type DtlsEndpoint = (IpAddr, u16);
+
+/**
+ * what became of a handshake fragment
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DtlsFragment {
+    // the record carried the whole message
+    Whole,
+    // part of a message still missing pieces
+    Partial,
+    // the last missing piece, with the number of fragments the message came in
+    Reassembled(usize),
+    // the same piece was first seen in that frame
+    Retransmission(FrameIndex),
+}
+
+#[derive(Clone, Debug)]
+pub struct DtlsRecordInfo {
+    pub content_type: u8,
+    pub epoch: u16,
+    // handshake message types in the record with their fragment state, only for plaintext records
+    pub messages: Vec<(u8, DtlsFragment)>,
+}
+
+/**
+ * the 12 byte header in front of every handshake fragment
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct DtlsHandshakeHeader {
+    pub msg_type: u8,
+    pub length: usize,
+    pub message_seq: u16,
+    pub offset: usize,
+    pub fragment_len: usize,
+}
+
+impl DtlsHandshakeHeader {
+    pub fn read(data: &[u8]) -> Option<Self> {
+        let head = data.get(..DTLS_HANDSHAKE_HEADER)?;
+        let u24 = |at: usize| u32::from_be_bytes([0, head[at], head[at + 1], head[at + 2]]) as usize;
+        Some(Self {
+            msg_type: head[0],
+            length: u24(1),
+            message_seq: u16::from_be_bytes([head[4], head[5]]),
+            offset: u24(6),
+            fragment_len: u24(9),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CookieCheck {
+    // the ClientHello carries the cookie of the HelloVerifyRequest in that frame
+    Echoed(FrameIndex),
+    Mismatch(FrameIndex),
+}
+
+/**
+ * a complete handshake message, in dtls form with a single fragment covering all of it
+ */
+#[derive(Clone, Debug)]
+pub struct DtlsMessage {
+    pub message_seq: u16,
+    pub msg_type: u8,
+    pub fragments: usize,
+    pub data: Vec<u8>,
+    pub cookie: Option<CookieCheck>,
+}
+
+#[derive(Default, Clone, Debug)]
+struct PendingMessage {
+    data: Vec<u8>,
+    received: Vec<Range<usize>>,
+    fragments: usize,
+}
+
+impl PendingMessage {
+    fn add(&mut self, offset: usize, fragment: &[u8]) -> bool {
+        self.data[offset..offset + fragment.len()].copy_from_slice(fragment);
+        self.received.push(offset..offset + fragment.len());
+        self.received.sort_by_key(|r| r.start);
+        self.fragments += 1;
+        let mut covered = 0;
+        for range in &self.received {
+            if range.start > covered {
+                return false;
+            }
+            covered = covered.max(range.end);
+        }
+        covered >= self.data.len()
+    }
+}
+
+/**
+ * handshake state of one direction between two endpoints
+ */
+#[derive(Default, Clone, Debug)]
+struct DtlsFlow {
+    pending: FastHashMap<u16, PendingMessage>,
+    // frame of every piece seen, by message_seq, fragment offset and length
+    seen: FastHashMap<(u16, usize, usize), FrameIndex>,
+    // cookie handed to this side by a HelloVerifyRequest
+    cookie: Option<(FrameIndex, Vec<u8>)>,
+}
+
+/**
+ * reassembles epoch 0 handshake messages per direction and checks cookies against the HelloVerifyRequest
+ */
+#[derive(Default)]
+pub struct DtlsTable {
+    flows: FastHashMap<(DtlsEndpoint, DtlsEndpoint), DtlsFlow>,
+    // messages by the frame whose fragment completed them
+    messages: FastHashMap<FrameIndex, Vec<DtlsMessage>>,
+}
+
+// session id then cookie, after client_version and random
+fn client_hello_cookie(body: &[u8]) -> Option<&[u8]> {
+    let sid_len = *body.get(34)? as usize;
+    let at = 35 + sid_len;
+    let len = *body.get(at)? as usize;
+    body.get(at + 1..at + 1 + len)
+}
+
+fn hello_verify_cookie(body: &[u8]) -> Option<&[u8]> {
+    let len = *body.get(2)? as usize;
+    body.get(3..3 + len)
+}
+
+impl DtlsTable {
+    pub fn add(&mut self, index: FrameIndex, source: DtlsEndpoint, target: DtlsEndpoint, header: &DtlsHandshakeHeader, fragment: &[u8]) -> DtlsFragment {
+        let flow = self.flows.entry((source, target)).or_default();
+        let end = header.offset + fragment.len();
+        if let Some(first) = flow.seen.get(&(header.message_seq, header.offset, fragment.len())) {
+            return DtlsFragment::Retransmission(*first);
+        }
+        flow.seen.insert((header.message_seq, header.offset, fragment.len()), index);
+        let whole = header.offset == 0 && fragment.len() == header.length;
+        if header.length > MESSAGE_LIMIT || end > header.length {
+            return DtlsFragment::Partial;
+        }
+        let (body, fragments) = match whole {
+            true => (fragment.to_vec(), 1),
+            false => {
+                let pending = flow.pending.entry(header.message_seq).or_insert_with(|| PendingMessage { data: vec![0; header.length], ..Default::default() });
+                // a fragment announcing another length starts the message over
+                if pending.data.len() != header.length {
+                    *pending = PendingMessage { data: vec![0; header.length], ..Default::default() };
+                }
+                if !pending.add(header.offset, fragment) {
+                    return DtlsFragment::Partial;
+                }
+                let done = flow.pending.remove(&header.message_seq).unwrap_or_default();
+                (done.data, done.fragments)
+            }
+        };
+        let mut cookie = None;
+        match header.msg_type {
+            DTLS_HELLO_VERIFY_REQUEST => {
+                if let Some(value) = hello_verify_cookie(&body) {
+                    self.flows.entry((target, source)).or_default().cookie = Some((index, value.to_vec()));
+                }
+            }
+            DTLS_CLIENT_HELLO => {
+                if let (Some((hvr, expected)), Some(value)) = (&flow.cookie, client_hello_cookie(&body)) {
+                    cookie = Some(match expected.as_slice() == value {
+                        true => CookieCheck::Echoed(*hvr),
+                        false => CookieCheck::Mismatch(*hvr),
+                    });
+                }
+            }
+            _ => {}
+        }
+        let mut data = Vec::with_capacity(DTLS_HANDSHAKE_HEADER + body.len());
+        let length = (header.length as u32).to_be_bytes();
+        data.push(header.msg_type);
+        data.extend_from_slice(&length[1..]);
+        data.extend_from_slice(&header.message_seq.to_be_bytes());
+        data.extend_from_slice(&[0, 0, 0]);
+        data.extend_from_slice(&length[1..]);
+        data.extend_from_slice(&body);
+        self.messages.entry(index).or_default().push(DtlsMessage { message_seq: header.message_seq, msg_type: header.msg_type, fragments, data, cookie });
+        match whole {
+            true => DtlsFragment::Whole,
+            false => DtlsFragment::Reassembled(fragments),
+        }
+    }
+
+    pub fn messages(&self, index: FrameIndex) -> &[DtlsMessage] {
+        self.messages.get(&index).map(|list| list.as_slice()).unwrap_or(&[])
+    }
+}
@@ -12,7 +12,7 @@ use thiserror::Error;
 
 use crate::{common::concept::{MessageIndex, NameService}, protocol::transport::tls::TLSList};
 
-use super::{connection::{TCPSegment, TLSSegment}, io::MacAddress};
+use super::{connection::{TCPSegment, TLSSegment}, io::MacAddress, quic::QuicInfo, dtls::DtlsRecordInfo};
 
 
 #[derive(Debug, EnumString, Display)]
@@ -60,7 +60,8 @@ pub enum FileType {
     NONE,
 }
 
-#[derive(Default, Display, Debug, Clone, Copy, Serialize)]
+#[derive(Default, Display, EnumString, Debug, Clone, Copy, PartialEq, Serialize)]
+#[strum(ascii_case_insensitive)]
 pub enum Protocol {
     #[default]
     None,
@@ -78,16 +79,26 @@ pub enum Protocol {
     RADIOTAP,
     IEEE1905A,
     IGMP,
+    OSPF,
+    VRRP,
+    AH,
+    ESP,
     UDP,
     TCP,
+    SCTP,
     DNS,
     MDNS,
     NBNS,
     DHCP,
     DHCP6,
+    HSRP,
     HTTP,
+    HTTP2,
+    WEBSOCKET,
     HTTPS,
     TLS,
+    QUIC,
+    DTLS,
     IEEE802_11,
 }
 
@@ -95,10 +106,39 @@ pub enum Protocol {
 pub enum TCPDetail {
     KEEPALIVE,
     NOPREVCAPTURE,
+    // held until the missing bytes before it arrive
+    OUTOFORDER,
     RETRANSMISSION,
     DUMP,
     RESET,
     NEXT,
+    // a single byte pushed into a closed window, the stream does not advance
+    ZEROWINDOWPROBE,
+}
+
+/**
+ * expert analysis of a segment, a frame may carry several of them
+ */
+#[derive(Display, Debug, Clone, Copy, PartialEq)]
+pub enum TCPExpert {
+    #[strum(serialize = "TCP Dup ACK")]
+    DupAck,
+    #[strum(serialize = "TCP Fast Retransmission")]
+    FastRetransmission,
+    #[strum(serialize = "TCP Spurious Retransmission")]
+    SpuriousRetransmission,
+    #[strum(serialize = "TCP Retransmission")]
+    Retransmission,
+    #[strum(serialize = "TCP ZeroWindow")]
+    ZeroWindow,
+    #[strum(serialize = "TCP Window Update")]
+    WindowUpdate,
+    #[strum(serialize = "TCP ZeroWindowProbe")]
+    ZeroWindowProbe,
+    #[strum(serialize = "TCP Window Full")]
+    WindowFull,
+    #[strum(serialize = "TCP Out-Of-Order")]
+    OutOfOrder,
 }
 
 #[derive(Display, Debug, Clone, Copy)]
@@ -122,6 +162,18 @@ pub enum PacketStatus {
     ERROR,
 }
 
+/**
+ * how a tcp connection ended, Open while neither a reset nor both fins were seen
+ */
+#[derive(Default, Clone, Copy, PartialEq, Debug, serde::Serialize)]
+pub enum TCPCloseReason {
+    #[default]
+    Open,
+    Fin,
+    PrimaryReset,
+    SecondReset,
+}
+
 #[derive(Default, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum TCPConnectStatus {
@@ -178,15 +230,26 @@ pub enum ProtocolInfoField {
     HttpSegment(usize),
     Icmp(u8, u8),
     Icmp6(u8, u8),
+    Igmp(u8, u8, Ipv4Addr, u16),
+    Ospf(u8, u8, Ipv4Addr),
+    Vrrp(u8, u8, u8),
+    Ipsec(u8, u32, u32),
     PPPoES(Option<u8>),
     UDP(u16),
+    // chunk types in packet order
+    Sctp(Vec<u8>),
     ARP(u16, u16, MacAddress, Ipv4Addr, MacAddress, Ipv4Addr),
     RARP(u16, u16, MacAddress, Ipv4Addr, MacAddress, Ipv4Addr),
     DHCP(u8),
     DHCPv6(u8, u32),
+    Hsrp(u8, u8, u8, u16),
     DNSQUERY(NameService, u16, usize),
     DNSRESPONSE(NameService, u16, usize),
     TLS(TLSList),
     TLSSegment,
+    // packets coalesced in the datagram
+    Quic(Vec<QuicInfo>),
+    // records in the datagram
+    Dtls(Vec<DtlsRecordInfo>),
     Ieee80211(u16),
 }
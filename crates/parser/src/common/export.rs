@@ -0,0 +1,196 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::common::concept::FrameIndex;
+
+// longest file name written, extension included
+const NAME_LIMIT: usize = 120;
+
+/**
+ * a response body that can be saved as a file
+ */
+#[derive(Serialize, Clone, Debug)]
+pub struct HttpObject {
+    // index of the http connection
+    pub index: usize,
+    // frame the response starts in
+    pub frame: FrameIndex,
+    pub hostname: String,
+    pub url: String,
+    // unique within the capture and safe to write
+    pub filename: String,
+    pub content_type: String,
+    // body bytes on the wire, before the content encoding is undone
+    pub size: usize,
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut rs = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                rs.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                rs.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&rs).to_string()
+}
+
+/**
+ * the file name of a Content-Disposition value, filename* preferred over filename
+ */
+pub fn disposition_filename(value: &str) -> Option<String> {
+    let params: Vec<(String, &str)> = value
+        .split(';')
+        .skip(1)
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((key.trim().to_ascii_lowercase(), value.trim().trim_matches('"')))
+        })
+        .collect();
+    if let Some((_, value)) = params.iter().find(|(key, _)| key == "filename*") {
+        // charset'language'value
+        let name = value.splitn(3, '\'').nth(2).unwrap_or(value);
+        return Some(percent_decode(name));
+    }
+    params.iter().find(|(key, _)| key == "filename").map(|(_, value)| value.to_string())
+}
+
+/**
+ * the last path segment of the request target, without query or fragment
+ */
+pub fn url_filename(target: &str) -> Option<String> {
+    let path = target.split(['?', '#']).next()?;
+    // absolute form, sent to proxies
+    let path = match path.split_once("://") {
+        Some((_, rest)) => rest.find('/').map(|pos| &rest[pos..]).unwrap_or("/"),
+        None => path,
+    };
+    let name = path.rsplit('/').next()?;
+    (!name.is_empty()).then(|| percent_decode(name))
+}
+
+fn mime_extension(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    let ext = match mime.as_str() {
+        "text/html" => "html",
+        "text/plain" => "txt",
+        "text/css" => "css",
+        "text/csv" => "csv",
+        "text/javascript" | "application/javascript" | "application/x-javascript" => "js",
+        "application/json" => "json",
+        "application/xml" | "text/xml" => "xml",
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        "application/gzip" => "gz",
+        "application/wasm" => "wasm",
+        "application/x-msdownload" | "application/x-dosexec" => "exe",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/svg+xml" => "svg",
+        "image/webp" => "webp",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        _ => return None,
+    };
+    Some(ext)
+}
+
+/**
+ * a name that stays inside the target directory on any platform and is no windows device
+ */
+pub fn safe_filename(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let rs: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' }).collect();
+    let rs = rs.trim_start_matches('.');
+    let rs = match rs.len() > NAME_LIMIT {
+        // keep the extension of a long name
+        true => match rs.rfind('.').filter(|pos| rs.len() - pos <= 16) {
+            Some(pos) => format!("{}{}", &rs[..NAME_LIMIT - (rs.len() - pos)], &rs[pos..]),
+            None => rs[..NAME_LIMIT].to_string(),
+        },
+        false => rs.to_string(),
+    };
+    // windows drops trailing dots
+    let rs = rs.trim_end_matches('.');
+    match rs.is_empty() {
+        true => "object".into(),
+        false if reserved_name(rs) => format!("_{rs}"),
+        false => rs.to_string(),
+    }
+}
+
+// windows device names, with any extension
+fn reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default().to_ascii_uppercase();
+    match stem.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => (stem.starts_with("COM") || stem.starts_with("LPT")) && stem.len() == 4 && matches!(stem.as_bytes()[3], b'1'..=b'9'),
+    }
+}
+
+/**
+ * the name an object is saved under, before it is made unique
+ */
+pub fn object_filename(target: &str, disposition: Option<&str>, content_type: &str) -> String {
+    let name = disposition.and_then(disposition_filename).or_else(|| url_filename(target)).unwrap_or_else(|| "index".into());
+    let mut name = safe_filename(&name);
+    if !name.contains('.') {
+        if let Some(ext) = mime_extension(content_type) {
+            name = format!("{name}.{ext}");
+        }
+    }
+    name
+}
+
+// name(n).ext
+fn numbered(name: &str, n: usize) -> String {
+    match name.rfind('.').filter(|pos| *pos > 0) {
+        Some(pos) => format!("{}({n}){}", &name[..pos], &name[pos..]),
+        None => format!("{name}({n})"),
+    }
+}
+
+/**
+ * numbers a name already taken
+ */
+pub fn unique_filename(name: String, taken: &mut HashSet<String>) -> String {
+    let mut rs = name.clone();
+    let mut n = 0;
+    while taken.contains(&rs) {
+        n += 1;
+        rs = numbered(&name, n);
+    }
+    taken.insert(rs.clone());
+    rs
+}
+
+/**
+ * a path under dir that does not replace an existing file
+ */
+pub fn free_path(dir: &Path, name: &str) -> PathBuf {
+    let mut path = dir.join(name);
+    let mut n = 0;
+    while path.exists() {
+        n += 1;
+        path = dir.join(numbered(name, n));
+    }
+    path
+}
@@ -0,0 +1,158 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::net::IpAddr;
+
+use serde::Serialize;
+
+use crate::common::{
+    concept::{FrameIndex, Timestamp},
+    FastHashMap,
+};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FhrpProtocol {
+    VRRP,
+    HSRP,
+}
+
+/**
+ * what an advertisement or hello says about its sender
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FhrpRole {
+    // vrrp advertisement or hsrp hello in active state
+    Master,
+    // vrrp priority 0 or hsrp resign
+    Resign,
+    Backup,
+}
+
+pub struct FhrpAdvertisement {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub protocol: FhrpProtocol,
+    pub version: u8,
+    pub group: u16,
+    pub router: IpAddr,
+    pub priority: u32,
+    pub role: FhrpRole,
+    pub virtual_ips: Vec<IpAddr>,
+}
+
+/**
+ * a period one router held the master (vrrp) or active (hsrp) role
+ */
+#[derive(Serialize, Clone, Debug)]
+pub struct FhrpTerm {
+    pub router: IpAddr,
+    pub priority: u32,
+    pub index: FrameIndex,
+    pub start: Timestamp,
+    // last advertisement of the term
+    pub end: Timestamp,
+    pub packets: u32,
+    pub resigned: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FhrpRouter {
+    pub address: IpAddr,
+    pub priority: u32,
+    pub packets: u32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FhrpGroup {
+    pub protocol: FhrpProtocol,
+    pub version: u8,
+    pub group: u16,
+    pub virtual_ips: Vec<IpAddr>,
+    pub routers: Vec<FhrpRouter>,
+    pub terms: Vec<FhrpTerm>,
+}
+
+impl FhrpGroup {
+    fn new(advertisement: &FhrpAdvertisement) -> Self {
+        Self { protocol: advertisement.protocol, version: advertisement.version, group: advertisement.group, virtual_ips: vec![], routers: vec![], terms: vec![] }
+    }
+
+    fn current(&self) -> Option<&FhrpTerm> {
+        self.terms.last().filter(|term| !term.resigned)
+    }
+}
+
+/**
+ * master election of vrrp and hsrp groups. A group is identified by protocol, group id and
+ * address family, the capture is assumed to hold one lan per group id
+ */
+#[derive(Default)]
+pub struct FhrpTable {
+    groups: Vec<FhrpGroup>,
+    keys: FastHashMap<(FhrpProtocol, u16, bool), usize>,
+    // frames where the role moved to another router, with the router that held it before
+    takeovers: FastHashMap<FrameIndex, Option<IpAddr>>,
+}
+
+impl FhrpTable {
+    pub fn add(&mut self, advertisement: FhrpAdvertisement) {
+        let key = (advertisement.protocol, advertisement.group, advertisement.router.is_ipv6());
+        let inx = *self.keys.entry(key).or_insert_with(|| {
+            self.groups.push(FhrpGroup::new(&advertisement));
+            self.groups.len() - 1
+        });
+        let group = &mut self.groups[inx];
+        for ip in &advertisement.virtual_ips {
+            if !group.virtual_ips.contains(ip) {
+                group.virtual_ips.push(*ip);
+            }
+        }
+        match group.routers.iter_mut().find(|r| r.address == advertisement.router) {
+            Some(router) => {
+                router.priority = advertisement.priority;
+                router.packets += 1;
+            }
+            None => group.routers.push(FhrpRouter { address: advertisement.router, priority: advertisement.priority, packets: 1 }),
+        }
+
+        let FhrpAdvertisement { index, ts, router, priority, .. } = advertisement;
+        match advertisement.role {
+            FhrpRole::Master => {
+                if let Some(term) = group.terms.last_mut().filter(|t| !t.resigned && t.router == router) {
+                    term.end = ts;
+                    term.packets += 1;
+                    term.priority = priority;
+                    return;
+                }
+                let previous = group.terms.last().map(|t| t.router);
+                group.terms.push(FhrpTerm { router, priority, index, start: ts, end: ts, packets: 1, resigned: false });
+                self.takeovers.insert(index, previous);
+            }
+            FhrpRole::Resign => {
+                if let Some(term) = group.terms.last_mut().filter(|t| !t.resigned && t.router == router) {
+                    term.end = ts;
+                    term.resigned = true;
+                }
+            }
+            FhrpRole::Backup => {}
+        }
+    }
+
+    /**
+     * the router a frame took the master role from, None inside the option for the first master of a group
+     */
+    pub fn takeover(&self, index: FrameIndex) -> Option<Option<IpAddr>> {
+        self.takeovers.get(&index).copied()
+    }
+
+    pub fn master(&self, protocol: FhrpProtocol, group: u16, ipv6: bool) -> Option<IpAddr> {
+        let inx = self.keys.get(&(protocol, group, ipv6))?;
+        self.groups[*inx].current().map(|term| term.router)
+    }
+
+    pub fn groups(&self) -> &[FhrpGroup] {
+        &self.groups
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::net::IpAddr;
+
+use anyhow::{bail, Result};
+use maxminddb::{geoip2, Reader};
+use serde::Serialize;
+
+use crate::common::{core::Context, ipsec::frame_ips, FastHashMap};
+
+const UNKNOWN: &str = "Unknown";
+
+/**
+ * what the loaded databases know about an address
+ */
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct GeoInfo {
+    // iso 3166 code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asn: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub as_org: Option<String>,
+}
+
+impl GeoInfo {
+    fn is_empty(&self) -> bool {
+        self.country.is_none() && self.city.is_none() && self.asn.is_none()
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct GeoCounter {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub packets: usize,
+    pub bytes: usize,
+}
+
+/**
+ * traffic per country and per autonomous system, a frame counts once for each distinct key of its two addresses
+ */
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct GeoStat {
+    pub countries: Vec<GeoCounter>,
+    pub asns: Vec<GeoCounter>,
+}
+
+fn english(names: &Option<std::collections::BTreeMap<&str, &str>>) -> Option<String> {
+    names.as_ref().and_then(|n| n.get("en")).map(|s| s.to_string())
+}
+
+/**
+ * local maxmind format databases, one slot per kind. Nothing is fetched, the caller hands over the file content
+ */
+#[derive(Default)]
+pub struct GeoIp {
+    country: Option<Reader<Vec<u8>>>,
+    city: Option<Reader<Vec<u8>>>,
+    asn: Option<Reader<Vec<u8>>>,
+}
+
+impl GeoIp {
+    /**
+     * loads an .mmdb file into the slot its database type names, replacing the previous one. Returns the database type
+     */
+    pub fn load(&mut self, data: Vec<u8>) -> Result<String> {
+        let reader = Reader::from_source(data)?;
+        let kind = reader.metadata.database_type.clone();
+        if kind.contains("ASN") {
+            self.asn = Some(reader);
+        } else if kind.contains("City") {
+            self.city = Some(reader);
+        } else if kind.contains("Country") {
+            self.country = Some(reader);
+        } else {
+            bail!("unsupported database type {kind}");
+        }
+        Ok(kind)
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.country.is_some() || self.city.is_some() || self.asn.is_some()
+    }
+
+    pub fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+        if !self.is_loaded() {
+            return None;
+        }
+        let mut info = GeoInfo::default();
+        // a city database carries the country as well
+        if let Some(record) = self.city.as_ref().and_then(|r| r.lookup::<geoip2::City>(ip).ok()) {
+            info.city = record.city.and_then(|c| english(&c.names));
+            if let Some(country) = record.country {
+                info.country = country.iso_code.map(|s| s.to_string());
+                info.country_name = english(&country.names);
+            }
+        }
+        if info.country.is_none() {
+            if let Some(country) = self.country.as_ref().and_then(|r| r.lookup::<geoip2::Country>(ip).ok()).and_then(|r| r.country) {
+                info.country = country.iso_code.map(|s| s.to_string());
+                info.country_name = english(&country.names);
+            }
+        }
+        if let Some(record) = self.asn.as_ref().and_then(|r| r.lookup::<geoip2::Asn>(ip).ok()) {
+            info.asn = record.autonomous_system_number;
+            info.as_org = record.autonomous_system_organization.map(|s| s.to_string());
+        }
+        match info.is_empty() {
+            true => None,
+            false => Some(info),
+        }
+    }
+
+    pub fn lookup_str(&self, ip: &str) -> Option<GeoInfo> {
+        self.lookup(ip.parse().ok()?)
+    }
+
+    pub fn traffic(&self, ctx: &Context) -> GeoStat {
+        if !self.is_loaded() {
+            return GeoStat::default();
+        }
+        let mut cache: FastHashMap<IpAddr, Option<GeoInfo>> = FastHashMap::default();
+        let mut countries: FastHashMap<String, GeoCounter> = FastHashMap::default();
+        let mut asns: FastHashMap<String, GeoCounter> = FastHashMap::default();
+        let count = |map: &mut FastHashMap<String, GeoCounter>, key: String, name: Option<String>, len: usize| {
+            let counter = map.entry(key.clone()).or_insert(GeoCounter { key, name, packets: 0, bytes: 0 });
+            counter.packets += 1;
+            counter.bytes += len;
+        };
+        for frame in &ctx.list {
+            let (source, target) = frame_ips(ctx, frame);
+            let ips: Vec<IpAddr> = [source, target].into_iter().flatten().collect();
+            if ips.is_empty() {
+                continue;
+            }
+            let len = frame.info.len as usize;
+            let mut country_keys = vec![];
+            let mut asn_keys = vec![];
+            for ip in ips {
+                let info = cache.entry(ip).or_insert_with(|| self.lookup(ip));
+                let country = info.as_ref().and_then(|i| i.country.clone().map(|c| (c, i.country_name.clone())));
+                let asn = info.as_ref().and_then(|i| i.asn.map(|a| (format!("AS{a}"), i.as_org.clone())));
+                let country = country.unwrap_or((UNKNOWN.into(), None));
+                let asn = asn.unwrap_or((UNKNOWN.into(), None));
+                if !country_keys.iter().any(|(k, _)| *k == country.0) {
+                    country_keys.push(country);
+                }
+                if !asn_keys.iter().any(|(k, _)| *k == asn.0) {
+                    asn_keys.push(asn);
+                }
+            }
+            for (key, name) in country_keys {
+                count(&mut countries, key, name, len);
+            }
+            for (key, name) in asn_keys {
+                count(&mut asns, key, name, len);
+            }
+        }
+        let sorted = |map: FastHashMap<String, GeoCounter>| {
+            let mut list: Vec<GeoCounter> = map.into_values().collect();
+            list.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.key.cmp(&b.key)));
+            list
+        };
+        GeoStat { countries: sorted(countries), asns: sorted(asns) }
+    }
+}
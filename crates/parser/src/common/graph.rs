@@ -0,0 +1,103 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use serde::Serialize;
+
+use crate::common::{
+    concept::{FrameIndex, LineChartData, Timestamp},
+    connection::Connection,
+};
+
+const THROUGHPUT_BUCKETS: u64 = 200;
+
+/**
+ * one segment of a connection as the sequence graphs see it, numbers relative to the initial sequence
+ */
+#[derive(Clone, Debug)]
+pub struct SegmentPoint {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    // sent by the primary endpoint
+    pub primary: bool,
+    pub seq: u32,
+    pub len: u16,
+    // 0 without the ack flag
+    pub ack: u32,
+    pub window: u32,
+    pub retransmission: bool,
+}
+
+/**
+ * throughput and tcptrace style sequence series of one connection. Sequence series have one column per point with
+ * rows seq, next, ack, window and retransmission, ack and window are what the other side advertised so far
+ */
+#[derive(Serialize, Default)]
+pub struct ConnectionGraph {
+    // payload bytes per time bucket, rows forward and backward
+    pub throughput: LineChartData,
+    pub forward: LineChartData,
+    pub backward: LineChartData,
+    pub retransmissions: Vec<FrameIndex>,
+}
+
+fn throughput(points: &[SegmentPoint]) -> LineChartData {
+    // merged captures are not in time order, the period spans the earliest to the latest point
+    let (Some(first), Some(last)) = (points.iter().map(|p| p.ts).min(), points.iter().map(|p| p.ts).max()) else {
+        return LineChartData::default();
+    };
+    let period = last - first;
+    let step = period.div_ceil(THROUGHPUT_BUCKETS).max(1);
+    let size = (period / step + 1) as usize;
+    let mut forward = vec![0; size];
+    let mut backward = vec![0; size];
+    for point in points {
+        let bucket = ((point.ts - first) / step) as usize;
+        let list = match point.primary {
+            true => &mut forward,
+            false => &mut backward,
+        };
+        list[bucket] += point.len as u32;
+    }
+    let x_axis = (0..size as u64).map(|i| first + i * step).collect();
+    LineChartData::new(x_axis, vec!["forward".into(), "backward".into()], vec![forward, backward])
+}
+
+fn sequence(points: &[SegmentPoint], primary: bool) -> LineChartData {
+    let mut x_axis = vec![];
+    let mut rows: Vec<Vec<u32>> = vec![vec![]; 5];
+    let (mut seq, mut next, mut ack, mut window) = (0, 0, 0, 0);
+    for point in points {
+        let retransmission = point.primary == primary && point.retransmission;
+        if point.primary == primary {
+            if point.len == 0 && !point.retransmission {
+                continue;
+            }
+            seq = point.seq;
+            next = point.seq.wrapping_add(point.len as u32);
+        } else {
+            if point.ack == 0 {
+                continue;
+            }
+            ack = point.ack;
+            window = point.ack.wrapping_add(point.window);
+        }
+        x_axis.push(point.ts);
+        for (row, value) in rows.iter_mut().zip([seq, next, ack, window, retransmission as u32]) {
+            row.push(value);
+        }
+    }
+    let names = ["seq", "next", "ack", "window", "retransmission"];
+    LineChartData::new(x_axis, names.iter().map(|n| (*n).into()).collect(), rows)
+}
+
+pub fn connection_graph(connection: &Connection) -> ConnectionGraph {
+    let points = &connection.points;
+    ConnectionGraph {
+        throughput: throughput(points),
+        forward: sequence(points, true),
+        backward: sequence(points, false),
+        retransmissions: points.iter().filter(|p| p.retransmission).map(|p| p.index).collect(),
+    }
+}
@@ -0,0 +1,293 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::collections::VecDeque;
+
+use anyhow::{bail, Result};
+
+// dynamic table size before a SETTINGS_HEADER_TABLE_SIZE or size update says otherwise
+pub const DEFAULT_TABLE_SIZE: usize = 4096;
+
+// every entry counts its name and value plus 32 bytes of overhead
+const ENTRY_OVERHEAD: usize = 32;
+
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+// first code of each bit length, canonical huffman code of rfc 7541 appendix b
+const FIRST: [u32; 31] = [
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x14, 0x5c,
+    0xf8, 0x0, 0x3f8, 0x7fa, 0xffa, 0x1ff8, 0x3ffc, 0x7ffc,
+    0x0, 0x0, 0x0, 0x7fff0, 0xfffe6, 0x1fffdc, 0x3fffd2, 0x7fffd8,
+    0xffffea, 0x1ffffec, 0x3ffffe0, 0x7ffffde, 0xfffffe2, 0x0, 0x3ffffffc,
+];
+const COUNT: [u16; 31] = [
+    0, 0, 0, 0, 0, 10, 26, 32, 6, 0, 5, 3, 2, 6, 2, 3,
+    0, 0, 0, 3, 8, 13, 26, 29, 12, 4, 15, 19, 29, 0, 4,
+];
+// symbols ordered by code length then code, 256 is eos
+const SYMBOLS: [u16; 257] = [
+    48, 49, 50, 97, 99, 101, 105, 111, 115, 116, 32, 37, 45, 46, 47, 51,
+    52, 53, 54, 55, 56, 57, 61, 65, 95, 98, 100, 102, 103, 104, 108, 109,
+    110, 112, 114, 117, 58, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76,
+    77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 89, 106, 107, 113, 118,
+    119, 120, 121, 122, 38, 42, 44, 59, 88, 90, 33, 34, 40, 41, 63, 39,
+    43, 124, 35, 62, 0, 36, 64, 91, 93, 126, 94, 125, 60, 96, 123, 92,
+    195, 208, 128, 130, 131, 162, 184, 194, 224, 226, 153, 161, 167, 172, 176, 177,
+    179, 209, 216, 217, 227, 229, 230, 129, 132, 133, 134, 136, 146, 154, 156, 160,
+    163, 164, 169, 170, 173, 178, 181, 185, 186, 187, 189, 190, 196, 198, 228, 232,
+    233, 1, 135, 137, 138, 139, 140, 141, 143, 147, 149, 150, 151, 152, 155, 157,
+    158, 165, 166, 168, 174, 175, 180, 182, 183, 188, 191, 197, 231, 239, 9, 142,
+    144, 145, 148, 159, 171, 206, 215, 225, 236, 237, 199, 207, 234, 235, 192, 193,
+    200, 201, 202, 205, 210, 213, 218, 219, 238, 240, 242, 243, 255, 203, 204, 211,
+    212, 214, 221, 222, 223, 241, 244, 245, 246, 247, 248, 250, 251, 252, 253, 254,
+    2, 3, 4, 5, 6, 7, 8, 11, 12, 14, 15, 16, 17, 18, 19, 20,
+    21, 23, 24, 25, 26, 27, 28, 29, 30, 31, 127, 220, 249, 10, 13, 22,
+    256,
+];
+
+/**
+ * how a header field was represented in the block
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HpackRepresentation {
+    Indexed,
+    IncrementalIndexing,
+    WithoutIndexing,
+    NeverIndexed,
+}
+
+#[derive(Clone, Debug)]
+pub struct HeaderField {
+    pub name: String,
+    pub value: String,
+    pub representation: HpackRepresentation,
+}
+
+impl HeaderField {
+    pub fn line(&self) -> String {
+        format!("{}: {}", self.name, self.value)
+    }
+}
+
+/**
+ * integer with an n bit prefix, the prefix bits of the first byte belong to the value
+ */
+fn read_integer(data: &[u8], at: &mut usize, prefix: u8) -> Result<usize> {
+    let max = (1usize << prefix) - 1;
+    let Some(first) = data.get(*at) else {
+        bail!("hpack: truncated integer");
+    };
+    *at += 1;
+    let mut value = (*first as usize) & max;
+    if value < max {
+        return Ok(value);
+    }
+    let mut shift = 0;
+    loop {
+        let Some(byte) = data.get(*at) else {
+            bail!("hpack: truncated integer");
+        };
+        *at += 1;
+        let bits = (*byte & 0x7f) as usize;
+        // usize is 32 bits on wasm, no bit may be shifted out
+        let part = bits.checked_shl(shift).filter(|part| part >> shift == bits);
+        match part.and_then(|part| value.checked_add(part)) {
+            Some(sum) if shift <= 28 => value = sum,
+            _ => bail!("hpack: integer overflow"),
+        }
+        shift += 7;
+        if *byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+pub fn huffman_decode(data: &[u8]) -> Result<Vec<u8>> {
+    let mut rs = Vec::with_capacity(data.len() * 8 / 5);
+    let (mut code, mut len, mut offset) = (0u32, 0usize, 0usize);
+    for byte in data {
+        for shift in (0..8).rev() {
+            code = (code << 1) | ((*byte >> shift) & 0x01) as u32;
+            len += 1;
+            let count = COUNT[len] as u32;
+            if count > 0 && code >= FIRST[len] && code - FIRST[len] < count {
+                let symbol = SYMBOLS[offset + (code - FIRST[len]) as usize];
+                if symbol == 256 {
+                    bail!("hpack: eos in huffman string");
+                }
+                rs.push(symbol as u8);
+                (code, len, offset) = (0, 0, 0);
+                continue;
+            }
+            offset += count as usize;
+            if len >= 30 {
+                bail!("hpack: bad huffman code");
+            }
+        }
+    }
+    // the last symbol is padded with the most significant bits of eos
+    if len > 7 || code != (1 << len) - 1 {
+        bail!("hpack: bad huffman padding");
+    }
+    Ok(rs)
+}
+
+fn read_string(data: &[u8], at: &mut usize) -> Result<String> {
+    let huffman = data.get(*at).map(|b| b & 0x80 != 0).unwrap_or(false);
+    let len = read_integer(data, at, 7)?;
+    let Some(end) = at.checked_add(len) else {
+        bail!("hpack: integer overflow");
+    };
+    let Some(raw) = data.get(*at..end) else {
+        bail!("hpack: truncated string");
+    };
+    *at = end;
+    let value = match huffman {
+        true => huffman_decode(raw)?,
+        false => raw.to_vec(),
+    };
+    Ok(String::from_utf8_lossy(&value).to_string())
+}
+
+/**
+ * the decoding context of one direction of a connection, the dynamic table lives across header blocks
+ */
+#[derive(Clone, Debug)]
+pub struct HpackDecoder {
+    table: VecDeque<(String, String)>,
+    size: usize,
+    max_size: usize,
+}
+
+impl Default for HpackDecoder {
+    fn default() -> Self {
+        Self { table: VecDeque::new(), size: 0, max_size: DEFAULT_TABLE_SIZE }
+    }
+}
+
+impl HpackDecoder {
+    fn get(&self, index: usize) -> Result<(String, String)> {
+        let entry = match index {
+            0 => None,
+            1..=61 => STATIC_TABLE.get(index - 1).map(|(name, value)| (name.to_string(), value.to_string())),
+            _ => self.table.get(index - 62).cloned(),
+        };
+        match entry {
+            Some(entry) => Ok(entry),
+            None => bail!("hpack: index {index} out of table"),
+        }
+    }
+    fn evict(&mut self) {
+        while self.size > self.max_size {
+            match self.table.pop_back() {
+                Some((name, value)) => self.size -= name.len() + value.len() + ENTRY_OVERHEAD,
+                None => break,
+            }
+        }
+    }
+    fn insert(&mut self, name: String, value: String) {
+        self.size += name.len() + value.len() + ENTRY_OVERHEAD;
+        self.table.push_front((name, value));
+        self.evict();
+    }
+    pub fn set_max_size(&mut self, size: usize) {
+        self.max_size = size;
+        self.evict();
+    }
+    pub fn decode(&mut self, block: &[u8]) -> Result<Vec<HeaderField>> {
+        let mut list = vec![];
+        let mut at = 0;
+        while at < block.len() {
+            let head = block[at];
+            let (representation, prefix) = match head {
+                _ if head & 0x80 != 0 => {
+                    let (name, value) = self.get(read_integer(block, &mut at, 7)?)?;
+                    list.push(HeaderField { name, value, representation: HpackRepresentation::Indexed });
+                    continue;
+                }
+                _ if head & 0xc0 == 0x40 => (HpackRepresentation::IncrementalIndexing, 6),
+                _ if head & 0xe0 == 0x20 => {
+                    let size = read_integer(block, &mut at, 5)?;
+                    self.set_max_size(size);
+                    continue;
+                }
+                _ if head & 0xf0 == 0x10 => (HpackRepresentation::NeverIndexed, 4),
+                _ => (HpackRepresentation::WithoutIndexing, 4),
+            };
+            let name = match read_integer(block, &mut at, prefix)? {
+                0 => read_string(block, &mut at)?,
+                index => self.get(index)?.0,
+            };
+            let value = read_string(block, &mut at)?;
+            if representation == HpackRepresentation::IncrementalIndexing {
+                self.insert(name.clone(), value.clone());
+            }
+            list.push(HeaderField { name, value, representation });
+        }
+        Ok(list)
+    }
+}
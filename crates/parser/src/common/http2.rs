@@ -0,0 +1,334 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::ops::Range;
+
+use crate::common::{
+    concept::{ConnectionIndex, FrameIndex, HttpConnectIndex, MessageIndex, Timestamp},
+    hpack::{HeaderField, HpackDecoder},
+    FastHashMap,
+};
+
+pub const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+pub const FRAME_HEADER: usize = 9;
+
+pub const DATA: u8 = 0;
+pub const HEADERS: u8 = 1;
+pub const PRIORITY: u8 = 2;
+pub const RST_STREAM: u8 = 3;
+pub const SETTINGS: u8 = 4;
+pub const PUSH_PROMISE: u8 = 5;
+pub const PING: u8 = 6;
+pub const GOAWAY: u8 = 7;
+pub const WINDOW_UPDATE: u8 = 8;
+pub const CONTINUATION: u8 = 9;
+
+// flags, ACK shares the bit of END_STREAM on SETTINGS and PING
+pub const FLAG_END_STREAM: u8 = 0x01;
+pub const FLAG_ACK: u8 = 0x01;
+pub const FLAG_END_HEADERS: u8 = 0x04;
+pub const FLAG_PADDED: u8 = 0x08;
+pub const FLAG_PRIORITY: u8 = 0x20;
+
+pub fn http2_frame_type_mapper(frame_type: u8) -> &'static str {
+    match frame_type {
+        DATA => "DATA",
+        HEADERS => "HEADERS",
+        PRIORITY => "PRIORITY",
+        RST_STREAM => "RST_STREAM",
+        SETTINGS => "SETTINGS",
+        PUSH_PROMISE => "PUSH_PROMISE",
+        PING => "PING",
+        GOAWAY => "GOAWAY",
+        WINDOW_UPDATE => "WINDOW_UPDATE",
+        CONTINUATION => "CONTINUATION",
+        _ => "Unknown",
+    }
+}
+
+pub fn http2_settings_mapper(id: u16) -> &'static str {
+    match id {
+        1 => "SETTINGS_HEADER_TABLE_SIZE",
+        2 => "SETTINGS_ENABLE_PUSH",
+        3 => "SETTINGS_MAX_CONCURRENT_STREAMS",
+        4 => "SETTINGS_INITIAL_WINDOW_SIZE",
+        5 => "SETTINGS_MAX_FRAME_SIZE",
+        6 => "SETTINGS_MAX_HEADER_LIST_SIZE",
+        8 => "SETTINGS_ENABLE_CONNECT_PROTOCOL",
+        _ => "Unknown",
+    }
+}
+
+pub fn http2_error_mapper(code: u32) -> &'static str {
+    match code {
+        0 => "NO_ERROR",
+        1 => "PROTOCOL_ERROR",
+        2 => "INTERNAL_ERROR",
+        3 => "FLOW_CONTROL_ERROR",
+        4 => "SETTINGS_TIMEOUT",
+        5 => "STREAM_CLOSED",
+        6 => "FRAME_SIZE_ERROR",
+        7 => "REFUSED_STREAM",
+        8 => "CANCEL",
+        9 => "COMPRESSION_ERROR",
+        10 => "CONNECT_ERROR",
+        11 => "ENHANCE_YOUR_CALM",
+        12 => "INADEQUATE_SECURITY",
+        13 => "HTTP_1_1_REQUIRED",
+        _ => "Unknown",
+    }
+}
+
+/**
+ * a frame completed in a packet
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Http2Frame {
+    pub frame_type: u8,
+    pub flags: u8,
+    pub stream: u32,
+    pub length: usize,
+    // where the frame starts in the packet payload, when the packet carries all of it
+    pub offset: Option<usize>,
+    // header and payload of a frame reassembled across packets, DATA keeps only its header
+    pub data: Vec<u8>,
+    // the stream a PUSH_PROMISE reserves
+    pub promised: Option<u32>,
+    // the header block this frame ends, decoded
+    pub headers: Option<Vec<HeaderField>>,
+    // hpack failed on the block
+    pub error: Option<String>,
+    // where the DATA payload lies in the capture, without padding
+    pub body: Vec<(FrameIndex, Range<usize>)>,
+}
+
+impl Http2Frame {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.as_ref()?.iter().find(|h| h.name == name).map(|h| h.value.as_str())
+    }
+    /**
+     * method and path of a request block, status of a response block
+     */
+    pub fn headline(&self) -> Option<String> {
+        if let Some(method) = self.header(":method") {
+            let path = self.header(":path").or(self.header(":authority")).unwrap_or_default();
+            return Some(format!("{method} {path}"));
+        }
+        self.header(":status").map(|status| status.to_string())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Http2Packet {
+    // bytes of the client connection preface at the start of the payload
+    pub preface: usize,
+    pub frames: Vec<Http2Frame>,
+    // bytes of frames that started in an earlier packet or end in a later one
+    pub fragments: Vec<Range<usize>>,
+}
+
+// a header block waiting for its CONTINUATION frames
+#[derive(Default)]
+struct HeaderBlock {
+    fragment: Vec<u8>,
+}
+
+/**
+ * frame reassembly and hpack state of one direction of a connection
+ */
+#[derive(Default)]
+struct Http2Flow {
+    started: bool,
+    // preface bytes still to skip
+    preface: usize,
+    // the frame under reassembly, header first
+    buffer: Vec<u8>,
+    // where the buffered bytes are in the capture
+    pieces: Vec<(FrameIndex, Range<usize>)>,
+    decoder: HpackDecoder,
+    block: Option<HeaderBlock>,
+}
+
+// capture ranges of the buffer bytes in range
+fn locate(pieces: &[(FrameIndex, Range<usize>)], range: Range<usize>) -> Vec<(FrameIndex, Range<usize>)> {
+    let mut list = vec![];
+    let mut at = 0;
+    for (index, piece) in pieces {
+        let len = piece.end - piece.start;
+        let (start, end) = (range.start.max(at), range.end.min(at + len));
+        if start < end {
+            list.push((*index, piece.start + start - at..piece.start + end - at));
+        }
+        at += len;
+    }
+    list
+}
+
+impl Http2Flow {
+    fn complete(&mut self, offset: Option<usize>) -> Http2Frame {
+        let buffer = std::mem::take(&mut self.buffer);
+        let pieces = std::mem::take(&mut self.pieces);
+        let length = u32::from_be_bytes([0, buffer[0], buffer[1], buffer[2]]) as usize;
+        let mut frame = Http2Frame {
+            frame_type: buffer[3],
+            flags: buffer[4],
+            stream: u32::from_be_bytes([buffer[5], buffer[6], buffer[7], buffer[8]]) & 0x7fff_ffff,
+            length,
+            offset,
+            ..Default::default()
+        };
+        let payload = &buffer[FRAME_HEADER..];
+        let padded = frame.flags & FLAG_PADDED != 0 && matches!(frame.frame_type, DATA | HEADERS | PUSH_PROMISE);
+        let (mut start, mut end) = (0, payload.len());
+        if padded {
+            let pad = payload.first().copied().unwrap_or_default() as usize;
+            start = 1;
+            end = end.saturating_sub(pad).max(start);
+        }
+        match frame.frame_type {
+            DATA => {
+                frame.body = locate(&pieces, FRAME_HEADER + start..FRAME_HEADER + end);
+            }
+            HEADERS => {
+                if frame.flags & FLAG_PRIORITY != 0 {
+                    start += 5;
+                }
+                self.block = Some(HeaderBlock::default());
+                self.add_fragment(&mut frame, payload.get(start..end).unwrap_or_default());
+            }
+            PUSH_PROMISE => {
+                if let Some(id) = payload.get(start..start + 4) {
+                    frame.promised = Some(u32::from_be_bytes([id[0], id[1], id[2], id[3]]) & 0x7fff_ffff);
+                }
+                self.block = Some(HeaderBlock::default());
+                self.add_fragment(&mut frame, payload.get(start + 4..end).unwrap_or_default());
+            }
+            CONTINUATION if self.block.is_some() => {
+                self.add_fragment(&mut frame, payload);
+            }
+            _ => {}
+        }
+        frame.data = match (offset, frame.frame_type) {
+            (Some(_), _) => vec![],
+            (None, DATA) => buffer[..FRAME_HEADER].to_vec(),
+            (None, _) => buffer,
+        };
+        frame
+    }
+    fn add_fragment(&mut self, frame: &mut Http2Frame, fragment: &[u8]) {
+        let Some(block) = self.block.as_mut() else {
+            return;
+        };
+        block.fragment.extend_from_slice(fragment);
+        if frame.flags & FLAG_END_HEADERS == 0 {
+            return;
+        }
+        let block = self.block.take().unwrap_or_default();
+        match self.decoder.decode(&block.fragment) {
+            Ok(headers) => frame.headers = Some(headers),
+            Err(e) => frame.error = Some(e.to_string()),
+        }
+    }
+    fn feed(&mut self, index: FrameIndex, data: &[u8], base: usize) -> Http2Packet {
+        let mut packet = Http2Packet::default();
+        let mut at = 0;
+        if !self.started {
+            self.started = true;
+            let size = data.len().min(HTTP2_PREFACE.len());
+            if data[..size] == HTTP2_PREFACE[..size] {
+                self.preface = HTTP2_PREFACE.len();
+            }
+        }
+        if self.preface > 0 {
+            packet.preface = self.preface.min(data.len());
+            self.preface -= packet.preface;
+            at = packet.preface;
+        }
+        while at < data.len() {
+            // a frame begun in an earlier packet
+            let offset = match self.buffer.is_empty() {
+                true => Some(at),
+                false => None,
+            };
+            let begin = at;
+            if self.buffer.len() < FRAME_HEADER {
+                let size = (FRAME_HEADER - self.buffer.len()).min(data.len() - at);
+                self.buffer.extend_from_slice(&data[at..at + size]);
+                at += size;
+            }
+            if self.buffer.len() >= FRAME_HEADER {
+                let length = u32::from_be_bytes([0, self.buffer[0], self.buffer[1], self.buffer[2]]) as usize;
+                let size = (FRAME_HEADER + length - self.buffer.len()).min(data.len() - at);
+                self.buffer.extend_from_slice(&data[at..at + size]);
+                at += size;
+            }
+            self.pieces.push((index, base + begin..base + at));
+            let done = self.buffer.len() >= FRAME_HEADER && self.buffer.len() == FRAME_HEADER + u32::from_be_bytes([0, self.buffer[0], self.buffer[1], self.buffer[2]]) as usize;
+            if offset.is_none() || !done {
+                packet.fragments.push(begin..at);
+            }
+            if done {
+                packet.frames.push(self.complete(offset));
+            }
+        }
+        packet
+    }
+}
+
+/**
+ * what a stream of a connection turned into, for pairing its request and response
+ */
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Http2Stream {
+    // the http connection entry of the stream, with the time of its request
+    pub connect: Option<(HttpConnectIndex, Timestamp)>,
+    // message each tcp direction sent on the stream
+    pub messages: [Option<MessageIndex>; 2],
+    // the message has no content-length, its DATA frames add up the length
+    pub counted: [bool; 2],
+}
+
+#[derive(Default)]
+struct Http2Connection {
+    flows: [Http2Flow; 2],
+    streams: FastHashMap<u32, Http2Stream>,
+}
+
+/**
+ * http/2 state per tcp connection, frames of each direction are reassembled across segments
+ */
+#[derive(Default)]
+pub struct Http2Table {
+    connections: FastHashMap<ConnectionIndex, Http2Connection>,
+    // frames by the packet that completed them
+    packets: FastHashMap<FrameIndex, Http2Packet>,
+}
+
+impl Http2Table {
+    /**
+     * the payload of a packet in one direction, data starts at base in the capture
+     */
+    pub fn feed(&mut self, index: FrameIndex, key: ConnectionIndex, direction: usize, data: &[u8], base: usize) -> Http2Packet {
+        let connection = self.connections.entry(key).or_default();
+        connection.flows[direction & 1].feed(index, data, base)
+    }
+    pub fn stream(&mut self, key: ConnectionIndex, stream: u32) -> &mut Http2Stream {
+        self.connections.entry(key).or_default().streams.entry(stream).or_default()
+    }
+    /**
+     * an http/1.1 request upgraded to h2c, its response comes on stream 1
+     */
+    pub fn upgrade(&mut self, key: ConnectionIndex, direction: usize, request: MessageIndex, connect: (HttpConnectIndex, Timestamp)) {
+        let stream = self.stream(key, 1);
+        stream.connect = Some(connect);
+        stream.messages[direction & 1] = Some(request);
+    }
+    pub fn insert(&mut self, index: FrameIndex, packet: Http2Packet) {
+        self.packets.insert(index, packet);
+    }
+    pub fn packet(&self, index: FrameIndex) -> Option<&Http2Packet> {
+        self.packets.get(&index)
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::net::IpAddr;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+use crate::common::{
+    concept::{ConnectionIndex, FrameIndex, Timestamp},
+    enum_def::DataError,
+    io::Reader,
+    FastHashMap,
+};
+
+pub const IP_PROTO_TCP: u8 = 6;
+pub const IP_PROTO_UDP: u8 = 17;
+
+/**
+ * addresses and ports of the datagram an ICMP error refers to, in the direction it was sent
+ */
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct QuotedDatagram {
+    pub protocol: u8,
+    pub source: IpAddr,
+    pub target: IpAddr,
+    pub source_port: u16,
+    pub target_port: u16,
+}
+
+impl QuotedDatagram {
+    /**
+     * reads the quoted ip header and the first transport bytes, only tcp and udp are recognized
+     */
+    pub fn read(reader: &mut Reader) -> Result<Option<Self>> {
+        let first = reader.next()?;
+        let (protocol, source, target) = match first >> 4 {
+            4 => {
+                let head_len = (first & 0x0f) as usize * 4;
+                if head_len < 20 || reader.left() < head_len {
+                    bail!(DataError::Ipv4HeadLengthInvalid);
+                }
+                reader.forward(9);
+                let protocol = reader.read8()?;
+                reader.forward(2);
+                let source = IpAddr::V4(reader.read_ip4()?);
+                let target = IpAddr::V4(reader.read_ip4()?);
+                reader.forward(head_len - 20);
+                (protocol, source, target)
+            }
+            6 => {
+                reader.forward(6);
+                let protocol = reader.read8()?;
+                reader.forward(1);
+                let source = IpAddr::V6(reader.read_ip6()?);
+                let target = IpAddr::V6(reader.read_ip6()?);
+                (protocol, source, target)
+            }
+            _ => bail!(DataError::FormatMismatch),
+        };
+        if protocol != IP_PROTO_TCP && protocol != IP_PROTO_UDP {
+            return Ok(None);
+        }
+        let source_port = reader.read16(true)?;
+        let target_port = reader.read16(true)?;
+        Ok(Some(Self { protocol, source, target, source_port, target_port }))
+    }
+
+    /**
+     * same key format as the udp conversation list
+     */
+    pub fn udp_key(&self) -> String {
+        format!("{}:{}-{}:{}", self.source, self.source_port, self.target, self.target_port)
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct IcmpError {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub version: u8,
+    pub icmp_type: u8,
+    pub code: u8,
+    // next-hop mtu of fragmentation needed / packet too big
+    pub mtu: Option<u32>,
+    pub quoted: QuotedDatagram,
+    pub connection: Option<ConnectionIndex>,
+}
+
+/**
+ * icmp errors whose quoted datagram could be read, linked to the tcp connection they refer to
+ */
+#[derive(Default)]
+pub struct IcmpErrorTable {
+    list: Vec<IcmpError>,
+    frames: FastHashMap<FrameIndex, usize>,
+}
+
+impl IcmpErrorTable {
+    pub fn add(&mut self, error: IcmpError) {
+        self.frames.insert(error.index, self.list.len());
+        self.list.push(error);
+    }
+
+    pub fn get(&self, index: FrameIndex) -> Option<&IcmpError> {
+        self.frames.get(&index).and_then(|inx| self.list.get(*inx))
+    }
+
+    pub fn list(&self) -> &[IcmpError] {
+        &self.list
+    }
+
+    pub fn by_udp_key(&self, key: &str) -> Vec<FrameIndex> {
+        self.list.iter().filter(|e| e.quoted.protocol == IP_PROTO_UDP && e.quoted.udp_key() == key).map(|e| e.index).collect()
+    }
+}
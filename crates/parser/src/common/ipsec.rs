@@ -0,0 +1,398 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::{net::IpAddr, ops::Range};
+
+use aes::{Aes128, Aes192, Aes256};
+use aes_gcm::{
+    aead::{consts::{U12, U16}, AeadInPlace, KeyInit, Nonce, Tag},
+    AesGcm,
+};
+use anyhow::{anyhow, bail, Result};
+use cbc::cipher::{block_padding::NoPadding, BlockDecryptMut, KeyIvInit};
+use serde::Serialize;
+
+use crate::common::{
+    concept::FrameIndex,
+    enum_def::AddressField,
+    core::Context,
+    quick_hash, FastHashMap, Frame, NString, ResourceLoader,
+};
+
+pub const IPSEC_ESP: u8 = 50;
+pub const IPSEC_AH: u8 = 51;
+
+// decrypted payloads are addressed past the end of any capture, below 2^53 so javascript keeps the offsets exact
+#[cfg(target_pointer_width = "64")]
+pub const PLAIN_BASE: usize = 1 << 52;
+#[cfg(not(target_pointer_width = "64"))]
+pub const PLAIN_BASE: usize = 1 << 31;
+
+// RFC 4303 3.4.3 recommends a receive window of at least 64 packets
+const REPLAY_WINDOW: u32 = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EspEncryption {
+    Null,
+    AesCbc,
+    // icv length in bytes
+    AesGcm(usize),
+    Unsupported,
+}
+
+#[derive(Clone, Debug)]
+pub struct EspSa {
+    pub spi: u32,
+    // None matches any address
+    pub source: Option<IpAddr>,
+    pub target: Option<IpAddr>,
+    pub encryption: EspEncryption,
+    pub key: Vec<u8>,
+    pub icv_len: usize,
+}
+
+impl EspSa {
+    pub fn matches(&self, spi: u32, source: Option<IpAddr>, target: Option<IpAddr>) -> bool {
+        let addr_match = |expect: &Option<IpAddr>, actual: &Option<IpAddr>| match (expect, actual) {
+            (None, _) => true,
+            (Some(e), Some(a)) => e == a,
+            _ => false,
+        };
+        self.spi == spi && addr_match(&self.source, &source) && addr_match(&self.target, &target)
+    }
+    pub fn iv_len(&self) -> usize {
+        match self.encryption {
+            EspEncryption::AesCbc => 16,
+            EspEncryption::AesGcm(_) => 8,
+            _ => 0,
+        }
+    }
+    pub fn name(&self) -> NString {
+        match self.encryption {
+            EspEncryption::Null => "NULL",
+            EspEncryption::AesCbc => "AES-CBC",
+            EspEncryption::AesGcm(_) => "AES-GCM",
+            EspEncryption::Unsupported => "Unsupported",
+        }
+    }
+}
+
+fn hex_key(text: &str) -> Result<Vec<u8>> {
+    let text = text.trim();
+    let text = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).unwrap_or(text);
+    if !text.len().is_multiple_of(2) {
+        bail!("odd key length");
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|e| anyhow!(e))).collect()
+}
+
+fn sa_address(text: &str) -> Result<Option<IpAddr>> {
+    let text = text.split('/').next().unwrap_or("").trim();
+    if text.is_empty() || text.contains('*') {
+        return Ok(None);
+    }
+    Ok(Some(text.parse()?))
+}
+
+/**
+ * accepts the numeric values wireshark stores as well as the algorithm names
+ */
+fn sa_encryption(text: &str) -> EspEncryption {
+    let upper = text.to_uppercase();
+    match upper.trim() {
+        "0" => EspEncryption::Null,
+        "2" => EspEncryption::AesCbc,
+        "8" => EspEncryption::AesGcm(8),
+        "9" => EspEncryption::AesGcm(12),
+        "10" => EspEncryption::AesGcm(16),
+        s if s.starts_with("NULL") => EspEncryption::Null,
+        s if s.starts_with("AES-CBC") => EspEncryption::AesCbc,
+        s if s.starts_with("AES-GCM") && s.contains("8 OCTET") => EspEncryption::AesGcm(8),
+        s if s.starts_with("AES-GCM") && s.contains("12 OCTET") => EspEncryption::AesGcm(12),
+        s if s.starts_with("AES-GCM") => EspEncryption::AesGcm(16),
+        _ => EspEncryption::Unsupported,
+    }
+}
+
+fn sa_icv_len(text: &str) -> usize {
+    let upper = text.to_uppercase();
+    match upper.trim() {
+        "1" | "2" | "6" | "7" | "9" => 12,
+        "3" | "10" => 16,
+        "4" | "11" => 24,
+        "5" | "12" => 32,
+        "8" => 8,
+        s if s.starts_with("HMAC-SHA-1-96") || s.starts_with("HMAC-MD5-96") || s.starts_with("MAC-RIPEMD-160-96") => 12,
+        s if s.starts_with("HMAC-SHA-256-96") => 12,
+        s if s.starts_with("HMAC-SHA-256-128") => 16,
+        s if s.starts_with("HMAC-SHA-384-192") => 24,
+        s if s.starts_with("HMAC-SHA-512-256") => 32,
+        s if s.starts_with("ANY") => s.trim_start_matches("ANY").trim().split(' ').next().and_then(|n| n.parse::<usize>().ok()).map(|n| n / 8).unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/**
+ * wireshark esp_sa format, one SA per line:
+ * "IPv4","10.0.0.1","10.0.0.2","0x00001000","AES-CBC [RFC3602]","0x<key>","HMAC-SHA-1-96 [RFC2404]","0x<key>"
+ */
+pub fn parse_esp_sa(content: &str) -> Result<Vec<EspSa>> {
+    let mut list = vec![];
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let cols: Vec<&str> = line.split(',').map(|c| c.trim().trim_matches('"')).collect();
+        if cols.len() < 6 {
+            bail!("invalid esp_sa line: {line}");
+        }
+        let spi = hex_key(cols[3])?.iter().fold(0u32, |acc, b| (acc << 8) | *b as u32);
+        let encryption = sa_encryption(cols[4]);
+        let key = hex_key(cols[5])?;
+        let icv_len = match encryption {
+            EspEncryption::AesGcm(len) => len,
+            _ => cols.get(6).map(|c| sa_icv_len(c)).unwrap_or(0),
+        };
+        list.push(EspSa {
+            spi,
+            source: sa_address(cols[1])?,
+            target: sa_address(cols[2])?,
+            encryption,
+            key,
+            icv_len,
+        });
+    }
+    Ok(list)
+}
+
+pub fn frame_ips(ctx: &Context, frame: &Frame) -> (Option<IpAddr>, Option<IpAddr>) {
+    match &frame.address_field {
+        AddressField::IPv4(s, t) => (Some(IpAddr::V4(*s)), Some(IpAddr::V4(*t))),
+        AddressField::IPv6(key) => match ctx.ipv6map.get(key) {
+            Some((_, s, t)) => (Some(IpAddr::V6(*s)), Some(IpAddr::V6(*t))),
+            None => (None, None),
+        },
+        _ => (None, None),
+    }
+}
+
+fn cbc_open<C: KeyIvInit + BlockDecryptMut>(key: &[u8], iv: &[u8], buf: &mut [u8]) -> Result<()> {
+    let cipher = C::new_from_slices(key, iv).map_err(|_| anyhow!("invalid key length"))?;
+    cipher.decrypt_padded_mut::<NoPadding>(buf).map_err(|_| anyhow!("ciphertext is not block aligned"))?;
+    Ok(())
+}
+
+fn gcm_open<A: KeyInit + AeadInPlace>(key: &[u8], nonce: &[u8], aad: &[u8], buf: &mut [u8], tag: &[u8]) -> Result<()> {
+    let cipher = A::new_from_slice(key).map_err(|_| anyhow!("invalid key length"))?;
+    cipher
+        .decrypt_in_place_detached(Nonce::<A>::from_slice(nonce), aad, buf, Tag::<A>::from_slice(tag))
+        .map_err(|_| anyhow!("icv mismatch"))
+}
+
+/**
+ * payload starts after spi and sequence number: iv, ciphertext, icv.
+ * returns the plain text with the esp trailer removed, plus pad length and next header
+ */
+pub fn esp_decrypt(sa: &EspSa, spi: u32, seq: u32, payload: &[u8]) -> Result<(Vec<u8>, u8, u8)> {
+    let iv_len = sa.iv_len();
+    if payload.len() < iv_len + sa.icv_len + 2 {
+        bail!("esp payload too short");
+    }
+    let iv = &payload[..iv_len];
+    let icv = &payload[payload.len() - sa.icv_len..];
+    let mut buf = payload[iv_len..payload.len() - sa.icv_len].to_vec();
+    match sa.encryption {
+        EspEncryption::Null => {}
+        EspEncryption::AesCbc => match sa.key.len() {
+            16 => cbc_open::<cbc::Decryptor<Aes128>>(&sa.key, iv, &mut buf)?,
+            24 => cbc_open::<cbc::Decryptor<Aes192>>(&sa.key, iv, &mut buf)?,
+            32 => cbc_open::<cbc::Decryptor<Aes256>>(&sa.key, iv, &mut buf)?,
+            _ => bail!("invalid key length"),
+        },
+        EspEncryption::AesGcm(icv_len) => {
+            // RFC 4106: 4 byte salt follows the key, nonce is salt || iv, aad is spi || seq
+            if sa.key.len() < 4 {
+                bail!("invalid key length");
+            }
+            let (key, salt) = sa.key.split_at(sa.key.len() - 4);
+            let nonce = [salt, iv].concat();
+            let mut aad = spi.to_be_bytes().to_vec();
+            aad.extend_from_slice(&seq.to_be_bytes());
+            match (key.len(), icv_len) {
+                (16, 16) => gcm_open::<AesGcm<Aes128, U12, U16>>(key, &nonce, &aad, &mut buf, icv)?,
+                (24, 16) => gcm_open::<AesGcm<Aes192, U12, U16>>(key, &nonce, &aad, &mut buf, icv)?,
+                (32, 16) => gcm_open::<AesGcm<Aes256, U12, U16>>(key, &nonce, &aad, &mut buf, icv)?,
+                (16, 12) => gcm_open::<AesGcm<Aes128, U12, U12>>(key, &nonce, &aad, &mut buf, icv)?,
+                (24, 12) => gcm_open::<AesGcm<Aes192, U12, U12>>(key, &nonce, &aad, &mut buf, icv)?,
+                (32, 12) => gcm_open::<AesGcm<Aes256, U12, U12>>(key, &nonce, &aad, &mut buf, icv)?,
+                _ => bail!("unsupported aes-gcm key or icv length"),
+            }
+        }
+        EspEncryption::Unsupported => bail!("unsupported encryption algorithm"),
+    }
+    let next_header = buf[buf.len() - 1];
+    let pad_len = buf[buf.len() - 2];
+    let end = buf.len().checked_sub(pad_len as usize + 2).ok_or(anyhow!("invalid pad length"))?;
+    // RFC 4303 2.4: default padding is 1, 2, 3, ...; anything else means a wrong key
+    if buf[end..end + pad_len as usize].iter().enumerate().any(|(i, b)| *b as usize != i + 1) {
+        bail!("invalid padding");
+    }
+    buf.truncate(end);
+    Ok((buf, pad_len, next_header))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SequenceAnomaly {
+    // packets lost between the previous highest sequence number and this one
+    Gap(u32),
+    OutOfOrder,
+    Replay,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SaStatistic {
+    pub protocol: NString,
+    pub spi: u32,
+    pub source: String,
+    pub target: String,
+    pub packets: usize,
+    pub first_seq: u32,
+    pub last_seq: u32,
+    pub lost: u64,
+    pub gaps: usize,
+    pub out_of_order: usize,
+    pub replays: usize,
+    #[serde(skip)]
+    window: u64,
+}
+
+impl SaStatistic {
+    fn update(&mut self, seq: u32) -> Option<SequenceAnomaly> {
+        self.packets += 1;
+        if self.packets == 1 {
+            self.first_seq = seq;
+            self.last_seq = seq;
+            self.window = 1;
+            return None;
+        }
+        if seq > self.last_seq {
+            let diff = seq - self.last_seq;
+            self.window = if diff < REPLAY_WINDOW { (self.window << diff) | 1 } else { 1 };
+            self.last_seq = seq;
+            if diff > 1 {
+                self.gaps += 1;
+                self.lost += (diff - 1) as u64;
+                return Some(SequenceAnomaly::Gap(diff - 1));
+            }
+            return None;
+        }
+        let offset = self.last_seq - seq;
+        if offset >= REPLAY_WINDOW || self.window & (1 << offset) != 0 {
+            self.replays += 1;
+            return Some(SequenceAnomaly::Replay);
+        }
+        self.window |= 1 << offset;
+        self.out_of_order += 1;
+        self.lost = self.lost.saturating_sub(1);
+        Some(SequenceAnomaly::OutOfOrder)
+    }
+}
+
+/**
+ * per SA sequence number tracking for AH and ESP
+ */
+#[derive(Default)]
+pub struct IpsecTable {
+    index: FastHashMap<u64, usize>,
+    list: Vec<SaStatistic>,
+    anomalies: FastHashMap<FrameIndex, SequenceAnomaly>,
+    // esp payload length and plain text range of decrypted frames, the inner layers overwrite frame.iplen
+    decrypted: FastHashMap<FrameIndex, (usize, Range<usize>)>,
+    // plain text of all decrypted frames, starting at PLAIN_BASE
+    plain: Vec<u8>,
+}
+
+impl IpsecTable {
+    pub fn sequence(&mut self, frame: &Frame, addresses: (String, String), protocol: u8, spi: u32, seq: u32) -> Option<SequenceAnomaly> {
+        let key = quick_hash((protocol, spi, &addresses.0, &addresses.1));
+        let inx = match self.index.get(&key) {
+            Some(inx) => *inx,
+            None => {
+                let inx = self.list.len();
+                self.list.push(SaStatistic {
+                    protocol: if protocol == IPSEC_AH { "AH" } else { "ESP" },
+                    spi,
+                    source: addresses.0,
+                    target: addresses.1,
+                    ..Default::default()
+                });
+                self.index.insert(key, inx);
+                inx
+            }
+        };
+        let rs = self.list[inx].update(seq);
+        if let Some(anomaly) = rs {
+            self.anomalies.insert(frame.info.index, anomaly);
+        }
+        rs
+    }
+    pub fn anomaly(&self, index: FrameIndex) -> Option<SequenceAnomaly> {
+        self.anomalies.get(&index).copied()
+    }
+    /**
+     * keeps the plain text of a frame, returns the range the inner layers are parsed at
+     */
+    pub fn add_decrypted(&mut self, index: FrameIndex, len: usize, plain: &[u8]) -> Range<usize> {
+        let start = PLAIN_BASE + self.plain.len();
+        let range = start..start + plain.len();
+        self.plain.extend_from_slice(plain);
+        self.decrypted.insert(index, (len, range.clone()));
+        range
+    }
+    pub fn decrypted_len(&self, index: FrameIndex) -> Option<usize> {
+        self.decrypted.get(&index).map(|(len, _)| *len)
+    }
+    pub fn plain_range(&self, index: FrameIndex) -> Option<Range<usize>> {
+        self.decrypted.get(&index).map(|(_, range)| range.clone())
+    }
+    fn plain(&self, range: &Range<usize>) -> Result<Vec<u8>> {
+        let start = range.start - PLAIN_BASE;
+        match self.plain.get(start..start + range.len()) {
+            Some(data) => Ok(data.to_vec()),
+            None => bail!("plain text range {range:?} out of bounds"),
+        }
+    }
+    pub fn list(&self) -> &[SaStatistic] {
+        &self.list
+    }
+}
+
+/**
+ * reads ranges of the capture from the loader and ranges of decrypted payloads from the ipsec table
+ */
+pub struct PlainLoader<'a> {
+    pub loader: &'a dyn ResourceLoader,
+    pub ipsec: &'a IpsecTable,
+}
+
+impl ResourceLoader for PlainLoader<'_> {
+    fn load(&self, range: &Range<usize>) -> Result<Vec<u8>> {
+        match range.start >= PLAIN_BASE {
+            true => self.ipsec.plain(range),
+            false => self.loader.load(range),
+        }
+    }
+    fn loads(&self, ranges: &[Range<usize>]) -> Result<Vec<u8>> {
+        if ranges.iter().all(|range| range.start < PLAIN_BASE) {
+            return self.loader.loads(ranges);
+        }
+        let mut rs = vec![];
+        for range in ranges {
+            rs.extend(self.load(range)?);
+        }
+        Ok(rs)
+    }
+}
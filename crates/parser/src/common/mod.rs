@@ -7,27 +7,28 @@ use core::Context;
 use std::{
     borrow::Borrow,
     cmp,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     hash::{BuildHasherDefault, Hash, Hasher},
-    net::{Ipv4Addr, Ipv6Addr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     ops::Range,
+    path::Path,
 };
 
 use crate::{
     add_field_label_no_range,
     common::{
         concept::{
-            ConversationCriteria, CounterItem, DNSRecord, DNSResponse, FrameIndex, HttpCriteria, HttpMessageDetail, IndexHashMap, LineChartData, NameService, TLSConversation,
+            ConnectionOrder, ConversationCriteria, CounterItem, DNSRecord, DNSResponse, FrameIndex, HttpCriteria, HttpMessageDetail, IndexHashMap, InstanceConfig, LineChartData, NameService, TLSConversation, QUIC_TLS_CONVERSATION,
             TLSItem, UDPConversation, VConnection, VConversation, VHttpConnection,
-        }, connection::{TcpFlagField, TlsData}, core::HttpConntect, file::FileMetadata, util::date_str
+        }, connection::{Connection, TcpFlagField, TlsData}, core::HttpConntect, arp::ArpReport, dhcp::{DhcpClient, DhcpTransaction}, ospf::OspfAdjacency, fhrp::FhrpGroup, sctp::SctpAssociation, quic::QuicConnection, mptcp::{MptcpConnection, MptcpMapping}, websocket::{WebSocketConnection, WebSocketMessage}, export::{free_path, object_filename, unique_filename, HttpObject}, geoip::{GeoIp, GeoStat}, stream::StreamChunk, graph::ConnectionGraph, file::FileMetadata, icmp::IcmpError, ipsec::{PlainLoader, SaStatistic}, multicast::MulticastReport, util::date_str
     },
     files::{pcap::PCAP, pcapng::PCAPNG},
-    protocol::{application::dns, detail, parse, summary},
+    protocol::{application::dns, detail, parse, summary, transport::tcp},
 };
 use anyhow::{bail, Result};
 use concept::{Criteria, Field, FrameInfo, FrameInternInfo, ListResult, ProgressStatus};
 use connection::ConnectState;
-use enum_def::{AddressField, DataError, FileType, Protocol, ProtocolInfoField};
+use enum_def::{AddressField, DataError, FileType, PacketStatus, Protocol, ProtocolInfoField};
 use io::{DataSource, MacAddress, Reader, IO};
 use rustc_hash::FxHasher;
 
@@ -212,6 +213,20 @@ impl Frame {
     pub fn frame_range(&self) -> Option<Range<usize>> {
         self.range.clone()
     }
+    /**
+     * keep the first reason when several layers complain about the same frame
+     */
+    pub fn mark_error(&mut self, reason: NString) {
+        self.info.status = PacketStatus::ERROR;
+        if self.info.reason.is_none() {
+            self.info.reason = Some(reason);
+        }
+    }
+    pub fn mark_warning(&mut self, warning: NString) {
+        if self.info.warning.is_none() {
+            self.info.warning = Some(warning);
+        }
+    }
     pub fn tcp_description(&self) -> Option<String> {
         if let Some(stat) = &self.tcp_info {
             let mut source_port = 0;
@@ -221,7 +236,8 @@ impl Frame {
                 target_port = ports.1;
             }
             let state = TcpFlagField::from(stat.flag_bit);
-            return Some(format!("{} -> {} {} Seq={} Len={} ", source_port, target_port, state.list_str(), stat.seq, stat.len));
+            let experts: String = stat.experts.iter().map(|e| format!("[{e}] ")).collect();
+            return Some(format!("{}{} -> {} {} Seq={} Len={} ", experts, source_port, target_port, state.list_str(), stat.seq, stat.len));
         }
         None
     }
@@ -279,6 +295,8 @@ pub struct Instance<T> {
     pub ctx: Context,
     last: usize,
     progress: ProgressStatus,
+    // finish was called, a redissect ends the same way
+    finished: bool,
 }
 
 impl<T> Instance<T> {
@@ -294,6 +312,21 @@ impl<T> Instance<T> {
     pub fn progress(&self) -> ProgressStatus {
         self.progress.clone()
     }
+    pub fn config(&self) -> InstanceConfig {
+        self.ctx.config
+    }
+    /**
+     * takes effect for frames parsed after the call
+     */
+    pub fn set_config(&mut self, config: InstanceConfig) {
+        self.ctx.config = config;
+    }
+    /**
+     * content of a local country, city or asn .mmdb file, returns its database type. Applies to all later queries
+     */
+    pub fn add_geoip_database(&mut self, data: Vec<u8>) -> Result<String> {
+        self.ctx.geoip.load(data)
+    }
 }
 
 impl<T> Instance<T>
@@ -303,13 +336,16 @@ where
     pub fn new(batch_size: usize, loader: T) -> Self {
         let size = cmp::max(batch_size, 1024 * 128);
         let ds = DataSource::new(size, 0);
+        let mut ctx = Context::new();
+        ctx.config.batch_size = size;
         Self {
             loader,
             ds,
             // file_type: FileType::NONE,
-            ctx: Context::new(),
+            ctx,
             last: 0,
             progress: ProgressStatus::default(),
+            finished: false,
         }
     }
 
@@ -317,6 +353,72 @@ where
         &self.loader
     }
 
+    /**
+     * the loader for ranges recorded while parsing, which may point into decrypted payloads
+     */
+    pub fn resources(&self) -> PlainLoader<'_> {
+        PlainLoader { loader: &self.loader, ipsec: &self.ctx.ipsec }
+    }
+
+    /**
+     * content of a wireshark esp_sa file, returns the number of SAs. Frames already loaded are dissected again
+     */
+    pub fn set_esp_sa(&mut self, content: &str) -> Result<usize> {
+        self.ctx.esp_sa = ipsec::parse_esp_sa(content)?;
+        self.redissect()?;
+        Ok(self.ctx.esp_sa.len())
+    }
+
+    /**
+     * content of a decode as rule file, see decode::parse_decode_as. Frames already loaded are dissected again
+     */
+    pub fn set_decode_as(&mut self, content: &str) -> Result<usize> {
+        self.ctx.decode_as = decode::parse_decode_as(content)?;
+        self.redissect()?;
+        Ok(self.ctx.decode_as.len())
+    }
+
+    /**
+     * checks the ip, transport and icmp checksums of every frame. Frames already loaded are dissected again
+     */
+    pub fn set_verify_checksum(&mut self, verify: bool) -> Result<ProgressStatus> {
+        self.ctx.config.verify_checksum = verify;
+        self.redissect()
+    }
+
+    /**
+     * parses everything received so far again from the loader in batches, user settings are kept
+     */
+    pub fn redissect(&mut self) -> Result<ProgressStatus> {
+        let total = self.ds.range().end;
+        if total == 0 {
+            return Ok(self.progress.clone());
+        }
+        let batch = cmp::max(self.ctx.config.batch_size, 1);
+        let finished = self.finished;
+        self.destroy();
+        let mut rs = self.progress.clone();
+        let mut start = 0;
+        while start < total {
+            let end = cmp::min(start + batch, total);
+            let data = self.loader.load(&(start..end))?;
+            rs = self.update(data)?;
+            start = end;
+        }
+        if finished {
+            self.finish();
+        }
+        Ok(rs)
+    }
+
+    /**
+     * no more data will come, tcp segments still waiting for a gap to fill are passed on with the gap reported
+     */
+    pub fn finish(&mut self) {
+        tcp::flush(&mut self.ctx);
+        self.finished = true;
+    }
+
     pub fn parse(&mut self) -> Result<ProgressStatus> {
         let mut reader = Reader::new(&self.ds);
         reader.cursor = self.last;
@@ -399,10 +501,13 @@ where
                         break;
                     }
                     _ => {
+                        // set before parsing so a tunnel can point the tail at its inner protocols
+                        let tail = frame.tail;
+                        frame.tail = _next;
                         if let Ok(next) = parse(_next, ctx, &mut frame, &mut _reader) {
-                            frame.tail = _next;
                             _next = next;
                         } else {
+                            frame.tail = tail;
                             break;
                         }
                     }
@@ -410,6 +515,9 @@ where
             }
         }
         ctx.list.push(frame);
+        // data held back for this frame to fill the gap in front of it
+        let released = std::mem::take(&mut ctx.tcp_released);
+        tcp::replay(ctx, released);
     }
     pub fn update(&mut self, data: Vec<u8>) -> Result<ProgressStatus> {
         self.ds.update(data);
@@ -421,14 +529,23 @@ where
     }
     pub fn destroy(&mut self) -> bool {
         self.ds.destroy();
+        let config = self.ctx.config;
+        let esp_sa = std::mem::take(&mut self.ctx.esp_sa);
+        let geoip = std::mem::take(&mut self.ctx.geoip);
+        let decode_as = std::mem::take(&mut self.ctx.decode_as);
         self.ctx = Context::new();
+        self.ctx.config = config;
+        self.ctx.esp_sa = esp_sa;
+        self.ctx.geoip = geoip;
+        self.ctx.decode_as = decode_as;
         self.last = 0;
         self.progress = ProgressStatus::default();
+        self.finished = false;
         true
     }
 }
 
-fn conversation_list<V: AsRef<[T]>, T>(start: usize, size: usize, v: V) -> ListResult<VConversation>
+fn conversation_list<V: AsRef<[T]>, T>(start: usize, size: usize, v: V, geoip: &GeoIp) -> ListResult<VConversation>
 where
     T: Borrow<concept::Conversation>,
 {
@@ -441,7 +558,10 @@ where
     let _data = &slice[start..end];
     let mut list = vec![];
     for item in _data {
-        list.push(item.borrow().into());
+        let mut conv: VConversation = item.borrow().into();
+        conv.sender_geo = geoip.lookup_str(&conv.sender);
+        conv.receiver_geo = geoip.lookup_str(&conv.receiver);
+        list.push(conv);
     }
     ListResult::new(start, total, list)
 }
@@ -451,10 +571,61 @@ impl<T> Instance<T> {
         self.context().stat_frame()
     }
     pub fn stat_ip4(&self) -> Vec<CounterItem> {
-        self.context().stat_ip4()
+        let ctx = self.context();
+        ctx.stat_ip4().into_iter().map(|item| item.geo(&ctx.geoip)).collect()
     }
     pub fn stat_ip6(&self) -> Vec<CounterItem> {
-        self.context().stat_ip6()
+        let ctx = self.context();
+        ctx.stat_ip6().into_iter().map(|item| item.geo(&ctx.geoip)).collect()
+    }
+    pub fn multicast_report(&self) -> MulticastReport {
+        self.context().multicast.report()
+    }
+    pub fn ipsec_sa_list(&self) -> Vec<SaStatistic> {
+        self.context().ipsec.list().to_vec()
+    }
+    pub fn icmp_errors(&self) -> Vec<IcmpError> {
+        self.context().icmp_errors.list().to_vec()
+    }
+    pub fn arp_report(&self) -> ArpReport {
+        self.context().arp.report()
+    }
+    pub fn dhcp_clients(&self) -> Vec<DhcpClient> {
+        self.context().dhcp.clients()
+    }
+    pub fn ospf_adjacencies(&self) -> Vec<OspfAdjacency> {
+        self.context().ospf.adjacencies().to_vec()
+    }
+    pub fn fhrp_groups(&self) -> Vec<FhrpGroup> {
+        self.context().fhrp.groups().to_vec()
+    }
+    pub fn sctp_associations(&self) -> Vec<SctpAssociation> {
+        self.context().sctp.associations().to_vec()
+    }
+    pub fn quic_connections(&self) -> Vec<QuicConnection> {
+        self.context().quic.connections().to_vec()
+    }
+    pub fn mptcp_connections(&self) -> Vec<MptcpConnection> {
+        self.context().mptcp.connections().to_vec()
+    }
+    /**
+     * dss mappings of an mptcp connection across its subflows, in data sequence order
+     */
+    pub fn mptcp_data_sequence(&self, index: usize) -> Vec<MptcpMapping> {
+        self.context().mptcp.connection(index).map(|conn| conn.data_sequence()).unwrap_or_default()
+    }
+    pub fn websocket_connections(&self) -> Vec<WebSocketConnection> {
+        self.context().websocket.connections().to_vec()
+    }
+    /**
+     * messages of a websocket connection in capture order, text payloads decompressed
+     */
+    pub fn websocket_messages(&self, index: usize) -> Vec<WebSocketMessage> {
+        self.context().websocket.messages(index).to_vec()
+    }
+    pub fn stat_geo(&self) -> GeoStat {
+        let ctx = self.context();
+        ctx.geoip.traffic(ctx)
     }
     pub fn stat_http_host(&self) -> Vec<CounterItem> {
         self.context().stat_http_host()
@@ -604,6 +775,17 @@ where
         // add_field_label_no_range!(f, format!("Interface id: {}", interface_type));
         add_field_label_no_range!(f, format!("Frame length: {}", size));
         add_field_label_no_range!(f, format!("Capture length: {}", size));
+        if let Some(reason) = frame.info.reason {
+            add_field_label_no_range!(f, format!("[Expert Info: {reason}]"));
+        }
+        if let Some(warning) = frame.info.warning {
+            add_field_label_no_range!(f, format!("[Expert Info: {warning}]"));
+        }
+        if frame.has_proto(ProtoMask::ARP) {
+            for warning in self.ctx.arp.warnings(frame.info.index) {
+                add_field_label_no_range!(f, format!("[Expert Info: {warning}]"));
+            }
+        }
         f
     }
 
@@ -616,6 +798,47 @@ where
         }
         None
     }
+    /**
+     * a layer returning extra data (e.g. decrypted esp payload) hands the rest of the dissection to a new datasource
+     */
+    fn intern_detail(&self, frame: &Frame, proto: Protocol, reader: &mut Reader, source: u8, list: &mut Vec<Field>, datasources: &mut Vec<DataSource>) {
+        let mut _next = proto;
+        loop {
+            match &_next {
+                Protocol::None => {
+                    break;
+                }
+                _ => {
+                    let mut f = Field::children();
+                    f.start = reader.cursor;
+                    f.source = source;
+                    match detail(_next, &mut f, self, frame, reader, datasources) {
+                        Ok((next, Some(extra_data))) => {
+                            f.size = reader.cursor - f.start;
+                            list.push(f);
+                            // decrypted payloads are read at the range they were parsed at
+                            let start = self.ctx.ipsec.plain_range(frame.info.index).map_or(0, |range| range.start);
+                            let ds = DataSource::create(extra_data, start..start);
+                            datasources.push(DataSource::create(ds.data.clone(), start..start));
+                            let source = datasources.len() as u8;
+                            let mut _reader = Reader::new(&ds);
+                            self.intern_detail(frame, next, &mut _reader, source, list, datasources);
+                            break;
+                        }
+                        Ok((next, None)) => {
+                            f.size = reader.cursor - f.start;
+                            list.push(f);
+                            _next = next;
+                        }
+                        Err(_) => {
+                            f.summary = format!("Parse [{_next}] failed");
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
     pub fn select_frame(&self, index: usize) -> Option<(Vec<Field>, Vec<DataSource>)> {
         if let Some(frame) = self.frame(index) {
             if let Some(range) = frame.frame_range() {
@@ -624,27 +847,8 @@ where
                 let mut datasources = vec![];
                 let mut reader = Reader::new(&ds);
                 let mut list = vec![];
-                let mut _next = frame.head;
                 list.push(self.intern_frame_field(frame));
-                loop {
-                    match &_next {
-                        Protocol::None => {
-                            break;
-                        }
-                        _ => {
-                            let mut f = Field::children();
-                            f.start = reader.cursor;
-                            if let Ok((next, _extra_data)) = detail(_next, &mut f, self, frame, &mut reader, &mut datasources) {
-                                f.size = reader.cursor - f.start;
-                                list.push(f);
-                                _next = next;
-                            } else {
-                                f.summary = format!("Parse [{_next}] failed");
-                                break;
-                            }
-                        }
-                    }
-                }
+                self.intern_detail(frame, frame.head, &mut reader, 0, &mut list, &mut datasources);
                 datasources.insert(0, ds);
                 return Some((list, datasources));
                 // return Some((list, source, extra_data, range));
@@ -657,17 +861,29 @@ where
         let Criteria { start, size } = cri;
         if let Some(ip) = &filter.ip {
             let c_list: Vec<&concept::Conversation> = self.ctx.conversation_list.iter().filter(|conv| conv.match_ip(ip)).collect();
-            conversation_list(start, size, c_list)
+            conversation_list(start, size, c_list, &self.ctx.geoip)
         } else {
-            conversation_list(start, size, &self.ctx.conversation_list)
+            conversation_list(start, size, &self.ctx.conversation_list, &self.ctx.geoip)
         }
     }
     pub fn connections(&self, conversation_index: usize, cri: Criteria) -> ListResult<VConnection> {
-        if let Some(connects) = self.ctx.conversation_list.get(conversation_index) {
-            paging_into(&connects.connections, cri, |f| f.into())
-        } else {
-            ListResult::empty()
+        self.connections_by(conversation_index, cri, ConnectionOrder::Index)
+    }
+    pub fn connections_by(&self, conversation_index: usize, cri: Criteria, order: ConnectionOrder) -> ListResult<VConnection> {
+        let Some(connects) = self.ctx.conversation_list.get(conversation_index) else {
+            return ListResult::empty();
+        };
+        let mut list: Vec<(usize, &Connection)> = connects.connections.iter().enumerate().collect();
+        match order {
+            ConnectionOrder::Index => {}
+            ConnectionOrder::Handshake => list.sort_by_key(|(_, conn)| cmp::Reverse(conn.metrics.irtt)),
+            ConnectionOrder::Reset => list.sort_by_key(|(_, conn)| !conn.metrics.is_reset()),
         }
+        paging_into(&list, cri, |(index, conn)| {
+            let mut item: VConnection = (*conn).into();
+            item.index = *index;
+            item
+        })
     }
 
     fn iter_http<'a>(&'a self, asc: bool) -> Box<dyn Iterator<Item = &'a HttpConntect>+'a> {
@@ -696,13 +912,64 @@ where
     }
 
     pub fn http_detail(&self, index: usize) -> Option<Vec<HttpMessageDetail>> {
-        let loader = &self.loader;
+        let loader = self.resources();
         if let Some(http_connect) = self.ctx.http_connections.get(index) {
-            http_connect.convert_to_detail(&self.ctx, loader).ok()
+            http_connect.convert_to_detail(&self.ctx, &loader).ok()
         } else {
             None
         }
     }
+    /**
+     * response bodies of the http connections, each named for saving
+     */
+    pub fn http_objects(&self) -> Vec<HttpObject> {
+        let ctx = &self.ctx;
+        let mut taken = HashSet::new();
+        ctx.http_connections
+            .iter()
+            .enumerate()
+            .filter_map(|(index, connect)| {
+                let response = ctx.http_messages.get(connect.response? as usize)?;
+                let size: usize = response.content.to_range().iter().map(|range| range.len()).sum();
+                if size == 0 {
+                    return None;
+                }
+                let request = connect.request.and_then(|request| ctx.http_messages.get(request as usize));
+                let target = request.and_then(|request| request.host.split_whitespace().nth(1)).unwrap_or_default();
+                let hostname = request.and_then(|request| request.hostname.clone()).unwrap_or_default();
+                let url = match target.starts_with('/') && !hostname.is_empty() {
+                    true => format!("http://{hostname}{target}"),
+                    false => target.to_string(),
+                };
+                let content_type = response.content_type.as_deref().unwrap_or_default().trim().to_string();
+                let filename = unique_filename(object_filename(target, response.disposition.as_deref(), &content_type), &mut taken);
+                Some(HttpObject { index, frame: response.frame_index, hostname, url, filename, content_type, size })
+            })
+            .collect()
+    }
+    /**
+     * the response body of an http connection, dechunked with the content encoding undone
+     */
+    pub fn http_object_content(&self, index: usize) -> Option<Vec<u8>> {
+        let list = self.http_detail(index)?;
+        list.iter().find(|message| !message.is_request).map(|message| message.decoded_content())
+    }
+    /**
+     * writes the selected objects, or all of them, into dir without replacing files there, returns the paths written
+     */
+    pub fn export_http_objects(&self, dir: &Path, indexes: Option<&[usize]>) -> Result<Vec<String>> {
+        std::fs::create_dir_all(dir)?;
+        let mut written = vec![];
+        for object in self.http_objects().iter().filter(|object| indexes.is_none_or(|list| list.contains(&object.index))) {
+            let Some(content) = self.http_object_content(object.index) else {
+                continue;
+            };
+            let path = free_path(dir, &object.filename);
+            std::fs::write(&path, content)?;
+            written.push(path.to_string_lossy().to_string());
+        }
+        Ok(written)
+    }
 
     fn intern_udp_conversations(&self, ip: Option<String>, asc: bool) -> Vec<UDPConversation> {
         let mut map = FastHashMap::<String, UDPConversation>::default();
@@ -736,6 +1003,11 @@ where
                 }
             }
         }
+        for (key, item) in map.iter_mut() {
+            item.icmp_errors = self.ctx.icmp_errors.by_udp_key(key);
+            item.sender_geo = self.ctx.geoip.lookup_str(&item.sender);
+            item.receiver_geo = self.ctx.geoip.lookup_str(&item.receiver);
+        }
         let mut rs: Vec<UDPConversation> = map.into_values().collect();
         let compare = |a: &UDPConversation, b: &UDPConversation| {
             let rs = a.ts.cmp(&b.ts);
@@ -752,55 +1024,57 @@ where
         let list = self.intern_udp_conversations(filter, asc);
         paging(&list, cri)
     }
+    pub fn connection_graph(&self, conversation_index: usize, connection_index: usize) -> ConnectionGraph {
+        match self.ctx.conversation_list.get(conversation_index).and_then(|c| c.connections.get(connection_index)) {
+            Some(conn) => graph::connection_graph(conn),
+            None => ConnectionGraph::default(),
+        }
+    }
+    /**
+     * payload of both directions of a tcp connection in stream order
+     */
+    pub fn follow_tcp_stream(&self, conversation_index: usize, connection_index: usize, cri: Criteria) -> ListResult<StreamChunk> {
+        match self.ctx.conversation_list.get(conversation_index).and_then(|c| c.connections.get(connection_index)) {
+            Some(conn) => stream::load_stream(&self.ctx, &self.resources(), &conn.stream, cri),
+            None => ListResult::empty(),
+        }
+    }
+    pub fn follow_tcp_stream_by(&self, source: &IpAddr, source_port: u16, target: &IpAddr, target_port: u16, cri: Criteria) -> ListResult<StreamChunk> {
+        match self.ctx.find_connection(source, source_port, target, target_port) {
+            Some((conversation_index, connection_index)) => self.follow_tcp_stream(conversation_index, connection_index, cri),
+            None => ListResult::empty(),
+        }
+    }
+    /**
+     * datagrams of both directions of the udp conversation starting at frame index
+     */
+    pub fn follow_udp_stream(&self, index: usize, cri: Criteria) -> ListResult<StreamChunk> {
+        let Some((segments, reversed)) = stream::udp_segments(&self.ctx, index) else {
+            return ListResult::empty();
+        };
+        let mut rs = stream::load_stream(&self.ctx, &self.resources(), segments, cri);
+        if reversed {
+            rs.items.iter_mut().for_each(|chunk| chunk.direction = chunk.direction.reverse());
+        }
+        rs
+    }
 
     fn intern_parse_handshake(&self, tls_data: &TlsData, msg_type: u8, item: &mut TLSItem) -> Result<()> {
         let ranges: Vec<Range<usize>> = tls_data.segments.iter().map(|f| f.range.clone()).collect();
-        let data = self.loader.loads(&ranges)?;
+        let data = self.resources().loads(&ranges)?;
         let ds = DataSource::create(data, 0..0);
         let mut reader = Reader::new(&ds);
         reader.read8()?;
         reader.forward(2);
         let len1 = reader.read16(true)?;
-        let mt = reader.read8()?;
-        if mt != msg_type {
+        let head = reader.preview(4)?;
+        if head[0] != msg_type {
             bail!("msg type missmatch")
         }
-        let len2 = reader.read24()?;
+        let len2 = u32::from_be_bytes([0, head[1], head[2], head[3]]);
         let diff = len1.saturating_sub(len2 as u16);
         if diff == 4 {
-            match mt {
-                1 => {
-                    reader.forward(34);
-                    let session_id_len = reader.read8()? as usize;
-                    reader.forward(session_id_len);
-                    let cipher_suite_len = reader.read16(true)?;
-                    reader.forward(cipher_suite_len as usize);
-                    let compression_len = reader.read8()?;
-                    reader.forward(compression_len as usize);
-                    let extention_len = reader.read16(true)?;
-                    if extention_len > 0 {
-                        let mut extention_reader = reader.slice_as_reader(extention_len as usize)?;
-                        return resolve_sni(&mut extention_reader, item);
-                    }
-                }
-                2 => {
-                    reader.forward(34);
-                    let session_id_len = reader.read8()? as usize;
-                    reader.forward(session_id_len);
-                    let cs = reader.read16(true)?;
-                    // let suites = crate::constants::tls_cipher_suites_mapper(cs).to_string();
-                    // item.cipher_suite = Some(suites);
-                    item.set_cipher_suite(cs);
-
-                    reader.forward(1);
-                    let extention_len = reader.read16(true)?;
-                    if extention_len > 0 {
-                        let mut extention_reader = reader.slice_as_reader(extention_len as usize)?;
-                        return resolve_alpn(&mut extention_reader, item);
-                    }
-                }
-                _ => {}
-            }
+            return resolve_handshake(&mut reader, msg_type, item);
         }
 
         bail!("")
@@ -829,9 +1103,44 @@ where
                 }
             }
         }
+        list.extend((0..self.intern_quic_conversations().len()).map(|inx| QUIC_TLS_CONVERSATION + inx));
+        list
+    }
+    // quic connections grouped by client and server address, in order of appearance
+    fn intern_quic_conversations(&self) -> Vec<((IpAddr, IpAddr), Vec<&QuicConnection>)> {
+        let mut list: Vec<((IpAddr, IpAddr), Vec<&QuicConnection>)> = vec![];
+        for connection in self.ctx.quic.connections() {
+            let key = (connection.endpoints.0 .0, connection.endpoints.1 .0);
+            match list.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, connections)) => connections.push(connection),
+                None => list.push((key, vec![connection])),
+            }
+        }
         list
     }
+    fn intern_quic_tls_conv(&self, index: usize, complete: bool) -> Option<TLSConversation> {
+        let conversations = self.intern_quic_conversations();
+        let ((client, server), connections) = conversations.get(index)?;
+        let mut rs = TLSConversation::new(QUIC_TLS_CONVERSATION + index, client.to_string(), server.to_string());
+        rs.transport = "quic";
+        let mut map = FastHashMap::default();
+        for connection in connections {
+            let mut item = connection.tls_item();
+            if complete {
+                item.addr_1 = Some(connection.client.clone());
+                item.addr_2 = Some(connection.server.clone());
+            }
+            let key = item.get_trait();
+            let _item = map.entry(key).or_insert(item);
+            _item.update();
+        }
+        rs.list = map.into_values().collect();
+        Some(rs)
+    }
     fn intern_tls_conv_from_index(&self, conversaction_index: usize, complete: bool) -> Option<TLSConversation> {
+        if conversaction_index >= QUIC_TLS_CONVERSATION {
+            return self.intern_quic_tls_conv(conversaction_index - QUIC_TLS_CONVERSATION, complete);
+        }
         if let Some(conv) = self.ctx.conversation_list.get(conversaction_index) {
             let mut rs = TLSConversation::new(conversaction_index, conv.primary.clone(), conv.second.clone());
             let primary_host = &conv.primary;
@@ -946,13 +1255,22 @@ where
         let convert = |f: &DNSResponse| DNSResponse::convert(self, f, first);
         paging_into(&list, cri, convert)
     }
+    /**
+     * dhcp exchanges ending in a NAK, a failed status or without the final answer
+     */
+    pub fn dhcp_failures(&self, cri: Criteria) -> ListResult<DhcpTransaction> {
+        paging(&self.context().dhcp.failures(), cri)
+    }
     pub fn dns_record(&self, index: usize, cri: Criteria) -> ListResult<DNSRecord> {
         if let Some(frame) = self.ctx.list.get(index) {
             if let ProtocolInfoField::DNSRESPONSE(_, _, start) = &frame.protocol_field {
                 if let Some(ds) = self.frame_datasource(frame) {
                     let mut reader = Reader::new(&ds);
                     reader.cursor = *start;
-                    if let Ok(list) = dns::Visitor::answers(&mut reader) {
+                    if let Ok(mut list) = dns::Visitor::answers(&mut reader) {
+                        for record in list.iter_mut() {
+                            record.geo = record.info.as_deref().and_then(|info| self.ctx.geoip.lookup_str(info));
+                        }
                         return paging(&list, cri);
                     }
                 }
@@ -992,6 +1310,48 @@ where
     }
 }
 
+/**
+ * server name of a ClientHello, version, cipher suite and alpn of a ServerHello.
+ * reader starts at the handshake message type
+ */
+pub(crate) fn resolve_handshake(reader: &mut Reader, msg_type: u8, item: &mut TLSItem) -> Result<()> {
+    reader.read8()?;
+    reader.read24()?;
+    match msg_type {
+        1 => {
+            reader.forward(34);
+            let session_id_len = reader.read8()? as usize;
+            reader.forward(session_id_len);
+            let cipher_suite_len = reader.read16(true)?;
+            reader.forward(cipher_suite_len as usize);
+            let compression_len = reader.read8()?;
+            reader.forward(compression_len as usize);
+            let extention_len = reader.read16(true)?;
+            if extention_len > 0 {
+                let mut extention_reader = reader.slice_as_reader(extention_len as usize)?;
+                return resolve_sni(&mut extention_reader, item);
+            }
+        }
+        2 => {
+            reader.forward(34);
+            let session_id_len = reader.read8()? as usize;
+            reader.forward(session_id_len);
+            let cs = reader.read16(true)?;
+            item.set_cipher_suite(cs);
+
+            reader.forward(1);
+            let extention_len = reader.read16(true)?;
+            if extention_len > 0 {
+                let mut extention_reader = reader.slice_as_reader(extention_len as usize)?;
+                return resolve_alpn(&mut extention_reader, item);
+            }
+        }
+        _ => {}
+    }
+    bail!("")
+}
+
+// the alpn offer of the client stays when the server hello does not carry its choice, as in tls 1.3
 fn resolve_sni(reader: &mut Reader, item: &mut TLSItem) -> Result<()> {
     while reader.left() >= 4 {
         let extention_type = reader.read16(true)?;
@@ -1001,6 +1361,16 @@ fn resolve_sni(reader: &mut Reader, item: &mut TLSItem) -> Result<()> {
             ext_reader.forward(5);
             let sni = ext_reader.read_string((extention_len - 5) as usize)?;
             item.hostname = Some(sni);
+        } else if extention_type == 16 {
+            let mut ext_reader = reader.slice_as_reader(extention_len as usize)?;
+            let list_len = ext_reader.read16(true)?;
+            let mut list_reader = ext_reader.slice_as_reader(list_len as usize)?;
+            let mut list = vec![];
+            while list_reader.left() > 0 {
+                let item_len = list_reader.read8()?;
+                list.push(list_reader.read_string(item_len as usize)?);
+            }
+            item.alpn = Some(list);
         } else {
             reader.forward(extention_len as usize);
         }
@@ -1050,3 +1420,23 @@ pub mod io;
 pub mod macro_def;
 pub mod util;
 pub mod file;
+pub mod checksum;
+pub mod multicast;
+pub mod ipsec;
+pub mod icmp;
+pub mod arp;
+pub mod dhcp;
+pub mod ospf;
+pub mod fhrp;
+pub mod geoip;
+pub mod stream;
+pub mod graph;
+pub mod decode;
+pub mod sctp;
+pub mod quic;
+pub mod dtls;
+pub mod mptcp;
+pub mod hpack;
+pub mod http2;
+pub mod websocket;
+pub mod export;
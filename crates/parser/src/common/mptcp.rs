@@ -0,0 +1,386 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::net::IpAddr;
+
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::common::{
+    concept::{FrameIndex, Timestamp},
+    FastHashMap,
+};
+
+pub const MP_CAPABLE: u8 = 0;
+pub const MP_JOIN: u8 = 1;
+pub const MP_DSS: u8 = 2;
+pub const MP_ADD_ADDR: u8 = 3;
+pub const MP_REMOVE_ADDR: u8 = 4;
+pub const MP_PRIO: u8 = 5;
+pub const MP_FAIL: u8 = 6;
+pub const MP_FASTCLOSE: u8 = 7;
+pub const MP_TCPRST: u8 = 8;
+
+pub fn mptcp_subtype_mapper(subtype: u8) -> &'static str {
+    match subtype {
+        MP_CAPABLE => "MP_CAPABLE",
+        MP_JOIN => "MP_JOIN",
+        MP_DSS => "DSS",
+        MP_ADD_ADDR => "ADD_ADDR",
+        MP_REMOVE_ADDR => "REMOVE_ADDR",
+        MP_PRIO => "MP_PRIO",
+        MP_FAIL => "MP_FAIL",
+        MP_FASTCLOSE => "MP_FASTCLOSE",
+        MP_TCPRST => "MP_TCPRST",
+        _ => "Unknown",
+    }
+}
+
+/**
+ * what the table needs from a kind 30 option
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum MptcpOption {
+    Capable { version: u8, sender_key: Option<u64>, receiver_key: Option<u64> },
+    // token only on the SYN
+    Join { address_id: u8, backup: bool, token: Option<u32> },
+    // data sequence number, whether it is 64 bits wide, and the length of the mapping
+    Dss { mapping: Option<(u64, bool, u16)>, fin: bool },
+    AddAddr { address_id: u8, address: IpAddr, port: Option<u16>, echo: bool },
+    RemoveAddr(Vec<u8>),
+    Prio { backup: bool },
+    Fail(u64),
+    FastClose(u64),
+    Other(u8),
+}
+
+/**
+ * token and initial data sequence number derived from a key, SHA-1 for version 0 and SHA-256 from version 1
+ */
+pub fn key_hash(version: u8, key: u64) -> (u32, u64) {
+    let digest = match version {
+        0 => Sha1::digest(key.to_be_bytes()).to_vec(),
+        _ => Sha256::digest(key.to_be_bytes()).to_vec(),
+    };
+    let token = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+    let mut low = [0; 8];
+    low.copy_from_slice(&digest[digest.len() - 8..]);
+    (token, u64::from_be_bytes(low))
+}
+
+pub type MptcpEndpoint = (IpAddr, u16);
+
+fn t_endpoint(endpoint: &MptcpEndpoint) -> String {
+    match endpoint.0 {
+        IpAddr::V4(ip) => format!("{ip}:{}", endpoint.1),
+        IpAddr::V6(ip) => format!("[{ip}]:{}", endpoint.1),
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MptcpSubflow {
+    // the side that sent the SYN
+    pub source: String,
+    pub target: String,
+    // joined with MP_JOIN, the first subflow starts with MP_CAPABLE
+    pub join: bool,
+    pub address_id: Option<u8>,
+    pub backup: bool,
+    pub start: Timestamp,
+    pub packets: usize,
+    // tcp payload bytes
+    pub bytes: usize,
+    #[serde(skip)]
+    endpoints: (MptcpEndpoint, MptcpEndpoint),
+    // the SYN came from the connection client
+    #[serde(skip)]
+    from_client: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MptcpAddress {
+    pub id: u8,
+    pub address: String,
+    // announced by the client
+    pub from_client: bool,
+    pub removed: bool,
+}
+
+/**
+ * a DSS mapping of data level bytes onto a subflow
+ */
+#[derive(Serialize, Clone, Debug)]
+pub struct MptcpMapping {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub subflow: usize,
+    pub from_client: bool,
+    // relative to the initial data sequence number of the sender when its key is known
+    pub dsn: u64,
+    pub len: u16,
+    // data level bytes already mapped on some subflow
+    pub reinjection: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MptcpConnection {
+    pub index: usize,
+    pub version: u8,
+    pub client: String,
+    pub server: String,
+    pub client_key: Option<String>,
+    pub server_key: Option<String>,
+    pub client_token: Option<String>,
+    pub server_token: Option<String>,
+    pub subflows: Vec<MptcpSubflow>,
+    pub addresses: Vec<MptcpAddress>,
+    // data level bytes mapped in each direction
+    pub client_bytes: u64,
+    pub server_bytes: u64,
+    pub failures: usize,
+    pub fastclose: bool,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    // initial data sequence numbers, client then server
+    #[serde(skip)]
+    idsn: [Option<u64>; 2],
+    // highest data level byte mapped so far, client then server
+    #[serde(skip)]
+    mapped: [Option<u64>; 2],
+    #[serde(skip)]
+    mappings: Vec<MptcpMapping>,
+}
+
+impl MptcpConnection {
+    fn new(index: usize, ts: Timestamp, version: u8, client: MptcpEndpoint, server: MptcpEndpoint) -> Self {
+        Self {
+            index,
+            version,
+            client: t_endpoint(&client),
+            server: t_endpoint(&server),
+            client_key: None,
+            server_key: None,
+            client_token: None,
+            server_token: None,
+            subflows: vec![],
+            addresses: vec![],
+            client_bytes: 0,
+            server_bytes: 0,
+            failures: 0,
+            fastclose: false,
+            start: ts,
+            end: ts,
+            idsn: [None; 2],
+            mapped: [None; 2],
+            mappings: vec![],
+        }
+    }
+
+    /**
+     * mappings of both directions in data sequence order, the client side first
+     */
+    pub fn data_sequence(&self) -> Vec<MptcpMapping> {
+        let mut list = self.mappings.clone();
+        list.sort_by_key(|m| (!m.from_client, m.dsn, m.index));
+        list
+    }
+}
+
+pub struct MptcpSegment<'a> {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub source: MptcpEndpoint,
+    pub target: MptcpEndpoint,
+    pub syn: bool,
+    pub ack: bool,
+    pub len: usize,
+    pub options: &'a [MptcpOption],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MptcpFrameRef {
+    pub connection: usize,
+    pub subflow: usize,
+    // relative data sequence number of the mapping the segment carries
+    pub dsn: Option<u64>,
+}
+
+/**
+ * groups subflows into connections by the tokens the keys hash to
+ */
+#[derive(Default)]
+pub struct MptcpTable {
+    connections: Vec<MptcpConnection>,
+    // token to connection, and whether it is the client's
+    tokens: FastHashMap<u32, (usize, bool)>,
+    subflows: FastHashMap<(MptcpEndpoint, MptcpEndpoint), (usize, usize)>,
+    frames: FastHashMap<FrameIndex, MptcpFrameRef>,
+}
+
+fn path_key(a: MptcpEndpoint, b: MptcpEndpoint) -> (MptcpEndpoint, MptcpEndpoint) {
+    match a <= b {
+        true => (a, b),
+        false => (b, a),
+    }
+}
+
+impl MptcpTable {
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    // a SYN with MP_CAPABLE opens a connection, one with MP_JOIN adds a subflow to the connection its token names
+    fn open(&mut self, segment: &MptcpSegment) -> Option<(usize, usize)> {
+        let (connection, from_client, join) = segment.options.iter().find_map(|option| match option {
+            MptcpOption::Capable { version, .. } => {
+                let index = self.connections.len();
+                self.connections.push(MptcpConnection::new(index, segment.ts, *version, segment.source, segment.target));
+                Some((index, true, false))
+            }
+            MptcpOption::Join { token: Some(token), .. } => match self.tokens.get(token) {
+                // the token is the receiver's
+                Some((index, client_token)) => Some((*index, !*client_token, true)),
+                None => {
+                    // keys not captured, assume the joining side is the client
+                    let index = self.connections.len();
+                    let mut connection = MptcpConnection::new(index, segment.ts, 1, segment.source, segment.target);
+                    connection.server_token = Some(format!("{token:08x}"));
+                    self.connections.push(connection);
+                    self.tokens.insert(*token, (index, false));
+                    Some((index, true, true))
+                }
+            },
+            _ => None,
+        })?;
+        let conn = &mut self.connections[connection];
+        let subflow = conn.subflows.len();
+        conn.subflows.push(MptcpSubflow {
+            source: t_endpoint(&segment.source),
+            target: t_endpoint(&segment.target),
+            join,
+            address_id: None,
+            backup: false,
+            start: segment.ts,
+            packets: 0,
+            bytes: 0,
+            endpoints: (segment.source, segment.target),
+            from_client,
+        });
+        self.subflows.insert(path_key(segment.source, segment.target), (connection, subflow));
+        Some((connection, subflow))
+    }
+
+    fn set_key(&mut self, connection: usize, client: bool, key: u64) {
+        let conn = &mut self.connections[connection];
+        let (token, idsn) = key_hash(conn.version, key);
+        let side = if client { 0 } else { 1 };
+        if conn.idsn[side].is_some() {
+            return;
+        }
+        conn.idsn[side] = Some(idsn);
+        match client {
+            true => {
+                conn.client_key = Some(format!("{key:016x}"));
+                conn.client_token = Some(format!("{token:08x}"));
+            }
+            false => {
+                conn.server_key = Some(format!("{key:016x}"));
+                conn.server_token = Some(format!("{token:08x}"));
+            }
+        }
+        self.tokens.insert(token, (connection, client));
+    }
+
+    pub fn add(&mut self, segment: &MptcpSegment) -> Option<MptcpFrameRef> {
+        let (connection, subflow) = match self.subflows.get(&path_key(segment.source, segment.target)) {
+            Some(found) => *found,
+            None if segment.syn && !segment.ack => self.open(segment)?,
+            None => return None,
+        };
+        let initiator = self.connections[connection].subflows[subflow].endpoints.0 == segment.source;
+        // the segment was sent by the connection client
+        let from_client = self.connections[connection].subflows[subflow].from_client == initiator;
+        let mut dsn = None;
+        for option in segment.options {
+            match option {
+                MptcpOption::Capable { sender_key, receiver_key, .. } => {
+                    if let Some(key) = sender_key {
+                        self.set_key(connection, from_client, *key);
+                    }
+                    if let Some(key) = receiver_key {
+                        self.set_key(connection, !from_client, *key);
+                    }
+                }
+                MptcpOption::Join { address_id, backup, .. } if segment.syn && !segment.ack => {
+                    let sub = &mut self.connections[connection].subflows[subflow];
+                    sub.address_id = Some(*address_id);
+                    sub.backup = *backup;
+                }
+                MptcpOption::Dss { mapping: Some((raw, wide, len)), .. } => {
+                    let conn = &mut self.connections[connection];
+                    let side = if from_client { 0 } else { 1 };
+                    let relative = match (conn.idsn[side], wide) {
+                        (Some(idsn), true) => raw.wrapping_sub(idsn),
+                        (Some(idsn), false) => (*raw as u32).wrapping_sub(idsn as u32) as u64,
+                        (None, _) => *raw,
+                    };
+                    let end = relative.saturating_add(*len as u64);
+                    let reinjection = conn.mapped[side].is_some_and(|mapped| end <= mapped);
+                    if !reinjection {
+                        let bytes = end.saturating_sub(conn.mapped[side].unwrap_or(relative).max(relative));
+                        match from_client {
+                            true => conn.client_bytes += bytes,
+                            false => conn.server_bytes += bytes,
+                        }
+                        conn.mapped[side] = Some(end.max(conn.mapped[side].unwrap_or(0)));
+                    }
+                    conn.mappings.push(MptcpMapping { index: segment.index, ts: segment.ts, subflow, from_client, dsn: relative, len: *len, reinjection });
+                    dsn = Some(relative);
+                }
+                MptcpOption::AddAddr { address_id, address, port, echo: false } => {
+                    let address = match port {
+                        Some(port) => t_endpoint(&(*address, *port)),
+                        None => address.to_string(),
+                    };
+                    let addresses = &mut self.connections[connection].addresses;
+                    addresses.retain(|a| !(a.id == *address_id && a.from_client == from_client));
+                    addresses.push(MptcpAddress { id: *address_id, address, from_client, removed: false });
+                }
+                MptcpOption::RemoveAddr(ids) => {
+                    for address in self.connections[connection].addresses.iter_mut() {
+                        if address.from_client == from_client && ids.contains(&address.id) {
+                            address.removed = true;
+                        }
+                    }
+                }
+                MptcpOption::Prio { backup } => self.connections[connection].subflows[subflow].backup = *backup,
+                MptcpOption::Fail(_) => self.connections[connection].failures += 1,
+                MptcpOption::FastClose(_) => self.connections[connection].fastclose = true,
+                _ => {}
+            }
+        }
+        let conn = &mut self.connections[connection];
+        conn.end = segment.ts;
+        let sub = &mut conn.subflows[subflow];
+        sub.packets += 1;
+        sub.bytes += segment.len;
+        let frame = MptcpFrameRef { connection, subflow, dsn };
+        self.frames.insert(segment.index, frame);
+        Some(frame)
+    }
+
+    pub fn frame(&self, index: FrameIndex) -> Option<MptcpFrameRef> {
+        self.frames.get(&index).copied()
+    }
+
+    pub fn connection(&self, inx: usize) -> Option<&MptcpConnection> {
+        self.connections.get(inx)
+    }
+
+    pub fn connections(&self) -> &[MptcpConnection] {
+        &self.connections
+    }
+}
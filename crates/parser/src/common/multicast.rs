@@ -0,0 +1,141 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::{collections::hash_map::Entry, net::Ipv4Addr};
+
+use serde::Serialize;
+
+use crate::common::{
+    concept::{FrameIndex, Timestamp},
+    FastHashMap, NString,
+};
+
+// RFC 2236 8.5: robustness (2) * query interval (125s) + max response time (10s) / 2
+pub const DEFAULT_QUERIER_TIMEOUT: Timestamp = 255_000_000;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum MulticastAction {
+    Join,
+    Leave,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MulticastEvent {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub host: Ipv4Addr,
+    pub group: Ipv4Addr,
+    pub action: MulticastAction,
+    pub version: u8,
+    // empty means any source
+    pub sources: Vec<Ipv4Addr>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MulticastMembership {
+    pub host: Ipv4Addr,
+    pub group: Ipv4Addr,
+    pub join: Timestamp,
+    pub leave: Option<Timestamp>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct QuerierElection {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub querier: Ipv4Addr,
+    pub previous: Option<Ipv4Addr>,
+    pub reason: NString,
+}
+
+#[derive(Serialize, Default, Clone, Debug)]
+pub struct MulticastReport {
+    pub events: Vec<MulticastEvent>,
+    pub memberships: Vec<MulticastMembership>,
+    pub elections: Vec<QuerierElection>,
+}
+
+struct Querier {
+    address: Ipv4Addr,
+    last: Timestamp,
+    timeout: Timestamp,
+}
+
+/**
+ * membership changes and querier elections seen on the capture, timestamps in microseconds
+ */
+#[derive(Default)]
+pub struct MulticastTable {
+    events: Vec<MulticastEvent>,
+    elections: Vec<QuerierElection>,
+    querier: Option<Querier>,
+}
+
+impl MulticastTable {
+    pub fn add_event(&mut self, event: MulticastEvent) {
+        self.events.push(event);
+    }
+
+    /**
+     * the querier with the lowest address wins; a silent querier is replaced once its timeout passes
+     */
+    pub fn add_query(&mut self, index: FrameIndex, ts: Timestamp, source: Ipv4Addr, timeout: Timestamp) {
+        let reason = match &mut self.querier {
+            None => "initial",
+            Some(current) if current.address == source => {
+                current.last = ts;
+                current.timeout = timeout;
+                return;
+            }
+            Some(current) if source < current.address => "lower address",
+            Some(current) if ts.saturating_sub(current.last) > current.timeout => "querier timeout",
+            Some(_) => return,
+        };
+        let previous = self.querier.as_ref().map(|q| q.address);
+        self.elections.push(QuerierElection { index, ts, querier: source, previous, reason });
+        self.querier = Some(Querier { address: source, last: ts, timeout });
+    }
+
+    pub fn querier(&self) -> Option<Ipv4Addr> {
+        self.querier.as_ref().map(|q| q.address)
+    }
+
+    /**
+     * only a leave without sources ends a membership; blocking single sources keeps the host in the group
+     */
+    pub fn memberships(&self) -> Vec<MulticastMembership> {
+        let mut open: FastHashMap<(Ipv4Addr, Ipv4Addr), usize> = FastHashMap::default();
+        let mut list: Vec<MulticastMembership> = vec![];
+        for event in &self.events {
+            let key = (event.host, event.group);
+            match event.action {
+                MulticastAction::Join => {
+                    if let Entry::Vacant(entry) = open.entry(key) {
+                        entry.insert(list.len());
+                        list.push(MulticastMembership { host: event.host, group: event.group, join: event.ts, leave: None });
+                    }
+                }
+                MulticastAction::Leave => {
+                    if !event.sources.is_empty() {
+                        continue;
+                    }
+                    if let Some(inx) = open.remove(&key) {
+                        list[inx].leave = Some(event.ts);
+                    }
+                }
+            }
+        }
+        list.sort_by(|a, b| a.host.cmp(&b.host).then(a.join.cmp(&b.join)));
+        list
+    }
+
+    pub fn report(&self) -> MulticastReport {
+        MulticastReport {
+            events: self.events.clone(),
+            memberships: self.memberships(),
+            elections: self.elections.clone(),
+        }
+    }
+}
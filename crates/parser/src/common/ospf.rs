@@ -0,0 +1,277 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use serde::Serialize;
+
+use crate::common::{
+    concept::{FrameIndex, Timestamp},
+    FastHashMap,
+};
+
+// database description flags
+pub const DBD_INIT: u8 = 0x04;
+pub const DBD_MORE: u8 = 0x02;
+
+/**
+ * neighbour states of rfc 2328 10.1 that can be told apart from the wire, Attempt is NBMA only
+ */
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum OspfState {
+    Down,
+    Init,
+    TwoWay,
+    ExStart,
+    Exchange,
+    Loading,
+    Full,
+}
+
+/**
+ * common header fields of the packet being recorded
+ */
+#[derive(Clone, Copy)]
+pub struct OspfPacket {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub version: u8,
+    pub area: Ipv4Addr,
+    pub router: Ipv4Addr,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct OspfTransition {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub from: OspfState,
+    pub to: OspfState,
+    pub event: &'static str,
+}
+
+#[derive(Default, Clone, Copy, Debug)]
+struct Side {
+    last_hello: Option<Timestamp>,
+    dead_interval: Timestamp,
+    // the router lists the other one in its hello
+    sees_peer: bool,
+    // a database description without the init bit was seen from the router
+    exchanging: bool,
+    more: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct OspfAdjacency {
+    pub version: u8,
+    pub area: Ipv4Addr,
+    pub routers: (Ipv4Addr, Ipv4Addr),
+    pub state: OspfState,
+    pub transitions: Vec<OspfTransition>,
+    #[serde(skip)]
+    sides: [Side; 2],
+    #[serde(skip)]
+    pending_lsr: usize,
+}
+
+impl OspfAdjacency {
+    fn new(version: u8, area: Ipv4Addr, routers: (Ipv4Addr, Ipv4Addr)) -> Self {
+        Self { version, area, routers, state: OspfState::Down, transitions: vec![], sides: [Side::default(); 2], pending_lsr: 0 }
+    }
+
+    fn side(&mut self, router: Ipv4Addr) -> &mut Side {
+        match router == self.routers.0 {
+            true => &mut self.sides[0],
+            false => &mut self.sides[1],
+        }
+    }
+
+    fn set(&mut self, index: FrameIndex, ts: Timestamp, to: OspfState, event: &'static str) {
+        if self.state != to {
+            self.transitions.push(OspfTransition { index, ts, from: self.state, to, event });
+            self.state = to;
+        }
+    }
+
+    fn exchange_done(&self) -> bool {
+        self.sides.iter().all(|s| s.exchanging && !s.more)
+    }
+
+    fn reset(&mut self) {
+        self.sides = [Side::default(); 2];
+        self.pending_lsr = 0;
+    }
+}
+
+/**
+ * adjacency progress per router pair and area, followed passively from hello, database
+ * description, request and update packets. Packets sent to AllSPFRouters on a point-to-point
+ * link are matched to the only neighbour the sender announces
+ */
+#[derive(Default)]
+pub struct OspfTable {
+    adjacencies: Vec<OspfAdjacency>,
+    keys: FastHashMap<(Ipv4Addr, Ipv4Addr, Ipv4Addr), usize>,
+    // interface address to router id, learned from hellos
+    routers: FastHashMap<IpAddr, Ipv4Addr>,
+    neighbors: FastHashMap<Ipv4Addr, Vec<Ipv4Addr>>,
+}
+
+fn pair(a: Ipv4Addr, b: Ipv4Addr) -> (Ipv4Addr, Ipv4Addr) {
+    match a < b {
+        true => (a, b),
+        false => (b, a),
+    }
+}
+
+impl OspfTable {
+    fn adjacency(&mut self, version: u8, area: Ipv4Addr, a: Ipv4Addr, b: Ipv4Addr) -> &mut OspfAdjacency {
+        let routers = pair(a, b);
+        let inx = *self.keys.entry((area, routers.0, routers.1)).or_insert_with(|| {
+            self.adjacencies.push(OspfAdjacency::new(version, area, routers));
+            self.adjacencies.len() - 1
+        });
+        &mut self.adjacencies[inx]
+    }
+
+    /**
+     * the neighbour a packet is meant for, from a unicast destination or the single neighbour of a point-to-point link
+     */
+    pub fn peer(&self, router: Ipv4Addr, target: Option<IpAddr>) -> Option<Ipv4Addr> {
+        if let Some(peer) = target.and_then(|ip| self.routers.get(&ip)) {
+            return Some(*peer);
+        }
+        match self.neighbors.get(&router).map(|list| list.as_slice()) {
+            Some([peer]) => Some(*peer),
+            _ => None,
+        }
+    }
+
+    // neighbours whose hellos stopped for longer than their dead interval go down
+    fn expire(&mut self, index: FrameIndex, ts: Timestamp) {
+        for adjacency in self.adjacencies.iter_mut() {
+            let dead = adjacency.sides.iter().any(|s| s.last_hello.is_some_and(|last| ts.saturating_sub(last) > s.dead_interval));
+            if dead && adjacency.state != OspfState::Down {
+                adjacency.reset();
+                adjacency.set(index, ts, OspfState::Down, "inactivity timer");
+            }
+        }
+    }
+
+    pub fn hello(&mut self, packet: &OspfPacket, source: Option<IpAddr>, dead_interval: u32, neighbors: &[Ipv4Addr]) {
+        let OspfPacket { index, ts, version, area, router } = *packet;
+        self.expire(index, ts);
+        if let Some(source) = source {
+            self.routers.insert(source, router);
+        }
+        self.neighbors.insert(router, neighbors.to_vec());
+        for peer in neighbors {
+            self.adjacency(version, area, router, *peer);
+        }
+        for adjacency in self.adjacencies.iter_mut().filter(|a| a.area == area && (a.routers.0 == router || a.routers.1 == router)) {
+            let peer = match adjacency.routers.0 == router {
+                true => adjacency.routers.1,
+                false => adjacency.routers.0,
+            };
+            let side = adjacency.side(router);
+            side.last_hello = Some(ts);
+            side.dead_interval = dead_interval as Timestamp * 1_000_000;
+            side.sees_peer = neighbors.contains(&peer);
+            let both = adjacency.sides.iter().all(|s| s.sees_peer);
+            match adjacency.state {
+                OspfState::Down | OspfState::Init if both => adjacency.set(index, ts, OspfState::TwoWay, "2-way received"),
+                OspfState::Down => adjacency.set(index, ts, OspfState::Init, "hello received"),
+                state if !both && state > OspfState::Init => {
+                    adjacency.reset();
+                    adjacency.side(router).last_hello = Some(ts);
+                    adjacency.set(index, ts, OspfState::Init, "1-way received");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn dbd(&mut self, packet: &OspfPacket, target: Option<IpAddr>, flags: u8) {
+        let OspfPacket { index, ts, version, area, router } = *packet;
+        self.expire(index, ts);
+        let Some(peer) = self.peer(router, target) else {
+            return;
+        };
+        let adjacency = self.adjacency(version, area, router, peer);
+        if flags & DBD_INIT != 0 {
+            if adjacency.state > OspfState::ExStart {
+                // a restarted negotiation drops what was exchanged so far
+                adjacency.sides.iter_mut().for_each(|s| s.exchanging = false);
+                adjacency.pending_lsr = 0;
+            }
+            adjacency.set(index, ts, OspfState::ExStart, "negotiation");
+            return;
+        }
+        let side = adjacency.side(router);
+        side.exchanging = true;
+        side.more = flags & DBD_MORE != 0;
+        if adjacency.state < OspfState::Exchange {
+            adjacency.set(index, ts, OspfState::Exchange, "negotiation done");
+        }
+        if adjacency.state == OspfState::Exchange && adjacency.exchange_done() {
+            match adjacency.pending_lsr {
+                0 => adjacency.set(index, ts, OspfState::Full, "exchange done"),
+                _ => adjacency.set(index, ts, OspfState::Loading, "exchange done"),
+            }
+        }
+    }
+
+    pub fn lsr(&mut self, packet: &OspfPacket, target: Option<IpAddr>, count: usize) {
+        let OspfPacket { index, ts, version, area, router } = *packet;
+        self.expire(index, ts);
+        let Some(peer) = self.peer(router, target) else {
+            return;
+        };
+        let adjacency = self.adjacency(version, area, router, peer);
+        adjacency.pending_lsr += count;
+        if adjacency.state == OspfState::Exchange && adjacency.exchange_done() {
+            adjacency.set(index, ts, OspfState::Loading, "exchange done");
+        }
+    }
+
+    /**
+     * an update answers outstanding requests of the neighbours of the sender
+     */
+    pub fn lsu(&mut self, packet: &OspfPacket, count: usize) {
+        let OspfPacket { index, ts, area, router, .. } = *packet;
+        self.expire(index, ts);
+        for adjacency in self.adjacencies.iter_mut().filter(|a| a.area == area && (a.routers.0 == router || a.routers.1 == router)) {
+            if adjacency.pending_lsr == 0 {
+                continue;
+            }
+            adjacency.pending_lsr = adjacency.pending_lsr.saturating_sub(count);
+            if adjacency.pending_lsr == 0 && adjacency.state == OspfState::Loading {
+                adjacency.set(index, ts, OspfState::Full, "loading done");
+            }
+        }
+    }
+
+    /**
+     * a router or network lsa only lists fully adjacent neighbours
+     */
+    pub fn advertised(&mut self, packet: &OspfPacket, router: Ipv4Addr, neighbor: Ipv4Addr) {
+        if router == neighbor {
+            return;
+        }
+        let adjacency = self.adjacency(packet.version, packet.area, router, neighbor);
+        if adjacency.state < OspfState::Full {
+            adjacency.pending_lsr = 0;
+            adjacency.set(packet.index, packet.ts, OspfState::Full, "advertised in lsa");
+        }
+    }
+
+    pub fn state(&self, area: Ipv4Addr, a: Ipv4Addr, b: Ipv4Addr) -> Option<OspfState> {
+        let routers = pair(a, b);
+        self.keys.get(&(area, routers.0, routers.1)).map(|inx| self.adjacencies[*inx].state)
+    }
+
+    pub fn adjacencies(&self) -> &[OspfAdjacency] {
+        &self.adjacencies
+    }
+}
@@ -0,0 +1,502 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::net::IpAddr;
+
+use aes::{
+    cipher::{generic_array::GenericArray, BlockEncrypt},
+    Aes128,
+};
+use aes_gcm::{
+    aead::{AeadInPlace, KeyInit, Nonce, Tag},
+    Aes128Gcm,
+};
+use anyhow::{anyhow, bail, Result};
+use hkdf::Hkdf;
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::common::{
+    concept::{FrameIndex, TLSItem, Timestamp},
+    io::DataSource,
+    io::Reader,
+    resolve_handshake, FastHashMap,
+};
+
+pub const QUIC_V1: u32 = 1;
+pub const QUIC_V2: u32 = 0x6b33_43cf;
+
+// RFC 9001 5.2 and RFC 9369 3.3.1
+const INITIAL_SALT_V1: [u8; 20] = [
+    0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17, 0x9a, 0xe6, 0xa4, 0xc8, 0x0c, 0xad, 0xcc, 0xbb, 0x7f, 0x0a,
+];
+const INITIAL_SALT_V2: [u8; 20] = [
+    0x0d, 0xed, 0xe3, 0xde, 0xf7, 0x00, 0xa6, 0xdb, 0x81, 0x93, 0x81, 0xbe, 0x6e, 0x26, 0x9d, 0xcb, 0xf9, 0xbd, 0x2e, 0xd9,
+];
+
+// the crypto stream is only kept up to the end of the first handshake message
+const CRYPTO_LIMIT: usize = 64 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum QuicPacketType {
+    Initial,
+    ZeroRtt,
+    Handshake,
+    Retry,
+    VersionNegotiation,
+    // 1-RTT, short header
+    OneRtt,
+}
+
+impl QuicPacketType {
+    /**
+     * long header type bits, QUIC v2 rotates the values of v1
+     */
+    pub fn long(version: u32, bits: u8) -> Self {
+        let bits = match version {
+            QUIC_V2 => (bits + 3) & 0x03,
+            _ => bits & 0x03,
+        };
+        match bits {
+            0 => QuicPacketType::Initial,
+            1 => QuicPacketType::ZeroRtt,
+            2 => QuicPacketType::Handshake,
+            _ => QuicPacketType::Retry,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            QuicPacketType::Initial => "Initial",
+            QuicPacketType::ZeroRtt => "0-RTT",
+            QuicPacketType::Handshake => "Handshake",
+            QuicPacketType::Retry => "Retry",
+            QuicPacketType::VersionNegotiation => "Version Negotiation",
+            QuicPacketType::OneRtt => "Protected Payload",
+        }
+    }
+}
+
+/**
+ * what the info column shows of one packet of a datagram
+ */
+#[derive(Clone, Debug)]
+pub struct QuicInfo {
+    pub packet_type: QuicPacketType,
+    // only known for decrypted packets
+    pub packet_number: Option<u64>,
+    // frame types of a decrypted packet, repeats collapsed
+    pub frames: Vec<u64>,
+}
+
+pub fn quic_version(version: u32) -> String {
+    match version {
+        0 => "Version Negotiation".into(),
+        QUIC_V1 => "1".into(),
+        QUIC_V2 => "2".into(),
+        v if v & 0xffff_ff00 == 0xff00_0000 => format!("draft-{}", v & 0xff),
+        v if v & 0x0f0f_0f0f == 0x0a0a_0a0a => format!("Forcing Version Negotiation ({v:#010x})"),
+        v => format!("{v:#010x}"),
+    }
+}
+
+pub fn hex_id(id: &[u8]) -> String {
+    id.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/**
+ * packet protection keys of one direction
+ */
+pub struct QuicKeys {
+    pub key: [u8; 16],
+    pub iv: [u8; 12],
+    pub hp: [u8; 16],
+}
+
+// RFC 8446 7.1 HkdfLabel with an empty context
+fn expand_label(hkdf: &Hkdf<Sha256>, label: &str, out: &mut [u8]) -> Result<()> {
+    let label = format!("tls13 {label}");
+    let mut info = (out.len() as u16).to_be_bytes().to_vec();
+    info.push(label.len() as u8);
+    info.extend_from_slice(label.as_bytes());
+    info.push(0);
+    hkdf.expand(&info, out).map_err(|_| anyhow!("invalid hkdf length"))
+}
+
+/**
+ * RFC 9001 5.2: both directions derive their Initial keys from the destination connection id of the client's first Initial
+ */
+pub fn initial_keys(version: u32, dcid: &[u8], server: bool) -> Result<QuicKeys> {
+    let (salt, prefix) = match version {
+        QUIC_V1 => (&INITIAL_SALT_V1, "quic"),
+        QUIC_V2 => (&INITIAL_SALT_V2, "quicv2"),
+        _ => bail!("unsupported quic version {version:#010x}"),
+    };
+    let (_, initial) = Hkdf::<Sha256>::extract(Some(salt), dcid);
+    let mut secret = [0u8; 32];
+    expand_label(&initial, if server { "server in" } else { "client in" }, &mut secret)?;
+    let secret = Hkdf::<Sha256>::from_prk(&secret).map_err(|_| anyhow!("invalid prk"))?;
+    let mut keys = QuicKeys { key: [0; 16], iv: [0; 12], hp: [0; 16] };
+    expand_label(&secret, &format!("{prefix} key"), &mut keys.key)?;
+    expand_label(&secret, &format!("{prefix} iv"), &mut keys.iv)?;
+    expand_label(&secret, &format!("{prefix} hp"), &mut keys.hp)?;
+    Ok(keys)
+}
+
+pub struct QuicPlain {
+    // first byte without header protection
+    pub first: u8,
+    pub packet_number: u64,
+    pub pn_len: usize,
+    pub payload: Vec<u8>,
+}
+
+/**
+ * removes header protection and opens the payload. packet is the whole quic packet
+ * and pn_offset the position of the protected packet number in it
+ */
+pub fn open_packet(keys: &QuicKeys, packet: &[u8], pn_offset: usize) -> Result<QuicPlain> {
+    // RFC 9001 5.4.2: the sample starts 4 bytes after the packet number field begins
+    let sample = packet.get(pn_offset + 4..pn_offset + 20).ok_or(anyhow!("packet too short for header protection sample"))?;
+    let mut mask = GenericArray::clone_from_slice(sample);
+    Aes128::new(GenericArray::from_slice(&keys.hp)).encrypt_block(&mut mask);
+    let long = packet[0] & 0x80 != 0;
+    let first = packet[0] ^ (mask[0] & if long { 0x0f } else { 0x1f });
+    let pn_len = (first & 0x03) as usize + 1;
+    let mut header = packet[..pn_offset + pn_len].to_vec();
+    header[0] = first;
+    let mut packet_number = 0u64;
+    for i in 0..pn_len {
+        header[pn_offset + i] ^= mask[1 + i];
+        packet_number = (packet_number << 8) | header[pn_offset + i] as u64;
+    }
+    if packet.len() < header.len() + 16 {
+        bail!("packet too short for aead tag");
+    }
+    let (body, tag) = packet[header.len()..].split_at(packet.len() - header.len() - 16);
+    let mut nonce = keys.iv;
+    for (n, p) in nonce[4..].iter_mut().zip(packet_number.to_be_bytes()) {
+        *n ^= p;
+    }
+    let mut payload = body.to_vec();
+    let cipher = Aes128Gcm::new_from_slice(&keys.key).map_err(|_| anyhow!("invalid key length"))?;
+    cipher
+        .decrypt_in_place_detached(Nonce::<Aes128Gcm>::from_slice(&nonce), &header, &mut payload, Tag::<Aes128Gcm>::from_slice(tag))
+        .map_err(|_| anyhow!("authentication failed"))?;
+    Ok(QuicPlain { first, packet_number, pn_len, payload })
+}
+
+/**
+ * crypto frames of one direction, contiguous from offset 0
+ */
+#[derive(Default, Clone, Debug)]
+struct CryptoStream {
+    data: Vec<u8>,
+    pending: Vec<(usize, Vec<u8>)>,
+}
+
+impl CryptoStream {
+    fn add(&mut self, offset: usize, chunk: &[u8]) {
+        if offset + chunk.len() > CRYPTO_LIMIT || self.message().is_some() {
+            return;
+        }
+        self.pending.push((offset, chunk.to_vec()));
+        let data = &mut self.data;
+        loop {
+            let before = self.pending.len();
+            self.pending.retain(|(offset, chunk)| {
+                if *offset > data.len() {
+                    return true;
+                }
+                if offset + chunk.len() > data.len() {
+                    data.extend_from_slice(&chunk[data.len() - offset..]);
+                }
+                false
+            });
+            if self.pending.len() == before {
+                break;
+            }
+        }
+    }
+    // first handshake message once all of it arrived
+    fn message(&self) -> Option<&[u8]> {
+        let head = self.data.get(..4)?;
+        let len = u32::from_be_bytes([0, head[1], head[2], head[3]]) as usize;
+        self.data.get(..4 + len)
+    }
+}
+
+pub type QuicEndpoint = (IpAddr, u16);
+
+#[derive(Serialize, Clone, Debug)]
+pub struct QuicConnection {
+    pub index: usize,
+    pub client: String,
+    pub server: String,
+    pub version: String,
+    // destination connection id of the first client Initial
+    pub original_dcid: String,
+    // connection ids chosen by each side, in the order they showed up
+    pub client_cids: Vec<String>,
+    pub server_cids: Vec<String>,
+    pub sni: Option<String>,
+    pub alpn: Option<Vec<String>>,
+    pub packets: usize,
+    // datagrams of the connection seen on another address or port
+    pub migrations: usize,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    #[serde(skip)]
+    pub version_code: u32,
+    #[serde(skip)]
+    pub endpoints: (QuicEndpoint, QuicEndpoint),
+    #[serde(skip)]
+    dcid: Vec<u8>,
+    // a Retry switches the Initial keys to the connection id it offers
+    #[serde(skip)]
+    retry: Option<(FrameIndex, Vec<u8>)>,
+    #[serde(skip)]
+    cids: (Vec<Vec<u8>>, Vec<Vec<u8>>),
+    #[serde(skip)]
+    crypto: (CryptoStream, CryptoStream),
+    // frames completing the ClientHello and the ServerHello
+    #[serde(skip)]
+    hello: (Option<FrameIndex>, Option<FrameIndex>),
+}
+
+fn t_endpoint(endpoint: &QuicEndpoint) -> String {
+    match endpoint.0 {
+        IpAddr::V4(ip) => format!("{ip}:{}", endpoint.1),
+        IpAddr::V6(ip) => format!("[{ip}]:{}", endpoint.1),
+    }
+}
+
+impl QuicConnection {
+    fn add_cid(&mut self, from_client: bool, cid: &[u8]) -> bool {
+        if cid.is_empty() {
+            return false;
+        }
+        let (cids, labels) = match from_client {
+            true => (&mut self.cids.0, &mut self.client_cids),
+            false => (&mut self.cids.1, &mut self.server_cids),
+        };
+        if cids.iter().any(|c| c == cid) {
+            return false;
+        }
+        cids.push(cid.to_vec());
+        labels.push(hex_id(cid));
+        true
+    }
+    /**
+     * Initial keys of a direction as they were when the frame was captured
+     */
+    pub fn initial_keys(&self, index: FrameIndex, server: bool) -> Result<QuicKeys> {
+        let dcid = match &self.retry {
+            Some((retry, dcid)) if *retry < index => dcid,
+            _ => &self.dcid,
+        };
+        initial_keys(self.version_code, dcid, server)
+    }
+    pub fn client_hello(&self) -> Option<&[u8]> {
+        self.crypto.0.message().filter(|m| m[0] == 1)
+    }
+    pub fn server_hello(&self) -> Option<&[u8]> {
+        self.crypto.1.message().filter(|m| m[0] == 2)
+    }
+    /**
+     * the hello message a frame completed, for showing it under that frame
+     */
+    pub fn hello_of(&self, index: FrameIndex) -> Option<&[u8]> {
+        match self.hello {
+            (Some(inx), _) if inx == index => self.client_hello(),
+            (_, Some(inx)) if inx == index => self.server_hello(),
+            _ => None,
+        }
+    }
+    pub fn tls_item(&self) -> TLSItem {
+        let mut item = TLSItem::default();
+        for hello in [self.client_hello(), self.server_hello()].into_iter().flatten() {
+            let ds = DataSource::create(hello.to_vec(), 0..0);
+            resolve_handshake(&mut Reader::new(&ds), hello[0], &mut item).ok();
+        }
+        item
+    }
+}
+
+/**
+ * the header fields of a packet the table needs
+ */
+pub struct QuicHeader<'a> {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub source: QuicEndpoint,
+    pub target: QuicEndpoint,
+    pub packet_type: QuicPacketType,
+    pub version: u32,
+    pub dcid: &'a [u8],
+    pub scid: &'a [u8],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct QuicFrameRef {
+    pub connection: usize,
+    pub from_client: bool,
+}
+
+/**
+ * connections by the connection ids both sides chose, with the address pair as fallback for zero length ids
+ */
+#[derive(Default)]
+pub struct QuicTable {
+    connections: Vec<QuicConnection>,
+    cids: FastHashMap<Vec<u8>, usize>,
+    // lengths of the connection ids in cids, a short header does not carry its own
+    cid_lens: Vec<usize>,
+    paths: FastHashMap<(QuicEndpoint, QuicEndpoint), usize>,
+    frames: FastHashMap<FrameIndex, QuicFrameRef>,
+}
+
+fn path_key(a: QuicEndpoint, b: QuicEndpoint) -> (QuicEndpoint, QuicEndpoint) {
+    match a <= b {
+        true => (a, b),
+        false => (b, a),
+    }
+}
+
+impl QuicTable {
+    /**
+     * connection id length of a short header packet: a known id it starts with, else the length
+     * of the ids its receiver uses on that path
+     */
+    pub fn short_dcid_len(&self, source: QuicEndpoint, target: QuicEndpoint, data: &[u8]) -> usize {
+        let mut lens: Vec<usize> = self.cid_lens.iter().copied().filter(|len| *len <= data.len()).collect();
+        lens.sort_unstable_by(|a, b| b.cmp(a));
+        if let Some(len) = lens.into_iter().find(|len| self.cids.contains_key(&data[..*len])) {
+            return len;
+        }
+        match self.paths.get(&path_key(source, target)).map(|inx| &self.connections[*inx]) {
+            Some(conn) => {
+                let cids = match conn.endpoints.0 == target {
+                    true => &conn.cids.0,
+                    false => &conn.cids.1,
+                };
+                cids.last().map(|cid| cid.len()).unwrap_or(0)
+            }
+            None => 0,
+        }
+    }
+
+    fn register(&mut self, inx: usize, cid: &[u8]) {
+        if !cid.is_empty() {
+            self.cids.insert(cid.to_vec(), inx);
+            if !self.cid_lens.contains(&cid.len()) {
+                self.cid_lens.push(cid.len());
+            }
+        }
+    }
+
+    /**
+     * finds or opens the connection of a packet, only a client Initial opens one
+     */
+    pub fn add(&mut self, header: &QuicHeader) -> Option<QuicFrameRef> {
+        let path = path_key(header.source, header.target);
+        let by_cid = match header.dcid.is_empty() {
+            true => None,
+            false => self.cids.get(header.dcid).copied(),
+        };
+        let inx = match by_cid.or_else(|| self.paths.get(&path).copied()) {
+            Some(inx) => inx,
+            None if header.packet_type == QuicPacketType::Initial => {
+                let inx = self.connections.len();
+                self.connections.push(QuicConnection {
+                    index: inx,
+                    client: t_endpoint(&header.source),
+                    server: t_endpoint(&header.target),
+                    version: quic_version(header.version),
+                    original_dcid: hex_id(header.dcid),
+                    client_cids: vec![],
+                    server_cids: vec![],
+                    sni: None,
+                    alpn: None,
+                    packets: 0,
+                    migrations: 0,
+                    start: header.ts,
+                    end: header.ts,
+                    version_code: header.version,
+                    endpoints: (header.source, header.target),
+                    dcid: header.dcid.to_vec(),
+                    retry: None,
+                    cids: (vec![], vec![]),
+                    crypto: Default::default(),
+                    hello: (None, None),
+                });
+                self.register(inx, header.dcid);
+                inx
+            }
+            None => return None,
+        };
+        let conn = &mut self.connections[inx];
+        // the ids a side chose show up as destination of the packets it receives
+        let from_client = match conn.cids.0.iter().any(|c| c == header.dcid) {
+            true => false,
+            false if conn.cids.1.iter().any(|c| c == header.dcid) || conn.dcid == header.dcid => true,
+            false => conn.endpoints.0 == header.source || conn.endpoints.1 == header.target,
+        };
+        conn.packets += 1;
+        conn.end = header.ts;
+        if !self.paths.contains_key(&path) {
+            if self.paths.values().any(|i| *i == inx) {
+                conn.migrations += 1;
+            }
+            self.paths.insert(path, inx);
+        }
+        let mut ids = vec![];
+        if header.packet_type != QuicPacketType::OneRtt && conn.add_cid(from_client, header.scid) {
+            ids.push(header.scid);
+        }
+        if header.dcid != conn.dcid.as_slice() && conn.add_cid(!from_client, header.dcid) {
+            ids.push(header.dcid);
+        }
+        if header.packet_type == QuicPacketType::Retry && conn.retry.is_none() {
+            conn.retry = Some((header.index, header.scid.to_vec()));
+        }
+        for id in ids {
+            self.register(inx, id);
+        }
+        let frame = QuicFrameRef { connection: inx, from_client };
+        self.frames.insert(header.index, frame);
+        Some(frame)
+    }
+
+    /**
+     * data of a CRYPTO frame in a decrypted Initial packet
+     */
+    pub fn add_crypto(&mut self, frame: QuicFrameRef, index: FrameIndex, offset: usize, data: &[u8]) {
+        let conn = &mut self.connections[frame.connection];
+        let (stream, hello) = match frame.from_client {
+            true => (&mut conn.crypto.0, &mut conn.hello.0),
+            false => (&mut conn.crypto.1, &mut conn.hello.1),
+        };
+        let complete = stream.message().is_some();
+        stream.add(offset, data);
+        if !complete && stream.message().is_some() {
+            *hello = Some(index);
+            let item = conn.tls_item();
+            conn.sni = item.hostname;
+            conn.alpn = item.alpn;
+        }
+    }
+
+    pub fn frame(&self, index: FrameIndex) -> Option<QuicFrameRef> {
+        self.frames.get(&index).copied()
+    }
+
+    pub fn connection(&self, inx: usize) -> Option<&QuicConnection> {
+        self.connections.get(inx)
+    }
+
+    pub fn connections(&self) -> &[QuicConnection] {
+        &self.connections
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::{net::IpAddr, ops::Range};
+
+use serde::Serialize;
+
+use crate::common::{
+    concept::{FrameIndex, Timestamp},
+    FastHashMap,
+};
+
+pub const SCTP_DATA: u8 = 0;
+pub const SCTP_INIT: u8 = 1;
+pub const SCTP_INIT_ACK: u8 = 2;
+pub const SCTP_ABORT: u8 = 6;
+pub const SCTP_SHUTDOWN: u8 = 7;
+pub const SCTP_COOKIE_ACK: u8 = 11;
+pub const SCTP_SHUTDOWN_COMPLETE: u8 = 14;
+pub const SCTP_I_DATA: u8 = 64;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum SctpState {
+    // INIT seen, waiting for the cookie exchange
+    Init,
+    Established,
+    ShuttingDown,
+    Closed,
+    Aborted,
+}
+
+/**
+ * user message of one DATA or I-DATA chunk, for the upper layer to pick up by ppid and stream
+ */
+#[derive(Clone, Debug)]
+pub struct SctpPayload {
+    pub tsn: u32,
+    pub stream: u16,
+    pub ppid: u32,
+    pub range: Range<usize>,
+}
+
+/**
+ * what the table needs from a chunk
+ */
+pub enum SctpChunk {
+    // initiate tag, outbound streams, inbound streams
+    Init(u32, u16, u16),
+    InitAck(u32, u16, u16),
+    Data(SctpPayload),
+    Other(u8),
+}
+
+pub struct SctpPacket {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub source: (IpAddr, u16),
+    pub target: (IpAddr, u16),
+    pub chunks: Vec<SctpChunk>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SctpStream {
+    pub id: u16,
+    pub chunks: usize,
+    pub bytes: usize,
+    // payload protocol identifiers seen on the stream
+    pub ppids: Vec<u32>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SctpAssociation {
+    pub index: usize,
+    // sender of the INIT, or of the first packet when the handshake was not captured
+    pub initiator: String,
+    pub responder: String,
+    // verification tags each side asked for
+    pub initiator_tag: Option<u32>,
+    pub responder_tag: Option<u32>,
+    // outbound and inbound streams offered in INIT and INIT ACK
+    pub initiator_streams: Option<(u16, u16)>,
+    pub responder_streams: Option<(u16, u16)>,
+    pub state: SctpState,
+    pub packets: usize,
+    pub data_chunks: usize,
+    pub bytes: usize,
+    pub retransmissions: usize,
+    pub streams: Vec<SctpStream>,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    #[serde(skip)]
+    endpoints: ((IpAddr, u16), (IpAddr, u16)),
+    // highest tsn per direction
+    #[serde(skip)]
+    highest_tsn: (Option<u32>, Option<u32>),
+}
+
+fn t_endpoint(endpoint: &(IpAddr, u16)) -> String {
+    match endpoint.0 {
+        IpAddr::V4(ip) => format!("{ip}:{}", endpoint.1),
+        IpAddr::V6(ip) => format!("[{ip}]:{}", endpoint.1),
+    }
+}
+
+impl SctpAssociation {
+    fn new(index: usize, packet: &SctpPacket) -> Self {
+        Self {
+            index,
+            initiator: t_endpoint(&packet.source),
+            responder: t_endpoint(&packet.target),
+            initiator_tag: None,
+            responder_tag: None,
+            initiator_streams: None,
+            responder_streams: None,
+            state: SctpState::Init,
+            packets: 0,
+            data_chunks: 0,
+            bytes: 0,
+            retransmissions: 0,
+            streams: vec![],
+            start: packet.ts,
+            end: packet.ts,
+            endpoints: (packet.source, packet.target),
+            highest_tsn: (None, None),
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        matches!(self.state, SctpState::Closed | SctpState::Aborted)
+    }
+
+    // returns true when the tsn was seen before in this direction
+    fn data(&mut self, forward: bool, payload: &SctpPayload) -> bool {
+        let highest = match forward {
+            true => &mut self.highest_tsn.0,
+            false => &mut self.highest_tsn.1,
+        };
+        let retransmission = matches!(highest, Some(h) if (payload.tsn.wrapping_sub(*h) as i32) <= 0);
+        if !retransmission {
+            *highest = Some(payload.tsn);
+        }
+        let len = payload.range.len();
+        self.data_chunks += 1;
+        self.bytes += len;
+        if retransmission {
+            self.retransmissions += 1;
+        }
+        let stream = match self.streams.iter().position(|s| s.id == payload.stream) {
+            Some(inx) => &mut self.streams[inx],
+            None => {
+                self.streams.push(SctpStream { id: payload.stream, chunks: 0, bytes: 0, ppids: vec![] });
+                self.streams.last_mut().unwrap()
+            }
+        };
+        stream.chunks += 1;
+        stream.bytes += len;
+        if !stream.ppids.contains(&payload.ppid) {
+            stream.ppids.push(payload.ppid);
+        }
+        retransmission
+    }
+}
+
+/**
+ * associations by their two transport addresses, a new INIT after the previous one closed starts another association
+ */
+#[derive(Default)]
+pub struct SctpTable {
+    associations: Vec<SctpAssociation>,
+    active: FastHashMap<((IpAddr, u16), (IpAddr, u16)), usize>,
+    frames: FastHashMap<FrameIndex, usize>,
+    payloads: FastHashMap<FrameIndex, Vec<SctpPayload>>,
+    // frames carrying a DATA chunk with a tsn seen before
+    retransmitted: FastHashMap<FrameIndex, usize>,
+}
+
+impl SctpTable {
+    pub fn add(&mut self, packet: SctpPacket) {
+        let key = match packet.source <= packet.target {
+            true => (packet.source, packet.target),
+            false => (packet.target, packet.source),
+        };
+        let init = packet.chunks.iter().any(|c| matches!(c, SctpChunk::Init(..)));
+        let current = self.active.get(&key).copied().filter(|inx| {
+            let association = &self.associations[*inx];
+            // a repeated INIT belongs to the handshake in progress
+            !(association.is_closed() || init && association.state != SctpState::Init)
+        });
+        let inx = match current {
+            Some(inx) => inx,
+            None => {
+                let inx = self.associations.len();
+                self.associations.push(SctpAssociation::new(inx, &packet));
+                self.active.insert(key, inx);
+                inx
+            }
+        };
+        let association = &mut self.associations[inx];
+        let forward = association.endpoints.0 == packet.source;
+        association.packets += 1;
+        association.end = packet.ts;
+        self.frames.insert(packet.index, inx);
+        let mut payloads = vec![];
+        for chunk in packet.chunks {
+            match chunk {
+                SctpChunk::Init(tag, os, mis) => {
+                    association.initiator_tag = Some(tag);
+                    association.initiator_streams = Some((os, mis));
+                }
+                SctpChunk::InitAck(tag, os, mis) => {
+                    association.responder_tag = Some(tag);
+                    association.responder_streams = Some((os, mis));
+                }
+                SctpChunk::Data(payload) => {
+                    if association.state == SctpState::Init {
+                        association.state = SctpState::Established;
+                    }
+                    if association.data(forward, &payload) {
+                        *self.retransmitted.entry(packet.index).or_default() += 1;
+                    }
+                    payloads.push(payload);
+                }
+                SctpChunk::Other(chunk_type) => match chunk_type {
+                    SCTP_COOKIE_ACK => association.state = SctpState::Established,
+                    SCTP_SHUTDOWN => association.state = SctpState::ShuttingDown,
+                    SCTP_SHUTDOWN_COMPLETE => association.state = SctpState::Closed,
+                    SCTP_ABORT => association.state = SctpState::Aborted,
+                    _ => {}
+                },
+            }
+        }
+        if !payloads.is_empty() {
+            self.payloads.insert(packet.index, payloads);
+        }
+    }
+
+    pub fn association_of(&self, index: FrameIndex) -> Option<&SctpAssociation> {
+        self.frames.get(&index).map(|inx| &self.associations[*inx])
+    }
+
+    /**
+     * user messages of a frame in chunk order
+     */
+    pub fn payloads(&self, index: FrameIndex) -> &[SctpPayload] {
+        self.payloads.get(&index).map(|list| list.as_slice()).unwrap_or_default()
+    }
+
+    pub fn retransmitted(&self, index: FrameIndex) -> usize {
+        self.retransmitted.get(&index).copied().unwrap_or(0)
+    }
+
+    pub fn associations(&self) -> &[SctpAssociation] {
+        &self.associations
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::{cmp, ops::Range};
+
+use serde::Serialize;
+
+use crate::common::{
+    concept::{Criteria, FrameIndex, ListResult, Timestamp},
+    core::Context,
+    ipsec::frame_ips,
+    ProtoMask, ResourceLoader,
+};
+use crate::protocol::transport::udp::flow_key;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum StreamDirection {
+    // tcp: sent by the primary endpoint of the connection, udp: sent by the sender of the conversation
+    Forward,
+    Backward,
+}
+
+impl StreamDirection {
+    pub fn reverse(self) -> Self {
+        match self {
+            StreamDirection::Forward => StreamDirection::Backward,
+            StreamDirection::Backward => StreamDirection::Forward,
+        }
+    }
+}
+
+/**
+ * payload of one frame in stream order, the bytes stay in the capture file until a page is loaded
+ */
+#[derive(Clone, Debug)]
+pub struct StreamSegment {
+    pub index: FrameIndex,
+    pub direction: StreamDirection,
+    pub range: Range<usize>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StreamChunk {
+    pub index: FrameIndex,
+    pub ts: Timestamp,
+    pub direction: StreamDirection,
+    pub data: Vec<u8>,
+}
+
+/**
+ * datagrams exchanged between the two endpoints of the udp frame at index, in both directions. The directions are
+ * those of the first frame of the flow, reversed tells the frame at index was sent the other way
+ */
+pub fn udp_segments(ctx: &Context, index: usize) -> Option<(&[StreamSegment], bool)> {
+    let frame = ctx.list.get(index).filter(|f| f.has_proto(ProtoMask::UDP))?;
+    let ports = frame.ports?;
+    let (first, sender) = ctx.udp_streams.get(&flow_key(ctx, frame, ports))?;
+    let reversed = *sender != (frame_ips(ctx, frame).0, ports.0);
+    let segments = ctx.udp_segments.get(first).map(|list| list.as_slice()).unwrap_or(&[]);
+    Some((segments, reversed))
+}
+
+/**
+ * loads the payload of one page of the stream
+ */
+pub fn load_stream(ctx: &Context, loader: &dyn ResourceLoader, segments: &[StreamSegment], cri: Criteria) -> ListResult<StreamChunk> {
+    let Criteria { start, size } = cri;
+    let total = segments.len();
+    let end = cmp::min(start + size, total);
+    if end <= start {
+        return ListResult::new(start, 0, vec![]);
+    }
+    let items = segments[start..end]
+        .iter()
+        .map(|segment| StreamChunk {
+            index: segment.index,
+            ts: ctx.list.get(segment.index as usize).map(|f| f.info.time).unwrap_or_default(),
+            direction: segment.direction,
+            data: loader.load(&segment.range).unwrap_or_default(),
+        })
+        .collect();
+    ListResult::new(start, total, items)
+}
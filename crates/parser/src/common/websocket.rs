@@ -0,0 +1,415 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::ops::Range;
+
+use flate2::{Decompress, FlushDecompress, Status};
+use serde::Serialize;
+
+use crate::common::{
+    concept::{ConnectionIndex, FrameIndex, Timestamp},
+    util::date_str,
+    FastHashMap,
+};
+
+pub const WS_CONTINUATION: u8 = 0x0;
+pub const WS_TEXT: u8 = 0x1;
+pub const WS_BINARY: u8 = 0x2;
+pub const WS_CLOSE: u8 = 0x8;
+pub const WS_PING: u8 = 0x9;
+pub const WS_PONG: u8 = 0xa;
+
+// payload kept of a message, the length still counts the rest
+const MESSAGE_LIMIT: usize = 1024 * 1024;
+
+// the empty stored block a permessage-deflate sender strips from every message
+const DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+pub fn ws_opcode_mapper(opcode: u8) -> &'static str {
+    match opcode {
+        WS_CONTINUATION => "Continuation",
+        WS_TEXT => "Text",
+        WS_BINARY => "Binary",
+        WS_CLOSE => "Connection Close",
+        WS_PING => "Ping",
+        WS_PONG => "Pong",
+        _ => "Reserved",
+    }
+}
+
+pub fn ws_close_code_mapper(code: u16) -> &'static str {
+    match code {
+        1000 => "Normal Closure",
+        1001 => "Going Away",
+        1002 => "Protocol Error",
+        1003 => "Unsupported Data",
+        1005 => "No Status Received",
+        1006 => "Abnormal Closure",
+        1007 => "Invalid Frame Payload Data",
+        1008 => "Policy Violation",
+        1009 => "Message Too Big",
+        1010 => "Mandatory Extension",
+        1011 => "Internal Error",
+        1015 => "TLS Handshake",
+        _ => "Unknown",
+    }
+}
+
+/**
+ * the fixed part of a frame header, enough to know how long the rest is
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WsFrameHeader {
+    pub fin: bool,
+    pub rsv1: bool,
+    pub opcode: u8,
+    pub mask: Option<[u8; 4]>,
+    pub length: usize,
+    // bytes of the header, extended length and masking key included
+    pub size: usize,
+}
+
+impl WsFrameHeader {
+    /**
+     * None until data holds the whole header
+     */
+    pub fn read(data: &[u8]) -> Option<Self> {
+        let (first, second) = (*data.first()?, *data.get(1)?);
+        let masked = second & 0x80 != 0;
+        let (length, mut size) = match second & 0x7f {
+            126 => (u16::from_be_bytes(data.get(2..4)?.try_into().ok()?) as usize, 4),
+            127 => (u64::from_be_bytes(data.get(2..10)?.try_into().ok()?) as usize, 10),
+            len => (len as usize, 2),
+        };
+        let mask = match masked {
+            true => {
+                let key: [u8; 4] = data.get(size..size + 4)?.try_into().ok()?;
+                size += 4;
+                Some(key)
+            }
+            false => None,
+        };
+        Some(Self { fin: first & 0x80 != 0, rsv1: first & 0x40 != 0, opcode: first & 0x0f, mask, length, size })
+    }
+}
+
+pub fn unmask(data: &mut [u8], mask: Option<[u8; 4]>, offset: usize) {
+    if let Some(key) = mask {
+        for (i, b) in data.iter_mut().enumerate() {
+            *b ^= key[(offset + i) % 4];
+        }
+    }
+}
+
+/**
+ * a frame completed in a packet
+ */
+#[derive(Clone, Debug, Default)]
+pub struct WsFrame {
+    pub header: WsFrameHeader,
+    // where the frame starts in the packet payload, when the packet carries all of it
+    pub offset: Option<usize>,
+    // raw bytes of a frame reassembled across packets
+    pub data: Vec<u8>,
+    // the message this frame completes
+    pub message: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct WsPacket {
+    pub frames: Vec<WsFrame>,
+    // bytes of frames that started in an earlier packet or end in a later one
+    pub fragments: Vec<Range<usize>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct WebSocketMessage {
+    // frame of the last fragment
+    pub index: FrameIndex,
+    pub ts: String,
+    pub from_client: bool,
+    pub opcode: String,
+    pub fragments: usize,
+    // payload bytes, after decompression
+    pub length: usize,
+    pub compressed: bool,
+    // text messages and close reasons
+    pub text: Option<String>,
+    pub close_code: Option<u16>,
+    // the payload went over the limit or did not inflate
+    pub truncated: bool,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct WebSocketConnection {
+    pub index: usize,
+    // request line and host of the upgrade
+    pub url: String,
+    pub protocol: Option<String>,
+    pub extensions: Option<String>,
+    pub messages: usize,
+    pub client_bytes: usize,
+    pub server_bytes: usize,
+    // code of the first close frame, with the side that sent it
+    pub close: Option<(u16, bool)>,
+}
+
+/**
+ * permessage-deflate parameters from Sec-WebSocket-Extensions of the 101 response
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WsDeflate {
+    pub client_no_context_takeover: bool,
+    pub server_no_context_takeover: bool,
+}
+
+impl WsDeflate {
+    pub fn parse(extensions: &str) -> Option<Self> {
+        let offer = extensions.split(',').find(|offer| offer.split(';').next().map(|name| name.trim().eq_ignore_ascii_case("permessage-deflate")).unwrap_or(false))?;
+        let mut rs = Self::default();
+        for param in offer.split(';').skip(1).map(|p| p.trim().to_ascii_lowercase()) {
+            match param.as_str() {
+                "client_no_context_takeover" => rs.client_no_context_takeover = true,
+                "server_no_context_takeover" => rs.server_no_context_takeover = true,
+                _ => {}
+            }
+        }
+        Some(rs)
+    }
+}
+
+// a message assembled from its data frames
+#[derive(Default)]
+struct PendingMessage {
+    opcode: u8,
+    compressed: bool,
+    fragments: usize,
+    data: Vec<u8>,
+    length: usize,
+}
+
+/**
+ * frame reassembly of one direction of a connection
+ */
+#[derive(Default)]
+struct WsFlow {
+    // the frame under reassembly, header bytes first
+    buffer: Vec<u8>,
+    header: Option<WsFrameHeader>,
+    // payload bytes of the current frame still to come
+    left: usize,
+    message: Option<PendingMessage>,
+    inflater: Option<Decompress>,
+}
+
+fn inflate(inflater: &mut Decompress, data: &[u8]) -> Option<Vec<u8>> {
+    let mut input = data.to_vec();
+    input.extend_from_slice(&DEFLATE_TAIL);
+    let mut out = Vec::with_capacity(input.len() * 4);
+    let mut at = 0;
+    loop {
+        if out.len() == out.capacity() {
+            out.reserve(out.capacity().max(1024));
+        }
+        let (before_in, before_out) = (inflater.total_in(), inflater.total_out());
+        let status = inflater.decompress_vec(&input[at..], &mut out, FlushDecompress::Sync).ok()?;
+        at += (inflater.total_in() - before_in) as usize;
+        let progress = inflater.total_in() != before_in || inflater.total_out() != before_out;
+        if status == Status::StreamEnd || out.len() > MESSAGE_LIMIT || (at >= input.len() && out.len() < out.capacity()) || !progress {
+            return Some(out);
+        }
+    }
+}
+
+#[derive(Default)]
+struct WsConnectionState {
+    connection: usize,
+    // tcp direction of the client, known from the upgrade
+    client: Option<usize>,
+    deflate: Option<WsDeflate>,
+    flows: [WsFlow; 2],
+}
+
+/**
+ * websocket connections by tcp connection, each upgraded by a 101 response
+ */
+#[derive(Default)]
+pub struct WebSocketTable {
+    states: FastHashMap<ConnectionIndex, WsConnectionState>,
+    connections: Vec<WebSocketConnection>,
+    messages: Vec<Vec<WebSocketMessage>>,
+    // frames by the packet that completed them
+    packets: FastHashMap<FrameIndex, WsPacket>,
+}
+
+impl WebSocketTable {
+    fn state(&mut self, key: ConnectionIndex) -> &mut WsConnectionState {
+        let (connections, messages) = (&mut self.connections, &mut self.messages);
+        self.states.entry(key).or_insert_with(|| {
+            let index = connections.len();
+            connections.push(WebSocketConnection { index, ..Default::default() });
+            messages.push(vec![]);
+            WsConnectionState { connection: index, ..Default::default() }
+        })
+    }
+    /**
+     * the handshake of a connection, client is the tcp direction of the request
+     */
+    pub fn upgrade(&mut self, key: ConnectionIndex, client: usize, url: String, protocol: Option<String>, extensions: Option<String>) {
+        let state = self.state(key);
+        state.client = Some(client & 1);
+        state.deflate = extensions.as_deref().and_then(WsDeflate::parse);
+        let index = state.connection;
+        if let Some(conn) = self.connections.get_mut(index) {
+            conn.url = url;
+            conn.protocol = protocol;
+            conn.extensions = extensions;
+        }
+    }
+
+    fn complete(&mut self, key: ConnectionIndex, direction: usize, index: FrameIndex, ts: Timestamp, header: &WsFrameHeader, payload: Vec<u8>) -> Option<usize> {
+        let state = self.state(key);
+        let from_client = match state.client {
+            Some(client) => client == direction,
+            None => header.mask.is_some(),
+        };
+        let deflate = state.deflate;
+        let flow = &mut state.flows[direction];
+        let control = header.opcode & 0x08 != 0;
+        let mut message = match (control, header.opcode) {
+            (true, _) => PendingMessage { opcode: header.opcode, ..Default::default() },
+            (false, WS_CONTINUATION) => flow.message.take().unwrap_or_default(),
+            (false, opcode) => PendingMessage { opcode, compressed: header.rsv1 && deflate.is_some(), ..Default::default() },
+        };
+        message.fragments += 1;
+        message.length += header.length;
+        if message.data.len() < MESSAGE_LIMIT {
+            message.data.extend_from_slice(&payload);
+        }
+        if !header.fin && !control {
+            flow.message = Some(message);
+            return None;
+        }
+        let mut truncated = message.length > message.data.len();
+        let mut data = message.data;
+        if message.compressed && !truncated {
+            let no_takeover = deflate.map(|d| if from_client { d.client_no_context_takeover } else { d.server_no_context_takeover }).unwrap_or(false);
+            if no_takeover || flow.inflater.is_none() {
+                flow.inflater = Some(Decompress::new(false));
+            }
+            match flow.inflater.as_mut().and_then(|inflater| inflate(inflater, &data)) {
+                Some(plain) => data = plain,
+                None => {
+                    flow.inflater = None;
+                    truncated = true;
+                }
+            }
+        }
+        let length = match message.compressed && !truncated {
+            true => data.len(),
+            false => message.length,
+        };
+        let (mut text, mut close_code) = (None, None);
+        match message.opcode {
+            WS_TEXT if !(message.compressed && truncated) => text = Some(String::from_utf8_lossy(&data).to_string()),
+            WS_CLOSE if data.len() >= 2 => {
+                close_code = Some(u16::from_be_bytes([data[0], data[1]]));
+                text = Some(String::from_utf8_lossy(&data[2..]).to_string());
+            }
+            _ => {}
+        }
+        let connection = state.connection;
+        let conn = self.connections.get_mut(connection)?;
+        match from_client {
+            true => conn.client_bytes += length,
+            false => conn.server_bytes += length,
+        }
+        if let (Some(code), None) = (close_code, conn.close) {
+            conn.close = Some((code, from_client));
+        }
+        let messages = self.messages.get_mut(connection)?;
+        messages.push(WebSocketMessage {
+            index,
+            ts: date_str(ts),
+            from_client,
+            opcode: ws_opcode_mapper(message.opcode).to_string(),
+            fragments: message.fragments,
+            length,
+            compressed: message.compressed,
+            text,
+            close_code,
+            truncated,
+        });
+        conn.messages = messages.len();
+        Some(messages.len() - 1)
+    }
+
+    /**
+     * the payload of a packet in one direction, frames completed here become messages
+     */
+    pub fn feed(&mut self, key: ConnectionIndex, direction: usize, index: FrameIndex, ts: Timestamp, data: &[u8]) -> WsPacket {
+        let direction = direction & 1;
+        let mut packet = WsPacket::default();
+        let mut at = 0;
+        while at < data.len() {
+            let flow = &mut self.state(key).flows[direction];
+            let begin = at;
+            // a frame begun in an earlier packet
+            let offset = match flow.buffer.is_empty() && flow.header.is_none() {
+                true => Some(at),
+                false => None,
+            };
+            while flow.header.is_none() && at < data.len() {
+                flow.buffer.push(data[at]);
+                at += 1;
+                if let Some(header) = WsFrameHeader::read(&flow.buffer) {
+                    flow.header = Some(header);
+                    flow.left = header.length;
+                }
+            }
+            let Some(header) = flow.header else {
+                packet.fragments.push(begin..at);
+                break;
+            };
+            let size = flow.left.min(data.len() - at);
+            let keep = MESSAGE_LIMIT.saturating_sub(flow.buffer.len() - header.size).min(size);
+            flow.buffer.extend_from_slice(&data[at..at + keep]);
+            flow.left -= size;
+            at += size;
+            if flow.left > 0 || offset.is_none() {
+                packet.fragments.push(begin..at);
+            }
+            if flow.left > 0 {
+                break;
+            }
+            let buffer = std::mem::take(&mut flow.buffer);
+            flow.header = None;
+            let mut payload = buffer[header.size..].to_vec();
+            unmask(&mut payload, header.mask, 0);
+            let data = match offset {
+                Some(_) => vec![],
+                None => buffer,
+            };
+            let message = self.complete(key, direction, index, ts, &header, payload);
+            packet.frames.push(WsFrame { header, offset, data, message });
+        }
+        packet
+    }
+    pub fn insert(&mut self, index: FrameIndex, packet: WsPacket) {
+        self.packets.insert(index, packet);
+    }
+    pub fn packet(&self, index: FrameIndex) -> Option<&WsPacket> {
+        self.packets.get(&index)
+    }
+    pub fn connection_of(&self, key: ConnectionIndex) -> Option<usize> {
+        self.states.get(&key).map(|state| state.connection)
+    }
+    pub fn connections(&self) -> &[WebSocketConnection] {
+        &self.connections
+    }
+    pub fn messages(&self, connection: usize) -> &[WebSocketMessage] {
+        self.messages.get(connection).map(|list| list.as_slice()).unwrap_or(&[])
+    }
+}
@@ -1375,7 +1375,7 @@ pub fn tls_hs_message_type_mapper(code: u8) -> &'static str {
 		0 => "HelloRequest",
 		1 => "ClientHello",
 		2 => "ServerHello",
-		3 => "HELLO_VERIFY_REQUEST",
+		3 => "HelloVerifyRequest",
 		4 => "NewSessionTicket",
 		5 => "END_OF_EARLY_DATA",
 		6 => "HELLO_RETRY_REQUEST",
@@ -11,9 +11,49 @@ use crate::{
         Frame, concept::{DNSRecord, Field, NameService}, core::Context, enum_def::{Protocol, ProtocolInfoField}, io::Reader
     },
     constants::{dns_class_mapper, dns_type_mapper},
+    protocol::heuristic::Confidence,
 };
 use anyhow::Result;
 
+const PROBE_MAX_RECORDS: u16 = 64;
+
+/**
+ * a standard query or its response carrying a single question of class IN
+ */
+pub fn probe(reader: &Reader) -> Option<Confidence> {
+    let mut reader = reader.clone();
+    let _transaction_id = reader.read16(true).ok()?;
+    let flags = reader.read16(true).ok()?;
+    // opcode QUERY and the reserved bit clear
+    if (flags >> 11) & 0x0f != 0 || flags & 0x0040 != 0 {
+        return None;
+    }
+    let questions = reader.read16(true).ok()?;
+    for _ in 0..3 {
+        if reader.read16(true).ok()? > PROBE_MAX_RECORDS {
+            return None;
+        }
+    }
+    if questions != 1 {
+        return None;
+    }
+    let mut name_len = 0;
+    loop {
+        let len = reader.read8().ok()? as usize;
+        if len == 0 {
+            break;
+        }
+        name_len += len + 1;
+        if len > 63 || name_len > 255 || !reader.forward(len) {
+            return None;
+        }
+    }
+    let _qtype = reader.read16(true).ok()?;
+    let class = reader.read16(true).ok()?;
+    // the top bit is the mdns unicast response flag
+    (class & 0x7fff == 1).then_some(Confidence::Low)
+}
+
 // Helper function to parse DNS name from DNS packet
 fn parse_dns_name(_reader: &mut Reader, start_offset: usize) -> Result<String> {
     let mut reader = _reader.clone();
@@ -10,6 +10,7 @@ use crate::common::enum_def::{ProtocolInfoField, SegmentStatus};
 use crate::common::io::Reader;
 use crate::common::{enum_def::Protocol, Frame};
 use crate::common::{hex_num, quick_trim_num, std_string, trim_data};
+use crate::protocol::heuristic::Confidence;
 use anyhow::Result;
 
 pub fn detect(reader: &Reader) -> (bool, bool) {
@@ -30,6 +31,10 @@ pub fn detect(reader: &Reader) -> (bool, bool) {
     (false, false)
 }
 
+pub fn probe(reader: &Reader) -> Option<Confidence> {
+    detect(reader).0.then_some(Confidence::High)
+}
+
 pub fn detect_length(data: &[u8]) -> Option<usize> {
     let size: usize = data.len();
     if size >= 15 {
@@ -74,6 +79,13 @@ pub fn detect_type(data: &[u8]) -> Option<String> {
     }
     None
 }
+pub fn detect_disposition(data: &[u8]) -> Option<String> {
+    let size = data.len();
+    if size >= 20 && data[..20].eq_ignore_ascii_case(b"content-disposition:") {
+        return Some(String::from_utf8_lossy(trim_data(&data[20..])).to_string());
+    }
+    None
+}
 pub fn detect_hostname(data: &[u8]) -> Option<String> {
     let size = data.len();
     if size >= 5 {
@@ -87,36 +99,103 @@ pub fn detect_hostname(data: &[u8]) -> Option<String> {
     None
 }
 
+fn header_value<'a>(headers: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    headers.split(|b| *b == b'\n').find_map(|header| {
+        let pos = header.iter().position(|b| *b == b':')?;
+        header[..pos].eq_ignore_ascii_case(name).then(|| trim_data(&header[pos + 1..]))
+    })
+}
+
+/**
+ * the protocol a 101 response switches the connection to
+ */
+fn upgrade_protocol(line: &str, headers: &[u8]) -> Option<Protocol> {
+    if !line.starts_with("HTTP/1.1 101") {
+        return None;
+    }
+    let upgrade = header_value(headers, b"upgrade")?;
+    if upgrade.eq_ignore_ascii_case(b"h2c") {
+        Some(Protocol::HTTP2)
+    } else if upgrade.eq_ignore_ascii_case(b"websocket") {
+        Some(Protocol::WEBSOCKET)
+    } else {
+        None
+    }
+}
+
+/**
+ * the websocket address from the request of the handshake
+ */
+fn websocket_url(request: &HttpMessage) -> String {
+    let path = request.host.split_whitespace().nth(1).unwrap_or("/");
+    match &request.hostname {
+        Some(hostname) => format!("ws://{hostname}{path}"),
+        None => request.host.clone(),
+    }
+}
+
 fn read_line(reader: &mut Reader, len: usize) -> Result<String> {
     let data = reader.slice(len, true)?;
     Ok(std_string(data)?.to_string())
 }
 
+// the start line and headers of a message, up to the empty line
+fn read_header_lines(field: &mut Field, reader: &mut Reader) -> Result<()> {
+    loop {
+        let left = reader.left();
+        if left >= 2 && reader.preview(2)? == b"\r\n" {
+            reader.forward(2);
+            break;
+        }
+        match reader.search_enter(0xffff) {
+            Some(pos) => {
+                add_field_format!(field, reader, read_line(reader, pos)?, "{}");
+                reader.forward(2);
+            }
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+/**
+ * what follows the empty line ending the headers
+ */
+fn header_end(record: &HttpMessage, message_index: MessageIndex) -> SegmentStatus {
+    if record.bodiless {
+        return SegmentStatus::Finish;
+    }
+    if record.chunked {
+        return SegmentStatus::HttpChunkedContinue(message_index, 0);
+    }
+    match record.length {
+        Some(length) if length > 0 => SegmentStatus::HttpContentContinue(message_index, length),
+        Some(_) => SegmentStatus::Finish,
+        // a response without a length runs until the connection closes
+        None if record.host.starts_with("HTTP/") => SegmentStatus::HttpContentContinue(message_index, usize::MAX),
+        None => SegmentStatus::Finish,
+    }
+}
+
+// the empty line closing a chunked body, trailer fields are not kept
+fn chunked_end(reader: &mut Reader) -> Result<SegmentStatus> {
+    if reader.left() >= 2 && reader.preview(2)? == b"\r\n" {
+        reader.forward(2);
+    }
+    Ok(SegmentStatus::Finish)
+}
+
 pub fn parse_http_header(record: &mut HttpMessage, reader: &mut Reader, message_index: MessageIndex) -> Result<SegmentStatus> {
     loop {
         let left = reader.left();
         if left == 2 && reader.preview(2)? == b"\r\n" {
             reader.forward(2);
-            if record.chunked {
-                return Ok(SegmentStatus::HttpChunkedContinue(message_index, 0));
-            } else if let Some(length) = record.length {
-                if length > 0 {
-                    return Ok(SegmentStatus::HttpContentContinue(message_index, length));
-                }
-            }
-            return Ok(SegmentStatus::Finish);
+            return Ok(header_end(record, message_index));
         }
         if let Some(size) = reader.search_enter(0xffff) {
             if size == 0 {
                 reader.forward(2);
-                if record.chunked {
-                    return Ok(SegmentStatus::HttpChunkedContinue(message_index, 0));
-                } else if let Some(length) = record.length {
-                    if length > 0 {
-                        return Ok(SegmentStatus::HttpContentContinue(message_index, length));
-                    }
-                }
-                return Ok(SegmentStatus::Finish);
+                return Ok(header_end(record, message_index));
             }
             let data = reader.slice(size, true)?;
             if record.length.is_none() {
@@ -128,6 +207,9 @@ pub fn parse_http_header(record: &mut HttpMessage, reader: &mut Reader, message_
             if record.content_type.is_none() {
                 record.content_type = detect_type(data);
             }
+            if record.disposition.is_none() {
+                record.disposition = detect_disposition(data);
+            }
 
             if record.hostname.is_none() {
                 record.hostname = detect_hostname(data);
@@ -173,7 +255,7 @@ pub fn parse_http_chunked(record: &mut HttpMessage, index: FrameIndex, reader: &
             if let Ok(len) = hex_num(data) {
                 reader.forward(2);
                 if len == 0 {
-                    return Ok(SegmentStatus::Finish);
+                    return chunked_end(reader);
                 }
                 if let Some(ll) = record.length {
                     record.length = Some(ll + len);
@@ -270,6 +352,9 @@ fn parse_http(ctx: &mut Context, reader: &mut Reader, frame_index: FrameIndex, s
                     if record.content_type.is_none() {
                         record.content_type = detect_type(&extra);
                     }
+                    if record.disposition.is_none() {
+                        record.disposition = detect_disposition(&extra);
+                    }
                     if record.hostname.is_none() {
                         record.hostname = detect_hostname(&extra);
                         if let Some(hn) = &record.hostname {
@@ -304,7 +389,7 @@ fn parse_http(ctx: &mut Context, reader: &mut Reader, frame_index: FrameIndex, s
                 };
                 let orgin = std::mem::take(&mut record.content);
                 record.content = segment_append(orgin, segment);
-
+                reader.forward(size);
                 if _left >= left {
                     return Ok(SegmentStatus::Init);
                 } else {
@@ -330,7 +415,7 @@ fn parse_http(ctx: &mut Context, reader: &mut Reader, frame_index: FrameIndex, s
                     reader.forward(1);
                     if let Ok(len) = hex_num(&extra[..extra.len() - 2]) {
                         if len == 0 {
-                            return Ok(SegmentStatus::Finish);
+                            return chunked_end(reader);
                         }
                         if let Some(ll) = record.length {
                             record.length = Some(ll + len);
@@ -343,7 +428,7 @@ fn parse_http(ctx: &mut Context, reader: &mut Reader, frame_index: FrameIndex, s
                     reader.forward(2);
                     if let Ok(len) = hex_num(&extra) {
                         if len == 0 {
-                            return Ok(SegmentStatus::Finish);
+                            return chunked_end(reader);
                         }
                         if let Some(ll) = record.length {
                             record.length = Some(ll + len);
@@ -379,55 +464,137 @@ impl Visitor {
         }
         let mut reader = _reader.slice_as_reader(left)?;
         let mut next_status = SegmentStatus::Init;
+        let mut upgraded = None;
+        // start lines of the messages begun in this segment, pipelined ones included
+        let mut lines = vec![];
+        let mut first_message = None;
         if let Some((conversation_key, endpoint)) = ctx.connection(frame) {
             let frame_index = frame.info.index;
             let ts = frame.info.time;
-            let mut _status = std::mem::replace(&mut endpoint.segment_status, SegmentStatus::Init);
-            match _status {
-                SegmentStatus::Init | SegmentStatus::Error | SegmentStatus::Finish => {
-                    let rs = detect(&reader);
-                    if rs.0 {
-                        if let Some(pos) = reader.search_enter(0xffff) {
-                            let data = reader.slice(pos, true)?.to_vec();
-                            let line = String::from_utf8_lossy(&data).to_string();
-                            // let mut mi = None;
-                            reader.forward(2);
-                            // let hostname = re
-                            let message_index = ctx.init_segment_message(frame_index, line.clone(), rs.1, conversation_key, ts);
-                            next_status = parse_http(ctx, &mut reader, frame_index, SegmentStatus::HttpDetected(message_index))?;
-                            frame.protocol_field = ProtocolInfoField::Http(line, message_index);
+            let mut status = std::mem::replace(&mut endpoint.segment_status, SegmentStatus::Init);
+            loop {
+                let cursor = reader.cursor;
+                match status {
+                    SegmentStatus::Init | SegmentStatus::Error | SegmentStatus::Finish => {
+                        let rs = detect(&reader);
+                        if !rs.0 {
+                            break;
+                        }
+                        let Some(pos) = reader.search_enter(0xffff) else {
+                            break;
+                        };
+                        let data = reader.slice(pos, true)?.to_vec();
+                        let line = String::from_utf8_lossy(&data).to_string();
+                        reader.forward(2);
+                        let pending = ctx.pending_request(&conversation_key);
+                        let header_start = reader.cursor;
+                        let message_index = ctx.init_segment_message(frame_index, line.clone(), rs.1, conversation_key, ts);
+                        first_message.get_or_insert(message_index);
+                        status = parse_http(ctx, &mut reader, frame_index, SegmentStatus::HttpDetected(message_index))?;
+                        let headers = reader._slice(header_start..reader.cursor)?;
+                        let switch = match rs.1 {
+                            true => None,
+                            false => upgrade_protocol(&line, headers),
+                        };
+                        let direction = frame.tcp_info.as_ref().and_then(|info| info.connection).map(|(_, reverse)| reverse as usize ^ 1);
+                        match switch {
+                            Some(Protocol::HTTP2) => {
+                                // the request is answered again on stream 1
+                                let request = pending.and_then(|(connect, ts)| Some((ctx.http_connections.get(connect as usize)?.request?, (connect, ts))));
+                                if let (Some((request, connect)), Some(client)) = (request, direction) {
+                                    ctx.http2.upgrade(conversation_key, client, request, connect);
+                                }
+                            }
+                            Some(Protocol::WEBSOCKET) => {
+                                let request = pending.and_then(|(connect, _)| ctx.http_connections.get(connect as usize)?.request).and_then(|request| ctx.http_messages.get(request as usize));
+                                let url = request.map(websocket_url).unwrap_or_default();
+                                let text = |name: &[u8]| header_value(headers, name).map(|value| String::from_utf8_lossy(value).to_string());
+                                let (protocol, extensions) = (text(b"sec-websocket-protocol"), text(b"sec-websocket-extensions"));
+                                if let Some(client) = direction {
+                                    ctx.websocket.upgrade(conversation_key, client, url, protocol, extensions);
+                                }
+                            }
+                            _ => {}
+                        }
+                        lines.push(line);
+                        if let Some(protocol) = switch {
+                            ctx.upgrade_connection(frame, protocol);
+                            upgraded = Some(protocol);
+                            break;
                         }
                     }
+                    _ => {
+                        status = parse_http(ctx, &mut reader, frame_index, status)?;
+                    }
+                };
+                // a message waits for the next segment, or nothing more was read
+                if reader.left() == 0 || reader.cursor == cursor || !matches!(status, SegmentStatus::Init | SegmentStatus::Finish) {
+                    break;
                 }
-                _ => {
-                    next_status = parse_http(ctx, &mut reader, frame_index, _status)?;
-                }
-            };
+            }
+            next_status = status;
+        }
+        if let Some(message_index) = first_message {
+            frame.protocol_field = ProtocolInfoField::Http(lines.join(", "), message_index);
         }
 
+        if let Some(protocol) = upgraded {
+            // frames sent right behind the 101 response
+            if reader.left() > 0 {
+                _reader.set(reader.cursor);
+                return Ok(protocol);
+            }
+            return Ok(Protocol::None);
+        }
         if let Some((_, endpoint)) = ctx.connection(frame) {
             endpoint.segment_status = next_status;
         }
         Ok(Protocol::None)
     }
-    pub fn detail(field: &mut Field, _: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
         match &frame.protocol_field {
-            ProtocolInfoField::Http(_, _mi) => {
-                loop {
-                    let left = reader.left();
-                    if left >= 2 && reader.preview(2)? == b"\r\n" {
-                        reader.forward(2);
-                        //todo header parse finish
-                        break;
+            ProtocolInfoField::Http(_, mi) => {
+                let index = frame.info.index;
+                // where each message begun in this segment starts, its start line ahead of the first header segment
+                let starts: Vec<(usize, &str)> = ctx
+                    .http_messages
+                    .iter()
+                    .skip(*mi as usize)
+                    .take_while(|message| message.frame_index == index)
+                    .filter_map(|message| {
+                        let segment = match &message.headers {
+                            SegmentData::Single(segment) => segment,
+                            SegmentData::Multiple(segments) => segments.first()?,
+                            SegmentData::None => return None,
+                        };
+                        Some((segment.range.start.checked_sub(message.host.len() + 2)?, message.host.as_str()))
+                    })
+                    .collect();
+                match starts.len() {
+                    0 | 1 => {
+                        if let Some((start, _)) = starts.first() {
+                            reader.set(*start);
+                        }
+                        read_header_lines(field, reader)?;
                     }
-                    if let Some(pos) = reader.search_enter(0xffff) {
-                        add_field_format!(field, reader, read_line(reader, pos)?, "{}");
-                        reader.forward(2);
-                    } else {
-                        break;
+                    _ => {
+                        for (start, line) in starts {
+                            reader.set(start);
+                            let mut message_field = Field::with_children(line.to_string(), start, 0);
+                            message_field.source = field.source;
+                            read_header_lines(&mut message_field, reader)?;
+                            message_field.size = reader.cursor - start;
+                            field.children.as_mut().unwrap().push(message_field);
+                        }
                     }
                 }
                 field.summary = "Hypertext Transfer Protocol".to_string();
+                if ctx.http2.packet(frame.info.index).is_some() {
+                    return Ok(Protocol::HTTP2);
+                }
+                if ctx.websocket.packet(frame.info.index).is_some() {
+                    return Ok(Protocol::WEBSOCKET);
+                }
             }
             // ProtocolInfoField::HttpSegment(mi) => {
 
@@ -0,0 +1,334 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::ops::Range;
+
+use crate::{
+    add_field_backstep, add_field_format, add_field_forward, add_field_label_no_range, add_field_rest_format,
+    common::{
+        concept::{ConnectionIndex, Field, FrameIndex},
+        core::Context,
+        enum_def::{Protocol, ProtocolInfoField},
+        http2::{
+            http2_error_mapper, http2_frame_type_mapper, http2_settings_mapper, Http2Frame, CONTINUATION, DATA, FLAG_ACK, FLAG_END_HEADERS, FLAG_END_STREAM, FLAG_PADDED, FLAG_PRIORITY, FRAME_HEADER, GOAWAY, HEADERS, HTTP2_PREFACE, PING,
+            PRIORITY, PUSH_PROMISE, RST_STREAM, SETTINGS, WINDOW_UPDATE,
+        },
+        io::{DataSource, Reader},
+        util::bytes_to_hex,
+        Frame,
+    },
+    protocol::heuristic::Confidence,
+};
+use anyhow::Result;
+
+/**
+ * prior knowledge h2c, the client opens with the connection preface
+ */
+pub fn probe(reader: &Reader) -> Option<Confidence> {
+    let size = reader.left().min(HTTP2_PREFACE.len());
+    (size >= 4 && reader.preview(size).ok()? == &HTTP2_PREFACE[..size]).then_some(Confidence::High)
+}
+
+// the tcp payload, without the padding of short ethernet frames
+fn payload_len(frame: &Frame, reader: &Reader) -> usize {
+    match &frame.tcp_info {
+        Some(tcp) => reader.left().min(tcp.len as usize),
+        None => reader.left(),
+    }
+}
+
+fn flags_text(frame_type: u8, flags: u8) -> String {
+    let mut list = vec![];
+    match frame_type {
+        SETTINGS | PING if flags & FLAG_ACK != 0 => list.push("ACK"),
+        DATA | HEADERS if flags & FLAG_END_STREAM != 0 => list.push("END_STREAM"),
+        _ => {}
+    }
+    if matches!(frame_type, HEADERS | PUSH_PROMISE | CONTINUATION) && flags & FLAG_END_HEADERS != 0 {
+        list.push("END_HEADERS");
+    }
+    if matches!(frame_type, DATA | HEADERS | PUSH_PROMISE) && flags & FLAG_PADDED != 0 {
+        list.push("PADDED");
+    }
+    if frame_type == HEADERS && flags & FLAG_PRIORITY != 0 {
+        list.push("PRIORITY");
+    }
+    match list.is_empty() {
+        true => format!("Flags: {flags:#04x}"),
+        false => format!("Flags: {flags:#04x} [{}]", list.join(", ")),
+    }
+}
+
+fn read_pad_length(reader: &mut Reader, field: &mut Field, item: &Http2Frame) -> Result<usize> {
+    if item.flags & FLAG_PADDED == 0 {
+        return Ok(0);
+    }
+    Ok(add_field_format!(field, reader, reader.read8()?, "Pad Length: {}") as usize)
+}
+
+fn read_dependency(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    let value = reader.read32(true)?;
+    add_field_backstep!(field, reader, 4, format!("Exclusive: {}", value >> 31 == 1));
+    add_field_backstep!(field, reader, 4, format!("Stream Dependency: {}", value & 0x7fff_ffff));
+    add_field_format!(field, reader, reader.read8()? as u16 + 1, "Weight: {}");
+    Ok(())
+}
+
+fn read_block(reader: &mut Reader, field: &mut Field, pad: usize) -> Result<()> {
+    let size = reader.left().saturating_sub(pad);
+    add_field_forward!(field, reader, size, format!("Header Block Fragment ({size} bytes)"));
+    reader.forward(size);
+    Ok(())
+}
+
+/**
+ * fields of a whole frame, the reader starts at the frame header
+ */
+fn read_frame_field(reader: &mut Reader, field: &mut Field, item: &Http2Frame) -> Result<()> {
+    let name = http2_frame_type_mapper(item.frame_type);
+    add_field_format!(field, reader, reader.read24()?, "Length: {}");
+    add_field_format!(field, reader, reader.read8()?, "Type: {name} ({})");
+    add_field_format!(field, reader, flags_text(item.frame_type, reader.read8()?), "{}");
+    let stream = reader.read32(true)?;
+    add_field_backstep!(field, reader, 4, format!("Reserved: {}", stream >> 31));
+    add_field_backstep!(field, reader, 4, format!("Stream Identifier: {}", stream & 0x7fff_ffff));
+    field.summary = format!("Stream: {name}, Stream ID: {}, Length {}", item.stream, item.length);
+    if let Some(headline) = item.headline() {
+        field.summary = format!("{}, {headline}", field.summary);
+    }
+    match item.frame_type {
+        DATA => match item.offset {
+            Some(_) => {
+                let pad = read_pad_length(reader, field, item)?;
+                let size = reader.left().saturating_sub(pad);
+                add_field_forward!(field, reader, size, format!("Data ({size} bytes)"));
+                reader.forward(size);
+            }
+            // only the header of a reassembled DATA frame is kept
+            None => {
+                let size: usize = item.body.iter().map(|(_, range)| range.len()).sum();
+                add_field_label_no_range!(field, format!("[Data ({size} bytes) in {} segments]", item.body.len()));
+            }
+        },
+        HEADERS => {
+            let pad = read_pad_length(reader, field, item)?;
+            if item.flags & FLAG_PRIORITY != 0 {
+                read_dependency(reader, field)?;
+            }
+            read_block(reader, field, pad)?;
+        }
+        PRIORITY => read_dependency(reader, field)?,
+        RST_STREAM => {
+            let code = reader.read32(true)?;
+            add_field_backstep!(field, reader, 4, format!("Error: {} ({code})", http2_error_mapper(code)));
+        }
+        SETTINGS => {
+            while reader.left() >= 6 {
+                let id = reader.read16(true)?;
+                let value = reader.read32(true)?;
+                add_field_backstep!(field, reader, 6, format!("Settings - {}: {value}", http2_settings_mapper(id)));
+            }
+        }
+        PUSH_PROMISE => {
+            let pad = read_pad_length(reader, field, item)?;
+            add_field_format!(field, reader, reader.read32(true)? & 0x7fff_ffff, "Promised-Stream-ID: {}");
+            read_block(reader, field, pad)?;
+        }
+        PING => {
+            let size = 8.min(reader.left());
+            let data = bytes_to_hex(reader.slice(size, true)?);
+            add_field_backstep!(field, reader, size, format!("Ping: {data}"));
+        }
+        GOAWAY => {
+            add_field_format!(field, reader, reader.read32(true)? & 0x7fff_ffff, "Last-Stream-ID: {}");
+            let code = reader.read32(true)?;
+            add_field_backstep!(field, reader, 4, format!("Error: {} ({code})", http2_error_mapper(code)));
+            add_field_rest_format!(field, reader, format!("Additional Debug Data: {}", String::from_utf8_lossy(reader.preview(reader.left())?)));
+            reader.forward(reader.left());
+        }
+        WINDOW_UPDATE => {
+            let value = reader.read32(true)?;
+            add_field_backstep!(field, reader, 4, format!("Window Size Increment: {}", value & 0x7fff_ffff));
+        }
+        CONTINUATION => read_block(reader, field, 0)?,
+        _ => {}
+    }
+    add_field_rest_format!(field, reader, format!("Padding ({} bytes)", reader.left()));
+    if let Some(headers) = &item.headers {
+        add_field_label_no_range!(field, format!("[Header Length: {}, Header Count: {}]", headers.iter().map(|h| h.name.len() + h.value.len() + 32).sum::<usize>(), headers.len()));
+        for header in headers {
+            add_field_label_no_range!(field, format!("Header: {}", header.line()));
+        }
+    }
+    if let Some(error) = &item.error {
+        add_field_label_no_range!(field, format!("[Expert Info: Header block not decoded, {error}]"));
+    }
+    Ok(())
+}
+
+/**
+ * a decoded header block becomes the request or response of its stream, trailers join the message they end
+ */
+fn header_block(ctx: &mut Context, frame: &Frame, key: ConnectionIndex, direction: usize, item: &Http2Frame) {
+    let Some(headers) = &item.headers else {
+        return;
+    };
+    let (index, ts) = (frame.info.index, frame.info.time);
+    let stream_id = item.promised.unwrap_or(item.stream);
+    // a PUSH_PROMISE carries the request of the pushed stream, sent on behalf of the client
+    let side = match item.promised {
+        Some(_) => direction ^ 1,
+        None => direction,
+    };
+    let lines: Vec<String> = headers.iter().map(|h| h.line()).collect();
+    let (host, is_request) = match (item.header(":method"), item.header(":status")) {
+        (Some(_), _) => (format!("{} HTTP/2", item.headline().unwrap_or_default()), true),
+        // informational responses come before the final one
+        (None, Some(status)) if status.starts_with('1') => return,
+        (None, Some(status)) => (format!("HTTP/2 {status}"), false),
+        (None, None) => {
+            if let Some(message_index) = ctx.http2.stream(key, stream_id).messages[side] {
+                if let Some(message) = ctx.get_http_message(message_index) {
+                    message.decoded_headers.extend(lines);
+                }
+            }
+            return;
+        }
+    };
+    let request = ctx.http2.stream(key, stream_id).connect;
+    let (message_index, connect) = ctx.init_stream_message(index, host, is_request, key, request, ts);
+    let length = item.header("content-length").and_then(|v| v.trim().parse::<usize>().ok());
+    let hostname = item.header(":authority").or(item.header("host")).map(|v| v.to_string());
+    if let Some(message) = ctx.get_http_message(message_index) {
+        message.hostname = hostname.clone();
+        message.content_type = item.header("content-type").map(|v| v.to_string());
+        message.disposition = item.header("content-disposition").map(|v| v.to_string());
+        message.length = length.or(Some(0));
+        message.decoded_headers = lines;
+    }
+    if let Some(hostname) = &hostname {
+        ctx.add_http_hostname(message_index, hostname);
+    }
+    let stream = ctx.http2.stream(key, stream_id);
+    if is_request {
+        stream.connect = Some((connect, ts));
+    }
+    stream.messages[side] = Some(message_index);
+    stream.counted[side] = length.is_none();
+}
+
+fn data_block(ctx: &mut Context, key: ConnectionIndex, direction: usize, stream_id: u32, body: &[(FrameIndex, Range<usize>)]) {
+    let stream = *ctx.http2.stream(key, stream_id);
+    let Some(message_index) = stream.messages[direction] else {
+        return;
+    };
+    if let Some(message) = ctx.get_http_message(message_index) {
+        for (index, range) in body {
+            if stream.counted[direction] {
+                message.length = Some(message.length.unwrap_or_default() + range.len());
+            }
+            message.append_body(*index, range.clone());
+        }
+    }
+}
+
+pub struct Visitor;
+impl Visitor {
+    pub fn info(ctx: &Context, frame: &Frame) -> Option<String> {
+        let Some(packet) = ctx.http2.packet(frame.info.index) else {
+            return frame.tcp_description();
+        };
+        let mut list = vec![];
+        // the upgrade response shares the segment with the first frames
+        if let ProtocolInfoField::Http(line, _) = &frame.protocol_field {
+            list.push(line.clone());
+        }
+        if packet.preface > 0 {
+            list.push("Magic".to_string());
+        }
+        for item in &packet.frames {
+            let name = http2_frame_type_mapper(item.frame_type);
+            match item.headline() {
+                Some(headline) => list.push(format!("{name}[{}]: {headline}", item.stream)),
+                None => list.push(format!("{name}[{}]", item.stream)),
+            }
+        }
+        if list.is_empty() {
+            return Some("[HTTP/2 frame fragment]".to_string());
+        }
+        Some(list.join(", "))
+    }
+    /**
+     * feeds the segment to the frame reassembly of its direction, decoded header blocks become http messages
+     */
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+        let Some((key, reverse)) = frame.tcp_info.as_ref().and_then(|info| info.connection) else {
+            return Ok(Protocol::None);
+        };
+        let direction = reverse as usize;
+        let len = payload_len(frame, reader);
+        let base = reader.cursor;
+        let data = reader.slice(len, true)?;
+        let packet = ctx.http2.feed(frame.info.index, key, direction, data, base);
+        for item in &packet.frames {
+            header_block(ctx, frame, key, direction, item);
+            if item.frame_type == DATA {
+                data_block(ctx, key, direction, item.stream, &item.body);
+            }
+        }
+        ctx.http2.insert(frame.info.index, packet);
+        Ok(Protocol::None)
+    }
+
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader, datasources: &mut Vec<DataSource>) -> Result<Protocol> {
+        let Some(packet) = ctx.http2.packet(frame.info.index) else {
+            return Ok(Protocol::None);
+        };
+        let len = payload_len(frame, reader);
+        let mut reader = reader.slice_as_reader(len)?;
+        let start = reader.cursor;
+        if packet.preface > 0 {
+            add_field_forward!(field, reader, packet.preface, "Stream: Magic, PRI * HTTP/2.0\\r\\n\\r\\nSM\\r\\n\\r\\n".to_string());
+        }
+        // in payload order, a reassembled frame follows the fragment that completed it
+        let mut fragments = packet.fragments.iter().peekable();
+        for item in &packet.frames {
+            while let Some(range) = fragments.next_if(|range| item.offset.is_none_or(|offset| range.start < offset)) {
+                reader.set(start + range.start);
+                add_field_forward!(field, reader, range.len(), format!("[Frame fragment ({} bytes)]", range.len()));
+                if item.offset.is_none() {
+                    break;
+                }
+            }
+            match item.offset {
+                Some(offset) => {
+                    reader.set(start + offset);
+                    let size = (FRAME_HEADER + item.length).min(reader.left());
+                    let mut frame_reader = reader.slice_as_reader(size)?;
+                    let mut frame_field = Field::with_children(String::new(), frame_reader.cursor, size);
+                    frame_field.source = field.source;
+                    read_frame_field(&mut frame_reader, &mut frame_field, item)?;
+                    field.children.as_mut().unwrap().push(frame_field);
+                }
+                None => {
+                    let ds = DataSource::create(item.data.clone(), 0..0);
+                    let mut frame_reader = Reader::new(&ds);
+                    let mut frame_field = Field::with_children(String::new(), 0, item.data.len());
+                    frame_field.source = (datasources.len() + 1) as u8;
+                    read_frame_field(&mut frame_reader, &mut frame_field, item)?;
+                    frame_field.summary = format!("{} (reassembled)", frame_field.summary);
+                    field.children.as_mut().unwrap().push(frame_field);
+                    datasources.push(ds);
+                }
+            }
+        }
+        for range in fragments {
+            reader.set(start + range.start);
+            add_field_forward!(field, reader, range.len(), format!("[Frame fragment ({} bytes)]", range.len()));
+        }
+        field.summary = "HyperText Transfer Protocol 2".into();
+        Ok(Protocol::None)
+    }
+}
@@ -4,6 +4,8 @@
 // Licensed under the MIT License - see https://opensource.org/licenses/MIT
 
 pub mod http;
+pub mod http2;
+pub mod websocket;
 pub mod dns;
 pub mod mdns;
 pub mod nbns;
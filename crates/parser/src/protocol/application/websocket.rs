@@ -0,0 +1,192 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use crate::{
+    add_field_backstep, add_field_forward, add_field_label_no_range,
+    common::{
+        concept::Field,
+        core::Context,
+        enum_def::{Protocol, ProtocolInfoField},
+        io::{DataSource, Reader},
+        util::{bytes_to_hex, bytes_to_hex_limit},
+        websocket::{unmask, ws_close_code_mapper, ws_opcode_mapper, WebSocketMessage, WsFrame, WS_CLOSE, WS_TEXT},
+        Frame,
+    },
+};
+use anyhow::Result;
+
+// characters of a payload shown in a label
+const PREVIEW: usize = 256;
+
+// the tcp payload, without the padding of short ethernet frames
+fn payload_len(frame: &Frame, reader: &Reader) -> usize {
+    match &frame.tcp_info {
+        Some(tcp) => reader.left().min(tcp.len as usize),
+        None => reader.left(),
+    }
+}
+
+fn preview(text: &str) -> String {
+    match text.char_indices().nth(PREVIEW) {
+        Some((pos, _)) => format!("{}...", &text[..pos]),
+        None => text.to_string(),
+    }
+}
+
+fn frame_title(item: &WsFrame) -> String {
+    let header = &item.header;
+    let mut title = format!("WebSocket {}", ws_opcode_mapper(header.opcode));
+    if header.fin {
+        title.push_str(" [FIN]");
+    }
+    if header.mask.is_some() {
+        title.push_str(" [MASKED]");
+    }
+    title
+}
+
+fn read_frame_field(reader: &mut Reader, field: &mut Field, item: &WsFrame, message: Option<&WebSocketMessage>) -> Result<()> {
+    let header = &item.header;
+    let first = reader.read8()?;
+    add_field_backstep!(field, reader, 1, format!("Fin: {}", header.fin));
+    add_field_backstep!(field, reader, 1, format!("Reserved: {:#x}", (first >> 4) & 0x07));
+    if header.rsv1 {
+        add_field_backstep!(field, reader, 1, "Per-Message Compressed: true".to_string());
+    }
+    add_field_backstep!(field, reader, 1, format!("Opcode: {} ({})", ws_opcode_mapper(header.opcode), header.opcode));
+    let second = reader.read8()?;
+    add_field_backstep!(field, reader, 1, format!("Mask: {}", header.mask.is_some()));
+    add_field_backstep!(field, reader, 1, format!("Payload length: {}", second & 0x7f));
+    match second & 0x7f {
+        126 => {
+            reader.forward(2);
+            add_field_backstep!(field, reader, 2, format!("Extended Payload length (16 bits): {}", header.length));
+        }
+        127 => {
+            reader.forward(8);
+            add_field_backstep!(field, reader, 8, format!("Extended Payload length (64 bits): {}", header.length));
+        }
+        _ => {}
+    }
+    if let Some(key) = header.mask {
+        reader.forward(4);
+        add_field_backstep!(field, reader, 4, format!("Masking-Key: {}", bytes_to_hex(&key)));
+    }
+    field.summary = format!("{}, Length {}", frame_title(item), header.length);
+    let size = header.length.min(reader.left());
+    let mut payload = reader.slice(size, false)?.to_vec();
+    add_field_forward!(field, reader, size, format!("Payload ({} bytes)", header.length));
+    unmask(&mut payload, header.mask, 0);
+    if header.mask.is_some() && !payload.is_empty() {
+        add_field_label_no_range!(field, format!("[Unmasked payload: {}]", bytes_to_hex_limit(&payload, PREVIEW / 2)));
+    }
+    match header.opcode {
+        WS_CLOSE if payload.len() >= 2 => {
+            let code = u16::from_be_bytes([payload[0], payload[1]]);
+            add_field_label_no_range!(field, format!("Status code: {} ({code})", ws_close_code_mapper(code)));
+            if payload.len() > 2 {
+                add_field_label_no_range!(field, format!("Reason: {}", String::from_utf8_lossy(&payload[2..])));
+            }
+            field.summary = format!("{}, {} ({code})", field.summary, ws_close_code_mapper(code));
+        }
+        WS_TEXT if header.fin && !header.rsv1 => add_field_label_no_range!(field, format!("Text: {}", preview(&String::from_utf8_lossy(&payload)))),
+        _ => {}
+    }
+    // the message this frame completes, assembled and inflated
+    if let Some(message) = message {
+        if message.fragments > 1 || message.compressed {
+            let kind = if message.compressed { "Decompressed" } else { "Reassembled" };
+            add_field_label_no_range!(field, format!("[{kind} {} message: {} bytes in {} frames]", message.opcode, message.length, message.fragments));
+            if let Some(text) = &message.text {
+                add_field_label_no_range!(field, format!("Text: {}", preview(text)));
+            }
+        }
+        if message.truncated {
+            add_field_label_no_range!(field, "[Expert Info: Message payload truncated or not inflated]".to_string());
+        }
+    }
+    Ok(())
+}
+
+pub struct Visitor;
+impl Visitor {
+    pub fn info(ctx: &Context, frame: &Frame) -> Option<String> {
+        let Some(packet) = ctx.websocket.packet(frame.info.index) else {
+            return frame.tcp_description();
+        };
+        let mut list = vec![];
+        // the upgrade response shares the segment with the first frames
+        if let ProtocolInfoField::Http(line, _) = &frame.protocol_field {
+            list.push(line.clone());
+        }
+        list.extend(packet.frames.iter().map(frame_title));
+        if list.is_empty() {
+            return Some("[WebSocket frame fragment]".to_string());
+        }
+        Some(list.join(", "))
+    }
+    /**
+     * feeds the segment to the frame reassembly of its direction, completed frames build the messages of the connection
+     */
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+        let Some((key, reverse)) = frame.tcp_info.as_ref().and_then(|info| info.connection) else {
+            return Ok(Protocol::None);
+        };
+        let len = payload_len(frame, reader);
+        let data = reader.slice(len, true)?;
+        let packet = ctx.websocket.feed(key, reverse as usize, frame.info.index, frame.info.time, data);
+        ctx.websocket.insert(frame.info.index, packet);
+        Ok(Protocol::None)
+    }
+
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader, datasources: &mut Vec<DataSource>) -> Result<Protocol> {
+        let Some(packet) = ctx.websocket.packet(frame.info.index) else {
+            return Ok(Protocol::None);
+        };
+        let messages = frame.tcp_info.as_ref().and_then(|info| info.connection).and_then(|(key, _)| ctx.websocket.connection_of(key)).map(|connection| ctx.websocket.messages(connection)).unwrap_or_default();
+        let len = payload_len(frame, reader);
+        let mut reader = reader.slice_as_reader(len)?;
+        let start = reader.cursor;
+        // in payload order, a reassembled frame follows the fragment that completed it
+        let mut fragments = packet.fragments.iter().peekable();
+        for item in &packet.frames {
+            while let Some(range) = fragments.next_if(|range| item.offset.is_none_or(|offset| range.start < offset)) {
+                reader.set(start + range.start);
+                add_field_forward!(field, reader, range.len(), format!("[Frame fragment ({} bytes)]", range.len()));
+                if item.offset.is_none() {
+                    break;
+                }
+            }
+            let message = item.message.and_then(|index| messages.get(index));
+            match item.offset {
+                Some(offset) => {
+                    reader.set(start + offset);
+                    let size = (item.header.size + item.header.length).min(reader.left());
+                    let mut frame_reader = reader.slice_as_reader(size)?;
+                    let mut frame_field = Field::with_children(String::new(), frame_reader.cursor, size);
+                    frame_field.source = field.source;
+                    read_frame_field(&mut frame_reader, &mut frame_field, item, message)?;
+                    field.children.as_mut().unwrap().push(frame_field);
+                }
+                None => {
+                    let ds = DataSource::create(item.data.clone(), 0..0);
+                    let mut frame_reader = Reader::new(&ds);
+                    let mut frame_field = Field::with_children(String::new(), 0, item.data.len());
+                    frame_field.source = (datasources.len() + 1) as u8;
+                    read_frame_field(&mut frame_reader, &mut frame_field, item, message)?;
+                    frame_field.summary = format!("{} (reassembled)", frame_field.summary);
+                    field.children.as_mut().unwrap().push(frame_field);
+                    datasources.push(ds);
+                }
+            }
+        }
+        for range in fragments {
+            reader.set(start + range.start);
+            add_field_forward!(field, reader, range.len(), format!("[Frame fragment ({} bytes)]", range.len()));
+        }
+        field.summary = "WebSocket".into();
+        Ok(Protocol::None)
+    }
+}
@@ -0,0 +1,57 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use crate::{
+    common::{decode::Transport, enum_def::Protocol, io::Reader},
+    protocol::{application, transport},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    // shape matches but could be something else
+    Low,
+    // a signature only this protocol starts with
+    High,
+}
+
+/**
+ * a content based probe, consulted on the first payload of a tcp connection or udp flow without a port match
+ */
+pub struct Heuristic {
+    pub protocol: Protocol,
+    // breaks ties between probes of the same confidence, higher wins
+    pub priority: u8,
+    pub probe: fn(&Reader) -> Option<Confidence>,
+}
+
+const TCP_HEURISTICS: &[Heuristic] = &[
+    Heuristic { protocol: Protocol::HTTP2, priority: 25, probe: application::http2::probe },
+    Heuristic { protocol: Protocol::HTTP, priority: 20, probe: application::http::probe },
+    Heuristic { protocol: Protocol::TLS, priority: 10, probe: transport::tls::probe },
+];
+
+const UDP_HEURISTICS: &[Heuristic] = &[
+    Heuristic { protocol: Protocol::QUIC, priority: 20, probe: transport::quic::probe },
+    Heuristic { protocol: Protocol::DTLS, priority: 15, probe: transport::dtls::probe },
+    Heuristic { protocol: Protocol::DNS, priority: 10, probe: application::dns::probe },
+];
+
+pub fn registry(transport: Transport) -> &'static [Heuristic] {
+    match transport {
+        Transport::TCP => TCP_HEURISTICS,
+        Transport::UDP => UDP_HEURISTICS,
+    }
+}
+
+/**
+ * protocol of the most confident probe, every probe sees the payload from its start
+ */
+pub fn heuristic(transport: Transport, reader: &Reader) -> Option<Protocol> {
+    registry(transport)
+        .iter()
+        .filter_map(|h| (h.probe)(reader).map(|confidence| (confidence, h.priority, h.protocol)))
+        .max_by_key(|(confidence, priority, _)| (*confidence, *priority))
+        .map(|(_, _, protocol)| protocol)
+}
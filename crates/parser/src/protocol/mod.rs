@@ -10,6 +10,7 @@ use crate::common::{
     };
 
 pub mod application;
+pub mod heuristic;
 pub mod link;
 pub mod network;
 pub mod transport;
@@ -24,19 +25,30 @@ pub fn parse(protocol: Protocol, ctx: &mut Context, frame: &mut Frame, reader: &
         Protocol::IP6 => network::ip6::Visitor::parse(ctx, frame, reader),
         Protocol::TCP => transport::tcp::Visitor::parse(ctx, frame, reader),
         Protocol::UDP => transport::udp::Visitor::parse(ctx, frame, reader),
+        Protocol::SCTP => transport::sctp::Visitor::parse(ctx, frame, reader),
         Protocol::HTTP => application::http::Visitor::parse(ctx, frame, reader),
+        Protocol::HTTP2 => application::http2::Visitor::parse(ctx, frame, reader),
+        Protocol::WEBSOCKET => application::websocket::Visitor::parse(ctx, frame, reader),
         Protocol::ICMP => network::icmp::Visitor::parse(ctx, frame, reader),
         Protocol::ICMP6 => network::icmp6::Visitor::parse(ctx, frame, reader),
+        Protocol::IGMP => network::igmp::Visitor::parse(ctx, frame, reader),
+        Protocol::OSPF => network::ospf::Visitor::parse(ctx, frame, reader),
+        Protocol::VRRP => network::vrrp::Visitor::parse(ctx, frame, reader),
+        Protocol::AH => network::ipsec::AHVisitor::parse(ctx, frame, reader),
+        Protocol::ESP => network::ipsec::ESPVisitor::parse(ctx, frame, reader),
         Protocol::PPPoES => link::pppoes::Visitor::parse(ctx, frame, reader),
         Protocol::PPPoED => link::pppoed::Visitor::parse(ctx, frame, reader),
         Protocol::ARP => network::arp::Visitor::parse(ctx, frame, reader),
         Protocol::RARP => network::rarp::Visitor::parse(ctx, frame, reader),
         Protocol::DHCP => network::dhcp::Visitor::parse(ctx, frame, reader),
         Protocol::DHCP6 => network::dhcp6::Visitor::parse(ctx, frame, reader),
+        Protocol::HSRP => network::hsrp::Visitor::parse(ctx, frame, reader),
         Protocol::DNS => application::dns::Visitor::parse(ctx, frame, reader),
         Protocol::MDNS => application::mdns::Visitor::parse(ctx, frame, reader),
         Protocol::NBNS => application::nbns::Visitor::parse(ctx, frame, reader),
         Protocol::TLS => transport::tls::Visitor::parse(ctx, frame, reader),
+        Protocol::QUIC => transport::quic::Visitor::parse(ctx, frame, reader),
+        Protocol::DTLS => transport::dtls::Visitor::parse(ctx, frame, reader),
         Protocol::RADIOTAP => link::ieee802_11::link_127::Visitor::parse(ctx, frame, reader),
         Protocol::IEEE802_11 => link::ieee802_11::link_105::Visitor::parse(ctx, frame, reader),
         // "arp" => network::arp::Visitor::parse(frame, reader),
@@ -46,7 +58,7 @@ pub fn parse(protocol: Protocol, ctx: &mut Context, frame: &mut Frame, reader: &
 }
 pub fn detail<T>(protocol: Protocol, field: &mut Field, ins: &Instance<T>, frame: &Frame, reader: &mut crate::common::io::Reader, _datasources: &mut Vec<DataSource>) -> Result<(Protocol, Option<Vec<u8>>)> where T: ResourceLoader{
     let ctx = ins.context();
-    let loader = ins.loader();
+    let loader = &ins.resources();
     let protocol = match &protocol {
         Protocol::ETHERNET => link::ethernet::EthernetVisitor::detail(field, ctx, frame, reader),
         Protocol::SSL => link::ssl::Visitor::detail(field, ctx, frame, reader),
@@ -56,19 +68,30 @@ pub fn detail<T>(protocol: Protocol, field: &mut Field, ins: &Instance<T>, frame
         Protocol::IP6 => network::ip6::Visitor::detail(field, ctx, frame, reader),
         Protocol::TCP => transport::tcp::Visitor::detail(field, ctx, frame, reader),
         Protocol::UDP => transport::udp::Visitor::detail(field, ctx, frame, reader),
+        Protocol::SCTP => transport::sctp::Visitor::detail(field, ctx, frame, reader),
         Protocol::HTTP => application::http::Visitor::detail(field, ctx, frame, reader),
+        Protocol::HTTP2 => application::http2::Visitor::detail(field, ctx, frame, reader, _datasources),
+        Protocol::WEBSOCKET => application::websocket::Visitor::detail(field, ctx, frame, reader, _datasources),
         Protocol::ICMP => network::icmp::Visitor::detail(field, ctx, frame, reader),
         Protocol::ICMP6 => network::icmp6::Visitor::detail(field, ctx, frame, reader),
+        Protocol::IGMP => network::igmp::Visitor::detail(field, ctx, frame, reader),
+        Protocol::OSPF => network::ospf::Visitor::detail(field, ctx, frame, reader),
+        Protocol::VRRP => network::vrrp::Visitor::detail(field, ctx, frame, reader),
+        Protocol::AH => network::ipsec::AHVisitor::detail(field, ctx, frame, reader),
+        Protocol::ESP => return network::ipsec::ESPVisitor::detail(field, ctx, frame, reader),
         Protocol::PPPoES => link::pppoes::Visitor::detail(field, ctx, frame, reader),
         Protocol::PPPoED => link::pppoed::Visitor::detail(field, ctx, frame, reader),
         Protocol::ARP => network::arp::Visitor::detail(field, ctx, frame, reader),
         Protocol::RARP => network::rarp::Visitor::detail(field, ctx, frame, reader),
         Protocol::DHCP => network::dhcp::Visitor::detail(field, ctx, frame, reader),
         Protocol::DHCP6 => network::dhcp6::Visitor::detail(field, ctx, frame, reader),
+        Protocol::HSRP => network::hsrp::Visitor::detail(field, ctx, frame, reader),
         Protocol::DNS => application::dns::Visitor::detail(field, ctx, frame, reader),
         Protocol::MDNS => application::mdns::Visitor::detail(field, ctx, frame, reader),
         Protocol::NBNS => application::nbns::Visitor::detail(field, ctx, frame, reader),
         Protocol::TLS => transport::tls::Visitor::detail(field, ctx, loader, frame, reader, _datasources),
+        Protocol::QUIC => transport::quic::Visitor::detail(field, ctx, frame, reader, _datasources),
+        Protocol::DTLS => transport::dtls::Visitor::detail(field, ctx, frame, reader, _datasources),
         Protocol::RADIOTAP => link::ieee802_11::link_127::Visitor::detail(field, ctx, frame, reader),
         Protocol::IEEE802_11 => link::ieee802_11::link_105::Visitor::detail(field, ctx, frame, reader),
         
@@ -84,21 +107,32 @@ pub fn summary(protocol: Protocol, ctx: &Context, frame: &Frame) -> Option<Strin
     match protocol {
         Protocol::TCP => transport::tcp::Visitor::info(ctx, frame),
         Protocol::UDP => transport::udp::Visitor::info(ctx, frame),
+        Protocol::SCTP => transport::sctp::Visitor::info(ctx, frame),
         Protocol::IP4 => network::ip4::Visitor::info(ctx, frame),
         Protocol::IP6 => network::ip6::Visitor::info(ctx, frame),
         Protocol::HTTP => application::http::Visitor::info(ctx, frame),
+        Protocol::HTTP2 => application::http2::Visitor::info(ctx, frame),
+        Protocol::WEBSOCKET => application::websocket::Visitor::info(ctx, frame),
         Protocol::ICMP => network::icmp::Visitor::info(ctx, frame),
         Protocol::ICMP6 => network::icmp6::Visitor::info(ctx, frame),
+        Protocol::IGMP => network::igmp::Visitor::info(ctx, frame),
+        Protocol::OSPF => network::ospf::Visitor::info(ctx, frame),
+        Protocol::VRRP => network::vrrp::Visitor::info(ctx, frame),
+        Protocol::AH => network::ipsec::AHVisitor::info(ctx, frame),
+        Protocol::ESP => network::ipsec::ESPVisitor::info(ctx, frame),
         Protocol::PPPoES => link::pppoes::Visitor::info(ctx, frame),
         Protocol::PPPoED => link::pppoed::Visitor::info(ctx, frame),
         Protocol::ARP => network::arp::Visitor::info(ctx, frame),
         Protocol::RARP => network::rarp::Visitor::info(ctx, frame),
         Protocol::DHCP => network::dhcp::Visitor::info(ctx, frame),
         Protocol::DHCP6 => network::dhcp6::Visitor::info(ctx, frame),
+        Protocol::HSRP => network::hsrp::Visitor::info(ctx, frame),
         Protocol::DNS => application::dns::Visitor::info(ctx, frame),
         Protocol::MDNS => application::mdns::Visitor::info(ctx, frame),
         Protocol::NBNS => application::nbns::Visitor::info(ctx, frame),
         Protocol::TLS => transport::tls::Visitor::info(ctx, frame),
+        Protocol::QUIC => transport::quic::Visitor::info(ctx, frame),
+        Protocol::DTLS => transport::dtls::Visitor::info(ctx, frame),
         Protocol::RADIOTAP => link::ieee802_11::link_127::Visitor::info(ctx, frame),
         Protocol::IEEE802_11 => link::ieee802_11::link_105::Visitor::info(ctx, frame),
         _ => None
@@ -147,7 +181,12 @@ pub fn ip4_mapper(ipprototype: u8) -> Protocol {
         2 => Protocol::IGMP,
         6 => Protocol::TCP,
         17 => Protocol::UDP,
+        50 => Protocol::ESP,
+        51 => Protocol::AH,
         58 => Protocol::ICMP6,
+        89 => Protocol::OSPF,
+        112 => Protocol::VRRP,
+        132 => Protocol::SCTP,
         _ => Protocol::None,
     }
 }
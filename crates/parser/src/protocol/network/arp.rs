@@ -39,7 +39,7 @@ impl Visitor {
         None
     }
 
-    pub fn parse(_: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
         let hw_type = reader.read16(true)?;
         let _proto_type = reader.read16(true)?;
         let hw_size = reader.read8()?;
@@ -58,6 +58,7 @@ impl Visitor {
         let target_ip_data = reader.slice(proto_size as usize, true)?;
         let target_ip = Ipv4Addr::from(<[u8; 4]>::try_from(target_ip_data)?);
         
+        let sender = sender_mac.to_string();
         frame.protocol_field = ProtocolInfoField::ARP(
             hw_type,
             operation,
@@ -67,7 +68,10 @@ impl Visitor {
             target_ip,
         );
         frame.address_field = AddressField::IPv4(sender_ip, target_ip);
-        
+        if ctx.arp.add(frame.info.index, frame.info.time, operation, sender, sender_ip, target_ip) {
+            frame.mark_error("Duplicate IP address detected");
+        }
+
         frame.add_proto(crate::common::ProtoMask::ARP);
         Ok(Protocol::None)
     }
@@ -3,14 +3,16 @@
 // This file is part of the pcapview project.
 // Licensed under the MIT License - see https://opensource.org/licenses/MIT
 
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr};
 
 use crate::{
     add_field_backstep, add_field_format, add_sub_field_with_reader, common::{
         concept::Field,
         core::Context,
+        dhcp::{DhcpMessage, DHCP_ACK, DHCP_NAK, DHCP_OFFER},
         enum_def::{Protocol, ProtocolInfoField},
         io::{MacAddress, Reader},
+        ipsec::frame_ips,
         Frame,
     }, constants::{dhcp_option_type_mapper, dhcp_type_mapper}
 };
@@ -26,6 +28,10 @@ pub fn option_type_str(option_type: u8) -> String {
     format!("Option: {} ({})", dhcp_option_type_mapper(option_type), option_type)
 }
 
+fn ip4_list(data: &[u8]) -> Vec<IpAddr> {
+    data.chunks_exact(4).map(|c| IpAddr::V4(Ipv4Addr::new(c[0], c[1], c[2], c[3]))).collect()
+}
+
 pub struct Visitor;
 
 impl Visitor {
@@ -36,7 +42,7 @@ impl Visitor {
         None
     }
 
-    pub fn parse(_: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
         // Skip to the DHCP part if we're coming from UDP
         // DHCP header starts with message type (1 byte)
         let _op = reader.read8()?; // Message type (1=request, 2=reply)
@@ -44,23 +50,22 @@ impl Visitor {
         let hlen = reader.read8()?; // Hardware address length (6 for Ethernet)
         let _hops = reader.read8()?; // Hops
 
-        let _xid = reader.read32(true)?; // Transaction ID
+        let xid = reader.read32(true)?; // Transaction ID
         let _secs = reader.read16(true)?; // Seconds elapsed
         let _flags = reader.read16(true)?; // Flags
 
         // IP addresses
         let _client_ip = reader.read_ip4()?; // Client IP address (ciaddr)
-        let _your_ip = reader.read_ip4()?; // Your IP address (yiaddr)
+        let your_ip = reader.read_ip4()?; // Your IP address (yiaddr)
         let _server_ip = reader.read_ip4()?; // Server IP address (siaddr)
         let _gateway_ip = reader.read_ip4()?; // Gateway IP address (giaddr)
 
         // frame.address_field = AddressField::IPv4(client_ip, server_ip);
         // Client hardware address
-        reader.slice(hlen as usize, true)?;
-        // let client_mac = MacAddress::from(<[u8; 6]>::try_from(client_mac_data)?);
+        let client = reader.slice(hlen as usize, true)?.iter().map(|b| format!("{b:02x}")).collect::<Vec<String>>().join(":");
 
         // Skip the rest of the chaddr field (10 bytes)
-        reader.slice(10, true)?;
+        reader.slice(16_usize.saturating_sub(hlen as usize), true)?;
 
         // Server host name (64 bytes) and Boot file name (128 bytes)
         reader.slice(64, true)?; // Server host name
@@ -70,7 +75,10 @@ impl Visitor {
         let _magic_cookie = reader.read32(true)?;
 
         // Parse DHCP options
-        let mut msg_type: u8 = 0;
+        let mut message = DhcpMessage { index: frame.info.index, ts: frame.info.time, version: 4, xid, client, ..Default::default() };
+        if !your_ip.is_unspecified() {
+            message.address = Some(IpAddr::V4(your_ip));
+        }
 
         // Parse options until we reach the end option (0xFF) or run out of data
         while reader.left() > 0 {
@@ -88,18 +96,25 @@ impl Visitor {
 
             // Read option length and data
             let option_len = reader.read8()? as usize;
-
-            // Check for DHCP message type option (53)
-            if option_type == 53 && option_len == 1 {
-                msg_type = reader.read8()?;
-            } else {
-                // Skip other options
-                reader.slice(option_len, true)?;
+            let data = reader.slice(option_len, true)?;
+            match option_type {
+                53 if option_len == 1 => message.msg_type = data[0],
+                51 if option_len == 4 => message.lease_time = Some(u32::from_be_bytes([data[0], data[1], data[2], data[3]])),
+                54 if option_len == 4 => message.server = Some(IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3]))),
+                3 => message.routers = ip4_list(data),
+                6 => message.dns = ip4_list(data),
+                _ => {}
             }
         }
 
         // Store DHCP information in the frame
-        frame.protocol_field = ProtocolInfoField::DHCP(msg_type);
+        frame.protocol_field = ProtocolInfoField::DHCP(message.msg_type);
+        if message.msg_type > 0 {
+            if message.server.is_none() && matches!(message.msg_type, DHCP_OFFER | DHCP_ACK | DHCP_NAK) {
+                message.server = frame_ips(ctx, frame).0;
+            }
+            ctx.dhcp.add(message);
+        }
 
         Ok(Protocol::None)
     }
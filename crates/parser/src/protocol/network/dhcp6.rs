@@ -3,12 +3,19 @@
 // This file is part of the pcapview project.
 // Licensed under the MIT License - see https://opensource.org/licenses/MIT
 
+use std::{
+    cmp,
+    net::{IpAddr, Ipv6Addr},
+};
+
 use crate::{
     add_field_backstep, common::{
         concept::Field,
         core::Context,
+        dhcp::{DhcpMessage, DHCP6_SOLICIT},
         enum_def::{Protocol, ProtocolInfoField},
         io::Reader,
+        ipsec::frame_ips,
         Frame,
     }
 };
@@ -195,6 +202,73 @@ pub fn dhcpv6_option_code_mapper(code: u16) -> &'static str {
     }
 }
 
+fn option_list(data: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.len() < 4 {
+            return None;
+        }
+        let code = u16::from_be_bytes([rest[0], rest[1]]);
+        let len = cmp::min(u16::from_be_bytes([rest[2], rest[3]]) as usize, rest.len() - 4);
+        let (data, next) = rest[4..].split_at(len);
+        rest = next;
+        Some((code, data))
+    })
+}
+
+fn ip6(data: &[u8]) -> IpAddr {
+    let mut addr = [0u8; 16];
+    addr.copy_from_slice(&data[..16]);
+    IpAddr::V6(Ipv6Addr::from(addr))
+}
+
+fn read_status(data: &[u8], message: &mut DhcpMessage) {
+    if data.len() >= 2 {
+        let code = u16::from_be_bytes([data[0], data[1]]);
+        // the first failure wins over later success codes of other IAs
+        if message.status.is_none_or(|status| status == 0) {
+            message.status = Some(code);
+        }
+    }
+}
+
+// IA_NA: iaid, t1, t2 then options, the IAADDR valid lifetime is the lease time
+fn read_ia_na(data: &[u8], message: &mut DhcpMessage) {
+    for (code, data) in option_list(data.get(12..).unwrap_or_default()) {
+        match code {
+            5 if data.len() >= 24 => {
+                message.address = Some(ip6(data));
+                message.lease_time = Some(u32::from_be_bytes([data[20], data[21], data[22], data[23]]));
+                for (code, data) in option_list(&data[24..]) {
+                    if code == 13 {
+                        read_status(data, message);
+                    }
+                }
+            }
+            13 => read_status(data, message),
+            _ => {}
+        }
+    }
+}
+
+/**
+ * collects client id, lease and dns options, returns whether a server id is present
+ */
+fn read_lease_options(reader: &mut Reader, message: &mut DhcpMessage) -> Result<bool> {
+    let mut server_id = false;
+    for (code, data) in option_list(reader.slice(reader.left(), true)?) {
+        match code {
+            1 => message.client = data.iter().map(|b| format!("{b:02x}")).collect(),
+            2 => server_id = true,
+            3 => read_ia_na(data, message),
+            13 => read_status(data, message),
+            23 => message.dns = data.chunks_exact(16).map(ip6).collect(),
+            _ => {}
+        }
+    }
+    Ok(server_id)
+}
+
 pub struct Visitor;
 
 impl Visitor {
@@ -206,7 +280,7 @@ impl Visitor {
         None
     }
 
-    pub fn parse(_: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
         // DHCPv6 header starts with message type (1 byte)
         let msg_type = reader.read8()?;
         
@@ -218,6 +292,17 @@ impl Visitor {
         
         // Store DHCPv6 information in the frame
         frame.protocol_field = ProtocolInfoField::DHCPv6(msg_type, transaction_id);
+
+        // relay messages wrap the client message, only direct exchanges are tracked
+        if (DHCP6_SOLICIT..=11).contains(&msg_type) {
+            let mut message = DhcpMessage { index: frame.info.index, ts: frame.info.time, version: 6, msg_type, xid: transaction_id, ..Default::default() };
+            if read_lease_options(reader, &mut message)? {
+                message.server = frame_ips(ctx, frame).0;
+            }
+            if !message.client.is_empty() {
+                ctx.dhcp.add(message);
+            }
+        }
         Ok(Protocol::None)
     }
 
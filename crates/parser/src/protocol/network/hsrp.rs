@@ -0,0 +1,254 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use crate::{
+    add_field_backstep, add_field_format, add_field_format_fn, add_field_label_no_range, add_field_rest_format, add_sub_field_with_reader,
+    common::{
+        concept::Field,
+        core::Context,
+        enum_def::{Protocol, ProtocolInfoField},
+        fhrp::{FhrpAdvertisement, FhrpProtocol, FhrpRole},
+        io::Reader,
+        ipsec::frame_ips,
+        Frame,
+    },
+};
+use anyhow::Result;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+// HSRP Op Codes
+const HSRP_HELLO: u8 = 0;
+const HSRP_COUP: u8 = 1;
+const HSRP_RESIGN: u8 = 2;
+const HSRP_ADVERTISE: u8 = 3;
+
+// version 1 states are bit values (RFC 2281 5.3), version 2 numbers them
+const HSRP_ACTIVE: u8 = 16;
+const HSRP2_ACTIVE: u8 = 6;
+
+// HSRPv2 TLV types
+const HSRP2_GROUP_STATE: u8 = 1;
+const HSRP2_TEXT_AUTH: u8 = 3;
+const HSRP2_MD5_AUTH: u8 = 4;
+
+pub fn hsrp_opcode_mapper(opcode: u8) -> &'static str {
+    match opcode {
+        HSRP_HELLO => "Hello",
+        HSRP_COUP => "Coup",
+        HSRP_RESIGN => "Resign",
+        HSRP_ADVERTISE => "Advertise",
+        _ => "Unknown",
+    }
+}
+
+pub fn hsrp_state_mapper(version: u8, state: u8) -> &'static str {
+    match (version, state) {
+        (1, 0) => "Initial",
+        (1, 1) => "Learn",
+        (1, 2) => "Listen",
+        (1, 4) => "Speak",
+        (1, 8) => "Standby",
+        (1, HSRP_ACTIVE) => "Active",
+        (_, 0) => "Disabled",
+        (_, 1) => "Init",
+        (_, 2) => "Learn",
+        (_, 3) => "Listen",
+        (_, 4) => "Speak",
+        (_, 5) => "Standby",
+        (_, HSRP2_ACTIVE) => "Active",
+        _ => "Unknown",
+    }
+}
+
+fn is_active(version: u8, state: u8) -> bool {
+    match version {
+        1 => state == HSRP_ACTIVE,
+        _ => state == HSRP2_ACTIVE,
+    }
+}
+
+struct GroupState {
+    version: u8,
+    opcode: u8,
+    state: u8,
+    group: u16,
+    priority: u32,
+    virtual_ip: IpAddr,
+}
+
+/**
+ * version 1 starts with its version byte 0, version 2 with the group state tlv
+ */
+fn read_group_state(reader: &mut Reader) -> Result<Option<GroupState>> {
+    if reader.next()? == 0 {
+        reader.read8()?;
+        let opcode = reader.read8()?;
+        let state = reader.read8()?;
+        reader.forward(2); // hello time, hold time
+        let priority = reader.read8()? as u32;
+        let group = reader.read8()? as u16;
+        reader.forward(9); // reserved, authentication data
+        let virtual_ip = IpAddr::V4(reader.read_ip4()?);
+        return Ok(Some(GroupState { version: 1, opcode, state, group, priority, virtual_ip }));
+    }
+    while reader.left() >= 2 {
+        let tlv_type = reader.read8()?;
+        let tlv_len = reader.read8()? as usize;
+        if tlv_type != HSRP2_GROUP_STATE || tlv_len < 40 {
+            reader.forward(tlv_len);
+            continue;
+        }
+        let version = reader.read8()?;
+        let opcode = reader.read8()?;
+        let state = reader.read8()?;
+        let ip_version = reader.read8()?;
+        let group = reader.read16(true)?;
+        reader.forward(6); // identifier
+        let priority = reader.read32(true)?;
+        reader.forward(8); // hello time, hold time
+        let data = reader.slice(16, true)?;
+        let virtual_ip = match ip_version {
+            6 => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(data)?)),
+            _ => IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3])),
+        };
+        return Ok(Some(GroupState { version, opcode, state, group, priority, virtual_ip }));
+    }
+    Ok(None)
+}
+
+fn t_auth(data: &[u8]) -> String {
+    format!("Authentication Data: {}", String::from_utf8_lossy(data).trim_end_matches('\0'))
+}
+
+fn read_v1_field(field: &mut Field, reader: &mut Reader) -> Result<(u8, u8, u16)> {
+    add_field_format!(field, reader, reader.read8()?, "Version: {}");
+    let opcode = add_field_format_fn!(field, reader, reader.read8()?, |o| format!("Op Code: {} ({o})", hsrp_opcode_mapper(o)));
+    let state = add_field_format_fn!(field, reader, reader.read8()?, |s| format!("State: {} ({s})", hsrp_state_mapper(1, s)));
+    add_field_format!(field, reader, reader.read8()?, "Hellotime: {} seconds");
+    add_field_format!(field, reader, reader.read8()?, "Holdtime: {} seconds");
+    add_field_format!(field, reader, reader.read8()?, "Priority: {}");
+    let group = add_field_format!(field, reader, reader.read8()?, "Group: {}") as u16;
+    add_field_format!(field, reader, reader.read8()?, "Reserved: {}");
+    let text = t_auth(reader.slice(8, true)?);
+    add_field_backstep!(field, reader, 8, text);
+    add_field_format!(field, reader, reader.read_ip4()?, "Virtual IP Address: {}");
+    Ok((opcode, state, group))
+}
+
+fn read_group_state_field(reader: &mut Reader, field: &mut Field) -> Result<(u8, u8, u16)> {
+    add_field_format!(field, reader, reader.read8()?, "Type: Group State TLV ({})");
+    add_field_format!(field, reader, reader.read8()?, "Length: {}");
+    let version = add_field_format!(field, reader, reader.read8()?, "Version: {}");
+    let opcode = add_field_format_fn!(field, reader, reader.read8()?, |o| format!("Op Code: {} ({o})", hsrp_opcode_mapper(o)));
+    let state = add_field_format_fn!(field, reader, reader.read8()?, |s| format!("State: {} ({s})", hsrp_state_mapper(version, s)));
+    let ip_version = add_field_format!(field, reader, reader.read8()?, "IP Ver.: {}");
+    let group = add_field_format!(field, reader, reader.read16(true)?, "Group: {}");
+    add_field_format!(field, reader, reader.read_mac()?, "Identifier: {}");
+    add_field_format!(field, reader, reader.read32(true)?, "Priority: {}");
+    add_field_format!(field, reader, reader.read32(true)?, "Hellotime: {} milliseconds");
+    add_field_format!(field, reader, reader.read32(true)?, "Holdtime: {} milliseconds");
+    match ip_version {
+        6 => {
+            add_field_format!(field, reader, reader.read_ip6()?, "Virtual IPv6 Address: {}");
+        }
+        _ => {
+            add_field_format!(field, reader, reader.read_ip4()?, "Virtual IP Address: {}");
+            reader.forward(12);
+        }
+    }
+    field.summary = "Group State TLV".into();
+    Ok((opcode, state, group))
+}
+
+fn read_tlv_field(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    let head = reader.preview(2)?;
+    let (tlv_type, tlv_len) = (head[0], head[1] as usize);
+    let name = match tlv_type {
+        HSRP2_TEXT_AUTH => "Text Authentication TLV",
+        HSRP2_MD5_AUTH => "MD5 Authentication TLV",
+        _ => "Unknown TLV",
+    };
+    add_field_format!(field, reader, reader.read8()?, "Type: {}");
+    add_field_format!(field, reader, reader.read8()?, "Length: {}");
+    let mut _reader = reader.slice_as_reader(tlv_len)?;
+    match tlv_type {
+        HSRP2_TEXT_AUTH => {
+            let text = t_auth(_reader.slice(tlv_len, true)?);
+            add_field_backstep!(field, _reader, tlv_len, text);
+        }
+        _ => add_field_rest_format!(field, _reader, format!("Data: {tlv_len} bytes")),
+    }
+    field.summary = name.into();
+    Ok(())
+}
+
+pub struct Visitor;
+
+impl Visitor {
+    pub fn info(_: &Context, frame: &Frame) -> Option<String> {
+        if let ProtocolInfoField::Hsrp(version, opcode, state, group) = &frame.protocol_field {
+            return Some(format!("HSRPv{version} {} (state {}), group {group}", hsrp_opcode_mapper(*opcode), hsrp_state_mapper(*version, *state)));
+        }
+        Some("Cisco Hot Standby Router Protocol".to_string())
+    }
+
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+        let Some(message) = read_group_state(reader)? else {
+            return Ok(Protocol::None);
+        };
+        frame.protocol_field = ProtocolInfoField::Hsrp(message.version, message.opcode, message.state, message.group);
+        let role = match (message.opcode, is_active(message.version, message.state)) {
+            (HSRP_RESIGN, _) => FhrpRole::Resign,
+            (HSRP_HELLO, true) => FhrpRole::Master,
+            _ => FhrpRole::Backup,
+        };
+        let virtual_ips = match message.virtual_ip.is_unspecified() {
+            true => vec![],
+            false => vec![message.virtual_ip],
+        };
+        if let (Some(router), _) = frame_ips(ctx, frame) {
+            ctx.fhrp.add(FhrpAdvertisement {
+                index: frame.info.index,
+                ts: frame.info.time,
+                protocol: FhrpProtocol::HSRP,
+                version: message.version,
+                group: message.group,
+                router,
+                priority: message.priority,
+                role,
+                virtual_ips,
+            });
+        }
+        Ok(Protocol::None)
+    }
+
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
+        let summary = match reader.next()? {
+            0 => {
+                let (opcode, state, group) = read_v1_field(field, reader)?;
+                format!("Cisco Hot Standby Router Protocol, {} (state {}), group {group}", hsrp_opcode_mapper(opcode), hsrp_state_mapper(1, state))
+            }
+            _ => {
+                let mut summary = "Cisco Hot Standby Router Protocol Version 2".to_string();
+                while reader.left() >= 2 {
+                    if reader.next()? == HSRP2_GROUP_STATE {
+                        let (opcode, state, group) = add_sub_field_with_reader!(field, reader, read_group_state_field)?;
+                        summary = format!("{summary}, {} (state {}), group {group}", hsrp_opcode_mapper(opcode), hsrp_state_mapper(2, state));
+                    } else {
+                        add_sub_field_with_reader!(field, reader, read_tlv_field)?;
+                    }
+                }
+                summary
+            }
+        };
+        match ctx.fhrp.takeover(frame.info.index) {
+            Some(Some(previous)) => add_field_label_no_range!(field, format!("[Active router changed from {previous}]")),
+            Some(None) => add_field_label_no_range!(field, "[First active router of the group]".into()),
+            None => {}
+        }
+        field.summary = summary;
+        Ok(Protocol::None)
+    }
+}
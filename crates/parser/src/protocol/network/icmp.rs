@@ -4,15 +4,17 @@
 // Licensed under the MIT License - see https://opensource.org/licenses/MIT
 
 use crate::{
-    add_field_backstep, add_field_format, add_field_format_fn, add_field_rest_format, common::{
+    add_field_backstep, add_field_backstep_fn, add_field_format, add_field_format_fn, add_field_forward, add_field_label_no_range, add_field_rest_format, add_sub_field_with_reader, common::{
+        checksum::{t_checksum, verify_transport, ChecksumStatus, IP_PROTO_ICMP},
         concept::Field,
         core::Context,
-        enum_def::{Protocol, ProtocolInfoField},
+        enum_def::{DataError, Protocol, ProtocolInfoField},
+        icmp::{IcmpError, QuotedDatagram, IP_PROTO_TCP, IP_PROTO_UDP},
         io::Reader,
         Frame,
-    }
+    }, protocol::network::ip4::{head_lenstr, t_protocol}
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
 
 pub fn icmp_type_mapper(code: u8) -> &'static str {
     match code {
@@ -99,6 +101,108 @@ pub fn t_icmp_code(type_code: u8, code: u8) -> String {
     format!("Code: {} ({})", icmp_code_mapper(type_code, code), code)
 }
 
+/**
+ * records the error against the datagram it quotes, nothing is added to the conversation statistics
+ */
+pub fn record_error(ctx: &mut Context, frame: &Frame, reader: &mut Reader, version: u8, icmp_type: u8, code: u8, mtu: Option<u32>) {
+    if let Ok(Some(quoted)) = QuotedDatagram::read(reader) {
+        ctx.add_icmp_error(IcmpError {
+            index: frame.info.index,
+            ts: frame.info.time,
+            version,
+            icmp_type,
+            code,
+            mtu,
+            quoted,
+            connection: None,
+        });
+    }
+}
+
+fn read_quoted_ip4(reader: &mut Reader, field: &mut Field) -> Result<u8> {
+    let head_len = reader.read8()? & 0x0f;
+    if head_len < 5 {
+        bail!(DataError::Ipv4HeadLengthInvalid);
+    }
+    add_field_backstep!(field, reader, 1, "0100 .... = Version: 4".into());
+    add_field_backstep_fn!(field, reader, 1, head_lenstr(head_len));
+    add_field_format!(field, reader, reader.read8()?, "Differentiated Services Field: {:#04x}");
+    add_field_format!(field, reader, reader.read16(true)?, "Total Length: {}");
+    add_field_format!(field, reader, reader.read16(true)?, "Identification: {:#06x}");
+    add_field_format!(field, reader, reader.read16(true)?, "Flags and Fragment Offset: {:#06x}");
+    add_field_format!(field, reader, reader.read8()?, "Time To Live: {}");
+    let protocol = add_field_format_fn!(field, reader, reader.read8()?, t_protocol);
+    add_field_format!(field, reader, reader.read16(true)?, "Header Checksum: {:#06x}");
+    let source = add_field_format!(field, reader, reader.read_ip4()?, "Source Address: {}");
+    let target = add_field_format!(field, reader, reader.read_ip4()?, "Destination Address: {}");
+    let options = head_len as usize * 4 - 20;
+    if options > 0 {
+        add_field_forward!(field, reader, options, format!("Options: {options} bytes"));
+        reader.forward(options);
+    }
+    field.summary = format!("Internet Protocol Version 4, Src: {source}, Dst: {target}");
+    Ok(protocol)
+}
+
+fn read_quoted_ip6(reader: &mut Reader, field: &mut Field) -> Result<u8> {
+    let word = reader.read32(true)?;
+    add_field_backstep!(field, reader, 4, format!("0110 .... = Version: 6, Traffic Class: {:#04x}, Flow Label: {:#07x}", (word >> 20) & 0xff, word & 0xfffff));
+    add_field_format!(field, reader, reader.read16(true)?, "Payload Length: {}");
+    let protocol = add_field_format_fn!(field, reader, reader.read8()?, |p| t_protocol(p).replace("Protocol", "Next Header"));
+    add_field_format!(field, reader, reader.read8()?, "Hop Limit: {}");
+    let source = add_field_format!(field, reader, reader.read_ip6()?, "Source Address: {}");
+    let target = add_field_format!(field, reader, reader.read_ip6()?, "Destination Address: {}");
+    field.summary = format!("Internet Protocol Version 6, Src: {source}, Dst: {target}");
+    Ok(protocol)
+}
+
+// only the first bytes of the transport header are quoted, usually just ports and sequence number
+fn read_quoted_transport(reader: &mut Reader, field: &mut Field, protocol: u8) -> Result<()> {
+    let source_port = add_field_format!(field, reader, reader.read16(true)?, "Source Port: {}");
+    let target_port = add_field_format!(field, reader, reader.read16(true)?, "Destination Port: {}");
+    if protocol == IP_PROTO_TCP {
+        field.summary = format!("Transmission Control Protocol, Src Port: {source_port}, Dst Port: {target_port}");
+        if reader.left() >= 4 {
+            add_field_format!(field, reader, reader.read32(true)?, "Sequence Number: {}");
+        }
+    } else {
+        field.summary = format!("User Datagram Protocol, Src Port: {source_port}, Dst Port: {target_port}");
+        if reader.left() >= 4 {
+            add_field_format!(field, reader, reader.read16(true)?, "Length: {}");
+            add_field_format!(field, reader, reader.read16(true)?, "Checksum: {:#06x}");
+        }
+    }
+    Ok(())
+}
+
+/**
+ * dissects the quoted ip header and transport ports as subtrees, falls back to opaque data
+ */
+pub fn read_original_datagram(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader) -> Result<()> {
+    let read_ip = match reader.next().map(|b| b >> 4) {
+        Ok(4) => read_quoted_ip4,
+        Ok(6) => read_quoted_ip6,
+        _ => {
+            add_field_rest_format!(field, reader, format!("Original Datagram: {} bytes", reader.left()));
+            return Ok(());
+        }
+    };
+    let protocol = add_sub_field_with_reader!(field, reader, read_ip)?;
+    if (protocol == IP_PROTO_TCP || protocol == IP_PROTO_UDP) && reader.left() >= 4 {
+        add_sub_field_with_reader!(field, reader, |r: &mut Reader, f: &mut Field| read_quoted_transport(r, f, protocol))?;
+    }
+    add_field_rest_format!(field, reader, format!("Data: {} bytes", reader.left()));
+    if let Some(error) = ctx.icmp_errors.get(frame.info.index) {
+        let quoted = &error.quoted;
+        match error.connection {
+            Some((conversation, connection)) => add_field_label_no_range!(field, format!("[Related TCP connection: conversation {conversation}, connection {connection}]")),
+            None if quoted.protocol == IP_PROTO_UDP => add_field_label_no_range!(field, format!("[Related UDP conversation: {}]", quoted.udp_key())),
+            None => {}
+        }
+    }
+    Ok(())
+}
+
 pub struct Visitor;
 
 impl Visitor {
@@ -112,19 +216,36 @@ impl Visitor {
         }
     }
 
-    pub fn parse(_: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
         let _start = reader.left();
+        if ctx.config.verify_checksum && verify_transport(ctx, frame, reader, IP_PROTO_ICMP, frame.iplen as usize, 2).is_bad() {
+            frame.mark_error("Bad ICMP checksum");
+        }
         let _type = reader.read8()?;
         let code = reader.read8()?;
         frame.protocol_field = ProtocolInfoField::Icmp(_type, code);
+        if matches!(_type, 3 | 11 | 12) {
+            reader.forward(2);
+            let rest = reader.read32(true)?;
+            let mtu = match (_type, code) {
+                (3, 4) => Some(rest & 0xffff),
+                _ => None,
+            };
+            record_error(ctx, frame, reader, 4, _type, code, mtu);
+        }
         Ok(Protocol::None)
     }
 
-    pub fn detail(field: &mut Field, _: &Context, _: &Frame, reader: &mut Reader) -> Result<Protocol> {
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
         let _start = reader.left();
+        let status = match ctx.config.verify_checksum {
+            true => verify_transport(ctx, frame, reader, IP_PROTO_ICMP, frame.iplen as usize, 2),
+            false => ChecksumStatus::Unverified,
+        };
         let type_code = add_field_format_fn!(field, reader, reader.read8()?, t_icmp_type);
         let code = add_field_format_fn!(field, reader, reader.read8()?, |c| t_icmp_code(type_code, c));
-        add_field_format!(field, reader, reader.read16(true)?, "Checksum: {:#06x}");
+        let checksum = reader.read16(true)?;
+        add_field_backstep!(field, reader, 2, t_checksum("Checksum", checksum, status));
 
         match type_code {
             0 | 8 => {
@@ -138,27 +259,28 @@ impl Visitor {
                     reader.read16(true)?; // unused
                     add_field_format!(field, reader, reader.read16(true)?, "Next-hop MTU: {}");
                 } else {
+                    reader.forward(4);
                     add_field_backstep!(field, reader, 4, "Unused".into());
                 }
-                add_field_rest_format!(field, reader, format!("Original Datagram: {} bytes", reader.left()));
+                read_original_datagram(field, ctx, frame, reader)?;
             }
             5 => {
                 // Redirect
                 let _gateway = add_field_format!(field, reader, reader.read_ip4()?, "Gateway Address: {}");
-
-                add_field_rest_format!(field, reader, format!("Original Datagram: {} bytes", reader.left()));
+                read_original_datagram(field, ctx, frame, reader)?;
             }
             11 => {
                 // Time Exceeded
+                reader.forward(4);
                 add_field_backstep!(field, reader, 4, "Unused".into());
-
-                add_field_rest_format!(field, reader, format!("Original Datagram: {} bytes", reader.left()));
+                read_original_datagram(field, ctx, frame, reader)?;
             }
             12 => {
                 // Parameter Problem
                 add_field_format!(field, reader, reader.read8()?, "Pointer: {}");
+                reader.forward(3);
                 add_field_backstep!(field, reader, 3, "Unused".into());
-                add_field_rest_format!(field, reader, format!("Original Datagram: {} bytes", reader.left()));
+                read_original_datagram(field, ctx, frame, reader)?;
             }
             13 | 14 => {
                 add_field_format!(field, reader, reader.read16(true)?, "Identifier: {}");
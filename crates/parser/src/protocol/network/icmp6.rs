@@ -4,7 +4,9 @@
 // Licensed under the MIT License - see https://opensource.org/licenses/MIT
 
 use crate::{
-    add_field_backstep, add_field_format, add_field_format_fn, add_field_rest_format, common::{concept::Field, core::Context, enum_def::{Protocol, ProtocolInfoField}, io::Reader, Frame}
+    add_field_backstep, add_field_format, add_field_format_fn, add_field_rest_format, common::{
+        checksum::{t_checksum, verify_transport, ChecksumStatus, IP_PROTO_ICMP6}, concept::Field, core::Context, enum_def::{Protocol, ProtocolInfoField}, io::Reader, Frame},
+    protocol::network::icmp::{read_original_datagram, record_error}
 };
 use anyhow::Result;
 
@@ -241,20 +243,37 @@ impl Visitor {
         Some("Internet Control Message Protocol v6".to_string())
     }
 
-    pub fn parse(_: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
         let _start = reader.left();
+        if ctx.config.verify_checksum && verify_transport(ctx, frame, reader, IP_PROTO_ICMP6, frame.iplen as usize, 2).is_bad() {
+            frame.mark_error("Bad ICMPv6 checksum");
+        }
         let _type = reader.read8()?;
         let code = reader.read8()?;
         frame.protocol_field = ProtocolInfoField::Icmp6(_type, code);
+        if matches!(_type, 1..=4) {
+            reader.forward(2);
+            let rest = reader.read32(true)?;
+            let mtu = match _type {
+                2 => Some(rest),
+                _ => None,
+            };
+            record_error(ctx, frame, reader, 6, _type, code, mtu);
+        }
         Ok(Protocol::None)
     }
 
-    pub fn detail(field: &mut Field, _: &Context, _: &Frame, reader: &mut Reader) -> Result<Protocol> {
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
         let _start = reader.left();
+        let status = match ctx.config.verify_checksum {
+            true => verify_transport(ctx, frame, reader, IP_PROTO_ICMP6, frame.iplen as usize, 2),
+            false => ChecksumStatus::Unverified,
+        };
 
         let type_code = add_field_format_fn!(field, reader, reader.read8()?, t_icmp6_type);
         add_field_format_fn!(field, reader, reader.read8()?, |c| t_icmp6_code(type_code, c));
-        add_field_format!(field, reader, reader.read16(true)?, "Checksum: {:#06x}");
+        let checksum = reader.read16(true)?;
+        add_field_backstep!(field, reader, 2, t_checksum("Checksum", checksum, status));
 
         match type_code {
             // Echo Request/Reply
@@ -266,22 +285,18 @@ impl Visitor {
             // Packet Too Big
             2 => {
                 add_field_format!(field, reader, reader.read32(true)?, "MTU: {}");
-                add_field_format!(field, reader, reader.read16(true)?, "Original Packet: {} bytes");
+                read_original_datagram(field, ctx, frame, reader)?;
             },
-            // Destination Unreachable
-            1 => {
-                add_field_format!(field, reader, reader.read16(true)?, "Unused: {}");
-                add_field_format!(field, reader, reader.read16(true)?, "Original Packet: {} bytes");
-            },
-            // Time Exceeded
-            3 => {
+            // Destination Unreachable, Time Exceeded
+            1 | 3 => {
+                reader.forward(4);
                 add_field_backstep!(field, reader, 4, "Unused".into());
-                add_field_rest_format!(field, reader, format!("Original Packet: {} bytes", reader.left()));
+                read_original_datagram(field, ctx, frame, reader)?;
             },
             // Parameter Problem
             4 => {
                 add_field_format!(field, reader, reader.read32(true)?, "Pointer: {}");
-                add_field_rest_format!(field, reader, format!("Original Packet: {} bytes", reader.left()));
+                read_original_datagram(field, ctx, frame, reader)?;
             },
             // Router Solicitation
             133 => {
@@ -1,20 +1,22 @@
 // Copyright (c) 2025 sankooc
-// 
+//
 // This file is part of the pcapview project.
 // Licensed under the MIT License - see https://opensource.org/licenses/MIT
 
 use crate::{
+    add_field_backstep, add_field_format, add_field_format_fn, add_field_label_no_range, add_field_rest_format, add_sub_field_with_reader,
     common::{
+        checksum::{t_checksum, verify_transport, ChecksumStatus, IP_PROTO_IGMP},
         concept::Field,
         core::Context,
-        enum_def::{Protocol, ProtocolInfoField},
+        enum_def::{AddressField, Protocol, ProtocolInfoField},
         io::Reader,
+        multicast::{MulticastAction, MulticastEvent, DEFAULT_QUERIER_TIMEOUT},
         Frame,
     },
-    field_back_format,
 };
 use anyhow::Result;
-use std::net::Ipv4Addr;
+use std::{cmp, net::Ipv4Addr};
 
 // IGMP Message Types
 const IGMP_MEMBERSHIP_QUERY: u8 = 0x11;
@@ -23,8 +25,15 @@ const IGMP_MEMBERSHIP_REPORT_V2: u8 = 0x16;
 const IGMP_LEAVE_GROUP: u8 = 0x17;
 const IGMP_MEMBERSHIP_REPORT_V3: u8 = 0x22;
 
-// Helper function to get IGMP message type as string
-fn igmp_type_to_string(msg_type: u8) -> &'static str {
+// IGMPv3 Group Record Types (RFC 3376 4.2.12)
+const MODE_IS_INCLUDE: u8 = 1;
+const MODE_IS_EXCLUDE: u8 = 2;
+const CHANGE_TO_INCLUDE_MODE: u8 = 3;
+const CHANGE_TO_EXCLUDE_MODE: u8 = 4;
+const ALLOW_NEW_SOURCES: u8 = 5;
+const BLOCK_OLD_SOURCES: u8 = 6;
+
+pub fn igmp_type_mapper(msg_type: u8) -> &'static str {
     match msg_type {
         IGMP_MEMBERSHIP_QUERY => "Membership Query",
         IGMP_MEMBERSHIP_REPORT_V1 => "Membership Report (v1)",
@@ -35,165 +44,246 @@ fn igmp_type_to_string(msg_type: u8) -> &'static str {
     }
 }
 
-// Helper function to determine IGMP version based on message type and length
-fn determine_igmp_version(msg_type: u8, data_len: usize) -> u8 {
+pub fn igmp_record_type_mapper(record_type: u8) -> &'static str {
+    match record_type {
+        MODE_IS_INCLUDE => "Mode Is Include",
+        MODE_IS_EXCLUDE => "Mode Is Exclude",
+        CHANGE_TO_INCLUDE_MODE => "Change To Include Mode",
+        CHANGE_TO_EXCLUDE_MODE => "Change To Exclude Mode",
+        ALLOW_NEW_SOURCES => "Allow New Sources",
+        BLOCK_OLD_SOURCES => "Block Old Sources",
+        _ => "Unknown",
+    }
+}
+
+/**
+ * queries are told apart by length and max response time (RFC 3376 7.1)
+ */
+fn igmp_version(msg_type: u8, max_resp: u8, len: usize) -> u8 {
     match msg_type {
         IGMP_MEMBERSHIP_REPORT_V1 => 1,
         IGMP_MEMBERSHIP_REPORT_V2 | IGMP_LEAVE_GROUP => 2,
         IGMP_MEMBERSHIP_REPORT_V3 => 3,
-        IGMP_MEMBERSHIP_QUERY => {
-            // For queries, we need to check the length to determine version
-            if data_len == 8 {
-                1 // IGMPv1 query
-            } else if data_len == 12 {
-                3 // IGMPv3 query
-            } else {
-                2 // IGMPv2 query (or assume v2 if we can't determine)
-            }
-        }
-        _ => 0, // Unknown version
+        IGMP_MEMBERSHIP_QUERY if len >= 12 => 3,
+        IGMP_MEMBERSHIP_QUERY if max_resp == 0 => 1,
+        IGMP_MEMBERSHIP_QUERY => 2,
+        _ => 0,
     }
 }
 
+/**
+ * IGMPv3 floating point encoding for Max Resp Code and QQIC (RFC 3376 4.1.1)
+ */
+fn igmp_code_value(code: u8) -> u32 {
+    if code < 128 {
+        return code as u32;
+    }
+    let mant = (code & 0x0f) as u32;
+    let exp = ((code >> 4) & 0x07) as u32;
+    (mant | 0x10) << (exp + 3)
+}
+
+/**
+ * a record without sources in include mode means the host no longer wants the group
+ */
+fn record_action(record_type: u8, no_source: bool) -> Option<MulticastAction> {
+    match record_type {
+        MODE_IS_EXCLUDE | CHANGE_TO_EXCLUDE_MODE | ALLOW_NEW_SOURCES => Some(MulticastAction::Join),
+        MODE_IS_INCLUDE | CHANGE_TO_INCLUDE_MODE if no_source => Some(MulticastAction::Leave),
+        MODE_IS_INCLUDE | CHANGE_TO_INCLUDE_MODE => Some(MulticastAction::Join),
+        BLOCK_OLD_SOURCES => Some(MulticastAction::Leave),
+        _ => None,
+    }
+}
+
+// ip payload length, the reader may still hold ethernet padding
+fn igmp_len(frame: &Frame, reader: &Reader) -> usize {
+    match frame.iplen {
+        0 => reader.left(),
+        len => cmp::min(len as usize, reader.left()),
+    }
+}
+
+fn t_max_resp(version: u8, code: u8) -> String {
+    match version {
+        1 => format!("Max Resp Time: Reserved ({code:#04x})"),
+        _ => format!("Max Resp Time: {:.1} sec ({code:#04x})", igmp_code_value(code) as f32 / 10.0),
+    }
+}
+
+fn read_sources(reader: &mut Reader, count: u16) -> Result<Vec<Ipv4Addr>> {
+    let mut list = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        list.push(reader.read_ip4()?);
+    }
+    Ok(list)
+}
+
+fn read_group_record(reader: &mut Reader) -> Result<(u8, Ipv4Addr, Vec<Ipv4Addr>)> {
+    let record_type = reader.read8()?;
+    let aux_len = reader.read8()? as usize;
+    let count = reader.read16(true)?;
+    let group = reader.read_ip4()?;
+    let sources = read_sources(reader, count)?;
+    reader.forward(aux_len * 4);
+    Ok((record_type, group, sources))
+}
+
+fn read_group_record_field(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    let record_type = add_field_format_fn!(field, reader, reader.read8()?, |t| format!("Record Type: {} ({t})", igmp_record_type_mapper(t)));
+    let aux_len = add_field_format!(field, reader, reader.read8()?, "Aux Data Len: {}") as usize;
+    let count = add_field_format!(field, reader, reader.read16(true)?, "Num Src: {}");
+    let group = add_field_format!(field, reader, reader.read_ip4()?, "Multicast Address: {}");
+    for _ in 0..count {
+        add_field_format!(field, reader, reader.read_ip4()?, "Source Address: {}");
+    }
+    if aux_len > 0 {
+        let mut _reader = reader.slice_as_reader(aux_len * 4)?;
+        add_field_rest_format!(field, _reader, format!("Aux Data: {} bytes", aux_len * 4));
+    }
+    field.summary = format!("Group Record : {group}  {}", igmp_record_type_mapper(record_type));
+    Ok(())
+}
+
+fn read_query_v3(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    let flags = reader.read8()?;
+    let s_flag = (flags >> 3) & 0x01;
+    let qrv = flags & 0x07;
+    add_field_backstep!(field, reader, 1, format!(".... {s_flag}... = S: {}", if s_flag == 1 { "Suppress router side processing" } else { "Do not suppress router side processing" }));
+    add_field_backstep!(field, reader, 1, format!(".... .{qrv:03b} = QRV: {qrv}"));
+    add_field_format_fn!(field, reader, reader.read8()?, |c| format!("QQIC: {} sec ({c:#04x})", igmp_code_value(c)));
+    let count = add_field_format!(field, reader, reader.read16(true)?, "Num Src: {}");
+    for _ in 0..count {
+        add_field_format!(field, reader, reader.read_ip4()?, "Source Address: {}");
+    }
+    Ok(())
+}
+
 pub struct Visitor;
 
 impl Visitor {
     pub fn info(_: &Context, frame: &Frame) -> Option<String> {
-        if let ProtocolInfoField::IGMP(version, msg_type, group_addr) = &frame.protocol_field {
-            let type_str = igmp_type_to_string(*msg_type);
-            let addr_str = format!("{}", group_addr);
-            
-            return Some(format!("Internet Group Management Protocol v{}, Type: {}, Group: {}", 
-                               version, type_str, addr_str));
+        if let ProtocolInfoField::Igmp(version, msg_type, group, count) = &frame.protocol_field {
+            let rs = match *msg_type {
+                IGMP_MEMBERSHIP_QUERY if group.is_unspecified() => format!("IGMPv{version} Membership Query, general"),
+                IGMP_MEMBERSHIP_QUERY if *count > 0 => format!("IGMPv{version} Membership Query, specific for source/group {group}"),
+                IGMP_MEMBERSHIP_QUERY => format!("IGMPv{version} Membership Query, specific for group {group}"),
+                IGMP_MEMBERSHIP_REPORT_V3 => format!("IGMPv{version} Membership Report, {count} group record(s)"),
+                IGMP_LEAVE_GROUP => format!("IGMPv{version} Leave Group {group}"),
+                _ => format!("IGMPv{version} {} group {group}", igmp_type_mapper(*msg_type)),
+            };
+            return Some(rs);
         }
-        None
+        Some("Internet Group Management Protocol".to_string())
     }
 
-    pub fn parse(_: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
-        // IGMP header is at least 8 bytes
-        if reader.remaining() < 8 {
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+        let len = igmp_len(frame, reader);
+        if len < 8 {
             return Ok(Protocol::None);
         }
-        
-        // Read IGMP header fields
-        let msg_type = reader.read_u8()?;
-        let max_resp_time = reader.read_u8()?;
-        let _checksum = reader.read_u16()?;
-        
-        // Read group address (4 bytes for IPv4)
-        let group_addr_bytes = [
-            reader.read_u8()?,
-            reader.read_u8()?,
-            reader.read_u8()?,
-            reader.read_u8()?,
-        ];
-        let group_addr = Ipv4Addr::new(
-            group_addr_bytes[0],
-            group_addr_bytes[1],
-            group_addr_bytes[2],
-            group_addr_bytes[3],
-        );
-        
-        // Determine IGMP version based on message type and data length
-        let version = determine_igmp_version(msg_type, reader.remaining() + 8);
-        
-        // Store IGMP information in the frame
-        frame.protocol_field = ProtocolInfoField::IGMP(version, msg_type, group_addr);
-        
-        Ok(Protocol::None)
-    }
+        if ctx.config.verify_checksum && verify_transport(ctx, frame, reader, IP_PROTO_IGMP, len, 2).is_bad() {
+            frame.mark_error("Bad IGMP checksum");
+        }
+        let mut reader = reader.slice_as_reader(len)?;
+        let msg_type = reader.read8()?;
+        let max_resp = reader.read8()?;
+        reader.read16(true)?; // checksum
+        let version = igmp_version(msg_type, max_resp, len);
 
-    pub fn detail(field: &mut Field, _: &Context, _: &Frame, reader: &mut Reader) -> Result<Protocol> {
-        let mut list = vec![];
-        
-        // Save the initial cursor position
-        let start_pos = reader.cursor;
-        
-        // Read IGMP header fields
-        let msg_type = reader.read_u8()?;
-        let max_resp_time = reader.read_u8()?;
-        let checksum = reader.read_u16()?;
-        
-        // Read group address
-        let group_addr_bytes = [
-            reader.read_u8()?,
-            reader.read_u8()?,
-            reader.read_u8()?,
-            reader.read_u8()?,
-        ];
-        let group_addr = Ipv4Addr::new(
-            group_addr_bytes[0],
-            group_addr_bytes[1],
-            group_addr_bytes[2],
-            group_addr_bytes[3],
-        );
-        
-        // Determine IGMP version
-        let version = determine_igmp_version(msg_type, reader.remaining() + 8);
-        
-        // Format the header fields
-        field_back_format!(list, reader, 1, format!("Type: {} (0x{:02x})", igmp_type_to_string(msg_type), msg_type));
-        
-        // Max Response Time interpretation depends on version
-        if version == 1 {
-            field_back_format!(list, reader, 1, format!("Max Response Time: Reserved (0x{:02x})", max_resp_time));
-        } else {
-            // For IGMPv2/v3, max_resp_time is in 1/10 seconds
-            let resp_time_sec = if max_resp_time < 128 {
-                max_resp_time as f32 / 10.0
-            } else {
-                // For values >= 128, there's a special encoding in IGMPv3
-                let mant = max_resp_time & 0x0F;
-                let exp = (max_resp_time & 0x70) >> 4;
-                (mant | 0x10) as f32 * (1 << exp) as f32 / 10.0
-            };
-            field_back_format!(list, reader, 1, format!("Max Response Time: {:.1} sec (0x{:02x})", resp_time_sec, max_resp_time));
+        let host = match &frame.address_field {
+            AddressField::IPv4(source, _) => *source,
+            _ => Ipv4Addr::UNSPECIFIED,
+        };
+        let index = frame.info.index;
+        let ts = frame.info.time;
+        let event = |group: Ipv4Addr, action: MulticastAction, sources: Vec<Ipv4Addr>| MulticastEvent {
+            index,
+            ts,
+            host,
+            group,
+            action,
+            version,
+            sources,
+        };
+
+        if msg_type == IGMP_MEMBERSHIP_REPORT_V3 {
+            reader.read16(true)?; // reserved
+            let count = reader.read16(true)?;
+            let mut first = Ipv4Addr::UNSPECIFIED;
+            for i in 0..count {
+                let (record_type, group, sources) = read_group_record(&mut reader)?;
+                if i == 0 {
+                    first = group;
+                }
+                if let Some(action) = record_action(record_type, sources.is_empty()) {
+                    ctx.multicast.add_event(event(group, action, sources));
+                }
+            }
+            frame.protocol_field = ProtocolInfoField::Igmp(version, msg_type, first, count);
+            return Ok(Protocol::None);
         }
-        
-        field_back_format!(list, reader, 2, format!("Checksum: 0x{:04x}", checksum));
-        field_back_format!(list, reader, 4, format!("Group Address: {}", group_addr));
-        
-        // Parse IGMPv3-specific fields if present
-        if version == 3 && msg_type == IGMP_MEMBERSHIP_QUERY && reader.remaining() >= 4 {
-            let resv_s_qrv = reader.read_u8()?;
-            let qqic = reader.read_u8()?;
-            let num_sources = reader.read_u16()?;
-            
-            let s_flag = (resv_s_qrv & 0x08) != 0;
-            let qrv = resv_s_qrv & 0x07;
-            
-            field_back_format!(list, reader, 1, format!("Reserved/S/QRV: 0x{:02x} (S={}, QRV={})", resv_s_qrv, s_flag, qrv));
-            field_back_format!(list, reader, 1, format!("QQIC: {} sec", qqic));
-            field_back_format!(list, reader, 2, format!("Number of Sources: {}", num_sources));
-            
-            // Parse source addresses if present
-            for i in 0..num_sources {
-                if reader.remaining() >= 4 {
-                    let src_addr_bytes = [
-                        reader.read_u8()?,
-                        reader.read_u8()?,
-                        reader.read_u8()?,
-                        reader.read_u8()?,
-                    ];
-                    let src_addr = Ipv4Addr::new(
-                        src_addr_bytes[0],
-                        src_addr_bytes[1],
-                        src_addr_bytes[2],
-                        src_addr_bytes[3],
-                    );
-                    
-                    field_back_format!(list, reader, 4, format!("Source Address {}: {}", i+1, src_addr));
+
+        let group = reader.read_ip4()?;
+        let mut count = 0;
+        match msg_type {
+            IGMP_MEMBERSHIP_QUERY => {
+                let mut timeout = DEFAULT_QUERIER_TIMEOUT;
+                if version == 3 {
+                    let qrv = match reader.read8()? & 0x07 {
+                        0 => 2,
+                        v => v as u64,
+                    };
+                    let qqi = igmp_code_value(reader.read8()?) as u64;
+                    count = reader.read16(true)?;
+                    // robustness * query interval + max response time / 2
+                    timeout = qrv * qqi * 1_000_000 + igmp_code_value(max_resp) as u64 * 50_000;
                 }
+                ctx.multicast.add_query(index, ts, host, timeout);
+            }
+            IGMP_MEMBERSHIP_REPORT_V1 | IGMP_MEMBERSHIP_REPORT_V2 => {
+                ctx.multicast.add_event(event(group, MulticastAction::Join, vec![]));
+            }
+            IGMP_LEAVE_GROUP => {
+                ctx.multicast.add_event(event(group, MulticastAction::Leave, vec![]));
             }
+            _ => {}
+        }
+        frame.protocol_field = ProtocolInfoField::Igmp(version, msg_type, group, count);
+        Ok(Protocol::None)
+    }
+
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
+        let len = igmp_len(frame, reader);
+        let status = match ctx.config.verify_checksum {
+            true => verify_transport(ctx, frame, reader, IP_PROTO_IGMP, len, 2),
+            false => ChecksumStatus::Unverified,
+        };
+        let mut reader = reader.slice_as_reader(len)?;
+        let max_resp = reader.preview(2).map(|head| head[1]).unwrap_or(0);
+        let msg_type = reader.next()?;
+        let version = igmp_version(msg_type, max_resp, len);
+        field.summary = "Internet Group Management Protocol".to_string();
+        add_field_label_no_range!(field, format!("[IGMP Version: {version}]"));
+
+        add_field_format_fn!(field, reader, reader.read8()?, |t| format!("Type: {} ({t:#04x})", igmp_type_mapper(t)));
+        add_field_format_fn!(field, reader, reader.read8()?, |c| t_max_resp(version, c));
+        let checksum = reader.read16(true)?;
+        add_field_backstep!(field, reader, 2, t_checksum("Checksum", checksum, status));
+
+        if msg_type == IGMP_MEMBERSHIP_REPORT_V3 {
+            reader.read16(true)?;
+            add_field_backstep!(field, reader, 2, "Reserved".into());
+            let count = add_field_format!(field, reader, reader.read16(true)?, "Num Group Records: {}");
+            for _ in 0..count {
+                add_sub_field_with_reader!(field, &mut reader, read_group_record_field)?;
+            }
+            return Ok(Protocol::None);
+        }
+        add_field_format!(field, reader, reader.read_ip4()?, "Multicast Address: {}");
+        if msg_type == IGMP_MEMBERSHIP_QUERY && version == 3 {
+            read_query_v3(&mut reader, field)?;
         }
-        
-        // Set the summary
-        let type_str = igmp_type_to_string(msg_type);
-        field.summary = format!("Internet Group Management Protocol v{}, Type: {}, Group: {}", 
-                              version, type_str, group_addr);
-        
-        field.children = Some(list);
-        
         Ok(Protocol::None)
     }
 }
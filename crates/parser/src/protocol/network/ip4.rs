@@ -6,7 +6,7 @@
 use std::net::Ipv4Addr;
 
 use crate::{
-    add_field_backstep, add_field_backstep_fn, add_field_format, add_field_format_fn, add_sub_field, common::{concept::Field, core::Context, enum_def::{AddressField, DataError, Protocol}, io::Reader, Frame}, constants::ip_protocol_type_mapper, protocol::ip4_mapper
+    add_field_backstep, add_field_backstep_fn, add_field_format, add_field_format_fn, add_field_label_no_range, add_field_rest_format, add_sub_field, add_sub_field_with_reader, common::{checksum::{t_checksum, verify_ip4, ChecksumStatus}, concept::Field, core::Context, enum_def::{AddressField, DataError, Protocol}, io::Reader, Frame}, constants::ip_protocol_type_mapper, protocol::ip4_mapper
 };
 use anyhow::{bail, Result};
 
@@ -125,6 +125,190 @@ pub fn t_tos(tos: u8, main_field: &mut Field) {
     }
 }
 
+pub fn ip4_option_mapper(kind: u8) -> &'static str {
+    match kind {
+        0 => "End of Options List (EOL)",
+        1 => "No-Operation (NOP)",
+        7 => "Record Route",
+        68 => "Time Stamp",
+        130 => "Security",
+        131 => "Loose Source Route",
+        134 => "Commercial Security",
+        136 => "Stream ID",
+        137 => "Strict Source Route",
+        148 => "Router Alert",
+        _ => "Unknown",
+    }
+}
+
+fn ip4_option_class_mapper(class: u8) -> &'static str {
+    match class {
+        0 => "Control",
+        2 => "Debugging and measurement",
+        _ => "Reserved",
+    }
+}
+
+fn ip4_security_level_mapper(level: u8) -> &'static str {
+    match level {
+        0x3d => "Top Secret",
+        0x5a => "Secret",
+        0x96 => "Confidential",
+        0x66 => "Unclassified",
+        _ => "Reserved",
+    }
+}
+
+/**
+ * true if the option area carries a loose or strict source route
+ */
+pub fn has_source_route(data: &[u8]) -> bool {
+    let mut cursor = 0;
+    while cursor < data.len() {
+        match data[cursor] {
+            0 => break,
+            1 => cursor += 1,
+            kind => {
+                if kind == 131 || kind == 137 {
+                    return true;
+                }
+                let len = *data.get(cursor + 1).unwrap_or(&0) as usize;
+                if len < 2 {
+                    break;
+                }
+                cursor += len;
+            }
+        }
+    }
+    false
+}
+
+fn read_route(reader: &mut Reader, field: &mut Field, len: u8, label: &str) -> Result<()> {
+    let pointer = add_field_format!(field, reader, reader.read8()?, "Pointer: {}");
+    let count = (len.saturating_sub(3) / 4) as usize;
+    let used = (pointer.saturating_sub(4) / 4) as usize;
+    for i in 0..count {
+        let ip = reader.read_ip4()?;
+        let mark = if i == used { " <- (next)" } else { "" };
+        add_field_backstep!(field, reader, 4, format!("{label}: {ip}{mark}"));
+    }
+    Ok(())
+}
+
+fn read_timestamp(reader: &mut Reader, field: &mut Field, len: u8) -> Result<()> {
+    add_field_format!(field, reader, reader.read8()?, "Pointer: {}");
+    let flag_byte = reader.read8()?;
+    let overflow = flag_byte >> 4;
+    let flag = flag_byte & 0x0f;
+    add_field_backstep!(field, reader, 1, format!("{overflow:04b} .... = Overflow: {overflow}"));
+    let flag_str = match flag {
+        0 => "Time stamps only",
+        1 => "Time stamp and address",
+        3 => "Time stamps for prespecified addresses",
+        _ => "Unknown",
+    };
+    add_field_backstep!(field, reader, 1, format!(".... {flag:04b} = Flag: {flag_str} ({flag})"));
+    let mut left = len.saturating_sub(4) as usize;
+    while left >= 4 {
+        if flag == 1 || flag == 3 {
+            if left < 8 {
+                break;
+            }
+            add_field_format!(field, reader, reader.read_ip4()?, "Address: {}");
+            left -= 4;
+        }
+        add_field_format!(field, reader, reader.read32(true)?, "Time stamp: {}");
+        left -= 4;
+    }
+    reader.forward(left);
+    Ok(())
+}
+
+fn read_security(reader: &mut Reader, field: &mut Field, len: u8) -> Result<()> {
+    let level = reader.read8()?;
+    add_field_backstep!(field, reader, 1, format!("Classification Level: {} ({level:#04x})", ip4_security_level_mapper(level)));
+    let left = len.saturating_sub(3) as usize;
+    if left > 0 {
+        let flags = reader.slice(left, true)?;
+        let authority = flags.iter().map(|f| format!("{f:02x}")).collect::<Vec<String>>().join("");
+        add_field_backstep!(field, reader, left, format!("Protection Authority Flags: 0x{authority}"));
+    }
+    Ok(())
+}
+
+fn read_cipso(reader: &mut Reader, field: &mut Field, len: u8) -> Result<()> {
+    add_field_format!(field, reader, reader.read32(true)?, "DOI: {}");
+    let mut left = len.saturating_sub(6) as usize;
+    while left >= 2 {
+        let tag_type = reader.read8()?;
+        let tag_len = reader.read8()?;
+        if tag_len < 2 || tag_len as usize > left {
+            add_field_backstep!(field, reader, 2, format!("Tag Type: {tag_type}, Length: {tag_len} [malformed]"));
+            left -= 2;
+            break;
+        }
+        reader.forward(tag_len as usize - 2);
+        add_field_backstep!(field, reader, tag_len as usize, format!("Tag Type: {tag_type}, Length: {tag_len}"));
+        left -= tag_len as usize;
+    }
+    reader.forward(left);
+    Ok(())
+}
+
+fn read_ip4_option(reader: &mut Reader, field: &mut Field) -> Result<u8> {
+    let kind = reader.read8()?;
+    let name = ip4_option_mapper(kind);
+    let copied = kind >> 7;
+    let class = (kind >> 5) & 0x03;
+    let number = kind & 0x1f;
+    add_field_backstep!(field, reader, 1, format!("Type: {kind} ({name}), Copy on fragmentation: {}, Class: {} ({class}), Number: {number}", if copied == 1 { "Yes" } else { "No" }, ip4_option_class_mapper(class)));
+    field.summary = format!("IP Option - {name}");
+    match kind {
+        0 | 1 => {}
+        _ => {
+            let len = add_field_format!(field, reader, reader.read8()?, "Length: {}");
+            if len < 2 || (len as usize - 2) > reader.left() {
+                field.summary = format!("IP Option - {name} [bad length {len}]");
+                return Ok(0);
+            }
+            let mut _reader = reader.slice_as_reader(len as usize - 2)?;
+            match kind {
+                7 => read_route(&mut _reader, field, len, "Recorded Route")?,
+                131 | 137 => read_route(&mut _reader, field, len, "Source Route")?,
+                68 => read_timestamp(&mut _reader, field, len)?,
+                130 => read_security(&mut _reader, field, len)?,
+                134 => read_cipso(&mut _reader, field, len)?,
+                148 => {
+                    let value = add_field_format!(field, &mut _reader, _reader.read16(true)?, "Router Alert: {}");
+                    if value == 0 {
+                        field.summary = "IP Option - Router Alert (Router shall examine packet)".into();
+                    }
+                }
+                _ => {
+                    add_field_rest_format!(field, _reader, format!("Data: {} bytes", _reader.left()));
+                }
+            }
+            if kind == 131 || kind == 137 {
+                add_field_label_no_range!(field, "[Expert Info: Source route option, packet path chosen by sender]".into());
+            }
+        }
+    }
+    Ok(kind)
+}
+
+fn read_ip4_options(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    let total = reader.left();
+    while reader.left() > 0 {
+        let kind = add_sub_field_with_reader!(field, reader, read_ip4_option)?;
+        if kind == 0 {
+            add_field_rest_format!(field, reader, format!("Padding: {} bytes", reader.left()));
+            break;
+        }
+    }
+    field.summary = format!("Options: ({total} bytes)");
+    Ok(())
+}
+
 pub struct Visitor;
 
 impl Visitor {
@@ -136,6 +320,9 @@ impl Visitor {
     }
     pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
         let _start = reader.left();
+        if ctx.config.verify_checksum && verify_ip4(reader, ((reader.next()? & 0x0f) as usize) * 4).is_bad() {
+            frame.mark_error("Bad IPv4 header checksum");
+        }
         let head = reader.read8()?;
         let head_len = head & 0x0f;
         reader.read8()?; // tos
@@ -157,8 +344,8 @@ impl Visitor {
 
         frame.add_proto(crate::common::ProtoMask::IPV4);
         let ext = head_len - 5;
-        if ext > 0 {
-            reader.slice((ext * 4) as usize, true)?;
+        if ext > 0 && has_source_route(reader.slice((ext * 4) as usize, true)?) {
+            frame.mark_warning("IPv4 source route option");
         }
         let _stop = reader.left();
         if total_len == 0 {
@@ -172,9 +359,13 @@ impl Visitor {
 
         Ok(ip4_mapper(protocol_type))
     }
-    pub fn detail(field: &mut Field, _: &Context, _: &Frame, reader: &mut Reader) -> Result<Protocol> {
+    pub fn detail(field: &mut Field, ctx: &Context, _: &Frame, reader: &mut Reader) -> Result<Protocol> {
         let _start = reader.left();
         // let mut list = field.children.as_mut().unwrap();
+        let status = match ctx.config.verify_checksum {
+            true => verify_ip4(reader, ((reader.next()? & 0x0f) as usize) * 4),
+            false => ChecksumStatus::Unverified,
+        };
         let head = reader.read8()?;
         let head_len = head & 0x0f;
         add_field_backstep!(field, reader, 1, "0100 .... = Version: 4".into());
@@ -186,13 +377,18 @@ impl Visitor {
         add_sub_field!(field, reader, reader.read16(true)?, t_flags);
         add_field_format!(field, reader, reader.read8()?, "Time To Live: {}");
         let protocol_type = add_field_format_fn!(field, reader, reader.read8()?, t_protocol);
-        add_field_format!(field, reader, reader.read16(true)?, "Header Checksum: {}");
+        let checksum = reader.read16(true)?;
+        add_field_backstep!(field, reader, 2, t_checksum("Header Checksum", checksum, status));
 
         let source = add_field_format!(field, reader, reader.read_ip4()?, "Source Address: {}");
         let target = add_field_format!(field, reader, reader.read_ip4()?, "Destination Address: {}");
+        if head_len < 5 {
+            bail!(DataError::Ipv4HeadLengthInvalid)
+        }
         let ext = head_len - 5;
         if ext > 0 {
-            reader.forward((ext * 4) as usize);
+            let mut _reader = reader.slice_as_reader((ext * 4) as usize)?;
+            add_sub_field_with_reader!(field, &mut _reader, read_ip4_options)?;
         }
         let _stop = reader.left();
         if total_len == 0 {
@@ -66,13 +66,14 @@ impl Visitor {
         let mut reader = _reader.slice_as_reader(40)?;
         let data = reader.refer()?;
         let key: u64 = quick_hash(data);
+        frame.iplen = u16::from_be_bytes([data[4], data[5]]);
         frame.address_field = AddressField::IPv6(key);
         frame.add_proto(crate::common::ProtoMask::IPV6);
         if let Some(enty) = ctx.ipv6map.get(&key) {
             Ok(ip4_mapper(enty.0))
         } else {
             reader.read32(true)?;
-            frame.iplen = reader.read16(true)?;
+            reader.read16(true)?;
             let protocol_type = reader.read8()?;
             reader.read8()?; //hop
             let source = reader.read_ip6()?;
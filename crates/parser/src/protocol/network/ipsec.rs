@@ -1,188 +1,192 @@
 // Copyright (c) 2025 sankooc
-// 
+//
 // This file is part of the pcapview project.
 // Licensed under the MIT License - see https://opensource.org/licenses/MIT
 
 use crate::{
+    add_field_backstep, add_field_format, add_field_format_fn, add_field_forward, add_field_label_no_range, add_field_rest_format,
     common::{
         concept::Field,
         core::Context,
-        enum_def::{Protocol, ProtocolInfoField},
-        io::Reader,
+        enum_def::{DataError, Protocol, ProtocolInfoField},
+        io::{DataSource, Reader},
+        ipsec::{esp_decrypt, frame_ips, SequenceAnomaly, IPSEC_AH, IPSEC_ESP},
         Frame,
     },
-    field_back_format,
+    constants::ip_protocol_type_mapper,
+    protocol::{ip4_mapper, parse},
 };
-use anyhow::Result;
-
-// IPSec Protocol Types
-const IPSEC_AH: u8 = 51;  // Authentication Header
-const IPSEC_ESP: u8 = 50; // Encapsulating Security Payload
-
-// Helper function to get protocol name
-fn get_protocol_name(protocol: u8) -> &'static str {
-    match protocol {
-        IPSEC_AH => "Authentication Header (AH)",
-        IPSEC_ESP => "Encapsulating Security Payload (ESP)",
-        _ => "Unknown",
+use anyhow::{bail, Result};
+use std::cmp;
+
+fn t_next_header(next_header: u8) -> String {
+    format!("Next Header: {} ({})", ip_protocol_type_mapper(next_header as u16), next_header)
+}
+
+// tunnel mode carries a whole ip packet
+fn next_mapper(next_header: u8) -> Protocol {
+    match next_header {
+        4 => Protocol::IP4,
+        41 => Protocol::IP6,
+        _ => ip4_mapper(next_header),
     }
 }
 
-// Helper function to parse AH header
-fn parse_ah_header(reader: &mut Reader) -> Result<(u32, u32, u8)> {
-    let next_header = reader.read_u8()?;
-    let payload_len = reader.read_u8()?;
-    let _reserved = reader.read_u16()?;
-    let spi = reader.read_u32()?;
-    let seq_num = reader.read_u32()?;
-    
-    // Skip the ICV (Integrity Check Value) - variable length
-    // The payload_len field indicates the length of the AH header in 32-bit words, minus 2
-    // So the total length in bytes is (payload_len + 2) * 4
-    let total_ah_length = (payload_len as usize + 2) * 4;
-    let icv_length = total_ah_length - 12; // 12 bytes already read
-    reader.skip(icv_length)?;
-    
-    Ok((spi, seq_num, next_header))
+fn t_anomaly(anomaly: SequenceAnomaly) -> String {
+    match anomaly {
+        SequenceAnomaly::Gap(lost) => format!("[Expert Info: Sequence number gap, {lost} packet(s) missing]"),
+        SequenceAnomaly::OutOfOrder => "[Expert Info: Sequence number out of order]".to_string(),
+        SequenceAnomaly::Replay => "[Expert Info: Sequence number replayed]".to_string(),
+    }
 }
 
-// Helper function to parse ESP header
-fn parse_esp_header(reader: &mut Reader) -> Result<(u32, u32)> {
-    let spi = reader.read_u32()?;
-    let seq_num = reader.read_u32()?;
-    
-    // The rest of the ESP packet is encrypted, so we can't parse it further
-    // Just skip the remaining data
-    reader.skip(reader.remaining())?;
-    
-    Ok((spi, seq_num))
+fn sequence(ctx: &mut Context, frame: &mut Frame, protocol: u8, spi: u32, seq: u32) {
+    let addresses = frame.addresses(ctx).unwrap_or_default();
+    if let Some(SequenceAnomaly::Replay) = ctx.ipsec.sequence(frame, addresses, protocol, spi, seq) {
+        frame.mark_error("IPsec sequence number replayed");
+    }
 }
 
-pub struct Visitor;
+// ip payload left after the spi and sequence number, the reader may still hold ethernet padding
+fn esp_payload_len(frame: &Frame, reader: &Reader) -> usize {
+    match frame.iplen {
+        0 => reader.left(),
+        len => cmp::min((len as usize).saturating_sub(8), reader.left()),
+    }
+}
 
-impl Visitor {
+fn esp_info(name: &str, frame: &Frame) -> Option<String> {
+    if let ProtocolInfoField::Ipsec(_, spi, seq) = &frame.protocol_field {
+        return Some(format!("{name} (SPI={spi:#010x}, Seq={seq})"));
+    }
+    None
+}
+
+pub struct AHVisitor;
+
+impl AHVisitor {
     pub fn info(_: &Context, frame: &Frame) -> Option<String> {
-        if let ProtocolInfoField::IPSec(protocol, spi, seq_num) = &frame.protocol_field {
-            let protocol_name = get_protocol_name(*protocol);
-            return Some(format!("IPSec {}, SPI: 0x{:08x}, Seq: {}", protocol_name, spi, seq_num));
+        esp_info("AH", frame)
+    }
+
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+        let next_header = reader.read8()?;
+        let ah_len = (reader.read8()? as usize + 2) * 4;
+        reader.read16(true)?; // reserved
+        let spi = reader.read32(true)?;
+        let seq = reader.read32(true)?;
+        if ah_len < 12 || !reader.forward(ah_len - 12) {
+            bail!(DataError::BitSize);
         }
-        None
+        frame.iplen = frame.iplen.saturating_sub(ah_len as u16);
+        sequence(ctx, frame, IPSEC_AH, spi, seq);
+        frame.protocol_field = ProtocolInfoField::Ipsec(IPSEC_AH, spi, seq);
+        Ok(next_mapper(next_header))
     }
 
-    pub fn parse(_: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
-        // Get the protocol type from the IP header's protocol field
-        // This should be passed in via the context, but for now we'll assume it's in the frame
-        let protocol = if let ProtocolInfoField::IP(_, _, _, _, protocol, _, _) = frame.protocol_field {
-            protocol
-        } else {
-            return Ok(Protocol::None);
-        };
-        
-        // Parse based on protocol type
-        match protocol {
-            IPSEC_AH => {
-                // Parse Authentication Header
-                let (spi, seq_num, next_header) = parse_ah_header(reader)?;
-                
-                // Store IPSec information in the frame
-                frame.protocol_field = ProtocolInfoField::IPSec(IPSEC_AH, spi, seq_num);
-                
-                // Return the next protocol
-                match next_header {
-                    6 => Ok(Protocol::TCP),
-                    17 => Ok(Protocol::UDP),
-                    _ => Ok(Protocol::None),
-                }
-            },
-            IPSEC_ESP => {
-                // Parse Encapsulating Security Payload
-                let (spi, seq_num) = parse_esp_header(reader)?;
-                
-                // Store IPSec information in the frame
-                frame.protocol_field = ProtocolInfoField::IPSec(IPSEC_ESP, spi, seq_num);
-                
-                // ESP encrypts the payload, so we can't determine the next protocol
-                Ok(Protocol::None)
-            },
-            _ => Ok(Protocol::None),
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
+        let next_header = add_field_format_fn!(field, reader, reader.read8()?, t_next_header);
+        let ah_len = (reader.read8()? as usize + 2) * 4;
+        add_field_backstep!(field, reader, 1, format!("Length: {ah_len}"));
+        reader.read16(true)?;
+        add_field_backstep!(field, reader, 2, "Reserved".into());
+        let spi = add_field_format!(field, reader, reader.read32(true)?, "AH SPI: {:#010x}");
+        let seq = add_field_format!(field, reader, reader.read32(true)?, "AH Sequence: {}");
+        if let Some(anomaly) = ctx.ipsec.anomaly(frame.info.index) {
+            add_field_label_no_range!(field, t_anomaly(anomaly));
+        }
+        let icv_len = ah_len.saturating_sub(12);
+        if icv_len > 0 {
+            add_field_forward!(field, reader, icv_len, format!("AH ICV: {icv_len} bytes"));
+            reader.forward(icv_len);
         }
+        field.summary = format!("Authentication Header, SPI: {spi:#010x}, Seq: {seq}");
+        Ok(next_mapper(next_header))
+    }
+}
+
+pub struct ESPVisitor;
+
+impl ESPVisitor {
+    pub fn info(_: &Context, frame: &Frame) -> Option<String> {
+        esp_info("ESP", frame)
     }
 
-    pub fn detail(field: &mut Field, _: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
-        let mut list = vec![];
-        
-        // Get the protocol type from the IP header's protocol field
-        let protocol = if let ProtocolInfoField::IP(_, _, _, _, protocol, _, _) = frame.protocol_field {
-            protocol
-        } else {
+    /**
+     * with a matching SA the decrypted inner packet is dissected right away, so the frame
+     * reports the inner protocols. The plain text is kept in the ipsec table and the inner
+     * layers are parsed at its range there, see PlainLoader
+     */
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+        let spi = reader.read32(true)?;
+        let seq = reader.read32(true)?;
+        sequence(ctx, frame, IPSEC_ESP, spi, seq);
+        frame.protocol_field = ProtocolInfoField::Ipsec(IPSEC_ESP, spi, seq);
+
+        let len = esp_payload_len(frame, reader);
+        let (source, target) = frame_ips(ctx, frame);
+        let Some(sa) = ctx.esp_sa.iter().find(|sa| sa.matches(spi, source, target)) else {
             return Ok(Protocol::None);
         };
-        
-        match protocol {
-            IPSEC_AH => {
-                // Authentication Header
-                let start_pos = reader.cursor;
-                
-                let next_header = reader.read_u8()?;
-                let payload_len = reader.read_u8()?;
-                let reserved = reader.read_u16()?;
-                let spi = reader.read_u32()?;
-                let seq_num = reader.read_u32()?;
-                
-                field_back_format!(list, reader, 1, format!("Next Header: {} ({})", next_header, get_next_header_name(next_header)));
-                field_back_format!(list, reader, 1, format!("Payload Length: {} ({} bytes)", payload_len, (payload_len as u16 + 2) * 4));
-                field_back_format!(list, reader, 2, format!("Reserved: 0x{:04x}", reserved));
-                field_back_format!(list, reader, 4, format!("Security Parameters Index (SPI): 0x{:08x}", spi));
-                field_back_format!(list, reader, 4, format!("Sequence Number: {}", seq_num));
-                
-                // Calculate ICV length and add a field for it
-                let total_ah_length = (payload_len as usize + 2) * 4;
-                let icv_length = total_ah_length - 12; // 12 bytes already read
-                
-                if icv_length > 0 && reader.remaining() >= icv_length {
-                    field_back_format!(list, reader, icv_length, format!("Integrity Check Value (ICV): {} bytes", icv_length));
-                }
-                
-                field.summary = format!("IPSec Authentication Header, SPI: 0x{:08x}, Seq: {}", spi, seq_num);
-            },
-            IPSEC_ESP => {
-                // Encapsulating Security Payload
-                let start_pos = reader.cursor;
-                
-                let spi = reader.read_u32()?;
-                let seq_num = reader.read_u32()?;
-                
-                field_back_format!(list, reader, 4, format!("Security Parameters Index (SPI): 0x{:08x}", spi));
-                field_back_format!(list, reader, 4, format!("Sequence Number: {}", seq_num));
-                
-                // The rest is encrypted payload, padding, pad length, next header, and optional ICV
-                let encrypted_data_len = reader.remaining();
-                if encrypted_data_len > 0 {
-                    field_back_format!(list, reader, encrypted_data_len, format!("Encrypted Data: {} bytes", encrypted_data_len));
+        let Ok((plain, _, next_header)) = esp_decrypt(sa, spi, seq, reader.slice(len, true)?) else {
+            return Ok(Protocol::None);
+        };
+        let range = ctx.ipsec.add_decrypted(frame.info.index, len, &plain);
+        frame.iplen = plain.len() as u16;
+        let ds = DataSource::create(plain, range);
+        let mut inner = Reader::new(&ds);
+        let mut next = next_mapper(next_header);
+        while !matches!(next, Protocol::None) {
+            match parse(next, ctx, frame, &mut inner) {
+                Ok(_next) => {
+                    frame.tail = next;
+                    next = _next;
                 }
-                
-                field.summary = format!("IPSec Encapsulating Security Payload, SPI: 0x{:08x}, Seq: {}", spi, seq_num);
-            },
-            _ => {
-                field.summary = "Unknown IPSec Protocol".to_string();
+                Err(_) => break,
             }
         }
-        
-        field.children = Some(list);
-        
         Ok(Protocol::None)
     }
-}
 
-// Helper function to get next header protocol name
-fn get_next_header_name(protocol: u8) -> &'static str {
-    match protocol {
-        1 => "ICMP",
-        6 => "TCP",
-        17 => "UDP",
-        50 => "ESP",
-        51 => "AH",
-        58 => "ICMPv6",
-        _ => "Unknown",
+    /**
+     * returns the decrypted inner packet so the caller continues dissection on it
+     */
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader) -> Result<(Protocol, Option<Vec<u8>>)> {
+        let spi = add_field_format!(field, reader, reader.read32(true)?, "ESP SPI: {:#010x}");
+        let seq = add_field_format!(field, reader, reader.read32(true)?, "ESP Sequence: {}");
+        field.summary = format!("Encapsulating Security Payload, SPI: {spi:#010x}, Seq: {seq}");
+        if let Some(anomaly) = ctx.ipsec.anomaly(frame.info.index) {
+            add_field_label_no_range!(field, t_anomaly(anomaly));
+        }
+
+        let len = ctx.ipsec.decrypted_len(frame.info.index).unwrap_or_else(|| esp_payload_len(frame, reader));
+        let (source, target) = frame_ips(ctx, frame);
+        let sa = ctx.esp_sa.iter().find(|sa| sa.matches(spi, source, target));
+        let mut payload = reader.slice_as_reader(len)?;
+        let Some(sa) = sa else {
+            add_field_rest_format!(field, payload, format!("Encrypted Data: {len} bytes"));
+            return Ok((Protocol::None, None));
+        };
+        match esp_decrypt(sa, spi, seq, payload.preview(len)?) {
+            Ok((plain, pad_len, next_header)) => {
+                let iv_len = sa.iv_len();
+                if iv_len > 0 {
+                    add_field_forward!(field, payload, iv_len, format!("IV: {iv_len} bytes"));
+                }
+                payload.forward(iv_len);
+                let data_len = len.saturating_sub(iv_len + sa.icv_len);
+                add_field_forward!(field, payload, data_len, format!("Encrypted Data: {data_len} bytes ({} decrypted)", sa.name()));
+                payload.forward(data_len);
+                add_field_label_no_range!(field, format!("Pad Length: {pad_len}"));
+                add_field_label_no_range!(field, t_next_header(next_header));
+                add_field_rest_format!(field, payload, format!("ICV: {} bytes", sa.icv_len));
+                Ok((next_mapper(next_header), Some(plain)))
+            }
+            Err(e) => {
+                add_field_label_no_range!(field, format!("[Expert Info: Decryption failed with SA {spi:#010x}: {e}]"));
+                add_field_rest_format!(field, payload, format!("Encrypted Data: {len} bytes"));
+                Ok((Protocol::None, None))
+            }
+        }
     }
 }
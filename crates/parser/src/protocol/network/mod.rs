@@ -7,7 +7,12 @@ pub mod ip4;
 pub mod ip6;
 pub mod icmp;
 pub mod icmp6;
+pub mod igmp;
+pub mod ospf;
+pub mod vrrp;
+pub mod ipsec;
 pub mod arp;
 pub mod rarp;
 pub mod dhcp;
 pub mod dhcp6;
+pub mod hsrp;
@@ -0,0 +1,637 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use crate::{
+    add_field_backstep, add_field_format, add_field_format_fn, add_field_label_no_range, add_field_rest_format, add_sub_field_with_reader,
+    common::{
+        concept::Field,
+        core::Context,
+        enum_def::{Protocol, ProtocolInfoField},
+        io::Reader,
+        ipsec::frame_ips,
+        ospf::{OspfPacket, DBD_INIT, DBD_MORE},
+        Frame,
+    },
+};
+use anyhow::{bail, Result};
+use std::{
+    cmp,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+// OSPF Packet Types
+const OSPF_HELLO: u8 = 1;
+const OSPF_DB_DESCRIPTION: u8 = 2;
+const OSPF_LS_REQUEST: u8 = 3;
+const OSPF_LS_UPDATE: u8 = 4;
+const OSPF_LS_ACK: u8 = 5;
+
+// OSPFv2 LSA Types (RFC 2328 A.4.1, RFC 3101)
+const LSA_ROUTER: u16 = 1;
+const LSA_NETWORK: u16 = 2;
+const LSA_SUMMARY_NETWORK: u16 = 3;
+const LSA_SUMMARY_ASBR: u16 = 4;
+const LSA_AS_EXTERNAL: u16 = 5;
+const LSA_NSSA: u16 = 7;
+
+// OSPFv3 LSA Function Codes with flooding scope (RFC 5340 A.4.2.1)
+const LSA3_ROUTER: u16 = 0x2001;
+const LSA3_NETWORK: u16 = 0x2002;
+const LSA3_INTER_AREA_PREFIX: u16 = 0x2003;
+const LSA3_INTER_AREA_ROUTER: u16 = 0x2004;
+const LSA3_AS_EXTERNAL: u16 = 0x4005;
+const LSA3_NSSA: u16 = 0x2007;
+const LSA3_LINK: u16 = 0x0008;
+const LSA3_INTRA_AREA_PREFIX: u16 = 0x2009;
+
+// router lsa link types
+const LINK_POINT_TO_POINT: u8 = 1;
+
+const LSA_HEADER_LEN: usize = 20;
+
+pub fn ospf_type_mapper(msg_type: u8) -> &'static str {
+    match msg_type {
+        OSPF_HELLO => "Hello",
+        OSPF_DB_DESCRIPTION => "DB Description",
+        OSPF_LS_REQUEST => "LS Request",
+        OSPF_LS_UPDATE => "LS Update",
+        OSPF_LS_ACK => "LS Acknowledge",
+        _ => "Unknown",
+    }
+}
+
+pub fn lsa_type_mapper(version: u8, ls_type: u16) -> &'static str {
+    match (version, ls_type) {
+        (2, LSA_ROUTER) => "Router-LSA",
+        (2, LSA_NETWORK) => "Network-LSA",
+        (2, LSA_SUMMARY_NETWORK) => "Summary-LSA (IP network)",
+        (2, LSA_SUMMARY_ASBR) => "Summary-LSA (ASBR)",
+        (2, LSA_AS_EXTERNAL) => "AS-External-LSA (ASBR)",
+        (2, LSA_NSSA) => "NSSA AS-External-LSA",
+        (3, LSA3_ROUTER) => "Router-LSA",
+        (3, LSA3_NETWORK) => "Network-LSA",
+        (3, LSA3_INTER_AREA_PREFIX) => "Inter-Area-Prefix-LSA",
+        (3, LSA3_INTER_AREA_ROUTER) => "Inter-Area-Router-LSA",
+        (3, LSA3_AS_EXTERNAL) => "AS-External-LSA",
+        (3, LSA3_NSSA) => "NSSA-LSA",
+        (3, LSA3_LINK) => "Link-LSA",
+        (3, LSA3_INTRA_AREA_PREFIX) => "Intra-Area-Prefix-LSA",
+        _ => "Unknown",
+    }
+}
+
+fn link_type_mapper(link_type: u8) -> &'static str {
+    match link_type {
+        1 => "Point-to-point connection to another router",
+        2 => "Connection to a transit network",
+        3 => "Connection to a stub network",
+        4 => "Virtual link",
+        _ => "Unknown",
+    }
+}
+
+fn auth_type_mapper(auth_type: u16) -> &'static str {
+    match auth_type {
+        0 => "Null",
+        1 => "Simple password",
+        2 => "Cryptographic",
+        _ => "Unknown",
+    }
+}
+
+fn header_len(version: u8) -> usize {
+    match version {
+        2 => 24,
+        _ => 16,
+    }
+}
+
+/**
+ * the packet length field bounds the message, cryptographic authentication data and ethernet padding follow it
+ */
+fn ospf_reader<'a>(reader: &mut Reader<'a>) -> Result<(u8, u8, Reader<'a>)> {
+    let head = reader.preview(4)?;
+    let version = head[0];
+    let msg_type = head[1];
+    let len = u16::from_be_bytes([head[2], head[3]]) as usize;
+    if !(version == 2 || version == 3) || len < header_len(version) {
+        bail!("invalid ospf header");
+    }
+    let reader = reader.slice_as_reader(cmp::min(len, reader.left()))?;
+    Ok((version, msg_type, reader))
+}
+
+struct LsaHeader {
+    ls_type: u16,
+    ls_id: Ipv4Addr,
+    advertising: Ipv4Addr,
+    length: usize,
+}
+
+fn read_lsa_header(reader: &mut Reader, version: u8) -> Result<LsaHeader> {
+    reader.read16(true)?; // age
+    let ls_type = match version {
+        2 => {
+            reader.read8()?; // options
+            reader.read8()? as u16
+        }
+        _ => reader.read16(true)?,
+    };
+    let ls_id = reader.read_ip4()?;
+    let advertising = reader.read_ip4()?;
+    reader.forward(6); // sequence number, checksum
+    let length = reader.read16(true)? as usize;
+    if length < LSA_HEADER_LEN {
+        bail!("invalid lsa length");
+    }
+    Ok(LsaHeader { ls_type, ls_id, advertising, length })
+}
+
+/**
+ * neighbours a router or network lsa reports as fully adjacent to the advertising router
+ */
+fn read_lsa_neighbors(reader: &mut Reader, version: u8, header: &LsaHeader) -> Result<Vec<Ipv4Addr>> {
+    let mut list = vec![];
+    match (version, header.ls_type) {
+        (2, LSA_ROUTER) => {
+            reader.read16(true)?; // flags
+            let count = reader.read16(true)?;
+            for _ in 0..count {
+                let link_id = reader.read_ip4()?;
+                reader.read_ip4()?; // link data
+                let link_type = reader.read8()?;
+                let tos = reader.read8()? as usize;
+                reader.read16(true)?; // metric
+                reader.forward(tos * 4);
+                if link_type == LINK_POINT_TO_POINT {
+                    list.push(link_id);
+                }
+            }
+        }
+        (3, LSA3_ROUTER) => {
+            reader.read32(true)?; // flags, options
+            while reader.left() >= 16 {
+                let link_type = reader.read8()?;
+                reader.forward(11); // reserved, metric, interface ids
+                let neighbor = reader.read_ip4()?;
+                if link_type == LINK_POINT_TO_POINT {
+                    list.push(neighbor);
+                }
+            }
+        }
+        (2, LSA_NETWORK) | (3, LSA3_NETWORK) => {
+            reader.read32(true)?; // network mask, options
+            while reader.left() >= 4 {
+                list.push(reader.read_ip4()?);
+            }
+        }
+        _ => {}
+    }
+    Ok(list)
+}
+
+fn record_hello(ctx: &mut Context, frame: &Frame, reader: &mut Reader, packet: &OspfPacket) -> Result<()> {
+    let dead_interval = match packet.version {
+        2 => {
+            reader.forward(8); // network mask, hello interval, options, priority
+            reader.read32(true)?
+        }
+        _ => {
+            reader.forward(10); // interface id, priority, options, hello interval
+            reader.read16(true)? as u32
+        }
+    };
+    reader.forward(8); // designated router, backup designated router
+    let mut neighbors = vec![];
+    while reader.left() >= 4 {
+        neighbors.push(reader.read_ip4()?);
+    }
+    let (source, _) = frame_ips(ctx, frame);
+    ctx.ospf.hello(packet, source, dead_interval, &neighbors);
+    Ok(())
+}
+
+fn record_update(ctx: &mut Context, reader: &mut Reader, packet: &OspfPacket) -> Result<()> {
+    let count = reader.read32(true)? as usize;
+    ctx.ospf.lsu(packet, count);
+    for _ in 0..count {
+        let header = read_lsa_header(reader, packet.version)?;
+        let mut _reader = reader.slice_as_reader(cmp::min(header.length - LSA_HEADER_LEN, reader.left()))?;
+        // only router and network lsas name neighbours
+        for neighbor in read_lsa_neighbors(&mut _reader, packet.version, &header).unwrap_or_default() {
+            ctx.ospf.advertised(packet, header.advertising, neighbor);
+        }
+    }
+    Ok(())
+}
+
+fn read_prefix(reader: &mut Reader, prefix_len: u8) -> Result<Ipv6Addr> {
+    let size = (prefix_len as usize).div_ceil(32) * 4;
+    if size > 16 {
+        bail!("invalid prefix length");
+    }
+    let mut data = [0u8; 16];
+    data[..size].copy_from_slice(reader.slice(size, true)?);
+    Ok(Ipv6Addr::from(data))
+}
+
+fn t_options(version: u8, options: u32) -> String {
+    let flags: &[(u32, &str)] = match version {
+        2 => &[(0x40, "O"), (0x20, "DC"), (0x10, "EA"), (0x08, "NP"), (0x04, "MC"), (0x02, "E"), (0x01, "MT")],
+        _ => &[(0x20, "DC"), (0x10, "R"), (0x08, "N"), (0x04, "MC"), (0x02, "E"), (0x01, "V6")],
+    };
+    let set: Vec<&str> = flags.iter().filter(|(bit, _)| options & bit != 0).map(|(_, name)| *name).collect();
+    format!("Options: {options:#04x} ({})", set.join(", "))
+}
+
+fn read_hello_field(reader: &mut Reader, field: &mut Field, version: u8) -> Result<()> {
+    if version == 2 {
+        add_field_format!(field, reader, reader.read_ip4()?, "Network Mask: {}");
+        add_field_format!(field, reader, reader.read16(true)?, "Hello Interval [sec]: {}");
+        add_field_format_fn!(field, reader, reader.read8()?, |o| t_options(version, o as u32));
+        add_field_format!(field, reader, reader.read8()?, "Router Priority: {}");
+        add_field_format!(field, reader, reader.read32(true)?, "Router Dead Interval [sec]: {}");
+    } else {
+        add_field_format!(field, reader, reader.read32(true)?, "Interface ID: {}");
+        add_field_format!(field, reader, reader.read8()?, "Router Priority: {}");
+        add_field_format_fn!(field, reader, reader.read24()?, |o| t_options(version, o));
+        add_field_format!(field, reader, reader.read16(true)?, "Hello Interval [sec]: {}");
+        add_field_format!(field, reader, reader.read16(true)?, "Router Dead Interval [sec]: {}");
+    }
+    add_field_format!(field, reader, reader.read_ip4()?, "Designated Router: {}");
+    add_field_format!(field, reader, reader.read_ip4()?, "Backup Designated Router: {}");
+    while reader.left() >= 4 {
+        add_field_format!(field, reader, reader.read_ip4()?, "Active Neighbor: {}");
+    }
+    field.summary = "OSPF Hello Packet".into();
+    Ok(())
+}
+
+fn t_dbd_flags(flags: u8) -> String {
+    let mut set = vec![];
+    if flags & DBD_INIT != 0 {
+        set.push("Init");
+    }
+    if flags & DBD_MORE != 0 {
+        set.push("More");
+    }
+    if flags & 0x01 != 0 {
+        set.push("Master");
+    }
+    format!("DB Description: {flags:#04x} ({})", set.join(", "))
+}
+
+fn read_dbd_field(reader: &mut Reader, field: &mut Field, version: u8) -> Result<()> {
+    if version == 2 {
+        add_field_format!(field, reader, reader.read16(true)?, "Interface MTU: {}");
+        add_field_format_fn!(field, reader, reader.read8()?, |o| t_options(version, o as u32));
+    } else {
+        reader.read8()?;
+        add_field_format_fn!(field, reader, reader.read24()?, |o| t_options(version, o));
+        add_field_format!(field, reader, reader.read16(true)?, "Interface MTU: {}");
+        reader.read8()?;
+    }
+    add_field_format_fn!(field, reader, reader.read8()?, t_dbd_flags);
+    add_field_format!(field, reader, reader.read32(true)?, "DD Sequence: {}");
+    while reader.left() >= LSA_HEADER_LEN {
+        add_sub_field_with_reader!(field, reader, |r: &mut Reader, f: &mut Field| read_lsa_header_field(r, f, version))?;
+    }
+    field.summary = "OSPF DB Description".into();
+    Ok(())
+}
+
+fn read_lsr_field(reader: &mut Reader, field: &mut Field, version: u8) -> Result<()> {
+    let ls_type = match version {
+        2 => reader.read32(true)? as u16,
+        _ => (reader.read32(true)? & 0xffff) as u16,
+    };
+    add_field_backstep!(field, reader, 4, format!("LS Type: {} ({ls_type:#06x})", lsa_type_mapper(version, ls_type)));
+    let ls_id = add_field_format!(field, reader, reader.read_ip4()?, "Link State ID: {}");
+    let advertising = add_field_format!(field, reader, reader.read_ip4()?, "Advertising Router: {}");
+    field.summary = format!("Link State Request: {} {ls_id}, {advertising}", lsa_type_mapper(version, ls_type));
+    Ok(())
+}
+
+fn read_lsa_header_field(reader: &mut Reader, field: &mut Field, version: u8) -> Result<LsaHeader> {
+    add_field_format!(field, reader, reader.read16(true)?, "LS Age (seconds): {}");
+    let ls_type = match version {
+        2 => {
+            add_field_format_fn!(field, reader, reader.read8()?, |o| t_options(version, o as u32));
+            reader.read8()? as u16
+        }
+        _ => reader.read16(true)?,
+    };
+    let name = lsa_type_mapper(version, ls_type);
+    add_field_backstep!(field, reader, if version == 2 { 1 } else { 2 }, format!("LS Type: {name} ({ls_type:#06x})"));
+    let ls_id = add_field_format!(field, reader, reader.read_ip4()?, "Link State ID: {}");
+    let advertising = add_field_format!(field, reader, reader.read_ip4()?, "Advertising Router: {}");
+    add_field_format_fn!(field, reader, reader.read32(true)?, |s| format!("Sequence Number: {s:#010x}"));
+    add_field_format_fn!(field, reader, reader.read16(true)?, |c| format!("Checksum: {c:#06x}"));
+    let length = add_field_format!(field, reader, reader.read16(true)?, "Length: {}") as usize;
+    if length < LSA_HEADER_LEN {
+        bail!("invalid lsa length");
+    }
+    field.summary = format!("LSA-type {ls_type} ({name}), len {length}");
+    Ok(LsaHeader { ls_type, ls_id, advertising, length })
+}
+
+// one byte of flags or tos ahead of a 24 bit metric
+fn read_tos_metric(reader: &mut Reader, field: &mut Field) -> Result<u32> {
+    reader.read8()?;
+    Ok(add_field_format!(field, reader, reader.read24()?, "Metric: {}"))
+}
+
+fn read_router_link_field(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    let link_id = add_field_format!(field, reader, reader.read_ip4()?, "Link ID: {}");
+    add_field_format!(field, reader, reader.read_ip4()?, "Link Data: {}");
+    let link_type = add_field_format_fn!(field, reader, reader.read8()?, |t| format!("Link Type: {t} - {}", link_type_mapper(t)));
+    let tos = add_field_format!(field, reader, reader.read8()?, "Number of Metrics: {}");
+    let metric = add_field_format!(field, reader, reader.read16(true)?, "0 Metric: {}");
+    for _ in 0..tos {
+        let tos = add_field_format!(field, reader, reader.read8()?, "TOS: {}");
+        reader.read8()?;
+        let metric = reader.read16(true)?;
+        add_field_backstep!(field, reader, 2, format!("TOS {tos} Metric: {metric}"));
+    }
+    field.summary = format!("Type: {} ID: {link_id} Metric: {metric}", link_type_name(link_type));
+    Ok(())
+}
+
+fn link_type_name(link_type: u8) -> &'static str {
+    match link_type {
+        1 => "PTP",
+        2 => "Transit",
+        3 => "Stub",
+        4 => "Virtual",
+        _ => "Unknown",
+    }
+}
+
+fn read_router_link3_field(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    let link_type = add_field_format_fn!(field, reader, reader.read8()?, |t| format!("Type: {t} - {}", link_type_mapper(t)));
+    reader.read8()?;
+    let metric = add_field_format!(field, reader, reader.read16(true)?, "Metric: {}");
+    add_field_format!(field, reader, reader.read32(true)?, "Interface ID: {}");
+    add_field_format!(field, reader, reader.read32(true)?, "Neighbor Interface ID: {}");
+    let neighbor = add_field_format!(field, reader, reader.read_ip4()?, "Neighbor Router ID: {}");
+    field.summary = format!("Type: {} Neighbor: {neighbor} Metric: {metric}", link_type_name(link_type));
+    Ok(())
+}
+
+fn read_link_prefix_field(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    let head = reader.preview(1)?[0];
+    read_prefix_field_with(field, reader, |f, r| {
+        add_field_format!(f, r, r.read16(true)?, "Reserved: {}");
+        Ok(())
+    })?;
+    field.summary = format!("Prefix, length {head}");
+    Ok(())
+}
+
+fn read_intra_prefix_field(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    let head = reader.preview(1)?[0];
+    read_prefix_field_with(field, reader, |f, r| {
+        add_field_format!(f, r, r.read16(true)?, "Metric: {}");
+        Ok(())
+    })?;
+    field.summary = format!("Prefix, length {head}");
+    Ok(())
+}
+
+// prefix length, options, a 16 bit word and the address prefix (RFC 5340 A.4.1)
+fn read_prefix_field_with(field: &mut Field, reader: &mut Reader, mut word: impl FnMut(&mut Field, &mut Reader) -> Result<()>) -> Result<()> {
+    let prefix_len = add_field_format!(field, reader, reader.read8()?, "PrefixLength: {}");
+    add_field_format_fn!(field, reader, reader.read8()?, |o| format!("PrefixOptions: {o:#04x}"));
+    word(field, reader)?;
+    let start = reader.cursor;
+    let prefix = read_prefix(reader, prefix_len)?;
+    add_field_backstep!(field, reader, reader.cursor - start, format!("Address Prefix: {prefix}/{prefix_len}"));
+    Ok(())
+}
+
+fn read_lsa_body_field(field: &mut Field, reader: &mut Reader, version: u8, ls_type: u16) -> Result<()> {
+    match (version, ls_type) {
+        (2, LSA_ROUTER) => {
+            let flags = add_field_format_fn!(field, reader, reader.read8()?, |f| format!("Flags: {f:#04x}"));
+            add_field_label_no_range!(field, format!("[V: {}, E: {}, B: {}]", (flags >> 2) & 1, (flags >> 1) & 1, flags & 1));
+            reader.read8()?;
+            let count = add_field_format!(field, reader, reader.read16(true)?, "Number of Links: {}");
+            for _ in 0..count {
+                add_sub_field_with_reader!(field, reader, read_router_link_field)?;
+            }
+        }
+        (2, LSA_NETWORK) => {
+            add_field_format!(field, reader, reader.read_ip4()?, "Netmask: {}");
+            while reader.left() >= 4 {
+                add_field_format!(field, reader, reader.read_ip4()?, "Attached Router: {}");
+            }
+        }
+        (2, LSA_SUMMARY_NETWORK | LSA_SUMMARY_ASBR) => {
+            add_field_format!(field, reader, reader.read_ip4()?, "Netmask: {}");
+            read_tos_metric(reader, field)?;
+        }
+        (2, LSA_AS_EXTERNAL | LSA_NSSA) => {
+            add_field_format!(field, reader, reader.read_ip4()?, "Netmask: {}");
+            let flags = reader.next()?;
+            add_field_label_no_range!(field, format!("[External Type: {}]", if flags & 0x80 != 0 { 2 } else { 1 }));
+            read_tos_metric(reader, field)?;
+            add_field_format!(field, reader, reader.read_ip4()?, "Forwarding Address: {}");
+            add_field_format!(field, reader, reader.read32(true)?, "External Route Tag: {}");
+        }
+        (3, LSA3_ROUTER) => {
+            let flags = add_field_format_fn!(field, reader, reader.read8()?, |f| format!("Flags: {f:#04x}"));
+            add_field_label_no_range!(field, format!("[V: {}, E: {}, B: {}]", (flags >> 2) & 1, (flags >> 1) & 1, flags & 1));
+            add_field_format_fn!(field, reader, reader.read24()?, |o| t_options(version, o));
+            while reader.left() >= 16 {
+                add_sub_field_with_reader!(field, reader, read_router_link3_field)?;
+            }
+        }
+        (3, LSA3_NETWORK) => {
+            reader.read8()?;
+            add_field_format_fn!(field, reader, reader.read24()?, |o| t_options(version, o));
+            while reader.left() >= 4 {
+                add_field_format!(field, reader, reader.read_ip4()?, "Attached Router: {}");
+            }
+        }
+        (3, LSA3_INTER_AREA_PREFIX) => {
+            read_tos_metric(reader, field)?;
+            read_prefix_field_with(field, reader, |_, r| {
+                r.read16(true)?;
+                Ok(())
+            })?;
+        }
+        (3, LSA3_INTER_AREA_ROUTER) => {
+            reader.read8()?;
+            add_field_format_fn!(field, reader, reader.read24()?, |o| t_options(version, o));
+            read_tos_metric(reader, field)?;
+            add_field_format!(field, reader, reader.read_ip4()?, "Destination Router ID: {}");
+        }
+        (3, LSA3_AS_EXTERNAL | LSA3_NSSA) => {
+            let flags = add_field_format_fn!(field, reader, reader.read8()?, |f| format!("Flags: {f:#04x}"));
+            add_field_label_no_range!(field, format!("[E: {}, F: {}, T: {}]", (flags >> 2) & 1, (flags >> 1) & 1, flags & 1));
+            add_field_format!(field, reader, reader.read24()?, "Metric: {}");
+            let mut referenced = 0;
+            read_prefix_field_with(field, reader, |f, r| {
+                referenced = add_field_format_fn!(f, r, r.read16(true)?, |t| format!("Referenced LS Type: {t:#06x}"));
+                Ok(())
+            })?;
+            if flags & 0x02 != 0 {
+                add_field_format!(field, reader, reader.read_ip6()?, "Forwarding Address: {}");
+            }
+            if flags & 0x01 != 0 {
+                add_field_format!(field, reader, reader.read32(true)?, "External Route Tag: {}");
+            }
+            if referenced != 0 {
+                add_field_format!(field, reader, reader.read_ip4()?, "Referenced Link State ID: {}");
+            }
+        }
+        (3, LSA3_LINK) => {
+            add_field_format!(field, reader, reader.read8()?, "Router Priority: {}");
+            add_field_format_fn!(field, reader, reader.read24()?, |o| t_options(version, o));
+            add_field_format!(field, reader, reader.read_ip6()?, "Link-local Interface Address: {}");
+            let count = add_field_format!(field, reader, reader.read32(true)?, "# prefixes: {}");
+            for _ in 0..count {
+                add_sub_field_with_reader!(field, reader, read_link_prefix_field)?;
+            }
+        }
+        (3, LSA3_INTRA_AREA_PREFIX) => {
+            let count = add_field_format!(field, reader, reader.read16(true)?, "# prefixes: {}");
+            let referenced = reader.read16(true)?;
+            add_field_backstep!(field, reader, 2, format!("Referenced LS Type: {} ({referenced:#06x})", lsa_type_mapper(version, referenced)));
+            add_field_format!(field, reader, reader.read_ip4()?, "Referenced Link State ID: {}");
+            add_field_format!(field, reader, reader.read_ip4()?, "Referenced Advertising Router: {}");
+            for _ in 0..count {
+                add_sub_field_with_reader!(field, reader, read_intra_prefix_field)?;
+            }
+        }
+        _ => {}
+    }
+    add_field_rest_format!(field, reader, format!("Data: {} bytes", reader.left()));
+    Ok(())
+}
+
+fn read_lsa_field(reader: &mut Reader, field: &mut Field, version: u8) -> Result<()> {
+    let header = read_lsa_header_field(reader, field, version)?;
+    let mut _reader = reader.slice_as_reader(cmp::min(header.length - LSA_HEADER_LEN, reader.left()))?;
+    // a truncated body keeps the fields read so far
+    read_lsa_body_field(field, &mut _reader, version, header.ls_type).ok();
+    field.summary = format!("{}, Id: {}, Adv Router: {}", lsa_type_mapper(version, header.ls_type), header.ls_id, header.advertising);
+    Ok(())
+}
+
+fn read_lsu_field(reader: &mut Reader, field: &mut Field, version: u8) -> Result<()> {
+    let count = add_field_format!(field, reader, reader.read32(true)?, "Number of LSAs: {}");
+    for _ in 0..count {
+        add_sub_field_with_reader!(field, reader, |r: &mut Reader, f: &mut Field| read_lsa_field(r, f, version))?;
+    }
+    field.summary = "LS Update Packet".into();
+    Ok(())
+}
+
+fn read_lsack_field(reader: &mut Reader, field: &mut Field, version: u8) -> Result<()> {
+    while reader.left() >= LSA_HEADER_LEN {
+        add_sub_field_with_reader!(field, reader, |r: &mut Reader, f: &mut Field| read_lsa_header_field(r, f, version))?;
+    }
+    field.summary = "LSA Acknowledge".into();
+    Ok(())
+}
+
+fn read_header_field(reader: &mut Reader, field: &mut Field) -> Result<u8> {
+    let version = add_field_format!(field, reader, reader.read8()?, "Version: {}");
+    add_field_format_fn!(field, reader, reader.read8()?, |t| format!("Message Type: {} ({t})", ospf_type_mapper(t)));
+    add_field_format!(field, reader, reader.read16(true)?, "Packet Length: {}");
+    add_field_format!(field, reader, reader.read_ip4()?, "Source OSPF Router: {}");
+    let area = add_field_format!(field, reader, reader.read_ip4()?, "Area ID: {}");
+    if area.is_unspecified() {
+        add_field_label_no_range!(field, "[Area ID: Backbone]".into());
+    }
+    add_field_format_fn!(field, reader, reader.read16(true)?, |c| format!("Checksum: {c:#06x}"));
+    if version == 2 {
+        let auth_type = add_field_format_fn!(field, reader, reader.read16(true)?, |t| format!("Auth Type: {} ({t})", auth_type_mapper(t)));
+        match auth_type {
+            1 => {
+                let data = reader.slice(8, true)?;
+                let password = String::from_utf8_lossy(data).trim_end_matches('\0').to_string();
+                add_field_backstep!(field, reader, 8, format!("Auth Data (Simple): {password}"));
+            }
+            2 => {
+                reader.read16(true)?;
+                add_field_format!(field, reader, reader.read8()?, "Auth Crypt Key id: {}");
+                add_field_format!(field, reader, reader.read8()?, "Auth Crypt Data Length: {}");
+                add_field_format!(field, reader, reader.read32(true)?, "Auth Crypt Sequence Number: {}");
+            }
+            _ => {
+                reader.forward(8);
+                add_field_backstep!(field, reader, 8, "Auth Data (none)".into());
+            }
+        }
+    } else {
+        add_field_format!(field, reader, reader.read8()?, "Instance ID: {}");
+        reader.read8()?;
+        add_field_backstep!(field, reader, 1, "Reserved".into());
+    }
+    field.summary = "OSPF Header".into();
+    Ok(version)
+}
+
+pub struct Visitor;
+
+impl Visitor {
+    pub fn info(_: &Context, frame: &Frame) -> Option<String> {
+        if let ProtocolInfoField::Ospf(version, msg_type, router) = &frame.protocol_field {
+            return Some(format!("OSPFv{version} {} Packet, Router ID {router}", ospf_type_mapper(*msg_type)));
+        }
+        Some("Open Shortest Path First".to_string())
+    }
+
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+        let (version, msg_type, mut reader) = ospf_reader(reader)?;
+        reader.forward(4);
+        let router = reader.read_ip4()?;
+        let area = reader.read_ip4()?;
+        reader.forward(header_len(version) - 12);
+        frame.protocol_field = ProtocolInfoField::Ospf(version, msg_type, router);
+
+        let packet = OspfPacket { index: frame.info.index, ts: frame.info.time, version, area, router };
+        let (_, target) = frame_ips(ctx, frame);
+        match msg_type {
+            OSPF_HELLO => record_hello(ctx, frame, &mut reader, &packet)?,
+            OSPF_DB_DESCRIPTION => {
+                reader.forward(match version {
+                    2 => 3,
+                    _ => 7,
+                });
+                let flags = reader.read8()?;
+                ctx.ospf.dbd(&packet, target, flags);
+            }
+            OSPF_LS_REQUEST => ctx.ospf.lsr(&packet, target, reader.left() / 12),
+            OSPF_LS_UPDATE => {
+                // a malformed lsa does not void the adjacency progress of the packet
+                record_update(ctx, &mut reader, &packet).ok();
+            }
+            _ => {}
+        }
+        Ok(Protocol::None)
+    }
+
+    pub fn detail(field: &mut Field, _: &Context, _: &Frame, reader: &mut Reader) -> Result<Protocol> {
+        let (version, msg_type, mut reader) = ospf_reader(reader)?;
+        field.summary = "Open Shortest Path First".to_string();
+        add_sub_field_with_reader!(field, &mut reader, read_header_field)?;
+        match msg_type {
+            OSPF_HELLO => add_sub_field_with_reader!(field, &mut reader, |r: &mut Reader, f: &mut Field| read_hello_field(r, f, version))?,
+            OSPF_DB_DESCRIPTION => add_sub_field_with_reader!(field, &mut reader, |r: &mut Reader, f: &mut Field| read_dbd_field(r, f, version))?,
+            OSPF_LS_REQUEST => {
+                while reader.left() >= 12 {
+                    add_sub_field_with_reader!(field, &mut reader, |r: &mut Reader, f: &mut Field| read_lsr_field(r, f, version))?;
+                }
+            }
+            OSPF_LS_UPDATE => add_sub_field_with_reader!(field, &mut reader, |r: &mut Reader, f: &mut Field| read_lsu_field(r, f, version))?,
+            OSPF_LS_ACK => add_sub_field_with_reader!(field, &mut reader, |r: &mut Reader, f: &mut Field| read_lsack_field(r, f, version))?,
+            _ => add_field_rest_format!(field, reader, format!("Data: {} bytes", reader.left())),
+        }
+        Ok(Protocol::None)
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use crate::{
+    add_field_backstep, add_field_format, add_field_format_fn, add_field_label_no_range,
+    common::{
+        checksum::{t_checksum, verify_plain, verify_transport, ChecksumStatus, IP_PROTO_VRRP},
+        concept::Field,
+        core::Context,
+        enum_def::{AddressField, Protocol, ProtocolInfoField},
+        fhrp::{FhrpAdvertisement, FhrpProtocol, FhrpRole},
+        io::Reader,
+        ipsec::frame_ips,
+        Frame,
+    },
+};
+use anyhow::Result;
+use std::{cmp, net::IpAddr};
+
+const VRRP_ADVERTISEMENT: u8 = 1;
+const VRRP_AUTH_SIMPLE: u8 = 1;
+
+pub fn vrrp_type_mapper(msg_type: u8) -> &'static str {
+    match msg_type {
+        VRRP_ADVERTISEMENT => "Advertisement",
+        _ => "Unknown",
+    }
+}
+
+fn t_priority(priority: u8) -> String {
+    let meaning = match priority {
+        0 => "Current Master has stopped participating in VRRP",
+        100 => "Default priority for a backup VRRP router",
+        255 => "This VRRP router owns the virtual router's IP address(es)",
+        _ => "Non-default backup priority",
+    };
+    format!("Priority: {priority} ({meaning})")
+}
+
+fn auth_type_mapper(auth_type: u8) -> &'static str {
+    match auth_type {
+        0 => "No Authentication",
+        VRRP_AUTH_SIMPLE => "Simple Text Authentication",
+        2 => "IP Authentication Header",
+        _ => "Unknown",
+    }
+}
+
+// ip payload length, the reader may still hold ethernet padding
+fn vrrp_len(frame: &Frame, reader: &Reader) -> usize {
+    match frame.iplen {
+        0 => reader.left(),
+        len => cmp::min(len as usize, reader.left()),
+    }
+}
+
+// version 3 carries ipv6 addresses when sent over ipv6 (RFC 5798 5.2.9)
+fn is_ipv6(frame: &Frame) -> bool {
+    matches!(frame.address_field, AddressField::IPv6(_))
+}
+
+fn read_address(reader: &mut Reader, ipv6: bool) -> Result<IpAddr> {
+    match ipv6 {
+        true => Ok(IpAddr::V6(reader.read_ip6()?)),
+        false => Ok(IpAddr::V4(reader.read_ip4()?)),
+    }
+}
+
+fn verify(ctx: &Context, frame: &Frame, reader: &Reader, version: u8, len: usize) -> ChecksumStatus {
+    match version {
+        2 => verify_plain(reader, len, 6),
+        _ => verify_transport(ctx, frame, reader, IP_PROTO_VRRP, len, 6),
+    }
+}
+
+pub struct Visitor;
+
+impl Visitor {
+    pub fn info(_: &Context, frame: &Frame) -> Option<String> {
+        if let ProtocolInfoField::Vrrp(version, vrid, priority) = &frame.protocol_field {
+            return Some(format!("VRRPv{version} Advertisement, VRID {vrid}, Priority {priority}"));
+        }
+        Some("Virtual Router Redundancy Protocol".to_string())
+    }
+
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+        let len = vrrp_len(frame, reader);
+        if len < 8 {
+            return Ok(Protocol::None);
+        }
+        let version = reader.next()? >> 4;
+        if ctx.config.verify_checksum && verify(ctx, frame, reader, version, len).is_bad() {
+            frame.mark_error("Bad VRRP checksum");
+        }
+        let mut reader = reader.slice_as_reader(len)?;
+        let msg_type = reader.read8()? & 0x0f;
+        let vrid = reader.read8()?;
+        let priority = reader.read8()?;
+        let count = reader.read8()?;
+        reader.forward(4); // auth type and advertisement interval, or max advertisement interval; checksum
+        frame.protocol_field = ProtocolInfoField::Vrrp(version, vrid, priority);
+        if msg_type != VRRP_ADVERTISEMENT {
+            return Ok(Protocol::None);
+        }
+        let ipv6 = version == 3 && is_ipv6(frame);
+        let mut virtual_ips = vec![];
+        for _ in 0..count {
+            virtual_ips.push(read_address(&mut reader, ipv6)?);
+        }
+        if let (Some(router), _) = frame_ips(ctx, frame) {
+            let role = match priority {
+                0 => FhrpRole::Resign,
+                _ => FhrpRole::Master,
+            };
+            ctx.fhrp.add(FhrpAdvertisement {
+                index: frame.info.index,
+                ts: frame.info.time,
+                protocol: FhrpProtocol::VRRP,
+                version,
+                group: vrid as u16,
+                router,
+                priority: priority as u32,
+                role,
+                virtual_ips,
+            });
+        }
+        Ok(Protocol::None)
+    }
+
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
+        let len = vrrp_len(frame, reader);
+        let version = reader.next()? >> 4;
+        let status = match ctx.config.verify_checksum {
+            true => verify(ctx, frame, reader, version, len),
+            false => ChecksumStatus::Unverified,
+        };
+        let mut reader = reader.slice_as_reader(len)?;
+        let head = reader.read8()?;
+        add_field_backstep!(field, reader, 1, format!("{:04b} .... = Version: {version}", head >> 4));
+        add_field_backstep!(field, reader, 1, format!(".... {:04b} = Packet type: {} ({})", head & 0x0f, vrrp_type_mapper(head & 0x0f), head & 0x0f));
+        let vrid = add_field_format!(field, reader, reader.read8()?, "Virtual Rtr ID: {}");
+        let priority = add_field_format_fn!(field, reader, reader.read8()?, t_priority);
+        let count = add_field_format!(field, reader, reader.read8()?, "Addr Count: {}");
+        let mut auth_type = 0;
+        if version == 2 {
+            auth_type = add_field_format_fn!(field, reader, reader.read8()?, |t| format!("Auth Type: {} ({t})", auth_type_mapper(t)));
+            add_field_format!(field, reader, reader.read8()?, "Adver Int: {}");
+        } else {
+            let interval = reader.read16(true)? & 0x0fff;
+            add_field_backstep!(field, reader, 2, format!("Max Adver Int: {interval} ({:.2} sec)", interval as f32 / 100.0));
+        }
+        let checksum = reader.read16(true)?;
+        add_field_backstep!(field, reader, 2, t_checksum("Checksum", checksum, status));
+        let ipv6 = version == 3 && is_ipv6(frame);
+        for _ in 0..count {
+            add_field_format!(field, reader, read_address(&mut reader, ipv6)?, "IP Address: {}");
+        }
+        if version == 2 && reader.left() >= 8 {
+            let data = reader.slice(8, true)?;
+            let text = match auth_type {
+                VRRP_AUTH_SIMPLE => format!("Authentication String: {}", String::from_utf8_lossy(data).trim_end_matches('\0')),
+                _ => "Authentication Data".to_string(),
+            };
+            add_field_backstep!(field, reader, 8, text);
+        }
+        match ctx.fhrp.takeover(frame.info.index) {
+            Some(Some(previous)) => add_field_label_no_range!(field, format!("[Master changed from {previous}]")),
+            Some(None) => add_field_label_no_range!(field, "[First master of the virtual router]".into()),
+            None => {}
+        }
+        field.summary = format!("Virtual Router Redundancy Protocol Version {version}, VRID {vrid}, Prio {priority}");
+        Ok(Protocol::None)
+    }
+}
@@ -0,0 +1,302 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use crate::{
+    add_field_format, add_field_format_fn, add_field_forward, add_field_label_no_range, add_field_rest_format,
+    common::{
+        concept::Field,
+        core::Context,
+        dtls::{CookieCheck, DtlsFragment, DtlsHandshakeHeader, DtlsRecordInfo, DTLS_HANDSHAKE_HEADER},
+        enum_def::{Protocol, ProtocolInfoField},
+        io::{DataSource, Reader},
+        Frame,
+    },
+    constants::tls_hs_message_type_mapper,
+    protocol::{
+        heuristic::Confidence,
+        transport::{
+            quic::{datagram_len, endpoints},
+            tls::{
+                field_tls_type, field_tls_version,
+                record::{is_dtls, parse_record_detail, HANDSHAKE},
+                tls_version_map,
+            },
+        },
+    },
+};
+use anyhow::Result;
+
+// content type, version, epoch, 48 bit sequence number, length
+const RECORD_HEADER: usize = 13;
+
+const CHANGE_CIPHER_SPEC: u8 = 20;
+const ALERT: u8 = 21;
+const APPLICATION_DATA: u8 = 23;
+const HEARTBEAT: u8 = 24;
+// RFC 9146: record with a connection id, whose length was negotiated in the handshake
+const TLS12_CID: u8 = 25;
+
+/**
+ * RFC 9147 4: the first byte of a dtls 1.3 ciphertext record is 001CSLEE
+ */
+fn is_unified(first: u8) -> bool {
+    first & 0xe0 == 0x20
+}
+
+struct Record {
+    content_type: u8,
+    version: u16,
+    epoch: u16,
+    sequence: u64,
+    // bytes before the record payload
+    header: usize,
+    len: usize,
+}
+
+/**
+ * header of the record at the reader, the unified header keeps its first byte as content type
+ */
+fn read_record(reader: &Reader) -> Option<Record> {
+    let head = reader.preview(reader.left().min(RECORD_HEADER)).ok()?;
+    let first = *head.first()?;
+    if is_unified(first) {
+        // without the negotiated connection id length nothing after it can be located
+        if first & 0x10 != 0 {
+            return Some(Record { content_type: first, version: 0, epoch: (first & 0x03) as u16, sequence: 0, header: 1, len: reader.left() - 1 });
+        }
+        let seq_len = if first & 0x08 != 0 { 2 } else { 1 };
+        let header = 1 + seq_len + if first & 0x04 != 0 { 2 } else { 0 };
+        let head = reader.preview(header).ok()?;
+        let sequence = head[1..1 + seq_len].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+        let len = match first & 0x04 != 0 {
+            true => u16::from_be_bytes([head[1 + seq_len], head[2 + seq_len]]) as usize,
+            false => reader.left() - header,
+        };
+        return Some(Record { content_type: first, version: 0, epoch: (first & 0x03) as u16, sequence, header, len });
+    }
+    if !(CHANGE_CIPHER_SPEC..=TLS12_CID).contains(&first) || head.len() < RECORD_HEADER {
+        return None;
+    }
+    let version = u16::from_be_bytes([head[1], head[2]]);
+    if !is_dtls(version) {
+        return None;
+    }
+    let sequence = head[5..11].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+    let len = match first {
+        TLS12_CID => reader.left() - 11,
+        _ => u16::from_be_bytes([head[11], head[12]]) as usize,
+    };
+    let header = if first == TLS12_CID { 11 } else { RECORD_HEADER };
+    Some(Record { content_type: first, version, epoch: u16::from_be_bytes([head[3], head[4]]), sequence, header, len })
+}
+
+fn record_name(record: &DtlsRecordInfo) -> String {
+    match record.content_type {
+        HANDSHAKE if record.epoch > 0 => "Encrypted Handshake Message".into(),
+        HANDSHAKE if record.messages.is_empty() => "Handshake".into(),
+        HANDSHAKE => record
+            .messages
+            .iter()
+            .map(|(msg_type, fragment)| {
+                let name = tls_hs_message_type_mapper(*msg_type);
+                match fragment {
+                    DtlsFragment::Whole => name.to_string(),
+                    DtlsFragment::Partial => format!("{name} (Fragment)"),
+                    DtlsFragment::Reassembled(_) => format!("{name} (Reassembled)"),
+                    DtlsFragment::Retransmission(_) => format!("{name} (Retransmission)"),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        CHANGE_CIPHER_SPEC => "Change Cipher Spec".into(),
+        ALERT if record.epoch > 0 => "Encrypted Alert".into(),
+        ALERT => "Alert".into(),
+        APPLICATION_DATA => "Application Data".into(),
+        HEARTBEAT => "Heartbeat".into(),
+        TLS12_CID => "Connection ID".into(),
+        _ => "Encrypted Record".into(),
+    }
+}
+
+fn version_name(version: u16) -> &'static str {
+    tls_version_map(version).unwrap_or("DTLS")
+}
+
+/**
+ * a plaintext dtls record with a version and length that fit the datagram
+ */
+pub fn probe(reader: &Reader) -> Option<Confidence> {
+    let record = read_record(reader)?;
+    if is_unified(record.content_type) || record.content_type == TLS12_CID || record.header + record.len > reader.left() {
+        return None;
+    }
+    match record.content_type == HANDSHAKE && record.epoch == 0 {
+        true => Some(Confidence::High),
+        false => Some(Confidence::Low),
+    }
+}
+
+pub struct Visitor;
+
+impl Visitor {
+    pub fn info(_: &Context, frame: &Frame) -> Option<String> {
+        if let ProtocolInfoField::Dtls(records) = &frame.protocol_field {
+            let list: Vec<String> = records.iter().map(record_name).collect();
+            return Some(list.join(", "));
+        }
+        None
+    }
+
+    /**
+     * walks the records in the datagram, plaintext handshake fragments go to the table for reassembly
+     */
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+        let len = datagram_len(ctx, frame, reader);
+        let mut reader = reader.slice_as_reader(len)?;
+        let endpoints = endpoints(ctx, frame);
+        let index = frame.info.index;
+        let mut records = vec![];
+        while let Some(record) = read_record(&reader) {
+            reader.forward(record.header);
+            let payload = reader.slice(record.len.min(reader.left()), true)?;
+            let mut info = DtlsRecordInfo { content_type: record.content_type, epoch: record.epoch, messages: vec![] };
+            if record.content_type == HANDSHAKE && record.epoch == 0 {
+                let mut rest = payload;
+                while let Some(header) = DtlsHandshakeHeader::read(rest) {
+                    let end = (DTLS_HANDSHAKE_HEADER + header.fragment_len).min(rest.len());
+                    let fragment = &rest[DTLS_HANDSHAKE_HEADER..end];
+                    let state = match endpoints {
+                        Some((source, target)) => ctx.dtls.add(index, source, target, &header, fragment),
+                        None if header.offset == 0 && header.fragment_len == header.length => DtlsFragment::Whole,
+                        None => DtlsFragment::Partial,
+                    };
+                    info.messages.push((header.msg_type, state));
+                    rest = &rest[end..];
+                }
+            }
+            records.push(info);
+        }
+        if records.is_empty() {
+            return Ok(Protocol::None);
+        }
+        frame.protocol_field = ProtocolInfoField::Dtls(records);
+        Ok(Protocol::None)
+    }
+
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader, datasources: &mut Vec<DataSource>) -> Result<Protocol> {
+        let len = datagram_len(ctx, frame, reader);
+        let mut reader = reader.slice_as_reader(len)?;
+        let records = match &frame.protocol_field {
+            ProtocolInfoField::Dtls(records) => records.as_slice(),
+            _ => &[],
+        };
+        let mut version = 0;
+        let mut infos = records.iter();
+        while let Some(record) = read_record(&reader) {
+            let info = infos.next();
+            let size = (record.header + record.len).min(reader.left());
+            let mut record_reader = reader.slice_as_reader(size)?;
+            let mut record_field = Field::with_children(String::new(), record_reader.cursor, size);
+            record_field.source = field.source;
+            read_record_field(&record, info, &mut record_reader, &mut record_field)?;
+            field.children.as_mut().unwrap().push(record_field);
+            if record.version != 0 {
+                version = record.version;
+            }
+        }
+        add_field_rest_format!(field, reader, format!("Malformed Record ({} bytes)", reader.left()));
+
+        // messages whose last fragment arrived in this frame
+        for message in ctx.dtls.messages(frame.info.index) {
+            match message.cookie {
+                Some(CookieCheck::Echoed(hvr)) => add_field_label_no_range!(field, format!("[Cookie echoed from the HelloVerifyRequest in frame {}]", hvr + 1)),
+                Some(CookieCheck::Mismatch(hvr)) => add_field_label_no_range!(field, format!("[Expert Info: Cookie does not match the HelloVerifyRequest in frame {}]", hvr + 1)),
+                None => {}
+            }
+            if message.fragments < 2 {
+                continue;
+            }
+            let ds = DataSource::create(message.data.clone(), 0..0);
+            let mut message_reader = Reader::new(&ds);
+            let mut message_field = Field::with_children(String::new(), 0, message.data.len());
+            message_field.source = (datasources.len() + 1) as u8;
+            parse_record_detail(HANDSHAKE, version, &mut message_reader, &mut message_field)?;
+            message_field.summary = format!("{} (reassembled from {} fragments, {} bytes)", message_field.summary, message.fragments, message.data.len() - DTLS_HANDSHAKE_HEADER);
+            field.children.as_mut().unwrap().push(message_field);
+            datasources.push(ds);
+        }
+        field.summary = "Datagram Transport Layer Security".into();
+        Ok(Protocol::None)
+    }
+}
+
+fn read_record_field(record: &Record, info: Option<&DtlsRecordInfo>, reader: &mut Reader, field: &mut Field) -> Result<()> {
+    if is_unified(record.content_type) {
+        let first = add_field_format!(field, reader, reader.read8()?, "Unified Header: {:#04x}");
+        add_field_label_no_range!(field, format!("Connection ID Present: {}", first & 0x10 != 0));
+        add_field_label_no_range!(field, format!("Sequence Number Length: {} bits", if first & 0x08 != 0 { 16 } else { 8 }));
+        add_field_label_no_range!(field, format!("Length Present: {}", first & 0x04 != 0));
+        add_field_label_no_range!(field, format!("Epoch (low bits): {}", record.epoch));
+        if first & 0x10 != 0 {
+            add_field_rest_format!(field, reader, format!("Connection ID and Encrypted Record ({} bytes)", reader.left()));
+            field.summary = "DTLSv1.3 Record Layer: Encrypted Record".into();
+            return Ok(());
+        }
+        let seq_len = if first & 0x08 != 0 { 2 } else { 1 };
+        add_field_forward!(field, reader, seq_len, format!("Sequence Number (encrypted): {:#x}", record.sequence));
+        reader.forward(seq_len);
+        if first & 0x04 != 0 {
+            add_field_format!(field, reader, reader.read16(true)?, "Length: {}");
+        }
+        add_field_rest_format!(field, reader, format!("Encrypted Record ({} bytes)", reader.left()));
+        field.summary = "DTLSv1.3 Record Layer: Encrypted Record".into();
+        return Ok(());
+    }
+    let name = info.map(record_name).unwrap_or_default();
+    if record.content_type == TLS12_CID {
+        add_field_format!(field, reader, reader.read8()?, "Content Type: Connection ID ({})");
+    } else {
+        add_field_format_fn!(field, reader, reader.read8()?, field_tls_type);
+    }
+    add_field_format_fn!(field, reader, reader.read16(true)?, field_tls_version);
+    add_field_format!(field, reader, reader.read16(true)?, "Epoch: {}");
+    add_field_forward!(field, reader, 6, format!("Sequence Number: {}", record.sequence));
+    reader.forward(6);
+    field.summary = format!("{} Record Layer: {name}", version_name(record.version));
+    if record.content_type == TLS12_CID {
+        add_field_rest_format!(field, reader, format!("Connection ID and Encrypted Record ({} bytes)", reader.left()));
+        return Ok(());
+    }
+    add_field_format!(field, reader, reader.read16(true)?, "Length: {}");
+    match (record.content_type, record.epoch) {
+        (HANDSHAKE, 0) => {
+            let mut states = info.map(|i| i.messages.iter()).into_iter().flatten();
+            while reader.left() >= DTLS_HANDSHAKE_HEADER {
+                let header = DtlsHandshakeHeader::read(reader.preview(DTLS_HANDSHAKE_HEADER)?).unwrap();
+                let size = (DTLS_HANDSHAKE_HEADER + header.fragment_len).min(reader.left());
+                let mut message_reader = reader.slice_as_reader(size)?;
+                let mut message_field = Field::with_children(String::new(), message_reader.cursor, size);
+                message_field.source = field.source;
+                parse_record_detail(HANDSHAKE, record.version, &mut message_reader, &mut message_field)?;
+                match states.next().map(|(_, state)| *state) {
+                    Some(DtlsFragment::Reassembled(count)) => add_field_label_no_range!(message_field, format!("[Last of {count} fragments, reassembled below]")),
+                    Some(DtlsFragment::Retransmission(first)) => add_field_label_no_range!(message_field, format!("[Retransmission of the fragment in frame {}]", first + 1)),
+                    _ => {}
+                }
+                field.children.as_mut().unwrap().push(message_field);
+            }
+        }
+        (HANDSHAKE, _) => add_field_rest_format!(field, reader, format!("Encrypted Handshake Message ({} bytes)", reader.left())),
+        (APPLICATION_DATA, _) => add_field_rest_format!(field, reader, format!("Encrypted Application Data ({} bytes)", reader.left())),
+        (ALERT, epoch) if epoch > 0 => add_field_rest_format!(field, reader, format!("Encrypted Alert ({} bytes)", reader.left())),
+        _ => {
+            let mut body = Field::with_children(String::new(), reader.cursor, reader.left());
+            body.source = field.source;
+            parse_record_detail(record.content_type, record.version, reader, &mut body)?;
+            field.children.as_mut().unwrap().push(body);
+        }
+    }
+    Ok(())
+}
@@ -5,4 +5,8 @@
 
 pub mod tcp;
 pub mod udp;
+pub mod sctp;
 pub mod tls;
+pub mod quic;
+pub mod dtls;
+pub mod mptcp;
@@ -0,0 +1,260 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::{
+    add_field_backstep, add_field_format, add_field_rest_format,
+    common::{
+        concept::Field,
+        io::Reader,
+        mptcp::{mptcp_subtype_mapper, MptcpOption, MP_ADD_ADDR, MP_CAPABLE, MP_DSS, MP_FAIL, MP_FASTCLOSE, MP_JOIN, MP_PRIO, MP_REMOVE_ADDR, MP_TCPRST},
+        util::bytes_to_hex,
+    },
+};
+use anyhow::Result;
+
+pub const TCP_OPTION_MPTCP: u8 = 30;
+
+// DSS flags
+const DATA_FIN: u8 = 0x10;
+const DSN_WIDE: u8 = 0x08;
+const MAPPING: u8 = 0x04;
+const ACK_WIDE: u8 = 0x02;
+const DATA_ACK: u8 = 0x01;
+
+fn be(data: &[u8]) -> u64 {
+    data.iter().fold(0, |acc, b| (acc << 8) | *b as u64)
+}
+
+/**
+ * address family of ADD_ADDR: version 0 keeps the ip version in the low bits, version 1 the echo flag
+ */
+fn add_addr_layout(flags: u8, len: usize) -> Option<(bool, usize, bool)> {
+    // echo, address length, port present
+    match flags & 0x0f {
+        4 => Some((false, 4, len == 10)),
+        6 => Some((false, 16, len == 22)),
+        flags => {
+            let echo = flags & 0x01 == 1;
+            let rest = len.saturating_sub(if echo { 4 } else { 12 });
+            match rest {
+                4 | 6 => Some((echo, 4, rest == 6)),
+                16 | 18 => Some((echo, 16, rest == 18)),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn read_address(data: &[u8]) -> IpAddr {
+    match data.len() {
+        4 => IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3])),
+        _ => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(data);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+    }
+}
+
+/**
+ * an mptcp option from its subtype byte on, data is everything after kind and length
+ */
+pub fn read_option(data: &[u8]) -> Option<MptcpOption> {
+    let len = data.len() + 2;
+    let subtype = *data.first()? >> 4;
+    let flags = *data.first()? & 0x0f;
+    let option = match subtype {
+        MP_CAPABLE => MptcpOption::Capable {
+            version: flags,
+            sender_key: data.get(2..10).map(be),
+            receiver_key: data.get(10..18).map(be),
+        },
+        MP_JOIN => MptcpOption::Join {
+            address_id: *data.get(1)?,
+            backup: flags & 0x01 == 1,
+            token: match len {
+                12 => data.get(2..6).map(|t| be(t) as u32),
+                _ => None,
+            },
+        },
+        MP_DSS => {
+            let bits = *data.get(1)?;
+            let mut at = 2;
+            if bits & DATA_ACK != 0 {
+                at += if bits & ACK_WIDE != 0 { 8 } else { 4 };
+            }
+            let mapping = match bits & MAPPING != 0 {
+                true => {
+                    let width = if bits & DSN_WIDE != 0 { 8 } else { 4 };
+                    let dsn = be(data.get(at..at + width)?);
+                    let length = be(data.get(at + width + 4..at + width + 6)?) as u16;
+                    Some((dsn, width == 8, length))
+                }
+                false => None,
+            };
+            MptcpOption::Dss { mapping, fin: bits & DATA_FIN != 0 }
+        }
+        MP_ADD_ADDR => {
+            let (echo, size, port) = add_addr_layout(flags, len)?;
+            let address = read_address(data.get(2..2 + size)?);
+            let port = match port {
+                true => Some(be(data.get(2 + size..4 + size)?) as u16),
+                false => None,
+            };
+            MptcpOption::AddAddr { address_id: *data.get(1)?, address, port, echo }
+        }
+        MP_REMOVE_ADDR => MptcpOption::RemoveAddr(data.get(1..)?.to_vec()),
+        MP_PRIO => MptcpOption::Prio { backup: flags & 0x01 == 1 },
+        MP_FAIL => MptcpOption::Fail(be(data.get(2..10)?)),
+        MP_FASTCLOSE => MptcpOption::FastClose(be(data.get(2..10)?)),
+        _ => MptcpOption::Other(subtype),
+    };
+    Some(option)
+}
+
+/**
+ * the mptcp options of a segment, read without building fields
+ */
+pub fn options(reader: &mut Reader) -> Vec<MptcpOption> {
+    let mut list = vec![];
+    while reader.left() > 0 {
+        let Ok(kind) = reader.read8() else {
+            break;
+        };
+        match kind {
+            0 => break,
+            1 => continue,
+            _ => {}
+        }
+        let Ok(len) = reader.read8() else {
+            break;
+        };
+        if len < 2 {
+            break;
+        }
+        let Ok(data) = reader.slice((len - 2) as usize, true) else {
+            break;
+        };
+        if kind == TCP_OPTION_MPTCP {
+            if let Some(option) = read_option(data) {
+                list.push(option);
+            }
+        }
+    }
+    list
+}
+
+fn read_u64_field(reader: &mut Reader, field: &mut Field, width: usize, name: &str) -> Result<u64> {
+    let value = be(reader.slice(width, true)?);
+    add_field_backstep!(field, reader, width, format!("{name}: {value}"));
+    Ok(value)
+}
+
+/**
+ * fields of a kind 30 option, the reader is past kind and length
+ */
+pub fn read_option_field(reader: &mut Reader, field: &mut Field, len: u8) -> Result<()> {
+    let head = reader.read8()?;
+    let (subtype, flags) = (head >> 4, head & 0x0f);
+    let name = mptcp_subtype_mapper(subtype);
+    add_field_backstep!(field, reader, 1, format!("Multipath TCP subtype: {name} ({subtype})"));
+    field.summary = format!("Multipath TCP: {name}");
+    let len = len as usize;
+    match subtype {
+        MP_CAPABLE => {
+            add_field_backstep!(field, reader, 1, format!("Version: {flags}"));
+            let bits = reader.read8()?;
+            add_field_backstep!(field, reader, 1, format!("Flags: {bits:#04x}, Checksum required: {}, HMAC-SHA256: {}", bits & 0x80 != 0, bits & 0x01 != 0));
+            if len >= 12 {
+                add_field_format!(field, reader, reader.read64(true)?, "Sender's Key: {:#018x}");
+            }
+            if len >= 20 {
+                add_field_format!(field, reader, reader.read64(true)?, "Receiver's Key: {:#018x}");
+            }
+            if len >= 22 {
+                add_field_format!(field, reader, reader.read16(true)?, "Data-Level Length: {}");
+            }
+            if len >= 24 {
+                add_field_format!(field, reader, reader.read16(true)?, "Checksum: {:#06x}");
+            }
+        }
+        MP_JOIN if len == 24 => {
+            reader.forward(1);
+            let hmac = bytes_to_hex(reader.slice(20, true)?);
+            add_field_backstep!(field, reader, 20, format!("Sender's HMAC: {hmac}"));
+        }
+        MP_JOIN => {
+            add_field_backstep!(field, reader, 1, format!("Backup flag: {}", flags & 0x01));
+            add_field_format!(field, reader, reader.read8()?, "Address ID: {}");
+            if len == 12 {
+                let token = add_field_format!(field, reader, reader.read32(true)?, "Receiver's Token: {:#010x}");
+                add_field_format!(field, reader, reader.read32(true)?, "Sender's Random Number: {:#010x}");
+                field.summary = format!("Multipath TCP: MP_JOIN, token {token:08x}");
+            } else if len == 16 {
+                add_field_format!(field, reader, reader.read64(true)?, "Sender's Truncated HMAC: {:#018x}");
+                add_field_format!(field, reader, reader.read32(true)?, "Sender's Random Number: {:#010x}");
+            }
+        }
+        MP_DSS => {
+            let bits = reader.read8()?;
+            add_field_backstep!(field, reader, 1, format!("Flags: {bits:#04x}, DATA_FIN: {}, DSN 8 octets: {}, Mapping present: {}, Data ACK 8 octets: {}, Data ACK present: {}", bits & DATA_FIN != 0, bits & DSN_WIDE != 0, bits & MAPPING != 0, bits & ACK_WIDE != 0, bits & DATA_ACK != 0));
+            if bits & DATA_ACK != 0 {
+                read_u64_field(reader, field, if bits & ACK_WIDE != 0 { 8 } else { 4 }, "Original Data ACK")?;
+            }
+            if bits & MAPPING != 0 {
+                let dsn = read_u64_field(reader, field, if bits & DSN_WIDE != 0 { 8 } else { 4 }, "Data Sequence Number (raw)")?;
+                let ssn = add_field_format!(field, reader, reader.read32(true)?, "Subflow Sequence Number: {}");
+                let length = add_field_format!(field, reader, reader.read16(true)?, "Data-Level Length: {}");
+                if reader.left() >= 2 {
+                    add_field_format!(field, reader, reader.read16(true)?, "Checksum: {:#06x}");
+                }
+                field.summary = format!("Multipath TCP: DSS, DSN {dsn}, SSN {ssn}, Length {length}");
+            }
+        }
+        MP_ADD_ADDR => match add_addr_layout(flags, len) {
+            Some((echo, size, port)) => {
+                add_field_backstep!(field, reader, 1, format!("Echo: {echo}"));
+                let id = add_field_format!(field, reader, reader.read8()?, "Address ID: {}");
+                let address = read_address(reader.slice(size, true)?);
+                add_field_backstep!(field, reader, size, format!("Address: {address}"));
+                if port {
+                    add_field_format!(field, reader, reader.read16(true)?, "Port: {}");
+                }
+                if reader.left() >= 8 && !echo {
+                    add_field_format!(field, reader, reader.read64(true)?, "Truncated HMAC: {:#018x}");
+                }
+                field.summary = format!("Multipath TCP: ADD_ADDR, id {id}, {address}");
+            }
+            None => {}
+        },
+        MP_REMOVE_ADDR => {
+            let ids = reader.slice(len - 3, true)?.to_vec();
+            add_field_backstep!(field, reader, ids.len(), format!("Address IDs: {ids:?}"));
+        }
+        MP_PRIO => {
+            add_field_backstep!(field, reader, 1, format!("Backup flag: {}", flags & 0x01));
+            if len > 3 {
+                add_field_format!(field, reader, reader.read8()?, "Address ID: {}");
+            }
+        }
+        MP_FAIL => {
+            reader.forward(1);
+            add_field_format!(field, reader, reader.read64(true)?, "Data Sequence Number: {}");
+        }
+        MP_FASTCLOSE => {
+            reader.forward(1);
+            add_field_format!(field, reader, reader.read64(true)?, "Receiver's Key: {:#018x}");
+        }
+        MP_TCPRST => {
+            add_field_backstep!(field, reader, 1, format!("Flags: {flags:#03x}"));
+            add_field_format!(field, reader, reader.read8()?, "Reason: {}");
+        }
+        _ => {}
+    }
+    add_field_rest_format!(field, reader, format!("Option Data ({} bytes)", reader.left()));
+    Ok(())
+}
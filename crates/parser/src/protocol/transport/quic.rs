@@ -0,0 +1,482 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use std::ops::Range;
+
+use crate::{
+    add_field_backstep, add_field_format, add_field_format_fn, add_field_forward, add_field_label_no_range, add_field_rest_format, add_sub_field_with_reader,
+    common::{
+        concept::{Field, FrameIndex},
+        core::Context,
+        enum_def::{DataError, Protocol, ProtocolInfoField},
+        io::{DataSource, Reader},
+        ipsec::frame_ips,
+        quic::{hex_id, open_packet, quic_version, QuicEndpoint, QuicFrameRef, QuicHeader, QuicInfo, QuicPacketType, QuicPlain, QUIC_V1, QUIC_V2},
+        Frame,
+    },
+    protocol::{
+        heuristic::Confidence,
+        transport::tls::record::{parse_record_detail, HANDSHAKE},
+    },
+};
+use anyhow::{anyhow, bail, Result};
+
+const PADDING: u64 = 0x00;
+const PING: u64 = 0x01;
+const ACK: u64 = 0x02;
+const ACK_ECN: u64 = 0x03;
+const CRYPTO: u64 = 0x06;
+const NEW_TOKEN: u64 = 0x07;
+const CONNECTION_CLOSE: u64 = 0x1c;
+const CONNECTION_CLOSE_APP: u64 = 0x1d;
+const HANDSHAKE_DONE: u64 = 0x1e;
+
+// RFC 9000 14.1: a client pads datagrams carrying an Initial to at least 1200 bytes
+const MIN_INITIAL_DATAGRAM: usize = 1200;
+
+/**
+ * RFC 9000 16: the two high bits of the first byte give the length
+ */
+pub fn read_varint(reader: &mut Reader) -> Result<u64> {
+    let first = reader.read8()?;
+    let len = 1usize << (first >> 6);
+    let mut value = (first & 0x3f) as u64;
+    for _ in 1..len {
+        value = (value << 8) | reader.read8()? as u64;
+    }
+    Ok(value)
+}
+
+pub fn frame_type_mapper(frame_type: u64) -> &'static str {
+    match frame_type {
+        0x00 => "PADDING",
+        0x01 => "PING",
+        0x02 | 0x03 => "ACK",
+        0x04 => "RESET_STREAM",
+        0x05 => "STOP_SENDING",
+        0x06 => "CRYPTO",
+        0x07 => "NEW_TOKEN",
+        0x08..=0x0f => "STREAM",
+        0x10 => "MAX_DATA",
+        0x11 => "MAX_STREAM_DATA",
+        0x12 | 0x13 => "MAX_STREAMS",
+        0x14 => "DATA_BLOCKED",
+        0x15 => "STREAM_DATA_BLOCKED",
+        0x16 | 0x17 => "STREAMS_BLOCKED",
+        0x18 => "NEW_CONNECTION_ID",
+        0x19 => "RETIRE_CONNECTION_ID",
+        0x1a => "PATH_CHALLENGE",
+        0x1b => "PATH_RESPONSE",
+        0x1c | 0x1d => "CONNECTION_CLOSE",
+        0x1e => "HANDSHAKE_DONE",
+        0x30 | 0x31 => "DATAGRAM",
+        _ => "Unknown",
+    }
+}
+
+// positions relative to the start of the packet
+struct Layout {
+    packet_type: QuicPacketType,
+    version: u32,
+    dcid: Range<usize>,
+    scid: Range<usize>,
+    pn_offset: usize,
+    len: usize,
+}
+
+fn read_cid(reader: &mut Reader, start: usize) -> Result<Range<usize>> {
+    let len = reader.read8()? as usize;
+    if len > 20 {
+        bail!("connection id longer than 20 bytes");
+    }
+    let offset = reader.cursor - start;
+    reader.slice(len, true)?;
+    Ok(offset..offset + len)
+}
+
+/**
+ * header of the packet at the reader, a short header does not say how long its connection id is
+ */
+fn read_layout(reader: &Reader, short_dcid_len: usize) -> Result<Layout> {
+    let mut reader = reader.clone();
+    let start = reader.cursor;
+    let left = reader.left();
+    let first = reader.read8()?;
+    if first & 0x80 == 0 {
+        if first & 0x40 == 0 || left < 1 + short_dcid_len {
+            bail!(DataError::FormatMismatch);
+        }
+        return Ok(Layout {
+            packet_type: QuicPacketType::OneRtt,
+            version: 0,
+            dcid: 1..1 + short_dcid_len,
+            scid: 0..0,
+            pn_offset: 1 + short_dcid_len,
+            len: left,
+        });
+    }
+    let version = reader.read32(true)?;
+    let dcid = read_cid(&mut reader, start)?;
+    let scid = read_cid(&mut reader, start)?;
+    let packet_type = match version {
+        0 => QuicPacketType::VersionNegotiation,
+        _ => QuicPacketType::long(version, (first >> 4) & 0x03),
+    };
+    match packet_type {
+        QuicPacketType::VersionNegotiation | QuicPacketType::Retry => {
+            return Ok(Layout { packet_type, version, dcid, scid, pn_offset: reader.cursor - start, len: left });
+        }
+        QuicPacketType::Initial => {
+            let token_len = read_varint(&mut reader)? as usize;
+            reader.slice(token_len, true)?;
+        }
+        _ => {}
+    }
+    let length = read_varint(&mut reader)? as usize;
+    let pn_offset = reader.cursor - start;
+    if length > reader.left() {
+        bail!(DataError::BitSize);
+    }
+    Ok(Layout { packet_type, version, dcid, scid, pn_offset, len: pn_offset + length })
+}
+
+/**
+ * skips one frame of a decrypted payload, returns its type and the offset and data range of a CRYPTO frame
+ */
+fn read_frame(reader: &mut Reader) -> Result<(u64, Option<(usize, Range<usize>)>)> {
+    let frame_type = read_varint(reader)?;
+    match frame_type {
+        PADDING => {
+            while reader.left() > 0 && reader.next()? == 0 {
+                reader.forward(1);
+            }
+        }
+        PING | HANDSHAKE_DONE => {}
+        ACK | ACK_ECN => {
+            read_varint(reader)?;
+            read_varint(reader)?;
+            let count = read_varint(reader)?;
+            read_varint(reader)?;
+            for _ in 0..count * 2 {
+                read_varint(reader)?;
+            }
+            if frame_type == ACK_ECN {
+                for _ in 0..3 {
+                    read_varint(reader)?;
+                }
+            }
+        }
+        CRYPTO => {
+            let offset = read_varint(reader)? as usize;
+            let len = read_varint(reader)? as usize;
+            let start = reader.cursor;
+            reader.slice(len, true)?;
+            return Ok((frame_type, Some((offset, start..start + len))));
+        }
+        NEW_TOKEN => {
+            let len = read_varint(reader)? as usize;
+            reader.slice(len, true)?;
+        }
+        CONNECTION_CLOSE | CONNECTION_CLOSE_APP => {
+            read_varint(reader)?;
+            if frame_type == CONNECTION_CLOSE {
+                read_varint(reader)?;
+            }
+            let len = read_varint(reader)? as usize;
+            reader.slice(len, true)?;
+        }
+        _ => bail!("frame type {frame_type:#x} is not allowed in an Initial packet"),
+    }
+    Ok((frame_type, None))
+}
+
+fn read_frame_field(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    let frame_type = add_field_format_fn!(field, reader, read_varint(reader)?, |t| format!("Frame Type: {} ({t:#04x})", frame_type_mapper(t)));
+    field.summary = frame_type_mapper(frame_type).to_string();
+    match frame_type {
+        PADDING => {
+            let start = reader.cursor - 1;
+            while reader.left() > 0 && reader.next()? == 0 {
+                reader.forward(1);
+            }
+            field.summary = format!("PADDING Length: {}", reader.cursor - start);
+        }
+        ACK | ACK_ECN => {
+            let largest = add_field_format!(field, reader, read_varint(reader)?, "Largest Acknowledged: {}");
+            add_field_format!(field, reader, read_varint(reader)?, "ACK Delay: {}");
+            let count = add_field_format!(field, reader, read_varint(reader)?, "ACK Range Count: {}");
+            add_field_format!(field, reader, read_varint(reader)?, "First ACK Range: {}");
+            for _ in 0..count {
+                add_field_format!(field, reader, read_varint(reader)?, "Gap: {}");
+                add_field_format!(field, reader, read_varint(reader)?, "ACK Range: {}");
+            }
+            if frame_type == ACK_ECN {
+                add_field_format!(field, reader, read_varint(reader)?, "ECT(0) Count: {}");
+                add_field_format!(field, reader, read_varint(reader)?, "ECT(1) Count: {}");
+                add_field_format!(field, reader, read_varint(reader)?, "ECN-CE Count: {}");
+            }
+            field.summary = format!("ACK Largest Acknowledged: {largest}");
+        }
+        CRYPTO => {
+            let offset = add_field_format!(field, reader, read_varint(reader)?, "Offset: {}");
+            let len = add_field_format!(field, reader, read_varint(reader)?, "Length: {}") as usize;
+            add_field_forward!(field, reader, len, format!("Crypto Data ({len} bytes)"));
+            reader.slice(len, true)?;
+            field.summary = format!("CRYPTO Offset: {offset}, Length: {len}");
+        }
+        NEW_TOKEN => {
+            let len = add_field_format!(field, reader, read_varint(reader)?, "Token Length: {}") as usize;
+            add_field_forward!(field, reader, len, format!("Token ({len} bytes)"));
+            reader.slice(len, true)?;
+        }
+        CONNECTION_CLOSE | CONNECTION_CLOSE_APP => {
+            let code = add_field_format!(field, reader, read_varint(reader)?, "Error Code: {:#x}");
+            if frame_type == CONNECTION_CLOSE {
+                add_field_format_fn!(field, reader, read_varint(reader)?, |t| format!("Frame Type: {} ({t:#04x})", frame_type_mapper(t)));
+            }
+            let len = add_field_format!(field, reader, read_varint(reader)?, "Reason Phrase Length: {}") as usize;
+            let reason = add_field_format!(field, reader, reader.read_string(len)?, "Reason Phrase: {}");
+            field.summary = format!("CONNECTION_CLOSE Error Code: {code:#x}, Reason: {reason}");
+        }
+        PING | HANDSHAKE_DONE => {}
+        _ => {
+            add_field_rest_format!(field, reader, format!("Frame Data ({} bytes)", reader.left()));
+            reader.forward(reader.left());
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn endpoints(ctx: &Context, frame: &Frame) -> Option<(QuicEndpoint, QuicEndpoint)> {
+    match (frame_ips(ctx, frame), frame.ports) {
+        ((Some(source), Some(target)), Some((source_port, target_port))) => Some(((source, source_port), (target, target_port))),
+        _ => None,
+    }
+}
+
+fn short_dcid_len(ctx: &Context, endpoints: Option<(QuicEndpoint, QuicEndpoint)>, reader: &Reader) -> usize {
+    match (endpoints, reader.preview(reader.left())) {
+        (Some((source, target)), Ok(data)) if data.len() > 1 => ctx.quic.short_dcid_len(source, target, &data[1..]),
+        _ => 0,
+    }
+}
+
+// udp payload, without the link layer trailer
+pub(super) fn datagram_len(ctx: &Context, frame: &Frame, reader: &Reader) -> usize {
+    ctx.udp_payloads.get(&frame.info.index).map(|range| range.len()).unwrap_or(reader.left()).min(reader.left())
+}
+
+fn open_initial(ctx: &Context, quic: Option<QuicFrameRef>, index: FrameIndex, packet: &[u8], pn_offset: usize) -> Result<QuicPlain> {
+    let quic = quic.ok_or(anyhow!("no connection for the packet"))?;
+    let conn = ctx.quic.connection(quic.connection).ok_or(anyhow!("no connection for the packet"))?;
+    let keys = conn.initial_keys(index, !quic.from_client)?;
+    open_packet(&keys, packet, pn_offset)
+}
+
+fn read_packet_field(ctx: &Context, frame: &Frame, quic: Option<QuicFrameRef>, layout: &Layout, reader: &mut Reader, field: &mut Field, datasources: &mut Vec<DataSource>) -> Result<()> {
+    let packet = reader.preview(layout.len)?.to_vec();
+    let mut packet_reader = reader.slice_as_reader(layout.len)?;
+    let reader = &mut packet_reader;
+    let first = reader.read8()?;
+    let packet_type = layout.packet_type;
+    field.summary = format!("QUIC IETF {}", packet_type.name());
+    if first & 0x80 != 0 {
+        add_field_backstep!(field, reader, 1, "Header Form: Long Header (1)".into());
+        if packet_type != QuicPacketType::VersionNegotiation {
+            add_field_backstep!(field, reader, 1, format!("Packet Type: {} ({})", packet_type.name(), (first >> 4) & 0x03));
+        }
+        add_field_format_fn!(field, reader, reader.read32(true)?, |v| format!("Version: {} ({v:#010x})", quic_version(v)));
+        add_field_format!(field, reader, reader.read8()?, "Destination Connection ID Length: {}");
+        add_field_forward!(field, reader, layout.dcid.len(), format!("Destination Connection ID: {}", hex_id(&packet[layout.dcid.clone()])));
+        reader.forward(layout.dcid.len());
+        add_field_format!(field, reader, reader.read8()?, "Source Connection ID Length: {}");
+        add_field_forward!(field, reader, layout.scid.len(), format!("Source Connection ID: {}", hex_id(&packet[layout.scid.clone()])));
+        reader.forward(layout.scid.len());
+        match packet_type {
+            QuicPacketType::VersionNegotiation => {
+                while reader.left() >= 4 {
+                    add_field_format_fn!(field, reader, reader.read32(true)?, |v| format!("Supported Version: {} ({v:#010x})", quic_version(v)));
+                }
+                return Ok(());
+            }
+            QuicPacketType::Retry => {
+                let token_len = reader.left().saturating_sub(16);
+                add_field_forward!(field, reader, token_len, format!("Retry Token ({token_len} bytes)"));
+                reader.forward(token_len);
+                add_field_rest_format!(field, reader, "Retry Integrity Tag".into());
+                return Ok(());
+            }
+            QuicPacketType::Initial => {
+                let token_len = add_field_format!(field, reader, read_varint(reader)?, "Token Length: {}") as usize;
+                if token_len > 0 {
+                    add_field_forward!(field, reader, token_len, format!("Token ({token_len} bytes)"));
+                    reader.forward(token_len);
+                }
+            }
+            _ => {}
+        }
+        add_field_format!(field, reader, read_varint(reader)?, "Length: {}");
+    } else {
+        add_field_backstep!(field, reader, 1, "Header Form: Short Header (0)".into());
+        add_field_backstep!(field, reader, 1, format!("Spin Bit: {}", (first >> 5) & 0x01));
+        add_field_forward!(field, reader, layout.dcid.len(), format!("Destination Connection ID: {}", hex_id(&packet[layout.dcid.clone()])));
+        reader.forward(layout.dcid.len());
+    }
+    if packet_type != QuicPacketType::Initial {
+        add_field_rest_format!(field, reader, format!("Protected Payload ({} bytes)", reader.left()));
+        return Ok(());
+    }
+    match open_initial(ctx, quic, frame.info.index, &packet, layout.pn_offset) {
+        Ok(plain) => {
+            add_field_forward!(field, reader, plain.pn_len, format!("Packet Number: {}", plain.packet_number));
+            reader.forward(plain.pn_len);
+            let len = plain.payload.len();
+            let ds = DataSource::create(plain.payload, 0..0);
+            let mut payload = Reader::new(&ds);
+            let mut payload_field = Field::with_children(format!("Decrypted Payload ({len} bytes)"), 0, len);
+            payload_field.source = (datasources.len() + 1) as u8;
+            while payload.left() > 0 {
+                add_sub_field_with_reader!(payload_field, &mut payload, read_frame_field)?;
+            }
+            field.children.as_mut().unwrap().push(payload_field);
+            datasources.push(ds);
+            add_field_forward!(field, reader, len, format!("Encrypted Payload ({len} bytes)"));
+            reader.forward(len);
+            add_field_rest_format!(field, reader, "AEAD Tag".into());
+            field.summary = format!("QUIC IETF Initial, PKN: {}", plain.packet_number);
+        }
+        Err(e) => {
+            add_field_label_no_range!(field, format!("[Expert Info: Initial packet decryption failed: {e}]"));
+            add_field_rest_format!(field, reader, format!("Protected Payload ({} bytes)", reader.left()));
+        }
+    }
+    Ok(())
+}
+
+/**
+ * a long header with a version whose Initial keys are known, or a full size client Initial
+ */
+pub fn probe(reader: &Reader) -> Option<Confidence> {
+    let head = reader.preview(6).ok()?;
+    if head[0] & 0xc0 != 0xc0 {
+        return None;
+    }
+    let version = u32::from_be_bytes([head[1], head[2], head[3], head[4]]);
+    if !matches!(version, QUIC_V1 | QUIC_V2) || head[5] > 20 {
+        return None;
+    }
+    match QuicPacketType::long(version, (head[0] >> 4) & 0x03) == QuicPacketType::Initial && reader.left() >= MIN_INITIAL_DATAGRAM {
+        true => Some(Confidence::High),
+        false => Some(Confidence::Low),
+    }
+}
+
+pub struct Visitor;
+
+impl Visitor {
+    pub fn info(_: &Context, frame: &Frame) -> Option<String> {
+        if let ProtocolInfoField::Quic(packets) = &frame.protocol_field {
+            let list: Vec<String> = packets
+                .iter()
+                .map(|packet| {
+                    let mut parts = vec![packet.packet_type.name().to_string()];
+                    if let Some(pn) = packet.packet_number {
+                        parts.push(format!("PKN: {pn}"));
+                    }
+                    parts.extend(packet.frames.iter().map(|t| frame_type_mapper(*t).to_string()));
+                    parts.join(", ")
+                })
+                .collect();
+            return Some(list.join("; "));
+        }
+        None
+    }
+
+    /**
+     * walks the packets coalesced in the datagram, Initial packets are decrypted and their CRYPTO frames
+     * go to the connection so the TLS handshake can be read once complete
+     */
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+        let len = datagram_len(ctx, frame, reader);
+        let mut reader = reader.slice_as_reader(len)?;
+        let endpoints = endpoints(ctx, frame);
+        let index = frame.info.index;
+        let mut packets = vec![];
+        while reader.left() > 0 {
+            let Ok(layout) = read_layout(&reader, short_dcid_len(ctx, endpoints, &reader)) else {
+                break;
+            };
+            let packet = reader.slice(layout.len, true)?;
+            let mut info = QuicInfo { packet_type: layout.packet_type, packet_number: None, frames: vec![] };
+            let quic = match endpoints {
+                Some((source, target)) => ctx.quic.add(&QuicHeader {
+                    index,
+                    ts: frame.info.time,
+                    source,
+                    target,
+                    packet_type: layout.packet_type,
+                    version: layout.version,
+                    dcid: &packet[layout.dcid.clone()],
+                    scid: &packet[layout.scid.clone()],
+                }),
+                None => None,
+            };
+            if layout.packet_type == QuicPacketType::Initial {
+                if let Ok(plain) = open_initial(ctx, quic, index, packet, layout.pn_offset) {
+                    info.packet_number = Some(plain.packet_number);
+                    let ds = DataSource::create(plain.payload, 0..0);
+                    let mut payload = Reader::new(&ds);
+                    while payload.left() > 0 {
+                        let Ok((frame_type, crypto)) = read_frame(&mut payload) else {
+                            break;
+                        };
+                        if info.frames.last() != Some(&frame_type) {
+                            info.frames.push(frame_type);
+                        }
+                        if let (Some(quic), Some((offset, range))) = (quic, crypto) {
+                            ctx.quic.add_crypto(quic, index, offset, ds.slice(range)?);
+                        }
+                    }
+                }
+            }
+            packets.push(info);
+        }
+        if packets.is_empty() {
+            return Ok(Protocol::None);
+        }
+        frame.protocol_field = ProtocolInfoField::Quic(packets);
+        Ok(Protocol::None)
+    }
+
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader, datasources: &mut Vec<DataSource>) -> Result<Protocol> {
+        let len = datagram_len(ctx, frame, reader);
+        let mut reader = reader.slice_as_reader(len)?;
+        let endpoints = endpoints(ctx, frame);
+        let quic = ctx.quic.frame(frame.info.index);
+        while reader.left() > 0 {
+            let Ok(layout) = read_layout(&reader, short_dcid_len(ctx, endpoints, &reader)) else {
+                add_field_rest_format!(field, reader, format!("Malformed Packet ({} bytes)", reader.left()));
+                break;
+            };
+            add_sub_field_with_reader!(field, &mut reader, |r: &mut Reader, f: &mut Field| read_packet_field(ctx, frame, quic, &layout, r, f, datasources))?;
+        }
+        if let Some(conn) = quic.and_then(|q| ctx.quic.connection(q.connection)) {
+            add_field_label_no_range!(field, format!("[Connection index: {}]", conn.index));
+            // the handshake message is shown under the frame whose CRYPTO data completed it
+            if let Some(hello) = conn.hello_of(frame.info.index) {
+                let ds = DataSource::create(hello.to_vec(), 0..0);
+                let mut hello_reader = Reader::new(&ds);
+                let mut hello_field = Field::with_children(String::new(), 0, hello.len());
+                hello_field.source = (datasources.len() + 1) as u8;
+                parse_record_detail(HANDSHAKE, 0, &mut hello_reader, &mut hello_field)?;
+                hello_field.summary = format!("TLS {} (reassembled from CRYPTO frames, {} bytes)", hello_field.summary, hello.len());
+                field.children.as_mut().unwrap().push(hello_field);
+                datasources.push(ds);
+            }
+        }
+        field.summary = "QUIC IETF".into();
+        Ok(Protocol::None)
+    }
+}
@@ -0,0 +1,433 @@
+// Copyright (c) 2025 sankooc
+//
+// This file is part of the pcapview project.
+// Licensed under the MIT License - see https://opensource.org/licenses/MIT
+
+use crate::{
+    add_field_backstep, add_field_format, add_field_format_fn, add_field_label_no_range, add_field_rest_format, add_sub_field_with_reader,
+    common::{
+        checksum::{t_checksum32, verify_sctp, ChecksumStatus},
+        concept::Field,
+        core::Context,
+        enum_def::{Protocol, ProtocolInfoField},
+        io::Reader,
+        ipsec::frame_ips,
+        sctp::{SctpChunk, SctpPacket, SctpPayload, SCTP_ABORT, SCTP_DATA, SCTP_INIT, SCTP_INIT_ACK, SCTP_I_DATA, SCTP_SHUTDOWN, SCTP_SHUTDOWN_COMPLETE},
+        Frame,
+    },
+};
+use anyhow::Result;
+use std::cmp;
+
+const SCTP_SACK: u8 = 3;
+const SCTP_HEARTBEAT: u8 = 4;
+const SCTP_HEARTBEAT_ACK: u8 = 5;
+const SCTP_ERROR: u8 = 9;
+const SCTP_COOKIE_ECHO: u8 = 10;
+const SCTP_ECNE: u8 = 12;
+const SCTP_CWR: u8 = 13;
+const SCTP_FORWARD_TSN: u8 = 192;
+
+const PARAM_IPV4: u16 = 5;
+const PARAM_IPV6: u16 = 6;
+
+pub fn chunk_type_mapper(chunk_type: u8) -> &'static str {
+    match chunk_type {
+        SCTP_DATA => "DATA",
+        SCTP_INIT => "INIT",
+        SCTP_INIT_ACK => "INIT_ACK",
+        SCTP_SACK => "SACK",
+        SCTP_HEARTBEAT => "HEARTBEAT",
+        SCTP_HEARTBEAT_ACK => "HEARTBEAT_ACK",
+        SCTP_ABORT => "ABORT",
+        SCTP_SHUTDOWN => "SHUTDOWN",
+        8 => "SHUTDOWN_ACK",
+        SCTP_ERROR => "ERROR",
+        SCTP_COOKIE_ECHO => "COOKIE_ECHO",
+        11 => "COOKIE_ACK",
+        SCTP_ECNE => "ECNE",
+        SCTP_CWR => "CWR",
+        SCTP_SHUTDOWN_COMPLETE => "SHUTDOWN_COMPLETE",
+        15 => "AUTH",
+        16 => "NR-SACK",
+        SCTP_I_DATA => "I_DATA",
+        128 => "ASCONF_ACK",
+        130 => "RE_CONFIG",
+        132 => "PAD",
+        SCTP_FORWARD_TSN => "FORWARD_TSN",
+        193 => "ASCONF",
+        194 => "I_FORWARD_TSN",
+        _ => "Unknown",
+    }
+}
+
+/**
+ * payload protocol identifiers registered with iana
+ */
+pub fn ppid_mapper(ppid: u32) -> &'static str {
+    match ppid {
+        0 => "Not specified",
+        1 => "IUA",
+        2 => "M2UA",
+        3 => "M3UA",
+        4 => "SUA",
+        5 => "M2PA",
+        18 => "S1AP",
+        19 => "RUA",
+        20 => "HNBAP",
+        27 => "X2AP",
+        46 => "Diameter",
+        47 => "Diameter DTLS",
+        50 => "WebRTC Control",
+        51 => "WebRTC String",
+        53 => "WebRTC Binary",
+        60 => "NGAP",
+        61 => "XnAP",
+        62 => "F1AP",
+        _ => "Unknown",
+    }
+}
+
+fn parameter_type_mapper(param_type: u16) -> &'static str {
+    match param_type {
+        1 => "Heartbeat info",
+        PARAM_IPV4 => "IPv4 address",
+        PARAM_IPV6 => "IPv6 address",
+        7 => "State cookie",
+        8 => "Unrecognized parameter",
+        9 => "Cookie preservative",
+        11 => "Hostname address",
+        12 => "Supported address types",
+        0x8000 => "ECN",
+        0x8002 => "Random",
+        0x8003 => "Chunk list",
+        0x8004 => "Requested HMAC algorithm",
+        0x8005 => "Padding",
+        0x8008 => "Supported extensions",
+        0xc000 => "Forward TSN supported",
+        0xc001 => "Add IP address",
+        0xc002 => "Delete IP address",
+        0xc004 => "Set primary address",
+        0xc006 => "Adaptation layer indication",
+        _ => "Unknown",
+    }
+}
+
+fn cause_mapper(cause: u16) -> &'static str {
+    match cause {
+        1 => "Invalid stream identifier",
+        2 => "Missing mandatory parameter",
+        3 => "Stale cookie error",
+        4 => "Out of resource",
+        5 => "Unresolvable address",
+        6 => "Unrecognized chunk type",
+        7 => "Invalid mandatory parameter",
+        8 => "Unrecognized parameters",
+        9 => "No user data",
+        10 => "Cookie received while shutting down",
+        11 => "Restart of an association with new addresses",
+        12 => "User initiated abort",
+        13 => "Protocol violation",
+        _ => "Unknown",
+    }
+}
+
+// ip payload length, the reader may still hold ethernet padding
+fn sctp_len(frame: &Frame, reader: &Reader) -> usize {
+    match frame.iplen {
+        0 => reader.left(),
+        len => cmp::min(len as usize, reader.left()),
+    }
+}
+
+fn padding(len: usize) -> usize {
+    (4 - len % 4) % 4
+}
+
+// length of a well formed chunk at cursor
+fn chunk_len(reader: &Reader) -> Option<usize> {
+    let head = reader.preview(4).ok()?;
+    let len = u16::from_be_bytes([head[2], head[3]]) as usize;
+    (len >= 4 && len <= reader.left()).then_some(len)
+}
+
+// chunk at cursor, moves past its padding
+fn next_chunk<'a>(reader: &mut Reader<'a>) -> Result<Option<Reader<'a>>> {
+    let Some(len) = chunk_len(reader) else {
+        return Ok(None);
+    };
+    let chunk = reader.slice_as_reader(len)?;
+    reader.forward(cmp::min(padding(len), reader.left()));
+    Ok(Some(chunk))
+}
+
+fn read_chunk(reader: &mut Reader) -> Result<SctpChunk> {
+    let chunk_type = reader.read8()?;
+    let flags = reader.read8()?;
+    let _len = reader.read16(true)?;
+    let chunk = match chunk_type {
+        SCTP_INIT | SCTP_INIT_ACK => {
+            let tag = reader.read32(true)?;
+            let _a_rwnd = reader.read32(true)?;
+            let os = reader.read16(true)?;
+            let mis = reader.read16(true)?;
+            match chunk_type {
+                SCTP_INIT => SctpChunk::Init(tag, os, mis),
+                _ => SctpChunk::InitAck(tag, os, mis),
+            }
+        }
+        SCTP_DATA | SCTP_I_DATA => {
+            let tsn = reader.read32(true)?;
+            let stream = reader.read16(true)?;
+            let ppid = match chunk_type {
+                SCTP_DATA => {
+                    reader.read16(true)?;
+                    reader.read32(true)?
+                }
+                _ => {
+                    reader.forward(6);
+                    // later fragments carry the fragment sequence number instead
+                    let value = reader.read32(true)?;
+                    if flags & 0x02 != 0 { value } else { 0 }
+                }
+            };
+            SctpChunk::Data(SctpPayload { tsn, stream, ppid, range: reader.cursor..reader.cursor + reader.left() })
+        }
+        _ => SctpChunk::Other(chunk_type),
+    };
+    Ok(chunk)
+}
+
+fn read_parameters(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    while reader.left() >= 4 {
+        add_sub_field_with_reader!(field, reader, |r: &mut Reader, f: &mut Field| read_parameter(r, f))?;
+    }
+    Ok(())
+}
+
+fn read_parameter(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    let head = reader.preview(4)?;
+    let len = u16::from_be_bytes([head[2], head[3]]) as usize;
+    let mut param = reader.slice_as_reader(cmp::max(len, 4).min(reader.left()))?;
+    let param_type = add_field_format_fn!(field, param, param.read16(true)?, |t| format!("Parameter type: {} ({t:#06x})", parameter_type_mapper(t)));
+    add_field_format!(field, param, param.read16(true)?, "Parameter length: {}");
+    field.summary = format!("{} parameter", parameter_type_mapper(param_type));
+    match param_type {
+        PARAM_IPV4 if param.left() >= 4 => {
+            let ip = add_field_format!(field, param, param.read_ip4()?, "IP Version 4 address: {}");
+            field.summary = format!("IPv4 address parameter ({ip})");
+        }
+        PARAM_IPV6 if param.left() >= 16 => {
+            let ip = add_field_format!(field, param, param.read_ip6()?, "IP Version 6 address: {}");
+            field.summary = format!("IPv6 address parameter ({ip})");
+        }
+        _ => add_field_rest_format!(field, param, format!("Parameter value ({} bytes)", param.left())),
+    }
+    let pad = cmp::min(padding(len), reader.left());
+    if pad > 0 {
+        reader.forward(pad);
+        add_field_backstep!(field, reader, pad, format!("Parameter padding: {pad} bytes"));
+    }
+    Ok(())
+}
+
+fn read_causes(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    while reader.left() >= 4 {
+        let head = reader.preview(4)?;
+        let len = cmp::max(u16::from_be_bytes([head[2], head[3]]) as usize, 4).min(reader.left());
+        let mut cause = reader.slice_as_reader(len)?;
+        let code = cause.read16(true)?;
+        cause.forward(2);
+        add_field_backstep!(field, cause, 4, format!("Cause: {} ({code})", cause_mapper(code)));
+        add_field_rest_format!(field, cause, format!("Cause information ({} bytes)", cause.left()));
+        reader.forward(cmp::min(padding(len), reader.left()));
+    }
+    Ok(())
+}
+
+fn t_flag(flags: u8, bit: u8, name: &str) -> String {
+    let value = (flags >> bit) & 1;
+    let mut mask: Vec<char> = "........".chars().collect();
+    mask[7 - bit as usize] = if value == 1 { '1' } else { '0' };
+    let mask: String = mask.into_iter().collect();
+    format!("{} {} = {name}: {}", &mask[..4], &mask[4..], value == 1)
+}
+
+// single bit flags each chunk type defines
+fn chunk_flags(chunk_type: u8) -> &'static [(u8, &'static str)] {
+    match chunk_type {
+        SCTP_DATA | SCTP_I_DATA => &[(3, "I-Bit"), (2, "U-Bit"), (1, "B-Bit"), (0, "E-Bit")],
+        SCTP_ABORT | SCTP_SHUTDOWN_COMPLETE => &[(0, "T-Bit")],
+        _ => &[],
+    }
+}
+
+fn read_data_field(ctx: &Context, frame: &Frame, reader: &mut Reader, field: &mut Field, chunk_type: u8, flags: u8) -> Result<()> {
+    let tsn = add_field_format!(field, reader, reader.read32(true)?, "Transmission sequence number: {}");
+    let stream = add_field_format!(field, reader, reader.read16(true)?, "Stream identifier: {}");
+    let mut ppid = None;
+    if chunk_type == SCTP_DATA {
+        add_field_format!(field, reader, reader.read16(true)?, "Stream sequence number: {}");
+        ppid = Some(add_field_format_fn!(field, reader, reader.read32(true)?, |p| format!("Payload protocol identifier: {} ({p})", ppid_mapper(p))));
+    } else {
+        reader.forward(2);
+        add_field_format!(field, reader, reader.read32(true)?, "Message identifier: {}");
+        let value = reader.read32(true)?;
+        match flags & 0x02 != 0 {
+            true => {
+                add_field_backstep!(field, reader, 4, format!("Payload protocol identifier: {} ({value})", ppid_mapper(value)));
+                ppid = Some(value);
+            }
+            false => {
+                add_field_backstep!(field, reader, 4, format!("Fragment sequence number: {value}"));
+            }
+        }
+    }
+    let len = reader.left();
+    add_field_rest_format!(field, reader, format!("User data ({len} bytes)"));
+    if ctx.sctp.retransmitted(frame.info.index) > 0 && ctx.sctp.payloads(frame.info.index).iter().any(|p| p.tsn == tsn && p.stream == stream) {
+        add_field_label_no_range!(field, "[TSN seen before, retransmission]".into());
+    }
+    field.summary = match ppid {
+        Some(ppid) => format!("{} chunk (TSN: {tsn}, SID: {stream}, PPID: {}, payload length: {len} bytes)", chunk_type_mapper(chunk_type), ppid_mapper(ppid)),
+        None => format!("{} chunk (TSN: {tsn}, SID: {stream}, payload length: {len} bytes)", chunk_type_mapper(chunk_type)),
+    };
+    Ok(())
+}
+
+fn read_chunk_field(ctx: &Context, frame: &Frame, reader: &mut Reader, field: &mut Field) -> Result<()> {
+    let Some(mut chunk) = next_chunk(reader)? else {
+        return Ok(());
+    };
+    let chunk_type = add_field_format_fn!(field, chunk, chunk.read8()?, |t| format!("Chunk type: {} ({t})", chunk_type_mapper(t)));
+    let flags = chunk.read8()?;
+    let mut flags_field = Field::with_children(format!("Chunk flags: {flags:#04x}"), chunk.cursor - 1, 1);
+    flags_field.source = field.source;
+    for (bit, name) in chunk_flags(chunk_type) {
+        add_field_backstep!(flags_field, chunk, 1, t_flag(flags, *bit, name));
+    }
+    field.children.as_mut().unwrap().push(flags_field);
+    add_field_format!(field, chunk, chunk.read16(true)?, "Chunk length: {}");
+    field.summary = format!("{} chunk", chunk_type_mapper(chunk_type));
+    match chunk_type {
+        SCTP_DATA | SCTP_I_DATA => read_data_field(ctx, frame, &mut chunk, field, chunk_type, flags)?,
+        SCTP_INIT | SCTP_INIT_ACK => {
+            let tag = add_field_format!(field, chunk, chunk.read32(true)?, "Initiate tag: {:#010x}");
+            add_field_format!(field, chunk, chunk.read32(true)?, "Advertised receiver window credit (a_rwnd): {}");
+            let os = add_field_format!(field, chunk, chunk.read16(true)?, "Number of outbound streams: {}");
+            let mis = add_field_format!(field, chunk, chunk.read16(true)?, "Number of inbound streams: {}");
+            add_field_format!(field, chunk, chunk.read32(true)?, "Initial TSN: {}");
+            read_parameters(&mut chunk, field)?;
+            field.summary = format!("{} chunk (Outbound streams: {os}, inbound streams: {mis}, tag: {tag:#010x})", chunk_type_mapper(chunk_type));
+        }
+        SCTP_SACK => {
+            let ack = add_field_format!(field, chunk, chunk.read32(true)?, "Cumulative TSN ACK: {}");
+            let a_rwnd = add_field_format!(field, chunk, chunk.read32(true)?, "Advertised receiver window credit (a_rwnd): {}");
+            let gaps = add_field_format!(field, chunk, chunk.read16(true)?, "Number of gap acknowledgement blocks: {}");
+            let dups = add_field_format!(field, chunk, chunk.read16(true)?, "Number of duplicated TSNs: {}");
+            for _ in 0..gaps {
+                let start = chunk.read16(true)?;
+                let end = chunk.read16(true)?;
+                add_field_backstep!(field, chunk, 4, format!("Gap acknowledgement block: {start}-{end} (TSN {}-{})", ack.wrapping_add(start as u32), ack.wrapping_add(end as u32)));
+            }
+            for _ in 0..dups {
+                add_field_format!(field, chunk, chunk.read32(true)?, "Duplicate TSN: {}");
+            }
+            field.summary = format!("SACK chunk (Cumulative TSN: {ack}, a_rwnd: {a_rwnd}, gaps: {gaps}, duplicate TSNs: {dups})");
+        }
+        SCTP_HEARTBEAT | SCTP_HEARTBEAT_ACK => read_parameters(&mut chunk, field)?,
+        SCTP_ABORT | SCTP_ERROR => read_causes(&mut chunk, field)?,
+        SCTP_SHUTDOWN => {
+            let ack = add_field_format!(field, chunk, chunk.read32(true)?, "Cumulative TSN Ack: {}");
+            field.summary = format!("SHUTDOWN chunk (Cumulative TSN ack: {ack})");
+        }
+        SCTP_COOKIE_ECHO => add_field_rest_format!(field, chunk, format!("Cookie ({} bytes)", chunk.left())),
+        SCTP_ECNE | SCTP_CWR => {
+            add_field_format!(field, chunk, chunk.read32(true)?, "Lowest TSN: {}");
+        }
+        SCTP_FORWARD_TSN => {
+            add_field_format!(field, chunk, chunk.read32(true)?, "New cumulative TSN: {}");
+            while chunk.left() >= 4 {
+                let stream = chunk.read16(true)?;
+                let ssn = chunk.read16(true)?;
+                add_field_backstep!(field, chunk, 4, format!("Stream identifier: {stream}, stream sequence number: {ssn}"));
+            }
+        }
+        _ => add_field_rest_format!(field, chunk, format!("Chunk value ({} bytes)", chunk.left())),
+    }
+    let pad = reader.cursor - chunk.range.end;
+    if pad > 0 {
+        add_field_backstep!(field, reader, pad, format!("Chunk padding: {pad} bytes"));
+    }
+    Ok(())
+}
+
+pub struct Visitor;
+
+impl Visitor {
+    pub fn info(_: &Context, frame: &Frame) -> Option<String> {
+        if let ProtocolInfoField::Sctp(chunks) = &frame.protocol_field {
+            let names: Vec<&str> = chunks.iter().map(|t| chunk_type_mapper(*t)).collect();
+            return Some(names.join(" "));
+        }
+        None
+    }
+
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+        let len = sctp_len(frame, reader);
+        if len < 12 {
+            return Ok(Protocol::None);
+        }
+        if ctx.config.verify_checksum && verify_sctp(reader, len).is_bad() {
+            frame.mark_error("Bad SCTP checksum");
+        }
+        let mut reader = reader.slice_as_reader(len)?;
+        let source_port = reader.read16(true)?;
+        let target_port = reader.read16(true)?;
+        reader.forward(8); // verification tag, checksum
+        frame.ports = Some((source_port, target_port));
+        let mut types = vec![];
+        let mut chunks = vec![];
+        while let Some(mut chunk) = next_chunk(&mut reader)? {
+            types.push(chunk.next()?);
+            chunks.push(read_chunk(&mut chunk)?);
+        }
+        frame.protocol_field = ProtocolInfoField::Sctp(types);
+        if let (Some(source), Some(target)) = frame_ips(ctx, frame) {
+            ctx.sctp.add(SctpPacket {
+                index: frame.info.index,
+                ts: frame.info.time,
+                source: (source, source_port),
+                target: (target, target_port),
+                chunks,
+            });
+        }
+        Ok(Protocol::None)
+    }
+
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
+        let len = sctp_len(frame, reader);
+        let status = match ctx.config.verify_checksum {
+            true => verify_sctp(reader, len),
+            false => ChecksumStatus::Unverified,
+        };
+        let mut reader = reader.slice_as_reader(len)?;
+        let source_port = add_field_format!(field, reader, reader.read16(true)?, "Source port: {}");
+        let target_port = add_field_format!(field, reader, reader.read16(true)?, "Destination port: {}");
+        add_field_format!(field, reader, reader.read32(true)?, "Verification tag: {:#010x}");
+        let checksum = reader.read32(true)?;
+        add_field_backstep!(field, reader, 4, t_checksum32("Checksum", checksum, status));
+        if let Some(association) = ctx.sctp.association_of(frame.info.index) {
+            add_field_label_no_range!(field, format!("[Association index: {}]", association.index));
+        }
+        while reader.left() > 0 {
+            if chunk_len(&reader).is_none() {
+                add_field_rest_format!(field, reader, format!("Malformed chunk ({} bytes)", reader.left()));
+                break;
+            }
+            add_sub_field_with_reader!(field, &mut reader, |r: &mut Reader, f: &mut Field| read_chunk_field(ctx, frame, r, f))?;
+        }
+        field.summary = format!("Stream Control Transmission Protocol, Src Port: {source_port}, Dst Port: {target_port}");
+        Ok(Protocol::None)
+    }
+}
@@ -6,17 +6,22 @@
 use crate::{
     add_field_backstep, add_field_format, add_field_forward, add_field_label_no_range, add_sub_field_with_reader,
     common::{
+        checksum::{t_checksum, verify_transport, ChecksumStatus, IP_PROTO_TCP},
         concept::Field,
-        connection::{TCPStat, TcpFlagField},
+        connection::{HeldSegment, TCPStat, TcpFlagField},
         core::Context,
-        enum_def::{PacketStatus, Protocol, TCPDetail},
-        io::Reader,
+        enum_def::{PacketStatus, Protocol, SegmentStatus, TCPDetail, TCPFLAG},
+        io::{DataSource, Reader},
+        ipsec::frame_ips,
+        mptcp::MptcpSegment,
         util::{read_bit, read_bits},
         Frame,
     },
     constants::{ip_protocol_type_mapper, tcp_option_kind_mapper},
+    protocol::{parse, transport::mptcp},
 };
 use anyhow::Result;
+use std::cmp;
 
 fn read_tcp_flag(reader: &mut Reader, field: &mut Field) -> Result<TcpFlagField> {
     let flag_bit = reader.read16(true)?;
@@ -126,7 +131,14 @@ fn read_tcp_option(reader: &mut Reader, field: &mut Field) -> Result<u8> {
                 reader.forward((len - 2) as usize);
             }
         }
-        29 | 30 => {
+        mptcp::TCP_OPTION_MPTCP => {
+            let len = add_field_format!(field, reader, reader.read8()?, "Length {}");
+            if len > 2 {
+                let mut option = reader.slice_as_reader((len - 2) as usize)?;
+                mptcp::read_option_field(&mut option, field, len)?;
+            }
+        }
+        29 => {
             let len = add_field_format!(field, reader, reader.read8()?, "Length {}");
             if len > 2 {
                 reader.forward((len - 2) as usize);
@@ -138,6 +150,75 @@ fn read_tcp_option(reader: &mut Reader, field: &mut Field) -> Result<u8> {
     Ok(kind)
 }
 
+// the shift of the window scale option, read without building fields
+fn window_scale_option(reader: &mut Reader) -> Option<u8> {
+    while reader.left() > 0 {
+        let kind = reader.read8().ok()?;
+        match kind {
+            0 => return None,
+            1 => continue,
+            _ => {}
+        }
+        let len = reader.read8().ok()?;
+        if len < 2 {
+            return None;
+        }
+        if kind == 3 && len == 3 {
+            return reader.read8().ok();
+        }
+        if !reader.forward((len - 2) as usize) {
+            return None;
+        }
+    }
+    None
+}
+
+/**
+ * passes the payload of released out of order segments to the upper layer, in the frames they arrived with
+ */
+pub fn replay(ctx: &mut Context, segments: Vec<HeldSegment>) {
+    for segment in segments {
+        let index = segment.index as usize;
+        let Some(slot) = ctx.list.get_mut(index).filter(|f| f.info.index == segment.index) else {
+            continue;
+        };
+        let mut frame = std::mem::take(slot);
+        if let Some(info) = frame.tcp_info.as_mut() {
+            info.next_protocol = segment.protocol;
+            info.len = segment.data.len() as u16;
+            if segment.gap {
+                info.status = TCPDetail::NOPREVCAPTURE;
+            }
+        }
+        if segment.gap {
+            frame.info.status = PacketStatus::ERROR;
+            // the upper layer can not continue across missing bytes
+            if let Some((_, endpoint)) = ctx.connection(&frame) {
+                endpoint.segment_status = SegmentStatus::Init;
+            }
+        }
+        let ds = DataSource::create(segment.data, segment.range);
+        let mut reader = Reader::new(&ds);
+        let mut next = segment.protocol;
+        while !matches!(next, Protocol::None) {
+            frame.tail = next;
+            match parse(next, ctx, &mut frame, &mut reader) {
+                Ok(_next) => next = _next,
+                Err(_) => break,
+            }
+        }
+        ctx.list[index] = frame;
+    }
+}
+
+/**
+ * the input is complete, out of order data still waiting for its gap is passed on with the gap reported
+ */
+pub fn flush(ctx: &mut Context) {
+    let segments = ctx.flush_held();
+    replay(ctx, segments);
+}
+
 pub struct Visitor;
 pub fn t_protocol(protocol_type: u8) -> String {
     format!("Protocol: {} ({:#06x})", ip_protocol_type_mapper(protocol_type as u16), protocol_type)
@@ -149,19 +230,28 @@ impl Visitor {
     pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
         let index = frame.info.index;
         let start = reader.left();
+        if ctx.config.verify_checksum && verify_transport(ctx, frame, reader, IP_PROTO_TCP, frame.iplen as usize, 16).is_bad() {
+            frame.mark_error("Bad TCP checksum");
+        }
         let source_port = reader.read16(true)?;
         let target_port = reader.read16(true)?;
         let sequence = reader.read32(true)?;
         let ack = reader.read32(true)?;
         let flag_bit = reader.read16(true)?;
         let state = TcpFlagField::from(flag_bit);
-        let _window = reader.read16(true)?;
+        let (syn, ack_flag) = (state.contain(TCPFLAG::SYNC), state.contain(TCPFLAG::ACK));
+        let window = reader.read16(true)?;
         let crc = reader.read16(true)?;
         let _urgent = reader.read16(true)?;
         let len = state.head_len();
+        let mut window_scale = None;
+        let mut mptcp_options = vec![];
         if len > 5 {
             let skip = (len - 5) * 4;
-            reader.forward(skip as usize);
+            if let Ok(options) = reader.slice_as_reader(skip as usize) {
+                window_scale = window_scale_option(&mut options.clone());
+                mptcp_options = mptcp::options(&mut options.clone());
+            }
         }
         let mut left_size = reader.left();
         let iplen = frame.iplen as usize;
@@ -170,16 +260,34 @@ impl Visitor {
         }
         let ds = reader.ds();
         let range = reader.cursor..reader.cursor + left_size;
-        let tcp_state = TCPStat::new(index, sequence, ack, crc, state, left_size as u16);
+        let tcp_state = TCPStat::new(index, frame.info.time, sequence, ack, crc, state, left_size as u16).with_window(window, window_scale);
 
         frame.add_proto(crate::common::ProtoMask::TCP);
         if let Ok(mut tcp_info) = ctx.get_connect(frame, source_port, target_port, tcp_state, ds, range) {
             tcp_info.flag_bit = flag_bit;
-            frame.info.status = match &tcp_info.status {
-                TCPDetail::NEXT | TCPDetail::KEEPALIVE => PacketStatus::NORNAL,
-                _ => PacketStatus::ERROR,
-            };
+            if !matches!(&tcp_info.status, TCPDetail::NEXT | TCPDetail::KEEPALIVE | TCPDetail::OUTOFORDER | TCPDetail::ZEROWINDOWPROBE) {
+                frame.info.status = PacketStatus::ERROR;
+            }
+            let flushed = std::mem::take(&mut ctx.tcp_flushed);
+            replay(ctx, flushed);
             frame.ports = Some((source_port, target_port));
+            if let Some(irtt) = tcp_info.irtt {
+                frame.info.irtt = cmp::min(irtt / 1000, u16::MAX as u64) as u16;
+            }
+            if !mptcp_options.is_empty() || !ctx.mptcp.is_empty() {
+                if let (Some(source), Some(target)) = frame_ips(ctx, frame) {
+                    ctx.mptcp.add(&MptcpSegment {
+                        index,
+                        ts: frame.info.time,
+                        source: (source, source_port),
+                        target: (target, target_port),
+                        syn,
+                        ack: ack_flag,
+                        len: tcp_info.len as usize,
+                        options: &mptcp_options,
+                    });
+                }
+            }
             let mut next = tcp_info.next_protocol;
 
             if tcp_info.len == 0 {
@@ -191,8 +299,12 @@ impl Visitor {
         }
         Ok(Protocol::None)
     }
-    pub fn detail(field: &mut Field, _: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
         // let mut list = Vec::new();
+        let status = match ctx.config.verify_checksum {
+            true => verify_transport(ctx, frame, reader, IP_PROTO_TCP, frame.iplen as usize, 16),
+            false => ChecksumStatus::Unverified,
+        };
 
         let source_port = add_field_format!(field, reader, reader.read16(true)?, "Source Port: {}");
         let target_port = add_field_format!(field, reader, reader.read16(true)?, "Destination Port: {}");
@@ -211,7 +323,8 @@ impl Visitor {
         let state = add_sub_field_with_reader!(field, reader, read_tcp_flag)?;
         // let state = add_field_format!(field, reader, TcpFlagField::from(reader.read16(true)?), "{}");
         add_field_format!(field, reader, reader.read16(true)?, "Window: {}");
-        add_field_format!(field, reader, reader.read16(true)?, "Checksum: {:#06x} [unverified]");
+        let checksum = reader.read16(true)?;
+        add_field_backstep!(field, reader, 2, t_checksum("Checksum", checksum, status));
         add_field_format!(field, reader, reader.read16(true)?, "Urgent Pointer: {}");
         let len = state.head_len();
         if len > 5 {
@@ -223,6 +336,22 @@ impl Visitor {
         let payload_len = info.len as usize;
 
         add_field_forward!(field, reader, payload_len, format!("TCP payload ({} bytes)", payload_len));
+        if let TCPDetail::NOPREVCAPTURE = info.status {
+            add_field_label_no_range!(field, "[TCP Previous segment not captured]".into());
+        }
+        for expert in &info.experts {
+            add_field_label_no_range!(field, format!("[{expert}]"));
+        }
+        if let Some(mptcp) = ctx.mptcp.frame(frame.info.index) {
+            add_field_label_no_range!(field, format!("[MPTCP connection index: {}, subflow: {}]", mptcp.connection, mptcp.subflow));
+            if let Some(dsn) = mptcp.dsn {
+                add_field_label_no_range!(field, format!("[MPTCP relative data sequence number: {dsn}]"));
+            }
+        }
+        add_field_label_no_range!(field, format!("[Calculated window size: {}]", info.window));
+        if info.in_flight > 0 {
+            add_field_label_no_range!(field, format!("[Bytes in flight: {}]", info.in_flight));
+        }
 
         field.summary = format!("Transmission Control Protocol, Src Port: {}, Dst Port: {}, Len: {}", source_port, target_port, info.len);
         // field.children = Some(list);
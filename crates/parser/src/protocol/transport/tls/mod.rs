@@ -10,7 +10,7 @@ use crate::common::enum_def::{ProtocolInfoField, SegmentStatus};
 use crate::common::io::DataSource;
 use crate::common::{enum_def::Protocol, io::Reader, Frame};
 use crate::common::{NString, ResourceLoader};
-use crate::{add_field_format, add_field_format_fn};
+use crate::{add_field_format, add_field_format_fn, protocol::heuristic::Confidence};
 use anyhow::Result;
 use record::parse_record_detail;
 mod extension;
@@ -187,7 +187,7 @@ impl Visitor {
     }
 }
 
-fn field_tls_type(content_type: u8) -> String {
+pub fn field_tls_type(content_type: u8) -> String {
     format!("Content Type: {} ({})", tls_type(content_type), content_type)
 }
 
@@ -196,13 +196,19 @@ pub fn field_tls_version(val: u16) -> String {
 }
 
 pub fn tls_version_map(val: u16) -> Option<NString> {
-    let v = match val & 0x00ff {
-        0x00 => "SSLv3",
-        0x01 => "TLSv1.0",
-        0x02 => "TLSv1.1",
-        0x03 => "TLSv1.2",
-        0x04 => "TLSv1.3",
-        _ => return None,
+    let v = match val {
+        0xfeff => "DTLSv1.0",
+        0xfefd => "DTLSv1.2",
+        0xfefc => "DTLSv1.3",
+        _ if record::is_dtls(val) => return None,
+        _ => match val & 0x00ff {
+            0x00 => "SSLv3",
+            0x01 => "TLSv1.0",
+            0x02 => "TLSv1.1",
+            0x03 => "TLSv1.2",
+            0x04 => "TLSv1.3",
+            _ => return None,
+        },
     };
     Some(v)
 }
@@ -279,6 +285,17 @@ pub fn detect(reader: &Reader) -> bool {
     }
 }
 
+// a handshake record is how every session starts
+pub fn probe(reader: &Reader) -> Option<Confidence> {
+    if !detect(reader) {
+        return None;
+    }
+    match reader.preview(1).ok()?[0] {
+        22 => Some(Confidence::High),
+        _ => Some(Confidence::Low),
+    }
+}
+
 fn check_sni(sni_option: &mut TLSFlag, segment: &TlsData) {
     if segment.content_type == 22 {
         if let Some(sub_type) = segment.sub_type {
@@ -13,15 +13,16 @@ use anyhow::{Result};
 
 // TLS Record Content Types
 const CHANGE_CIPHER_SPEC: u8 = 20;
-// const ALERT: u8 = 21;
-const HANDSHAKE: u8 = 22;
+const ALERT: u8 = 21;
+pub const HANDSHAKE: u8 = 22;
 // const APPLICATION_DATA: u8 = 23;
 const HEARTBEAT: u8 = 24;
 
 // TLS Handshake Types
 // const HELLO_REQUEST: u8 = 0;
-const CLIENT_HELLO: u8 = 1;
+pub const CLIENT_HELLO: u8 = 1;
 const SERVER_HELLO: u8 = 2;
+pub const HELLO_VERIFY_REQUEST: u8 = 3;
 const NEW_SESSION_TICKET: u8 = 4;
 const CERTIFICATE: u8 = 11;
 // const SERVER_KEY_EXCHANGE: u8 = 12;
@@ -94,11 +95,23 @@ fn field_compression_list(len: u8, reader: &mut Reader, field: &mut Field) -> Re
     Ok(())
 }
 
+fn field_cookie_str(data: &[u8]) -> String {
+    format!("Cookie: {}", bytes_to_hex_limit(data, 32))
+}
+
+/**
+ * DTLS record versions are the one's complement of the TLS ones, 0xfeff is DTLS 1.0
+ */
+pub fn is_dtls(version: u16) -> bool {
+    version >> 8 == 0xfe
+}
+
 // Parse TLS record based on content type
-pub fn parse_record_detail(content_type: u8, _version: u16, reader: &mut Reader, field: &mut Field) -> Result<()> {
+pub fn parse_record_detail(content_type: u8, version: u16, reader: &mut Reader, field: &mut Field) -> Result<()> {
     match content_type {
         CHANGE_CIPHER_SPEC => parse_change_cipher_spec(reader, field),
-        HANDSHAKE => parse_handshake(reader, field)?,
+        ALERT => parse_alert(reader, field),
+        HANDSHAKE => parse_handshake(reader, field, is_dtls(version))?,
         HEARTBEAT => parse_heartbeat(reader, field),
         _ => {
             field.summary = "Application Data".to_string();
@@ -111,6 +124,49 @@ fn parse_change_cipher_spec(_reader: &mut Reader, field: &mut Field) {
     field.summary = "Change Cipher Spec".to_string();
 }
 
+fn alert_description(code: u8) -> &'static str {
+    match code {
+        0 => "Close Notify",
+        10 => "Unexpected Message",
+        20 => "Bad Record MAC",
+        22 => "Record Overflow",
+        40 => "Handshake Failure",
+        42 => "Bad Certificate",
+        45 => "Certificate Expired",
+        46 => "Certificate Unknown",
+        47 => "Illegal Parameter",
+        48 => "Unknown CA",
+        50 => "Decode Error",
+        51 => "Decrypt Error",
+        70 => "Protocol Version",
+        80 => "Internal Error",
+        90 => "User Canceled",
+        109 => "Missing Extension",
+        110 => "Unsupported Extension",
+        112 => "Unrecognized Name",
+        120 => "No Application Protocol",
+        _ => "Unknown",
+    }
+}
+
+// an alert longer than level and description is encrypted
+fn parse_alert(reader: &mut Reader, field: &mut Field) {
+    if reader.left() != 2 {
+        field.summary = "Encrypted Alert".to_string();
+        return;
+    }
+    let level = match reader.read8().unwrap_or_default() {
+        1 => "Warning",
+        2 => "Fatal",
+        _ => "Unknown",
+    };
+    add_field_backstep!(field, reader, 1, format!("Level: {level}"));
+    let code = reader.read8().unwrap_or_default();
+    let description = alert_description(code);
+    add_field_backstep!(field, reader, 1, format!("Description: {description} ({code})"));
+    field.summary = format!("Alert: {level}, {description}");
+}
+
 
 pub fn read24(reader: &mut Reader) -> Result<u32>{
     if reader.left() >= 3 {
@@ -122,8 +178,8 @@ pub fn read24(reader: &mut Reader) -> Result<u32>{
     }
     Ok(0)
 }
-// Parse Handshake record
-fn parse_handshake(reader: &mut Reader, field: &mut Field) -> Result<()> {
+// Parse Handshake record, a dtls message header also carries the message sequence and the fragment it holds
+fn parse_handshake(reader: &mut Reader, field: &mut Field, dtls: bool) -> Result<()> {
     field.summary = "Handshake".to_string();
     //tls_hs_message_type_mapper
     if reader.left() >= 4 {
@@ -139,13 +195,28 @@ fn parse_handshake(reader: &mut Reader, field: &mut Field) -> Result<()> {
         // Read length (3 bytes)
         let length = read24(reader)?;
         add_field_backstep!(field, reader, 3, format!("Length: {}", length));
+        if dtls && reader.left() >= 8 {
+            add_field_format!(field, reader, reader.read16(true)?, "Message Sequence: {}");
+            let offset = read24(reader)?;
+            add_field_backstep!(field, reader, 3, format!("Fragment Offset: {}", offset));
+            let fragment_len = read24(reader)?;
+            add_field_backstep!(field, reader, 3, format!("Fragment Length: {}", fragment_len));
+            if offset != 0 || fragment_len != length {
+                let len = (fragment_len as usize).min(reader.left());
+                reader.forward(len);
+                add_field_backstep!(field, reader, len, format!("Fragment Data ({len} bytes)"));
+                field.summary = format!("Handshake: {type_desc} (Fragment)");
+                return Ok(());
+            }
+        }
 
         // Parse specific handshake message based on type
         if reader.left() >= length as usize {
             let mut _reader = reader.slice_as_reader(length as usize)?;
             match msg_type {
-                CLIENT_HELLO => parse_client_hello(&mut _reader, field)?,
+                CLIENT_HELLO => parse_client_hello(&mut _reader, field, dtls)?,
                 SERVER_HELLO => parse_server_hello(&mut _reader, field)?,
+                HELLO_VERIFY_REQUEST => parse_hello_verify_request(&mut _reader, field)?,
                 CERTIFICATE => parse_certificates(&mut _reader, field)?,
                 NEW_SESSION_TICKET => add_sub_field_with_reader!(field, &mut _reader, parse_new_session_ticket)?,
                 // Other handshake types could be implemented here
@@ -186,8 +257,8 @@ fn parse_heartbeat(reader: &mut Reader, field: &mut Field) {
     }
 }
 
-// Parse ClientHello message
-fn parse_client_hello(reader: &mut Reader, field: &mut Field) -> Result<()> {
+// Parse ClientHello message, the dtls one echoes the cookie of a HelloVerifyRequest after the session id
+fn parse_client_hello(reader: &mut Reader, field: &mut Field, dtls: bool) -> Result<()> {
     add_field_format_fn!(field, reader, reader.read16(true)?, field_tls_version);
 
     if reader.left() >= 32 {
@@ -200,6 +271,13 @@ fn parse_client_hello(reader: &mut Reader, field: &mut Field) -> Result<()> {
         add_field_format_fn_nors!(field, reader, reader.slice(session_id_len as usize, true)?, field_session_id_str);
     }
 
+    if dtls && reader.left() >= 1 {
+        let cookie_len = add_field_format!(field, reader, reader.read8()?, "Cookie Length: {}");
+        if cookie_len > 0 {
+            add_field_format_fn_nors!(field, reader, reader.slice(cookie_len as usize, true)?, field_cookie_str);
+        }
+    }
+
     // Parse cipher suites
     if reader.left() >= 2 {
         let cipher_suites_len = add_field_format!(field, reader, reader.read16(true)?, "Cipher Suites Length: {}");
@@ -250,6 +328,14 @@ pub fn parse_server_hello(reader: &mut Reader, field: &mut Field) -> Result<()>
 
     Ok(())
 }
+// Parse HelloVerifyRequest message, a dtls server asks the client to prove its address with the cookie
+fn parse_hello_verify_request(reader: &mut Reader, field: &mut Field) -> Result<()> {
+    add_field_format_fn!(field, reader, reader.read16(true)?, field_tls_version);
+    let cookie_len = add_field_format!(field, reader, reader.read8()?, "Cookie Length: {}");
+    add_field_format_fn_nors!(field, reader, reader.slice(cookie_len as usize, true)?, field_cookie_str);
+    Ok(())
+}
+
 pub fn _read_len(reader: &mut Reader) -> Result<usize> {
     let _next = reader.read8()?;
     let _len = match _next {
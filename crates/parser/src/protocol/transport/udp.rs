@@ -4,15 +4,21 @@
 // Licensed under the MIT License - see https://opensource.org/licenses/MIT
 
 use crate::{
-    add_field_format, common::{
+    add_field_backstep, add_field_format, common::{
+        checksum::{t_checksum, verify_transport, ChecksumStatus, IP_PROTO_UDP},
         concept::Field,
         core::Context,
+        core::UdpFlow,
+        stream::{StreamDirection, StreamSegment},
+        decode::{decode_as, Transport},
         enum_def::{Protocol, ProtocolInfoField},
         io::Reader,
+        ipsec::frame_ips,
         Frame,
-    }, constants::ip_protocol_type_mapper
+    }, constants::ip_protocol_type_mapper, protocol::heuristic::heuristic
 };
 use anyhow::Result;
+use std::cmp;
 
 pub struct Visitor;
 
@@ -26,6 +32,10 @@ fn detect_protocol(source_port: u16, target_port: u16) -> Protocol {
         (5353, _) | (_, 5353) => Protocol::MDNS,
         (137, _) | (_, 137) => Protocol::NBNS,
         (67, _) | (_, 67) | (68, _) | (_, 68) => Protocol::DHCP,
+        (1985, _) | (_, 1985) | (2029, _) | (_, 2029) => Protocol::HSRP,
+        (443, _) | (_, 443) => Protocol::QUIC,
+        // coap over dtls
+        (5684, _) | (_, 5684) => Protocol::DTLS,
         // (123, _) | (_, 123) => Protocol::None,
         // (161, _) | (_, 161) | (162, _) | (_, 162) => Protocol::None,
         // (514, _) | (_, 514) => Protocol::None,
@@ -35,6 +45,23 @@ fn detect_protocol(source_port: u16, target_port: u16) -> Protocol {
     }
 }
 
+pub fn flow_key(ctx: &Context, frame: &Frame, ports: (u16, u16)) -> UdpFlow {
+    let (source, target) = frame_ips(ctx, frame);
+    let (a, b) = ((source, ports.0), (target, ports.1));
+    match a <= b {
+        true => (a, b),
+        false => (b, a),
+    }
+}
+
+// decode as rules first, then the well known ports
+fn assigned(ctx: &Context, frame: &Frame, ports: (u16, u16)) -> Option<Protocol> {
+    decode_as(ctx, frame, Transport::UDP, ports).or(match detect_protocol(ports.0, ports.1) {
+        Protocol::None => None,
+        protocol => Some(protocol),
+    })
+}
+
 impl Visitor {
     pub fn info(_: &Context, frame: &Frame) -> Option<String> {
         if let Some(ports) = &frame.ports {
@@ -51,24 +78,66 @@ impl Visitor {
         None
     }
 
-    pub fn parse(_: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+    pub fn parse(ctx: &mut Context, frame: &mut Frame, reader: &mut Reader) -> Result<Protocol> {
+        if ctx.config.verify_checksum {
+            let length = reader.preview(6).map(|head| u16::from_be_bytes([head[4], head[5]])).unwrap_or(0);
+            if verify_transport(ctx, frame, reader, IP_PROTO_UDP, length as usize, 6).is_bad() {
+                frame.mark_error("Bad UDP checksum");
+            }
+        }
         let source_port = reader.read16(true)?;
         let target_port = reader.read16(true)?;
         let length = reader.read16(true)?;
         let _checksum = reader.read16(true)?;
         frame.ports = Some((source_port, target_port));
         frame.protocol_field = ProtocolInfoField::UDP(length);
-        let next_protocol = detect_protocol(source_port, target_port);
+        let payload_len = cmp::min(length.saturating_sub(8) as usize, reader.left());
+        let ports = (source_port, target_port);
+        let key = flow_key(ctx, frame, ports);
+        let source = (frame_ips(ctx, frame).0, source_port);
+        let (first, sender) = *ctx.udp_streams.entry(key).or_insert((frame.info.index, source));
+        if payload_len > 0 {
+            let range = reader.cursor..reader.cursor + payload_len;
+            ctx.udp_payloads.insert(frame.info.index, range.clone());
+            let direction = match sender == source {
+                true => StreamDirection::Forward,
+                false => StreamDirection::Backward,
+            };
+            ctx.udp_segments.entry(first).or_default().push(StreamSegment { index: frame.info.index, direction, range });
+        }
+        let next_protocol = match assigned(ctx, frame, ports) {
+            Some(protocol) => protocol,
+            None if payload_len > 0 => {
+                // the heuristics decide once per flow, on its first payload
+                match ctx.udp_flows.get(&key) {
+                    Some(protocol) => *protocol,
+                    None => {
+                        let payload = reader.clone().slice_as_reader(payload_len)?;
+                        let protocol = heuristic(Transport::UDP, &payload).unwrap_or_default();
+                        ctx.udp_flows.insert(key, protocol);
+                        protocol
+                    }
+                }
+            }
+            None => Protocol::None,
+        };
         frame.add_proto(crate::common::ProtoMask::UDP);
         Ok(next_protocol)
     }
 
-    pub fn detail(field: &mut Field, _: &Context, _: &Frame, reader: &mut Reader) -> Result<Protocol> {
+    pub fn detail(field: &mut Field, ctx: &Context, frame: &Frame, reader: &mut Reader) -> Result<Protocol> {
+        let mut status = ChecksumStatus::Unverified;
+        if ctx.config.verify_checksum {
+            let length = reader.preview(6).map(|head| u16::from_be_bytes([head[4], head[5]])).unwrap_or(0);
+            status = verify_transport(ctx, frame, reader, IP_PROTO_UDP, length as usize, 6);
+        }
         let source_port = add_field_format!(field, reader, reader.read16(true)?, "Source Port: {}");
         let target_port = add_field_format!(field, reader, reader.read16(true)?, "Destination Port: {}");
         add_field_format!(field, reader, reader.read16(true)?, "Length: {}");
-        add_field_format!(field, reader, reader.read16(true)?, "Checksum: {:#06x} [unverified]");
-        let next_protocol = detect_protocol(source_port, target_port);
+        let checksum = reader.read16(true)?;
+        add_field_backstep!(field, reader, 2, t_checksum("Checksum", checksum, status));
+        let ports = (source_port, target_port);
+        let next_protocol = assigned(ctx, frame, ports).unwrap_or_else(|| ctx.udp_flows.get(&flow_key(ctx, frame, ports)).copied().unwrap_or_default());
         field.summary = format!("User Datagram Protocol, Src Port: {source_port}, Dst Port: {target_port}");
         Ok(next_protocol)
     }
@@ -2,15 +2,16 @@
 pub mod tc;
 #[cfg(test)]
 mod unit {
-    use std::net::Ipv4Addr;
+    use std::net::{IpAddr, Ipv4Addr};
 
     use crate::tc::{build_reader, print_field};
     use anyhow::Result;
+    use util::core::LocalResource;
     use pcap::{
         common::{
-            concept::Field, core::Context, enum_def::{AddressField, Protocol, ProtocolInfoField}, io::{DataSource, Reader}, util::{date_str, get_binary_text, get_masked_value}, Frame
+            checksum::{self, ChecksumStatus}, concept::{CounterItem, Criteria, Field}, core::{Context, SegmentData}, dhcp, enum_def::{AddressField, PacketStatus, Protocol, ProtocolInfoField, TCPCloseReason, TCPConnectStatus, TCPDetail, TCPExpert}, arp, decode, export, fhrp, geoip::GeoIp, graph, io::{DataSource, Reader}, ipsec::{self, SequenceAnomaly}, ospf, quic, dtls, mptcp, hpack, sctp, stream::{self, StreamDirection}, util::{date_str, get_binary_text, get_masked_value}, Frame, Instance, ResourceLoader
         },
-        protocol::{self, transport::tls::record::{parse_certificates, parse_new_session_ticket, parse_server_hello}},
+        protocol::{self, heuristic, transport::tls::record::{parse_certificates, parse_new_session_ticket, parse_server_hello}},
     };
 
     fn init(name: &str) -> (DataSource, Context, Frame) {
@@ -143,6 +144,1429 @@ mod unit {
         Ok(())
     }
     #[test]
+    fn test_ipv4_options() -> Result<()> {
+        let (ds, mut cx, mut frame) = init("ipv4_options");
+        {
+            let mut reader = Reader::new(&ds);
+            let next = protocol::network::ip4::Visitor::parse(&mut cx, &mut frame, &mut reader)?;
+            assert!(matches!(next, Protocol::UDP));
+            assert_eq!(reader.cursor, 32);
+            // a warning, the frame itself is well formed
+            assert_eq!(frame.info.warning, Some("IPv4 source route option"));
+            assert!(matches!(frame.info.status, PacketStatus::NORNAL));
+        }
+        {
+            let mut reader = Reader::new(&ds);
+            let mut f = Field::children();
+            let next = protocol::network::ip4::Visitor::detail(&mut f, &cx, &frame, &mut reader)?;
+            assert!(matches!(next, Protocol::UDP));
+            print_field(1, &f);
+            let options = f.children.as_ref().unwrap().last().unwrap();
+            assert_eq!(options.summary, "Options: (12 bytes)");
+            let list = options.children.as_ref().unwrap();
+            assert_eq!(list.len(), 3);
+            assert_eq!(list[0].summary, "IP Option - Loose Source Route");
+            assert_eq!(list[1].summary, "IP Option - No-Operation (NOP)");
+            assert_eq!(list[2].summary, "IP Option - Router Alert (Router shall examine packet)");
+        }
+        // a bad checksum is reported next to the source route
+        let mut data = build_reader("ipv4_options");
+        data[8] ^= 0x01;
+        let len = data.len();
+        let broken = DataSource::create(data, 0..len);
+        let mut cx = Context::default();
+        cx.config.verify_checksum = true;
+        let mut frame = Frame::default();
+        protocol::network::ip4::Visitor::parse(&mut cx, &mut frame, &mut Reader::new(&broken))?;
+        assert!(matches!(frame.info.status, PacketStatus::ERROR));
+        assert_eq!(frame.info.reason, Some("Bad IPv4 header checksum"));
+        assert_eq!(frame.info.warning, Some("IPv4 source route option"));
+        Ok(())
+    }
+    #[test]
+    fn test_igmp_v3_report() -> Result<()> {
+        let (ds, mut cx, _) = init("igmp_v3_report");
+        cx.config.verify_checksum = true;
+        let host = Ipv4Addr::new(10, 0, 0, 2);
+        let mut frame = Frame { address_field: AddressField::IPv4(host, Ipv4Addr::new(224, 0, 0, 22)), ..Default::default() };
+        frame.info.time = 1_000_000;
+        {
+            let mut reader = Reader::new(&ds);
+            let next = protocol::network::igmp::Visitor::parse(&mut cx, &mut frame, &mut reader)?;
+            assert!(matches!(next, Protocol::None));
+            assert!(matches!(frame.info.status, PacketStatus::NORNAL));
+            assert_eq!(protocol::network::igmp::Visitor::info(&cx, &frame).unwrap(), "IGMPv3 Membership Report, 2 group record(s)");
+        }
+        {
+            let mut reader = Reader::new(&ds);
+            let mut f = Field::children();
+            protocol::network::igmp::Visitor::detail(&mut f, &cx, &frame, &mut reader)?;
+            print_field(1, &f);
+            let list = f.children.as_ref().unwrap();
+            assert_eq!(list[3].summary, "Checksum: 0xebef [correct]");
+            let records: Vec<&Field> = list.iter().filter(|f| f.summary.starts_with("Group Record")).collect();
+            assert_eq!(records.len(), 2);
+            assert_eq!(records[0].summary, "Group Record : 239.1.1.1  Change To Exclude Mode");
+            assert_eq!(records[1].summary, "Group Record : 239.2.2.2  Change To Include Mode");
+            assert_eq!(records[1].children.as_ref().unwrap()[4].summary, "Source Address: 10.0.0.5");
+        }
+
+        let (ds, _, _) = init("igmp_leave");
+        let mut frame = Frame { address_field: AddressField::IPv4(host, Ipv4Addr::new(224, 0, 0, 2)), ..Default::default() };
+        frame.info.time = 5_000_000;
+        let mut reader = Reader::new(&ds);
+        protocol::network::igmp::Visitor::parse(&mut cx, &mut frame, &mut reader)?;
+        assert_eq!(protocol::network::igmp::Visitor::info(&cx, &frame).unwrap(), "IGMPv2 Leave Group 239.1.1.1");
+
+        let report = cx.multicast.report();
+        assert_eq!(report.events.len(), 3);
+        assert_eq!(report.events[1].sources, vec![Ipv4Addr::new(10, 0, 0, 5)]);
+        assert_eq!(report.memberships.len(), 2);
+        let first = report.memberships.iter().find(|m| m.group == Ipv4Addr::new(239, 1, 1, 1)).unwrap();
+        assert_eq!((first.host, first.join, first.leave), (host, 1_000_000, Some(5_000_000)));
+        let second = report.memberships.iter().find(|m| m.group == Ipv4Addr::new(239, 2, 2, 2)).unwrap();
+        assert!(second.leave.is_none());
+        Ok(())
+    }
+    #[test]
+    fn test_igmp_querier_election() -> Result<()> {
+        let (ds, mut cx, _) = init("igmp_v3_query");
+        let query = |cx: &mut Context, source: Ipv4Addr, time: u64| -> Result<()> {
+            let mut frame = Frame { address_field: AddressField::IPv4(source, Ipv4Addr::new(224, 0, 0, 1)), ..Default::default() };
+            frame.info.time = time;
+            let mut reader = Reader::new(&ds);
+            protocol::network::igmp::Visitor::parse(cx, &mut frame, &mut reader)?;
+            assert_eq!(protocol::network::igmp::Visitor::info(cx, &frame).unwrap(), "IGMPv3 Membership Query, general");
+            Ok(())
+        };
+        let high = Ipv4Addr::new(10, 0, 0, 9);
+        let low = Ipv4Addr::new(10, 0, 0, 1);
+        query(&mut cx, high, 0)?;
+        query(&mut cx, low, 10_000_000)?;
+        // higher address does not take over while the querier is alive
+        query(&mut cx, high, 100_000_000)?;
+        assert_eq!(cx.multicast.querier(), Some(low));
+        // qrv 2 * qqi 125s + max resp 10s / 2
+        query(&mut cx, high, 266_000_000)?;
+        let elections = cx.multicast.report().elections;
+        let reasons: Vec<&str> = elections.iter().map(|e| e.reason).collect();
+        assert_eq!(reasons, vec!["initial", "lower address", "querier timeout"]);
+        assert_eq!(elections[2].previous, Some(low));
+        assert_eq!(cx.multicast.querier(), Some(high));
+
+        let mut reader = Reader::new(&ds);
+        let mut f = Field::children();
+        protocol::network::igmp::Visitor::detail(&mut f, &cx, &Frame::default(), &mut reader)?;
+        print_field(1, &f);
+        let list = f.children.as_ref().unwrap();
+        assert_eq!(list[0].summary, "[IGMP Version: 3]");
+        assert_eq!(list[2].summary, "Max Resp Time: 10.0 sec (0x64)");
+        assert_eq!(list[7].summary, "QQIC: 125 sec (0x7d)");
+        Ok(())
+    }
+    // the fixture parsed as a whole packet starting at head, the frame is taken back out of the list
+    fn parse_at(cx: &mut Context, name: &str, time: u64, head: Protocol) -> Frame {
+        let (ds, _, mut frame) = init(name);
+        frame.range = Some(ds.range());
+        frame.info.time = time;
+        Instance::<LocalResource>::parse_packet(cx, frame, &ds, head);
+        cx.list.pop().unwrap()
+    }
+    #[test]
+    fn test_esp_decrypt() -> Result<()> {
+        let (ds, mut cx, _) = init("esp_cbc");
+        let frame = parse_at(&mut cx, "esp_cbc", 0, Protocol::IP4);
+        assert!(matches!(frame.tail, Protocol::ESP));
+        assert_eq!(protocol::summary(frame.tail, &cx, &frame).unwrap(), "ESP (SPI=0x00001000, Seq=1)");
+
+        let esp_sa = ipsec::parse_esp_sa(
+            "# spi 0x1000\n\
+             \"IPv4\",\"10.0.0.1\",\"10.0.0.2\",\"0x00001000\",\"AES-CBC [RFC3602]\",\"0x000102030405060708090a0b0c0d0e0f\",\"HMAC-SHA-1-96 [RFC2404]\",\"0x00\"\n\
+             \"IPv4\",\"*\",\"*\",\"0x00002000\",\"10\",\"0x101112131415161718191a1b1c1d1e1fcafebabe\",\"0\",\"\"",
+        )?;
+        let mut cx = Context { esp_sa, ..Default::default() };
+        assert_eq!(cx.esp_sa.len(), 2);
+        let frame = parse_at(&mut cx, "esp_cbc", 0, Protocol::IP4);
+        assert!(matches!(frame.tail, Protocol::UDP));
+        assert_eq!(frame.ports, Some((5000, 5001)));
+        {
+            let mut reader = Reader::new(&ds);
+            reader.forward(20);
+            let mut f = Field::children();
+            let (next, plain) = protocol::network::ipsec::ESPVisitor::detail(&mut f, &cx, &frame, &mut reader)?;
+            print_field(1, &f);
+            assert!(matches!(next, Protocol::UDP));
+            assert_eq!(&plain.unwrap()[8..], b"hello");
+            let list: Vec<&str> = f.children.as_ref().unwrap().iter().map(|f| f.summary.as_str()).collect();
+            assert!(list.contains(&"Encrypted Data: 16 bytes (AES-CBC decrypted)"));
+            assert!(list.contains(&"Pad Length: 1"));
+            assert!(list.contains(&"ICV: 12 bytes"));
+        }
+
+        // tunnel mode, inner ipv4 carries an echo request
+        let frame = parse_at(&mut cx, "esp_gcm", 0, Protocol::IP4);
+        assert!(matches!(frame.tail, Protocol::ICMP));
+        assert!(matches!(frame.protocol_field, ProtocolInfoField::Icmp(8, 0)));
+        assert!(matches!(frame.address_field, AddressField::IPv4(s, _) if s == Ipv4Addr::new(192, 168, 0, 1)));
+
+        // wrong key is rejected by the icv check
+        cx.esp_sa[1].key[0] ^= 0xff;
+        let frame = parse_at(&mut cx, "esp_gcm", 0, Protocol::IP4);
+        assert!(matches!(frame.tail, Protocol::ESP));
+        Ok(())
+    }
+    #[test]
+    fn test_ipsec_sequence() -> Result<()> {
+        let mut cx = Context::default();
+        let frame = parse_at(&mut cx, "ah", 0, Protocol::IP4);
+        assert!(matches!(frame.tail, Protocol::UDP));
+        assert_eq!(frame.ports, Some((5000, 5001)));
+        assert_eq!(frame.iplen, 13);
+
+        let mut table = ipsec::IpsecTable::default();
+        let addresses = || ("10.0.0.1".to_string(), "10.0.0.2".to_string());
+        let frame = Frame::default();
+        let seqs = [1, 2, 5, 4, 4, 100, 10];
+        let rs: Vec<Option<SequenceAnomaly>> = seqs.iter().map(|seq| table.sequence(&frame, addresses(), ipsec::IPSEC_ESP, 0x1000, *seq)).collect();
+        assert_eq!(
+            rs,
+            vec![None, None, Some(SequenceAnomaly::Gap(2)), Some(SequenceAnomaly::OutOfOrder), Some(SequenceAnomaly::Replay), Some(SequenceAnomaly::Gap(94)), Some(SequenceAnomaly::Replay)]
+        );
+        let stat = &table.list()[0];
+        assert_eq!((stat.packets, stat.first_seq, stat.last_seq), (7, 1, 100));
+        assert_eq!((stat.gaps, stat.lost, stat.out_of_order, stat.replays), (2, 95, 1, 2));
+        Ok(())
+    }
+    #[test]
+    fn test_icmp_quoted_tcp() -> Result<()> {
+        let mut cx = Context::default();
+        parse_at(&mut cx, "tcp_syn", 0, Protocol::IP4);
+        let frame = parse_at(&mut cx, "icmp_frag_needed", 0, Protocol::IP4);
+        let (ds, _, _) = init("icmp_frag_needed");
+        assert!(matches!(frame.tail, Protocol::ICMP));
+
+        // the quoted segment must not open a connection of its own
+        assert_eq!(cx.conversation_list.len(), 1);
+        assert_eq!(cx.conversation_list[0].connections.len(), 1);
+        assert_eq!(cx.conversation_list[0].connections[0].icmp_errors, vec![1]);
+        let error = cx.icmp_errors.get(1).unwrap();
+        assert_eq!((error.icmp_type, error.code, error.mtu), (3, 4, Some(1400)));
+        assert_eq!(error.connection, Some((0, 0)));
+        assert_eq!((error.quoted.source_port, error.quoted.target_port), (40000, 80));
+
+        let mut reader = Reader::new(&ds);
+        reader.forward(20);
+        let mut f = Field::children();
+        protocol::network::icmp::Visitor::detail(&mut f, &cx, &frame, &mut reader)?;
+        print_field(1, &f);
+        let list = f.children.as_ref().unwrap();
+        let summaries: Vec<&str> = list.iter().map(|f| f.summary.as_str()).collect();
+        assert!(summaries.contains(&"Next-hop MTU: 1400"));
+        assert!(summaries.contains(&"Internet Protocol Version 4, Src: 10.0.0.1, Dst: 10.0.0.2"));
+        assert!(summaries.contains(&"Transmission Control Protocol, Src Port: 40000, Dst Port: 80"));
+        assert!(summaries.contains(&"[Related TCP connection: conversation 0, connection 0]"));
+        let tcp = list.iter().find(|f| f.summary.starts_with("Transmission")).unwrap();
+        assert_eq!(tcp.children.as_ref().unwrap()[2].summary, "Sequence Number: 1000");
+        Ok(())
+    }
+    #[test]
+    fn test_icmp6_packet_too_big() -> Result<()> {
+        let (ds, mut cx, _) = init("icmp6_too_big");
+        let frame = parse_at(&mut cx, "icmp6_too_big", 0, Protocol::IP6);
+        assert!(matches!(frame.tail, Protocol::ICMP6));
+        let error = cx.icmp_errors.get(0).unwrap();
+        assert_eq!((error.version, error.icmp_type, error.mtu), (6, 2, Some(1280)));
+        assert_eq!(error.connection, None);
+        assert_eq!(error.quoted.udp_key(), "2001:db8::1:5353-2001:db8::2:9000");
+        assert_eq!(cx.icmp_errors.by_udp_key("2001:db8::1:5353-2001:db8::2:9000"), vec![0]);
+
+        let mut reader = Reader::new(&ds);
+        reader.forward(40);
+        let mut f = Field::children();
+        protocol::network::icmp6::Visitor::detail(&mut f, &cx, &frame, &mut reader)?;
+        let summaries: Vec<&str> = f.children.as_ref().unwrap().iter().map(|f| f.summary.as_str()).collect();
+        assert!(summaries.contains(&"MTU: 1280"));
+        assert!(summaries.contains(&"Internet Protocol Version 6, Src: 2001:db8::1, Dst: 2001:db8::2"));
+        assert!(summaries.contains(&"User Datagram Protocol, Src Port: 5353, Dst Port: 9000"));
+        assert!(summaries.contains(&"[Related UDP conversation: 2001:db8::1:5353-2001:db8::2:9000]"));
+        Ok(())
+    }
+    #[test]
+    fn test_arp_spoof() -> Result<()> {
+        let (ds, mut cx, _) = init("arp_reply");
+        let mut frame = Frame::default();
+        protocol::network::arp::Visitor::parse(&mut cx, &mut frame, &mut Reader::new(&ds))?;
+        assert!(matches!(frame.info.status, PacketStatus::NORNAL));
+
+        let (ds, _, _) = init("arp_spoof");
+        let mut frame = Frame::default();
+        frame.info.index = 1;
+        frame.info.time = 2_000_000;
+        protocol::network::arp::Visitor::parse(&mut cx, &mut frame, &mut Reader::new(&ds))?;
+        assert!(matches!(frame.info.status, PacketStatus::ERROR));
+        assert_eq!(frame.info.reason, Some("Duplicate IP address detected"));
+        let warnings: Vec<String> = cx.arp.warnings(1).iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "Duplicate IP address 192.168.1.1 configured, also in use by 00:11:22:33:44:55 (frame 1)",
+                "MAC address of 192.168.1.1 changed from 00:11:22:33:44:55 to de:ad:be:ef:00:01",
+            ]
+        );
+        let report = cx.arp.report();
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].macs, vec!["00:11:22:33:44:55", "de:ad:be:ef:00:01"]);
+        assert_eq!(report.changes.len(), 1);
+        Ok(())
+    }
+    #[test]
+    fn test_arp_table() {
+        let mut table = arp::ArpTable::default();
+        let host = Ipv4Addr::new(10, 0, 0, 1);
+        let gateway = Ipv4Addr::new(10, 0, 0, 254);
+        let mac = "00:00:00:00:00:01".to_string();
+
+        // answered and unanswered requests
+        table.add(0, 0, arp::ARP_REQUEST, mac.clone(), host, gateway);
+        table.add(1, 10, arp::ARP_REPLY, "00:00:00:00:00:fe".into(), gateway, host);
+        table.add(2, 20, arp::ARP_REQUEST, mac.clone(), host, Ipv4Addr::new(10, 0, 0, 9));
+        assert!(table.warnings(0).is_empty());
+        assert_eq!(table.warnings(2), vec![arp::ArpWarning::Unanswered(Ipv4Addr::new(10, 0, 0, 9))]);
+
+        // a binding replaced after the conflict window is a change, not a duplicate
+        assert!(!table.add(3, 100_000_000, arp::ARP_REPLY, "00:00:00:00:00:fd".into(), gateway, host));
+        assert_eq!(table.report().changes.len(), 1);
+
+        // gratuitous storm, the threshold is crossed by the eleventh announcement
+        let announcer = "00:00:00:00:00:aa".to_string();
+        let ip = Ipv4Addr::new(10, 0, 0, 50);
+        for i in 0..15 {
+            table.add(10 + i, 200_000_000 + i as u64 * 10_000, arp::ARP_REQUEST, announcer.clone(), ip, ip);
+        }
+        assert!(table.warnings(19).is_empty());
+        assert_eq!(table.warnings(20), vec![arp::ArpWarning::GratuitousStorm(announcer.clone(), 11)]);
+        let report = table.report();
+        assert_eq!(report.storms.len(), 1);
+        assert_eq!((report.storms[0].index, report.storms[0].count), (20, 15));
+        assert_eq!(report.unanswered.len(), 1);
+        assert_eq!(report.bindings.len(), 4);
+    }
+    fn parse_dhcp(cx: &mut Context, name: &str, index: u32, time: u64) -> Result<()> {
+        let (ds, _, mut frame) = init(name);
+        frame.info.index = index;
+        frame.info.time = time;
+        let mut reader = Reader::new(&ds);
+        match name.starts_with("dhcp6") {
+            true => protocol::network::dhcp6::Visitor::parse(cx, &mut frame, &mut reader)?,
+            false => protocol::network::dhcp::Visitor::parse(cx, &mut frame, &mut reader)?,
+        };
+        Ok(())
+    }
+    #[test]
+    fn test_dhcp_timeline() -> Result<()> {
+        let mut cx = Context::default();
+        parse_dhcp(&mut cx, "dhcp_discover", 0, 1_000_000)?;
+        parse_dhcp(&mut cx, "dhcp_offer", 1, 1_002_000)?;
+        parse_dhcp(&mut cx, "dhcp_request", 2, 1_003_000)?;
+        parse_dhcp(&mut cx, "dhcp_ack", 3, 1_005_000)?;
+        // the client starts over later, nobody answers
+        parse_dhcp(&mut cx, "dhcp_discover", 4, 90_000_000)?;
+
+        let clients = cx.dhcp.clients();
+        assert_eq!(clients.len(), 1);
+        assert_eq!(clients[0].client, "0a:0b:0c:0d:0e:0f");
+        let list = &clients[0].transactions;
+        assert_eq!(list.len(), 2);
+        let lease = &list[0];
+        assert_eq!(lease.status, dhcp::DhcpStatus::Completed);
+        assert_eq!(lease.messages, vec![1, 2, 3, 5]);
+        assert_eq!(lease.address, Some("192.168.1.100".parse()?));
+        assert_eq!(lease.server, Some("192.168.1.1".parse()?));
+        assert_eq!(lease.lease_time, Some(86400));
+        assert_eq!(lease.routers, vec!["192.168.1.1".parse::<std::net::IpAddr>()?]);
+        assert_eq!(lease.dns.len(), 2);
+        assert_eq!(lease.latency, Some(5_000));
+
+        let failures = cx.dhcp.failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].frames, vec![4]);
+        assert_eq!((failures[0].status, failures[0].waiting_for), (dhcp::DhcpStatus::Stalled, Some("OFFER")));
+        Ok(())
+    }
+    #[test]
+    fn test_dhcp6_timeline() -> Result<()> {
+        let mut cx = Context::default();
+        parse_dhcp(&mut cx, "dhcp6_solicit", 0, 0)?;
+        parse_dhcp(&mut cx, "dhcp6_reply", 1, 3_000)?;
+        parse_dhcp(&mut cx, "dhcp6_noaddr", 2, 10_000)?;
+
+        let clients = cx.dhcp.clients();
+        assert_eq!(clients.len(), 1);
+        assert_eq!(clients[0].client, "000100012a2b2c2d0a0b0c0d0e0f");
+        let lease = &clients[0].transactions[0];
+        assert_eq!(lease.status, dhcp::DhcpStatus::Completed);
+        assert_eq!(lease.address, Some("2001:db8::1000".parse()?));
+        assert_eq!(lease.lease_time, Some(7200));
+        assert_eq!(lease.dns, vec!["2001:db8::53".parse::<std::net::IpAddr>()?]);
+        assert_eq!(lease.latency, Some(3_000));
+
+        let failures = cx.dhcp.failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].status, dhcp::DhcpStatus::Failed(2));
+        Ok(())
+    }
+    #[test]
+    fn test_ospf_adjacency() -> Result<()> {
+        let mut cx = Context::default();
+        let r1 = Ipv4Addr::new(1, 1, 1, 1);
+        let r2 = Ipv4Addr::new(2, 2, 2, 2);
+        let area = Ipv4Addr::UNSPECIFIED;
+        let frame = parse_at(&mut cx, "ospf_hello_r2", 1_000_000, Protocol::IP4);
+        assert!(matches!(frame.tail, Protocol::OSPF));
+        assert_eq!(protocol::summary(frame.tail, &cx, &frame).unwrap(), "OSPFv2 Hello Packet, Router ID 2.2.2.2");
+        assert_eq!(cx.ospf.state(area, r1, r2), Some(ospf::OspfState::Init));
+        parse_at(&mut cx, "ospf_hello_r1", 2_000_000, Protocol::IP4);
+        assert_eq!(cx.ospf.state(area, r1, r2), Some(ospf::OspfState::TwoWay));
+        parse_at(&mut cx, "ospf_dbd_init", 2_100_000, Protocol::IP4);
+        assert_eq!(cx.ospf.state(area, r1, r2), Some(ospf::OspfState::ExStart));
+        parse_at(&mut cx, "ospf_dbd_slave", 2_200_000, Protocol::IP4);
+        assert_eq!(cx.ospf.state(area, r1, r2), Some(ospf::OspfState::Exchange));
+        parse_at(&mut cx, "ospf_lsr", 2_300_000, Protocol::IP4);
+        parse_at(&mut cx, "ospf_dbd_last", 2_400_000, Protocol::IP4);
+        assert_eq!(cx.ospf.state(area, r1, r2), Some(ospf::OspfState::Loading));
+        let (ds, _, _) = init("ospf_lsu");
+        let frame = parse_at(&mut cx, "ospf_lsu", 2_500_000, Protocol::IP4);
+        assert_eq!(cx.ospf.state(area, r1, r2), Some(ospf::OspfState::Full));
+        // r2 goes silent past its dead interval, r1 still lists it
+        parse_at(&mut cx, "ospf_hello_r1", 45_000_000, Protocol::IP4);
+        assert_eq!(cx.ospf.state(area, r1, r2), Some(ospf::OspfState::Init));
+
+        let list = cx.ospf.adjacencies();
+        assert_eq!(list.len(), 1);
+        let states: Vec<(u32, ospf::OspfState)> = list[0].transitions.iter().map(|t| (t.index, t.to)).collect();
+        assert_eq!(states.len(), 8);
+        assert_eq!(states[5], (6, ospf::OspfState::Full));
+        assert_eq!(states[6], (7, ospf::OspfState::Down));
+        assert_eq!(list[0].transitions[6].event, "inactivity timer");
+
+        let mut reader = Reader::new(&ds);
+        reader.forward(20);
+        let mut f = Field::children();
+        protocol::network::ospf::Visitor::detail(&mut f, &cx, &frame, &mut reader)?;
+        print_field(1, &f);
+        let update = &f.children.as_ref().unwrap()[1];
+        assert_eq!(update.summary, "LS Update Packet");
+        let lsa = &update.children.as_ref().unwrap()[1];
+        assert_eq!(lsa.summary, "Router-LSA, Id: 2.2.2.2, Adv Router: 2.2.2.2");
+        let links: Vec<&str> = lsa.children.as_ref().unwrap().iter().map(|f| f.summary.as_str()).collect();
+        assert!(links.contains(&"Type: PTP ID: 1.1.1.1 Metric: 10"));
+        assert!(links.contains(&"Type: Stub ID: 10.0.0.0 Metric: 10"));
+        Ok(())
+    }
+    #[test]
+    fn test_ospf3_lsa() -> Result<()> {
+        let (ds, mut cx, _) = init("ospf6_lsu");
+        let frame = parse_at(&mut cx, "ospf6_lsu", 0, Protocol::IP6);
+        assert!(matches!(frame.tail, Protocol::OSPF));
+        assert_eq!(protocol::summary(frame.tail, &cx, &frame).unwrap(), "OSPFv3 LS Update Packet, Router ID 2.2.2.2");
+        // the router lsa names 1.1.1.1 as a point-to-point neighbour
+        assert_eq!(cx.ospf.state(Ipv4Addr::UNSPECIFIED, Ipv4Addr::new(1, 1, 1, 1), Ipv4Addr::new(2, 2, 2, 2)), Some(ospf::OspfState::Full));
+
+        let mut reader = Reader::new(&ds);
+        reader.forward(40);
+        let mut f = Field::children();
+        protocol::network::ospf::Visitor::detail(&mut f, &cx, &frame, &mut reader)?;
+        print_field(1, &f);
+        let lsas = f.children.as_ref().unwrap()[1].children.as_ref().unwrap();
+        let summaries: Vec<&str> = lsas.iter().map(|f| f.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["Number of LSAs: 3", "Router-LSA, Id: 0.0.0.0, Adv Router: 2.2.2.2", "Link-LSA, Id: 0.0.0.5, Adv Router: 2.2.2.2", "Intra-Area-Prefix-LSA, Id: 0.0.0.0, Adv Router: 2.2.2.2"]);
+        let link = lsas[2].children.as_ref().unwrap();
+        assert!(link.iter().any(|f| f.summary == "Link-local Interface Address: fe80::2"));
+        let prefix = lsas[3].children.as_ref().unwrap().last().unwrap();
+        assert_eq!(prefix.children.as_ref().unwrap().last().unwrap().summary, "Address Prefix: 2001:db8:1::/64");
+        Ok(())
+    }
+    #[test]
+    fn test_vrrp_master_election() -> Result<()> {
+        let mut cx = Context::default();
+        cx.config.verify_checksum = true;
+        let r1: IpAddr = "10.0.0.1".parse()?;
+        let r2: IpAddr = "10.0.0.2".parse()?;
+        let frame = parse_at(&mut cx, "vrrp_r1", 1_000_000, Protocol::IP4);
+        assert!(matches!(frame.tail, Protocol::VRRP));
+        assert_eq!(protocol::summary(frame.tail, &cx, &frame).unwrap(), "VRRPv2 Advertisement, VRID 1, Priority 100");
+        parse_at(&mut cx, "vrrp_r1", 2_000_000, Protocol::IP4);
+        // a higher priority router preempts, then gives up the role
+        let (ds, _, _) = init("vrrp_r2");
+        let frame = parse_at(&mut cx, "vrrp_r2", 2_500_000, Protocol::IP4);
+        assert_eq!(cx.fhrp.master(fhrp::FhrpProtocol::VRRP, 1, false), Some(r2));
+        parse_at(&mut cx, "vrrp_r2_resign", 10_000_000, Protocol::IP4);
+        assert_eq!(cx.fhrp.master(fhrp::FhrpProtocol::VRRP, 1, false), None);
+        parse_at(&mut cx, "vrrp_r1", 10_100_000, Protocol::IP4);
+
+        let groups = cx.fhrp.groups();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].virtual_ips, vec!["10.0.0.254".parse::<IpAddr>()?]);
+        let terms: Vec<(IpAddr, u32, bool)> = groups[0].terms.iter().map(|t| (t.router, t.packets, t.resigned)).collect();
+        assert_eq!(terms, vec![(r1, 2, false), (r2, 1, true), (r1, 1, false)]);
+        assert_eq!(cx.fhrp.takeover(4), Some(Some(r2)));
+
+        let mut reader = Reader::new(&ds);
+        reader.forward(20);
+        let mut f = Field::children();
+        protocol::network::vrrp::Visitor::detail(&mut f, &cx, &frame, &mut reader)?;
+        print_field(1, &f);
+        let summaries: Vec<&str> = f.children.as_ref().unwrap().iter().map(|f| f.summary.as_str()).collect();
+        assert!(summaries.contains(&"Checksum: 0xceb1 [correct]"));
+        assert!(summaries.contains(&"Authentication String: secret"));
+        assert!(summaries.contains(&"[Master changed from 10.0.0.1]"));
+
+        let frame = parse_at(&mut cx, "vrrp3", 0, Protocol::IP4);
+        assert!(matches!(frame.info.status, PacketStatus::NORNAL));
+        let group = cx.fhrp.groups().iter().find(|g| g.group == 7).unwrap();
+        assert_eq!((group.version, group.virtual_ips.len()), (3, 2));
+        Ok(())
+    }
+    #[test]
+    fn test_sctp() -> Result<()> {
+        assert_eq!(checksum::crc32c(b"123456789"), 0xe306_9283);
+        let mut cx = Context::default();
+        cx.config.verify_checksum = true;
+        let frame = parse_at(&mut cx, "sctp_init", 1_000_000, Protocol::IP4);
+        assert!(matches!(frame.tail, Protocol::SCTP));
+        assert_eq!(frame.ports, Some((40002, 3868)));
+        assert_eq!(protocol::summary(frame.tail, &cx, &frame).unwrap(), "INIT");
+        assert!(matches!(frame.info.status, PacketStatus::NORNAL));
+        for (name, time) in [("sctp_init_ack", 1_100_000), ("sctp_cookie_echo", 1_200_000), ("sctp_cookie_ack", 1_300_000)] {
+            parse_at(&mut cx, name, time, Protocol::IP4);
+        }
+        assert_eq!(cx.sctp.associations()[0].state, sctp::SctpState::Established);
+        let (ds, _, _) = init("sctp_data");
+        let data = parse_at(&mut cx, "sctp_data", 1_400_000, Protocol::IP4);
+        let payloads = cx.sctp.payloads(data.info.index);
+        assert_eq!((payloads.len(), payloads[0].stream, payloads[0].ppid, payloads[0].range.len()), (1, 1, 46, 5));
+        parse_at(&mut cx, "sctp_sack", 1_500_000, Protocol::IP4);
+        let retransmission = parse_at(&mut cx, "sctp_data", 1_600_000, Protocol::IP4);
+        assert_eq!(cx.sctp.retransmitted(retransmission.info.index), 1);
+        let bad = parse_at(&mut cx, "sctp_data_bad", 1_700_000, Protocol::IP4);
+        assert!(matches!(bad.info.status, PacketStatus::ERROR));
+        parse_at(&mut cx, "sctp_abort", 1_800_000, Protocol::IP4);
+        // a new handshake on the same addresses is another association
+        parse_at(&mut cx, "sctp_init", 5_000_000, Protocol::IP4);
+
+        let associations = cx.sctp.associations();
+        assert_eq!(associations.len(), 2);
+        let association = &associations[0];
+        assert_eq!((association.initiator.as_str(), association.responder.as_str()), ("10.0.1.1:40002", "10.0.1.2:3868"));
+        assert_eq!((association.initiator_tag, association.responder_tag), (Some(0x1111_1111), Some(0x2222_2222)));
+        assert_eq!((association.initiator_streams, association.responder_streams), (Some((10, 10)), Some((5, 5))));
+        assert_eq!(association.state, sctp::SctpState::Aborted);
+        assert_eq!((association.packets, association.data_chunks, association.bytes, association.retransmissions), (9, 3, 15, 1));
+        assert_eq!(association.streams.len(), 1);
+        assert_eq!((association.streams[0].id, association.streams[0].chunks, association.streams[0].ppids.clone()), (1, 3, vec![46]));
+        assert_eq!(associations[1].state, sctp::SctpState::Init);
+
+        let mut reader = Reader::new(&ds);
+        reader.forward(20);
+        let mut f = Field::children();
+        protocol::transport::sctp::Visitor::detail(&mut f, &cx, &data, &mut reader)?;
+        print_field(1, &f);
+        let children = f.children.as_ref().unwrap();
+        assert!(children.iter().any(|c| c.summary.starts_with("Checksum: ") && c.summary.ends_with("[correct]")));
+        assert!(children.iter().any(|c| c.summary == "[Association index: 0]"));
+        let chunk = children.last().unwrap();
+        assert_eq!(chunk.summary, "DATA chunk (TSN: 100, SID: 1, PPID: Diameter, payload length: 5 bytes)");
+        let labels: Vec<&str> = chunk.children.as_ref().unwrap().iter().map(|c| c.summary.as_str()).collect();
+        assert!(labels.contains(&"Payload protocol identifier: Diameter (46)"));
+        assert!(labels.contains(&"Chunk padding: 3 bytes"));
+        Ok(())
+    }
+    #[test]
+    fn test_hsrp_active_router() -> Result<()> {
+        let mut cx = Context::default();
+        let frame = parse_at(&mut cx, "hsrp_active", 1_000_000, Protocol::IP4);
+        assert!(matches!(frame.tail, Protocol::HSRP));
+        assert_eq!(protocol::summary(frame.tail, &cx, &frame).unwrap(), "HSRPv1 Hello (state Active), group 1");
+        parse_at(&mut cx, "hsrp_standby", 1_100_000, Protocol::IP4);
+        parse_at(&mut cx, "hsrp_resign", 5_000_000, Protocol::IP4);
+        let (ds, _, _) = init("hsrp_coup");
+        let frame = parse_at(&mut cx, "hsrp_coup", 5_100_000, Protocol::IP4);
+
+        let group = &cx.fhrp.groups()[0];
+        assert_eq!(group.routers.len(), 2);
+        let terms: Vec<(String, bool)> = group.terms.iter().map(|t| (t.router.to_string(), t.resigned)).collect();
+        assert_eq!(terms, vec![("10.0.0.2".to_string(), true), ("10.0.0.3".to_string(), false)]);
+
+        let mut reader = Reader::new(&ds);
+        reader.forward(28);
+        let mut f = Field::children();
+        protocol::network::hsrp::Visitor::detail(&mut f, &cx, &frame, &mut reader)?;
+        print_field(1, &f);
+        assert_eq!(f.summary, "Cisco Hot Standby Router Protocol, Hello (state Active), group 1");
+        let summaries: Vec<&str> = f.children.as_ref().unwrap().iter().map(|f| f.summary.as_str()).collect();
+        assert!(summaries.contains(&"Authentication Data: cisco"));
+        assert!(summaries.contains(&"[Active router changed from 10.0.0.2]"));
+
+        let frame = parse_at(&mut cx, "hsrp2_active", 6_000_000, Protocol::IP4);
+        assert_eq!(protocol::summary(frame.tail, &cx, &frame).unwrap(), "HSRPv2 Hello (state Active), group 300");
+        assert_eq!(cx.fhrp.master(fhrp::FhrpProtocol::HSRP, 300, false), Some("10.0.0.4".parse()?));
+        Ok(())
+    }
+    #[test]
+    fn test_geoip() -> Result<()> {
+        let mut cx = Context::default();
+        assert!(cx.geoip.lookup("10.0.0.1".parse()?).is_none());
+        assert_eq!(cx.geoip.load(build_reader("geo_country"))?, "GeoLite2-Country");
+        assert_eq!(cx.geoip.load(build_reader("geo_asn"))?, "GeoLite2-ASN");
+        assert!(cx.geoip.load(vec![0; 64]).is_err());
+
+        let info = cx.geoip.lookup("10.0.0.1".parse()?).unwrap();
+        assert_eq!(info.country.as_deref(), Some("DE"));
+        assert_eq!(info.country_name.as_deref(), Some("Germany"));
+        assert_eq!(info.asn, Some(64500));
+        assert_eq!(info.as_org.as_deref(), Some("Example Net"));
+        let info = cx.geoip.lookup_str("192.0.2.9").unwrap();
+        assert_eq!((info.country.as_deref(), info.asn), (Some("JP"), None));
+        assert!(cx.geoip.lookup_str("203.0.113.1").is_none());
+        assert!(cx.geoip.lookup_str("2001:db8::1").is_none());
+        assert!(CounterItem::new("10.1.2.3".into(), 1).geo(&cx.geoip).geo.is_some());
+
+        // 10.0.0.1 -> 224.0.0.18
+        let frame = parse_at(&mut cx, "vrrp_r1", 1_000_000, Protocol::IP4);
+        let len = frame.info.len as usize;
+        cx.list.push(frame);
+        let geoip = std::mem::take(&mut cx.geoip);
+        let stat = geoip.traffic(&cx);
+        let countries: Vec<(&str, usize)> = stat.countries.iter().map(|c| (c.key.as_str(), c.packets)).collect();
+        assert_eq!(countries, vec![("DE", 1), ("Unknown", 1)]);
+        assert_eq!(stat.asns[0].key, "AS64500");
+        assert_eq!(stat.asns[0].name.as_deref(), Some("Example Net"));
+        assert_eq!(stat.asns[0].bytes, len);
+        assert!(GeoIp::default().traffic(&cx).countries.is_empty());
+        Ok(())
+    }
+    // parsed frames stay in the list so held segments can be passed on in them
+    fn parse_kept(cx: &mut Context, name: &str, time: u64) {
+        let frame = parse_at(cx, name, time, Protocol::IP4);
+        cx.list.push(frame);
+    }
+    #[test]
+    fn test_tcp_out_of_order() -> Result<()> {
+        let mut cx = Context::default();
+        parse_kept(&mut cx, "tcp_ooo_syn", 1_000_000);
+        parse_kept(&mut cx, "tcp_ooo_p1", 1_100_000);
+        parse_kept(&mut cx, "tcp_ooo_p3", 1_200_000);
+        {
+            let frame = &cx.list[2];
+            assert!(frame.tcp_info.as_ref().unwrap().status == TCPDetail::OUTOFORDER);
+            assert!(matches!(frame.tail, Protocol::TCP));
+            assert!(matches!(frame.info.status, PacketStatus::NORNAL));
+        }
+        parse_kept(&mut cx, "tcp_ooo_p2", 1_300_000);
+        assert!(cx.list[3].tcp_info.as_ref().unwrap().status == TCPDetail::NEXT);
+        // the held segment went to http after the one filling the gap
+        assert!(matches!(cx.list[2].tail, Protocol::HTTP));
+        assert_eq!(cx.http_messages.len(), 1);
+        let message = &cx.http_messages[0];
+        assert_eq!(message.hostname.as_deref(), Some("example.com"));
+        let SegmentData::Multiple(segments) = &message.headers else {
+            panic!("headers not reassembled");
+        };
+        assert_eq!(segments.first().map(|s| s.index), Some(1));
+        assert_eq!(segments.last().map(|s| s.index), Some(2));
+
+        // the gap never fills, the held segment is given up on with the next segment after the timeout
+        let mut cx = Context::default();
+        parse_kept(&mut cx, "tcp_ooo_syn", 1_000_000);
+        parse_kept(&mut cx, "tcp_ooo_p1", 1_100_000);
+        parse_kept(&mut cx, "tcp_ooo_p3", 1_200_000);
+        parse_kept(&mut cx, "tcp_ooo_p3", 5_000_000);
+        let frame = &cx.list[2];
+        assert!(frame.tcp_info.as_ref().unwrap().status == TCPDetail::NOPREVCAPTURE);
+        assert!(matches!(frame.info.status, PacketStatus::ERROR));
+        assert!(matches!(cx.list[3].tcp_info.as_ref().unwrap().status, TCPDetail::RETRANSMISSION | TCPDetail::DUMP));
+        assert_eq!(cx.http_messages[0].hostname, None);
+        Ok(())
+    }
+    #[test]
+    fn test_tcp_held_release() -> Result<()> {
+        let given_up = |cx: &Context| {
+            let frame = &cx.list[2];
+            frame.tcp_info.as_ref().unwrap().status == TCPDetail::NOPREVCAPTURE && matches!(frame.info.status, PacketStatus::ERROR)
+        };
+        // only the peer keeps talking, its segment after the timeout gives up on the gap
+        let mut cx = Context::default();
+        parse_kept(&mut cx, "tcp_ooo_syn", 1_000_000);
+        parse_kept(&mut cx, "tcp_ooo_p1", 1_100_000);
+        parse_kept(&mut cx, "tcp_ooo_p3", 1_200_000);
+        parse_kept(&mut cx, "tcp_ooo_ack", 1_300_000);
+        assert!(cx.list[2].tcp_info.as_ref().unwrap().status == TCPDetail::OUTOFORDER);
+        parse_kept(&mut cx, "tcp_ooo_ack", 5_000_000);
+        assert!(given_up(&cx));
+        assert_eq!(cx.conversation_list[0].connections[0].stream.len(), 2);
+
+        // a reset from the peer releases what the other side held
+        let mut cx = Context::default();
+        parse_kept(&mut cx, "tcp_ooo_syn", 1_000_000);
+        parse_kept(&mut cx, "tcp_ooo_p1", 1_100_000);
+        parse_kept(&mut cx, "tcp_ooo_p3", 1_200_000);
+        parse_kept(&mut cx, "tcp_ooo_rst", 1_300_000);
+        assert!(given_up(&cx));
+
+        // nothing arrives after the out of order segment, the end of the capture releases it
+        let file = pcap_file(&["tcp_ooo_syn", "tcp_ooo_p1", "tcp_ooo_p3"]);
+        let mut instance = Instance::new(1024, MemoryLoader(file.clone()));
+        instance.update(file)?;
+        assert!(instance.ctx.list[2].tcp_info.as_ref().unwrap().status == TCPDetail::OUTOFORDER);
+        instance.finish();
+        assert!(given_up(&instance.ctx));
+        // dissected again the same way
+        instance.set_decode_as("")?;
+        assert!(given_up(&instance.ctx));
+        Ok(())
+    }
+    const EXPERT_SEQUENCE: [&str; 16] = [
+        "tcp_ex_syn", "tcp_ex_synack", "tcp_ex_ack", "tcp_ex_d1", "tcp_ex_d2", "tcp_ex_ack2", "tcp_ex_ack2", "tcp_ex_ack2", "tcp_ex_d2", "tcp_ex_d1", "tcp_ex_ack3", "tcp_ex_d3", "tcp_ex_zero",
+        "tcp_ex_probe", "tcp_ex_update", "tcp_ex_d4",
+    ];
+    fn parse_expert_sequence() -> Context {
+        let mut cx = Context::default();
+        for (i, name) in EXPERT_SEQUENCE.iter().enumerate() {
+            parse_kept(&mut cx, name, 1_000_000 + i as u64 * 1000);
+        }
+        cx
+    }
+    #[test]
+    fn test_tcp_expert() -> Result<()> {
+        let cx = parse_expert_sequence();
+        let experts = |index: usize| cx.list[index].tcp_info.as_ref().unwrap().experts.clone();
+        for index in [0, 1, 2, 3, 4, 5] {
+            assert!(experts(index).is_empty(), "frame {index}");
+        }
+        assert_eq!(experts(6), vec![TCPExpert::DupAck]);
+        assert_eq!(experts(7), vec![TCPExpert::DupAck]);
+        assert_eq!(experts(8), vec![TCPExpert::FastRetransmission]);
+        assert!(cx.list[8].tcp_info.as_ref().unwrap().status == TCPDetail::RETRANSMISSION);
+        assert_eq!(experts(9), vec![TCPExpert::SpuriousRetransmission]);
+        assert!(experts(10).is_empty());
+        assert_eq!(experts(11), vec![TCPExpert::WindowFull]);
+        assert_eq!(experts(12), vec![TCPExpert::ZeroWindow]);
+        assert_eq!(experts(13), vec![TCPExpert::ZeroWindowProbe]);
+        assert!(cx.list[13].tcp_info.as_ref().unwrap().status == TCPDetail::ZEROWINDOWPROBE);
+        assert_eq!(experts(14), vec![TCPExpert::WindowUpdate]);
+        // the probe did not move the stream, the next segment carries the same sequence
+        let info = cx.list[15].tcp_info.as_ref().unwrap();
+        assert!(info.status == TCPDetail::NEXT && info.experts.is_empty());
+        assert_eq!(info.in_flight, 10);
+
+        // the client announced shift 7, both sides did
+        assert_eq!(cx.list[2].tcp_info.as_ref().unwrap().window, 512 << 7);
+        assert_eq!(cx.list[0].tcp_info.as_ref().unwrap().window, 65535);
+        assert!(cx.list[6].tcp_description().unwrap().starts_with("[TCP Dup ACK] 40001 -> 8080"));
+
+        let connection = &cx.conversation_list[0].connections[0];
+        let (client, server) = (&connection.primary.statistic, &connection.second.statistic);
+        assert_eq!((client.dup_ack, client.zero_window, client.window_update), (2, 1, 1));
+        assert_eq!((server.fast_retransmission, server.spurious_retransmission, server.window_full, server.zero_window_probe), (1, 1, 1, 1));
+        assert_eq!(server.retransmission, 2);
+        Ok(())
+    }
+    #[test]
+    fn test_connection_graph() -> Result<()> {
+        let cx = parse_expert_sequence();
+        let connection = &cx.conversation_list[0].connections[0];
+        let graph = graph::connection_graph(connection);
+        assert_eq!(graph.retransmissions, vec![8, 9]);
+        let server_primary = connection.primary.port == 8080;
+        let (sent, received) = match server_primary {
+            true => (&graph.throughput.data[0], &graph.throughput.data[1]),
+            false => (&graph.throughput.data[1], &graph.throughput.data[0]),
+        };
+        assert_eq!(sent.iter().sum::<u32>(), 539);
+        assert_eq!(received.iter().sum::<u32>(), 0);
+        assert_eq!(graph.throughput.x_axis.len(), sent.len());
+
+        let series = match server_primary {
+            true => &graph.forward,
+            false => &graph.backward,
+        };
+        let column = |i: usize| series.data.iter().map(|row| row[i]).collect::<Vec<_>>();
+        // the syn/ack carries no payload, the first column is the ack of the handshake
+        assert_eq!(column(0), vec![0, 0, 1, 1 + (512 << 7), 0]);
+        assert_eq!(column(1), vec![1, 101, 1, 1 + (512 << 7), 0]);
+        let retransmitted: Vec<u32> = series.data[4].clone();
+        assert_eq!(retransmitted.iter().sum::<u32>(), 2);
+        Ok(())
+    }
+    #[test]
+    fn test_connection_graph_unordered() -> Result<()> {
+        // a merged capture, the payload is later than the last segment
+        let mut cx = Context::default();
+        let sequence = [("tcp_ex_syn", 1_000_000), ("tcp_ex_synack", 1_010_000), ("tcp_ex_ack", 1_012_000), ("tcp_ex_d1", 9_000_000), ("tcp_ex_ack2", 1_050_000)];
+        for (name, time) in sequence {
+            parse_kept(&mut cx, name, time);
+        }
+        let graph = graph::connection_graph(&cx.conversation_list[0].connections[0]);
+        let throughput = &graph.throughput;
+        assert_eq!(throughput.x_axis[0], 1_000_000);
+        assert!(*throughput.x_axis.last().unwrap() <= 9_000_000);
+        assert_eq!(throughput.data.iter().map(|row| row.iter().sum::<u32>()).sum::<u32>(), 100);
+        assert!(throughput.data.iter().all(|row| *row.last().unwrap() == 100 || row.iter().sum::<u32>() == 0));
+        Ok(())
+    }
+    #[test]
+    fn test_tcp_metrics() -> Result<()> {
+        let mut cx = Context::default();
+        let sequence = [("tcp_ex_syn", 1_000_000), ("tcp_ex_synack", 1_010_000), ("tcp_ex_ack", 1_012_000), ("tcp_ex_d1", 1_020_000), ("tcp_ex_ack2", 1_050_000), ("tcp_ex_rst", 1_060_000)];
+        for (name, time) in sequence {
+            parse_kept(&mut cx, name, time);
+        }
+        let connection = &cx.conversation_list[0].connections[0];
+        let metrics = &connection.metrics;
+        assert_eq!((metrics.syn_ack_time, metrics.ack_time, metrics.irtt), (Some(10_000), Some(2_000), Some(12_000)));
+        // syn, syn/ack and the data segment were each acked once
+        assert_eq!(metrics.rtt_samples, 3);
+        assert_eq!((metrics.rtt_min, metrics.rtt_max, metrics.rtt_avg), (Some(2_000), Some(30_000), Some(14_000)));
+        assert_eq!(metrics.duration, 60_000);
+        let client_is_primary = connection.primary.port == 40001;
+        let expected = if client_is_primary { TCPCloseReason::PrimaryReset } else { TCPCloseReason::SecondReset };
+        assert_eq!(metrics.close, expected);
+        assert_eq!(cx.list[3].info.irtt, 12);
+        assert!(connection.primary.status == TCPConnectStatus::CLOSED);
+
+        let mut cx = Context::default();
+        let sequence = [("tcp_ex_syn", 1_000_000), ("tcp_ex_synack", 1_010_000), ("tcp_ex_ack", 1_012_000), ("tcp_ex_fin_c", 1_020_000), ("tcp_ex_fin_s", 1_030_000)];
+        for (name, time) in sequence {
+            parse_kept(&mut cx, name, time);
+        }
+        assert_eq!(cx.conversation_list[0].connections[0].metrics.close, TCPCloseReason::Fin);
+        Ok(())
+    }
+    #[test]
+    fn test_follow_stream() -> Result<()> {
+        let mut cx = Context::default();
+        parse_kept(&mut cx, "tcp_ooo_syn", 1_000_000);
+        parse_kept(&mut cx, "tcp_ooo_p1", 1_100_000);
+        parse_kept(&mut cx, "tcp_ooo_p3", 1_200_000);
+        parse_kept(&mut cx, "tcp_ooo_p2", 1_300_000);
+        // the stream follows sequence order, not capture order
+        let stream = &cx.conversation_list[0].connections[0].stream;
+        assert_eq!(stream.iter().map(|s| s.index).collect::<Vec<_>>(), vec![1, 3, 2]);
+        assert!(stream.iter().all(|s| s.direction == StreamDirection::Forward));
+        assert_eq!(stream.iter().map(|s| s.range.len()).collect::<Vec<_>>(), vec![28, 20, 12]);
+
+        let mut cx = Context::default();
+        parse_kept(&mut cx, "udp_ping", 1_000_000);
+        parse_kept(&mut cx, "udp_pong", 1_100_000);
+        parse_kept(&mut cx, "dns_response", 1_200_000);
+        // recorded per flow while parsing, forward is the sender of its first frame
+        let (segments, reversed) = stream::udp_segments(&cx, 1).unwrap();
+        assert!(reversed);
+        assert_eq!(segments.len(), 2);
+        assert_eq!((segments[0].index, segments[0].direction, segments[0].range.len()), (0, StreamDirection::Forward, 4));
+        assert_eq!((segments[1].index, segments[1].direction, segments[1].range.len()), (1, StreamDirection::Backward, 5));
+        assert!(!stream::udp_segments(&cx, 0).unwrap().1);
+        assert_eq!(stream::udp_segments(&cx, 0).unwrap().0.len(), 2);
+
+        // pages of the stream, seen from the frame asked for
+        let file = pcap_file(&["udp_ping", "udp_pong", "dns_response", "udp_ping"]);
+        let mut instance = Instance::new(1024, MemoryLoader(file.clone()));
+        instance.update(file)?;
+        let page = instance.follow_udp_stream(1, Criteria { start: 1, size: 5 });
+        assert_eq!(page.total, 3);
+        assert_eq!(page.items.iter().map(|c| (c.index, c.direction)).collect::<Vec<_>>(), vec![(1, StreamDirection::Forward), (3, StreamDirection::Backward)]);
+        assert_eq!(page.items[0].data, b"pong!");
+        Ok(())
+    }
+    struct MemoryLoader(Vec<u8>);
+    impl ResourceLoader for MemoryLoader {
+        fn load(&self, range: &std::ops::Range<usize>) -> Result<Vec<u8>> {
+            Ok(self.0[range.clone()].to_vec())
+        }
+        fn loads(&self, ranges: &[std::ops::Range<usize>]) -> Result<Vec<u8>> {
+            Ok(ranges.iter().flat_map(|r| self.0[r.clone()].to_vec()).collect())
+        }
+    }
+    // pcap file of ethernet frames around the ip packets of the fixtures
+    fn pcap_file(names: &[&str]) -> Vec<u8> {
+        let mut file = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0, 1, 0, 0, 0];
+        for (i, name) in names.iter().enumerate() {
+            let mut packet = vec![0x02, 0, 0, 0, 0, 0x01, 0x02, 0, 0, 0, 0, 0x02, 0x08, 0x00];
+            packet.extend(build_reader(name));
+            file.extend((i as u32 + 1).to_le_bytes());
+            file.extend(0u32.to_le_bytes());
+            file.extend((packet.len() as u32).to_le_bytes());
+            file.extend((packet.len() as u32).to_le_bytes());
+            file.extend(packet);
+        }
+        file
+    }
+    #[test]
+    fn test_decode_as() -> Result<()> {
+        let rules = decode::parse_decode_as("# comment\nudp 5300 DNS\ntcp 10.0.0.3:40001 10.0.0.4:8080 http\nudp 9999 none")?;
+        assert_eq!(rules.len(), 3);
+        assert!(matches!(rules[0].transport, decode::Transport::UDP) && rules[0].port == 5300 && rules[0].protocol == Protocol::DNS);
+        assert_eq!(rules[1].port, 8080);
+        assert_eq!(rules[1].tuple, Some((IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 4)), 40001)));
+        assert!(decode::parse_decode_as("tcp 53 dns").is_err());
+        assert!(decode::parse_decode_as("udp 53 gopher").is_err());
+
+        // hsrp on a port neither the port table nor the heuristics know
+        let mut cx = Context::default();
+        parse_kept(&mut cx, "udp_hsrp_alt", 1_000_000);
+        assert!(matches!(cx.list[0].tail, Protocol::UDP));
+        cx.decode_as = decode::parse_decode_as("udp 6985 hsrp")?;
+        parse_kept(&mut cx, "udp_hsrp_alt", 1_100_000);
+        assert!(matches!(cx.list[1].tail, Protocol::HSRP));
+
+        // a rule wins over the heuristics
+        let mut cx = Context::default();
+        parse_kept(&mut cx, "udp_dns_alt", 1_000_000);
+        assert!(matches!(cx.list[0].tail, Protocol::DNS));
+        let mut cx = Context { decode_as: decode::parse_decode_as("udp 5300 none")?, ..Default::default() };
+        parse_kept(&mut cx, "udp_dns_alt", 1_000_000);
+        assert!(matches!(cx.list[0].tail, Protocol::UDP));
+
+        // rules set after loading dissect the frames again
+        let file = pcap_file(&["udp_hsrp_alt", "udp_dns_alt", "udp_ping"]);
+        let mut instance = Instance::new(1024, MemoryLoader(file.clone()));
+        instance.update(file)?;
+        assert_eq!(instance.ctx.list.len(), 3);
+        assert!(matches!(instance.ctx.list[0].tail, Protocol::UDP));
+        assert!(matches!(instance.ctx.list[1].tail, Protocol::DNS));
+        assert_eq!(instance.set_decode_as("udp 6985 hsrp")?, 1);
+        assert_eq!(instance.ctx.list.len(), 3);
+        assert!(matches!(instance.ctx.list[0].tail, Protocol::HSRP));
+        assert!(matches!(instance.ctx.list[1].tail, Protocol::DNS));
+        assert_eq!(instance.set_decode_as("udp 6985 hsrp\nudp 5300 none")?, 2);
+        assert!(matches!(instance.ctx.list[0].tail, Protocol::HSRP));
+        assert!(matches!(instance.ctx.list[1].tail, Protocol::UDP));
+        assert!(matches!(instance.ctx.list[2].tail, Protocol::UDP));
+        Ok(())
+    }
+    // remembers the size of every load
+    struct CountingLoader(Vec<u8>, std::cell::RefCell<Vec<usize>>);
+    impl ResourceLoader for CountingLoader {
+        fn load(&self, range: &std::ops::Range<usize>) -> Result<Vec<u8>> {
+            self.1.borrow_mut().push(range.len());
+            Ok(self.0[range.clone()].to_vec())
+        }
+        fn loads(&self, ranges: &[std::ops::Range<usize>]) -> Result<Vec<u8>> {
+            Ok(ranges.iter().flat_map(|r| self.0[r.clone()].to_vec()).collect())
+        }
+    }
+    #[test]
+    fn test_redissect_batches() -> Result<()> {
+        let file = pcap_file(&vec!["udp_ping"; 5000]);
+        let mut instance = Instance::new(1024, CountingLoader(file.clone(), Default::default()));
+        instance.update(file.clone())?;
+        assert_eq!(instance.ctx.list.len(), 5000);
+        instance.set_decode_as("udp 5300 none")?;
+        assert_eq!(instance.ctx.list.len(), 5000);
+        let loads = instance.loader().1.borrow();
+        assert!(loads.len() > 1);
+        assert!(loads.iter().all(|len| *len <= 1024 * 128));
+        assert_eq!(loads.iter().sum::<usize>(), file.len());
+        Ok(())
+    }
+    #[test]
+    fn test_heuristic() -> Result<()> {
+        let probe = |transport, data: &[u8]| {
+            let ds = DataSource::create(data.to_vec(), 0..data.len());
+            heuristic::heuristic(transport, &Reader::new(&ds))
+        };
+        assert_eq!(probe(decode::Transport::TCP, b"GET / HTTP/1.1\r\n"), Some(Protocol::HTTP));
+        assert_eq!(probe(decode::Transport::TCP, b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"), Some(Protocol::HTTP2));
+        assert_eq!(probe(decode::Transport::TCP, &[22, 3, 1, 0, 64, 1]), Some(Protocol::TLS));
+        assert_eq!(probe(decode::Transport::TCP, b"SSH-2.0-OpenSSH_9.6\r\n"), None);
+        assert_eq!(probe(decode::Transport::UDP, &build_reader("dns_response")), Some(Protocol::DNS));
+        assert_eq!(probe(decode::Transport::UDP, b"ping"), None);
+        assert_eq!(probe(decode::Transport::UDP, &build_reader("quic_client_initial")[28..]), Some(Protocol::QUIC));
+        assert_eq!(probe(decode::Transport::UDP, &build_reader("dtls_client_hello")[28..]), Some(Protocol::DTLS));
+
+        // the first payload of a flow decides for the whole flow
+        let mut cx = Context::default();
+        parse_kept(&mut cx, "udp_dns_ping", 1_000_000);
+        assert!(matches!(cx.list[0].tail, Protocol::DNS));
+        let mut cx = Context::default();
+        parse_kept(&mut cx, "udp_ping", 1_000_000);
+        parse_kept(&mut cx, "udp_dns_ping", 1_100_000);
+        assert!(matches!(cx.list[1].tail, Protocol::UDP));
+        assert_eq!(cx.udp_flows.len(), 1);
+        Ok(())
+    }
+    fn find_summary<'a>(fields: &'a [Field], pred: &dyn Fn(&str) -> bool) -> Option<&'a Field> {
+        fields.iter().find_map(|f| match pred(&f.summary) {
+            true => Some(f),
+            false => f.children.as_deref().and_then(|c| find_summary(c, pred)),
+        })
+    }
+    #[test]
+    fn test_quic() -> Result<()> {
+        // RFC 9001 appendix A.1
+        let keys = quic::initial_keys(quic::QUIC_V1, &[0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08], false)?;
+        assert_eq!(quic::hex_id(&keys.key), "1f369613dd76d5467730efcbe3b1a22d");
+        assert_eq!(quic::hex_id(&keys.iv), "fa044b2f42a3fd3b46fb255c");
+        assert_eq!(quic::hex_id(&keys.hp), "9f50449e04a0e810283a1e9933adedd2");
+        let keys = quic::initial_keys(quic::QUIC_V1, &[0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08], true)?;
+        assert_eq!(quic::hex_id(&keys.key), "cf3a5331653c364c88f0f379b6067e37");
+
+        let names = ["quic_client_initial", "quic_client_initial2", "quic_server_initial", "quic_short", "quic_short_migrated", "quic_short_newcid"];
+        let mut cx = Context::default();
+        for (i, name) in names.iter().enumerate() {
+            parse_kept(&mut cx, name, 1_000_000 + i as u64 * 1000);
+        }
+        let info: Vec<String> = cx.list.iter().map(|f| protocol::summary(f.tail, &cx, f).unwrap_or_default()).collect();
+        assert!(cx.list.iter().all(|f| matches!(f.tail, Protocol::QUIC)));
+        assert_eq!(info[0], "Initial, PKN: 0, CRYPTO, PADDING");
+        assert_eq!(info[1], "Initial, PKN: 1, CRYPTO, PADDING");
+        assert_eq!(info[2], "Initial, PKN: 0, ACK, CRYPTO; Handshake");
+        assert_eq!(info[3], "Protected Payload");
+
+        let connections = cx.quic.connections();
+        assert_eq!(connections.len(), 1);
+        let conn = &connections[0];
+        assert_eq!((conn.client.as_str(), conn.server.as_str(), conn.version.as_str()), ("10.0.2.1:51000", "10.0.2.2:443", "1"));
+        assert_eq!(conn.original_dcid, "8394c8f03e515708");
+        assert_eq!(conn.sni.as_deref(), Some("example.com"));
+        assert_eq!(conn.alpn, Some(vec!["h3".to_string()]));
+        assert_eq!(conn.client_cids, vec!["0a0b0c0d0e0f1011"]);
+        // the server id from its Initial, then the new one the client switched to
+        assert_eq!(conn.server_cids, vec!["f0f1f2f3f4f5f6f7", "e0e1e2e3e4e5e6e7"]);
+        assert_eq!((conn.packets, conn.migrations), (7, 1));
+
+        // the tls view lists the quic connection next to tcp ones
+        let file = pcap_file(&names);
+        let mut instance = Instance::new(1024, MemoryLoader(file.clone()));
+        instance.update(file)?;
+        let conversations = instance.tls_connections(Criteria { start: 0, size: 10 });
+        assert_eq!(conversations.total, 1);
+        let conversation = &conversations.items[0];
+        assert_eq!((conversation.transport, conversation.primary.as_str(), conversation.second.as_str()), ("quic", "10.0.2.1", "10.0.2.2"));
+        let items = instance.tls_conv_list(conversation.index, Criteria { start: 0, size: 10 });
+        let item = &items.items[0];
+        assert_eq!(item.hostname.as_deref(), Some("example.com"));
+        assert_eq!(item.alpn, Some(vec!["h3".to_string()]));
+        assert_eq!(item.version.as_deref(), Some("TLSv1.3"));
+        assert_eq!(item.addr_1.as_deref(), Some("10.0.2.1:51000"));
+
+        // the second client Initial completes the ClientHello and shows it
+        let (fields, datasources) = instance.select_frame(1).unwrap();
+        let quic_field = fields.last().unwrap();
+        print_field(1, quic_field);
+        let payload = find_summary(&fields, &|s| s.starts_with("Decrypted Payload")).unwrap();
+        assert!(payload.source > 0 && (payload.source as usize) < datasources.len());
+        assert!(find_summary(&fields, &|s| s == "CRYPTO Offset: 1000, Length: 510").is_some());
+        let hello = find_summary(&fields, &|s| s.starts_with("TLS Handshake: ClientHello")).unwrap();
+        assert_eq!(datasources[hello.source as usize].len(), 1510);
+        assert!(find_summary(hello.children.as_deref().unwrap(), &|s| s.contains("example.com")).is_some());
+        let (fields, _) = instance.select_frame(0).unwrap();
+        assert!(find_summary(&fields, &|s| s.starts_with("TLS Handshake")).is_none());
+        Ok(())
+    }
+    #[test]
+    fn test_dtls() -> Result<()> {
+        let names = [
+            "dtls_client_hello",
+            "dtls_hello_verify",
+            "dtls_client_hello2",
+            "dtls_server_hello",
+            "dtls_certificate",
+            "dtls_certificate_retrans",
+            "dtls_client_finished",
+            "dtls_app_data",
+            "dtls_alert",
+            "dtls13_unified",
+            "dtls_bad_cookie",
+        ];
+        let mut cx = Context::default();
+        for (i, name) in names.iter().enumerate() {
+            parse_kept(&mut cx, name, 1_000_000 + i as u64 * 1000);
+        }
+        // port 4433 is not assigned, the ClientHello decides for the flow
+        assert!(cx.list.iter().all(|f| matches!(f.tail, Protocol::DTLS)));
+        let info: Vec<String> = cx.list.iter().map(|f| protocol::summary(f.tail, &cx, f).unwrap_or_default()).collect();
+        assert_eq!(info[0], "ClientHello");
+        assert_eq!(info[1], "HelloVerifyRequest");
+        assert_eq!(info[3], "ServerHello, Certificate (Fragment)");
+        assert_eq!(info[4], "Certificate (Reassembled), ServerHelloDone");
+        assert_eq!(info[5], "Certificate (Retransmission)");
+        assert_eq!(info[6], "ClientKeyExchange, Change Cipher Spec, Encrypted Handshake Message");
+        assert_eq!(info[7], "Application Data");
+        assert_eq!(info[8], "Alert");
+        assert_eq!(info[9], "Encrypted Record");
+
+        let messages = cx.dtls.messages(4);
+        assert_eq!(messages.len(), 2);
+        assert_eq!((messages[0].msg_type, messages[0].message_seq, messages[0].fragments), (11, 2, 2));
+        assert_eq!(cx.dtls.messages(2)[0].cookie, Some(dtls::CookieCheck::Echoed(1)));
+        assert_eq!(cx.dtls.messages(10)[0].cookie, Some(dtls::CookieCheck::Mismatch(1)));
+        assert_eq!(cx.dtls.messages(0)[0].cookie, None);
+        assert!(cx.dtls.messages(5).is_empty());
+
+        let file = pcap_file(&names);
+        let mut instance = Instance::new(1024, MemoryLoader(file.clone()));
+        instance.update(file)?;
+        let (fields, _) = instance.select_frame(0).unwrap();
+        let dtls_field = fields.last().unwrap();
+        print_field(1, dtls_field);
+        assert_eq!(dtls_field.summary, "Datagram Transport Layer Security");
+        assert!(find_summary(&fields, &|s| s == "DTLSv1.0 Record Layer: ClientHello").is_some());
+        assert!(find_summary(&fields, &|s| s == "Cookie Length: 0").is_some());
+        assert!(find_summary(&fields, &|s| s.contains("iot.example")).is_some());
+        assert!(find_summary(&fields, &|s| s == "Version: DTLSv1.3 (0xfefc)").is_some());
+        let (fields, _) = instance.select_frame(1).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Cookie: 0xc0c1c2c3c4c5c6c7c8c9cacbcccdcecf").is_some());
+        let (fields, _) = instance.select_frame(2).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Message Sequence: 1").is_some());
+        assert!(find_summary(&fields, &|s| s == "[Cookie echoed from the HelloVerifyRequest in frame 2]").is_some());
+        let (fields, _) = instance.select_frame(3).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Handshake: Certificate (Fragment)").is_some());
+        assert!(find_summary(&fields, &|s| s == "Epoch: 0").is_some());
+        assert!(find_summary(&fields, &|s| s == "Sequence Number: 2").is_some());
+
+        // the last fragment shows the whole message from its own data source
+        let (fields, datasources) = instance.select_frame(4).unwrap();
+        print_field(1, fields.last().unwrap());
+        assert!(find_summary(&fields, &|s| s == "[Last of 2 fragments, reassembled below]").is_some());
+        let cert = find_summary(&fields, &|s| s.starts_with("Certificate: 1 (reassembled from 2 fragments")).unwrap();
+        assert!(cert.source > 0 && (cert.source as usize) < datasources.len());
+        assert!(find_summary(cert.children.as_deref().unwrap(), &|s| s == "serialNumber: 0x1234").is_some());
+        let (fields, _) = instance.select_frame(5).unwrap();
+        assert!(find_summary(&fields, &|s| s == "[Retransmission of the fragment in frame 5]").is_some());
+        let (fields, _) = instance.select_frame(6).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Encrypted Handshake Message (40 bytes)").is_some());
+        let (fields, _) = instance.select_frame(8).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Alert: Fatal, Handshake Failure").is_some());
+        let (fields, _) = instance.select_frame(9).unwrap();
+        assert!(find_summary(&fields, &|s| s == "DTLSv1.3 Record Layer: Encrypted Record").is_some());
+        assert!(find_summary(&fields, &|s| s == "Epoch (low bits): 3").is_some());
+        assert!(find_summary(&fields, &|s| s == "Sequence Number (encrypted): 0x1234").is_some());
+        let (fields, _) = instance.select_frame(10).unwrap();
+        assert!(find_summary(&fields, &|s| s == "[Expert Info: Cookie does not match the HelloVerifyRequest in frame 2]").is_some());
+        // a fragment announcing a larger length than the pending message restarts it
+        let mut table = dtls::DtlsTable::default();
+        let endpoint = |port| (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), port);
+        let header = |length, offset, fragment_len| dtls::DtlsHandshakeHeader { msg_type: 11, length, message_seq: 7, offset, fragment_len };
+        assert_eq!(table.add(20, endpoint(4433), endpoint(5000), &header(10, 0, 4), &[1; 4]), dtls::DtlsFragment::Partial);
+        assert_eq!(table.add(21, endpoint(4433), endpoint(5000), &header(20, 12, 8), &[2; 8]), dtls::DtlsFragment::Partial);
+        assert_eq!(table.add(22, endpoint(4433), endpoint(5000), &header(20, 0, 12), &[3; 12]), dtls::DtlsFragment::Reassembled(2));
+        assert_eq!(table.messages(22)[0].data[dtls::DTLS_HANDSHAKE_HEADER..], [[3; 12].as_slice(), &[2; 8]].concat());
+        Ok(())
+    }
+    #[test]
+    fn test_mptcp() -> Result<()> {
+        // RFC 8684: token and idsn are the head and tail of SHA-256 over the key
+        assert_eq!(mptcp::key_hash(1, 0x1112131415161718), (0xccad45ac, 0x535beea38e087c8e));
+        let fail = [0x60, 0, 0, 0, 0, 0, 0, 0, 0, 42];
+        assert_eq!(protocol::transport::mptcp::read_option(&fail), Some(mptcp::MptcpOption::Fail(42)));
+        // version 0 ADD_ADDR keeps the ip version where version 1 has the echo flag
+        let add_addr = [0x34, 3, 192, 168, 1, 1];
+        let option = protocol::transport::mptcp::read_option(&add_addr);
+        assert!(matches!(option, Some(mptcp::MptcpOption::AddAddr { address_id: 3, port: None, echo: false, .. })));
+
+        let names = [
+            "mptcp_syn",
+            "mptcp_synack",
+            "mptcp_ack",
+            "mptcp_data1",
+            "mptcp_add_addr",
+            "mptcp_join_syn",
+            "mptcp_join_synack",
+            "mptcp_join_ack",
+            "mptcp_data2",
+            "mptcp_reinject",
+            "mptcp_remove_addr",
+            "mptcp_fastclose",
+            "mptcp_join_unknown",
+        ];
+        let mut cx = Context::default();
+        for (i, name) in names.iter().enumerate() {
+            parse_kept(&mut cx, name, 1_000_000 + i as u64 * 1000);
+        }
+        let connections = cx.mptcp.connections();
+        assert_eq!(connections.len(), 2);
+        let conn = &connections[0];
+        assert_eq!((conn.version, conn.client.as_str(), conn.server.as_str()), (1, "10.0.4.1:50000", "10.0.4.9:5201"));
+        assert_eq!((conn.client_key.as_deref(), conn.server_key.as_deref()), (Some("0102030405060708"), Some("1112131415161718")));
+        assert_eq!((conn.client_token.as_deref(), conn.server_token.as_deref()), (Some("66840dda"), Some("ccad45ac")));
+        // the MP_JOIN token names the server, so the second subflow joins this connection
+        assert_eq!(conn.subflows.len(), 2);
+        let joined = &conn.subflows[1];
+        assert_eq!((joined.source.as_str(), joined.join, joined.address_id, joined.backup), ("10.0.5.1:50001", true, Some(2), true));
+        assert_eq!((conn.subflows[0].packets, joined.packets), (8, 4));
+        assert_eq!(conn.addresses.len(), 1);
+        assert_eq!((conn.addresses[0].address.as_str(), conn.addresses[0].from_client, conn.addresses[0].removed), ("10.0.4.10", false, true));
+        assert_eq!((conn.client_bytes, conn.server_bytes), (150, 0));
+        assert!(conn.fastclose);
+
+        // data sequence across subflows, the resend on the first subflow is a reinjection
+        let mappings = conn.data_sequence();
+        let view: Vec<(u64, u16, usize, bool)> = mappings.iter().map(|m| (m.dsn, m.len, m.subflow, m.reinjection)).collect();
+        assert_eq!(view, vec![(1, 100, 0, false), (101, 50, 1, false), (101, 50, 0, true)]);
+
+        // a join whose token was never seen starts a connection of its own
+        assert_eq!(connections[1].server_token.as_deref(), Some("deadbeef"));
+        assert_eq!(connections[1].subflows.len(), 1);
+
+        let file = pcap_file(&names);
+        let mut instance = Instance::new(1024, MemoryLoader(file.clone()));
+        instance.update(file)?;
+        assert_eq!(instance.mptcp_connections().len(), 2);
+        assert_eq!(instance.mptcp_data_sequence(0).len(), 3);
+        let (fields, _) = instance.select_frame(0).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Multipath TCP: MP_CAPABLE").is_some());
+        let (fields, _) = instance.select_frame(2).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Receiver's Key: 0x1112131415161718").is_some());
+        let (fields, _) = instance.select_frame(3).unwrap();
+        print_field(1, fields.last().unwrap());
+        assert!(find_summary(&fields, &|s| s.starts_with("Multipath TCP: DSS") && s.ends_with("SSN 1, Length 100")).is_some());
+        assert!(find_summary(&fields, &|s| s == "[MPTCP relative data sequence number: 1]").is_some());
+        let (fields, _) = instance.select_frame(4).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Multipath TCP: ADD_ADDR, id 1, 10.0.4.10").is_some());
+        let (fields, _) = instance.select_frame(5).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Multipath TCP: MP_JOIN, token ccad45ac").is_some());
+        let (fields, _) = instance.select_frame(8).unwrap();
+        assert!(find_summary(&fields, &|s| s == "[MPTCP connection index: 0, subflow: 1]").is_some());
+        assert!(find_summary(&fields, &|s| s == "[MPTCP relative data sequence number: 101]").is_some());
+        let (fields, _) = instance.select_frame(11).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Receiver's Key: 0x1112131415161718").is_some());
+        Ok(())
+    }
+    #[test]
+    fn test_http2() -> Result<()> {
+        // RFC 7541 C.4, requests with huffman coding share the dynamic table
+        let mut decoder = hpack::HpackDecoder::default();
+        let first = decoder.decode(&[0x82, 0x86, 0x84, 0x41, 0x8c, 0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff])?;
+        let lines: Vec<String> = first.iter().map(|h| h.line()).collect();
+        assert_eq!(lines, [":method: GET", ":scheme: http", ":path: /", ":authority: www.example.com"]);
+        let second = decoder.decode(&[0x82, 0x86, 0x84, 0xbe, 0x58, 0x86, 0xa8, 0xeb, 0x10, 0x64, 0x9c, 0xbf])?;
+        assert_eq!(second[3].line(), ":authority: www.example.com");
+        assert_eq!(second[4].line(), "cache-control: no-cache");
+        assert!(decoder.decode(&[0xff, 0x7f]).is_err());
+        // peer supplied integers and string lengths fail instead of wrapping
+        let overflow = hpack::HpackDecoder::default().decode(&[0x0f, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]).unwrap_err();
+        assert_eq!(overflow.to_string(), "hpack: integer overflow");
+        assert!(hpack::HpackDecoder::default().decode(&[0x00, 0x7f, 0xff, 0xff, 0xff, 0xff, 0x0f]).is_err());
+
+        let names = [
+            "h2_syn", "h2_synack", "h2_ack", "h2_preface", "h2_server1", "h2_server2", "h2_server3", "h2_post", "h2_post_reply",
+            "h2c_syn", "h2c_synack", "h2c_ack", "h2c_request", "h2c_switch", "h2c_preface",
+        ];
+        let file = pcap_file(&names);
+        let mut instance = Instance::new(1024, MemoryLoader(file.clone()));
+        instance.update(file)?;
+        let cx = &instance.ctx;
+        let info: Vec<String> = cx.list.iter().map(|f| protocol::summary(f.tail, cx, f).unwrap_or_default()).collect();
+        assert_eq!(info[3], "Magic, SETTINGS[0], HEADERS[1]: GET /index.html, HEADERS[3]: GET /style.css");
+        // stream 3 is answered first, the HEADERS of stream 1 goes on in the next segment
+        assert_eq!(info[4], "SETTINGS[0], SETTINGS[0], HEADERS[3]: 200, DATA[3], PUSH_PROMISE[1]: GET /push.js");
+        assert_eq!(info[5], "HEADERS[1]: 200");
+        assert_eq!(info[6], "DATA[1], HEADERS[2]: 200, DATA[2]");
+        assert_eq!(info[7], "HEADERS[5], CONTINUATION[5]: POST /submit, DATA[5], WINDOW_UPDATE[0], PING[0], PRIORITY[7], RST_STREAM[7]");
+        assert_eq!(info[12], "GET /up HTTP/1.1");
+        assert_eq!(info[13], "HTTP/1.1 101 Switching Protocols, SETTINGS[0], HEADERS[1]: 200, DATA[1]");
+        assert!(matches!(cx.list[14].tail, Protocol::HTTP2));
+
+        // every stream is an http connection, the upgraded request gets its answer on stream 1
+        let list = instance.http_connections(Criteria { start: 0, size: 20 }, None, true);
+        let view: Vec<(String, String, usize)> = list.items.iter().map(|c| (c.request.clone().unwrap_or_default(), c.response.clone().unwrap_or_default(), c.length)).collect();
+        assert_eq!(
+            view,
+            [
+                ("GET /index.html HTTP/2".to_string(), "HTTP/2 200".to_string(), 18),
+                ("GET /style.css HTTP/2".to_string(), "HTTP/2 200".to_string(), 6),
+                ("GET /push.js HTTP/2".to_string(), "HTTP/2 200".to_string(), 2),
+                ("POST /submit HTTP/2".to_string(), "HTTP/2 201".to_string(), 2),
+                ("GET /up HTTP/1.1".to_string(), "HTTP/2 200".to_string(), 2),
+            ]
+        );
+        assert_eq!(list.items[0].hostname, "h2.example");
+        let detail = instance.http_detail(0).unwrap();
+        assert_eq!(detail[0].headers[4], ":authority: h2.example");
+        // the padded DATA frame of stream 1 spans two segments
+        assert_eq!(detail[1].content, b"<html>hello</html>");
+        let detail = instance.http_detail(3).unwrap();
+        assert_eq!(detail[0].content, b"a=1");
+        assert_eq!(detail[1].headers.last().unwrap(), "grpc-status: 0");
+        assert_eq!(instance.http_detail(4).unwrap()[1].content, b"up");
+
+        let (fields, datasources) = instance.select_frame(5).unwrap();
+        assert_eq!(datasources.len(), 2);
+        assert!(find_summary(&fields, &|s| s == "Stream: HEADERS, Stream ID: 1, Length 10, 200 (reassembled)").is_some());
+        assert!(find_summary(&fields, &|s| s == "[Frame fragment (22 bytes)]").is_some());
+        let (fields, _) = instance.select_frame(7).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Header: x-long: v0v1v2v3v4v5v6v7v8v9v10v11v12v13v14v15v16v17v18v19").is_some());
+        assert!(find_summary(&fields, &|s| s == "Error: CANCEL (8)").is_some());
+        let (fields, _) = instance.select_frame(8).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Additional Debug Data: bye").is_some());
+        let (fields, _) = instance.select_frame(13).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Hypertext Transfer Protocol").is_some());
+        assert!(find_summary(&fields, &|s| s == "Stream: DATA, Stream ID: 1, Length 2").is_some());
+        Ok(())
+    }
+    #[test]
+    fn test_http_pipelining() -> Result<()> {
+        let names = [
+            "pipe_syn", "pipe_synack", "pipe_ack", "pipe_requests", "pipe_replies1", "pipe_replies2", "pipe_post", "pipe_continue", "pipe_post_body", "pipe_replies3", "pipe_orphan_body",
+        ];
+        let file = pcap_file(&names);
+        let mut instance = Instance::new(1024, MemoryLoader(file.clone()));
+        instance.update(file)?;
+        let cx = &instance.ctx;
+        let info: Vec<String> = cx.list.iter().map(|f| protocol::summary(f.tail, cx, f).unwrap_or_default()).collect();
+        assert_eq!(info[3], "GET /a HTTP/1.1, HEAD /b HTTP/1.1, GET /c HTTP/1.1");
+        assert_eq!(info[9], "HTTP/1.1 204 No Content, HTTP/1.1 200 OK, HTTP/1.1 200 OK");
+
+        // responses are matched to the requests in order, interim and bodiless ones included
+        let list = instance.http_connections(Criteria { start: 0, size: 20 }, None, true);
+        let view: Vec<(String, String)> = list.items.iter().map(|c| (c.request.clone().unwrap_or_default(), c.response.clone().unwrap_or_default())).collect();
+        assert_eq!(
+            view,
+            [
+                ("GET /a HTTP/1.1".to_string(), "HTTP/1.1 200 OK".to_string()),
+                ("HEAD /b HTTP/1.1".to_string(), "HTTP/1.1 200 OK".to_string()),
+                ("GET /c HTTP/1.1".to_string(), "HTTP/1.1 304 Not Modified".to_string()),
+                ("POST /d HTTP/1.1".to_string(), "HTTP/1.1 204 No Content".to_string()),
+                ("GET /e HTTP/1.1".to_string(), "HTTP/1.1 200 OK".to_string()),
+                (String::new(), "HTTP/1.1 200 OK".to_string()),
+            ]
+        );
+        // one packet apart for the first two, /c waits for the next one, /d skips the 100 Continue
+        let latency: Vec<f64> = instance.ctx.http_connections.iter().map(|c| c.latency.0).collect();
+        assert_eq!(latency[1], latency[0]);
+        assert_eq!(latency[2], latency[0] * 2.0);
+        assert_eq!(latency[3], latency[0] * 3.0);
+        assert_eq!(latency[4], latency[0]);
+
+        assert_eq!(instance.http_detail(0).unwrap()[1].content, b"hello");
+        // the HEAD response ends with its headers, the next one starts right after
+        assert!(instance.http_detail(1).unwrap()[1].content.is_empty());
+        assert!(instance.http_detail(2).unwrap()[1].content.is_empty());
+        assert_eq!(instance.http_detail(3).unwrap()[0].content, b"data");
+        assert_eq!(instance.http_detail(4).unwrap()[1].content, b"abc");
+        assert_eq!(instance.http_detail(5).unwrap()[0].content, b"ok");
+
+        let (fields, _) = instance.select_frame(3).unwrap();
+        assert!(find_summary(&fields, &|s| s == "HEAD /b HTTP/1.1").is_some());
+        assert!(find_summary(&fields, &|s| s == "Host: pipe.example").is_some());
+        let (fields, _) = instance.select_frame(8).unwrap();
+        assert!(find_summary(&fields, &|s| s == "GET /e HTTP/1.1").is_some());
+        Ok(())
+    }
+    #[test]
+    fn test_http_objects() -> Result<()> {
+        let names = [
+            "obj_syn", "obj_synack", "obj_ack", "obj_req1", "obj_res1", "obj_req2", "obj_res2", "obj_req3", "obj_res3", "obj_req4", "obj_res4", "obj_req5", "obj_res5", "obj_req6", "obj_res6",
+        ];
+        let file = pcap_file(&names);
+        let mut instance = Instance::new(1024, MemoryLoader(file.clone()));
+        instance.update(file)?;
+
+        // named from Content-Disposition, then the url, then the mime type; the 304 has no body
+        let objects = instance.http_objects();
+        let view: Vec<(usize, &str, &str)> = objects.iter().map(|o| (o.index, o.filename.as_str(), o.content_type.as_str())).collect();
+        assert_eq!(
+            view,
+            [
+                (0, "index.html", "text/html; charset=utf-8"),
+                (1, "logo.png", "image/png"),
+                (2, "logo(1).png", "image/png"),
+                (3, "passwd.txt", "text/plain"),
+                (4, "na_ve_report.pdf", "application/pdf"),
+            ]
+        );
+        assert_eq!(objects[0].url, "http://obj.example/");
+        assert_eq!(objects[0].frame, 4);
+        assert_eq!(objects[1].size, 4);
+        assert_eq!(instance.http_object_content(0).unwrap(), b"<html>hello</html>");
+
+        assert_eq!(export::safe_filename("..\\..\\boot.ini"), "boot.ini");
+        assert_eq!(export::safe_filename("..."), "object");
+        assert_eq!(export::safe_filename("nul.txt"), "_nul.txt");
+        assert_eq!(export::safe_filename("CON"), "_CON");
+        assert_eq!(export::safe_filename("com1.tar.gz"), "_com1.tar.gz");
+        assert_eq!(export::safe_filename("com10.txt"), "com10.txt");
+        assert_eq!(export::safe_filename("console.log"), "console.log");
+        assert_eq!(export::safe_filename("report.."), "report");
+        assert_eq!(export::safe_filename(&format!("{}.tar.gz", "a".repeat(200))).len(), 120);
+        assert_eq!(export::url_filename("http://obj.example/a%20b.js?x=/y"), Some("a b.js".to_string()));
+
+        let dir = std::env::temp_dir().join(format!("pcap_objects_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let files = instance.export_http_objects(&dir, Some(&[0, 3]))?;
+        assert_eq!(files.len(), 2);
+        assert_eq!(std::fs::read(dir.join("index.html"))?, b"<html>hello</html>");
+        assert_eq!(std::fs::read(dir.join("passwd.txt"))?, b"secret");
+        // a second export keeps the files already there
+        let files = instance.export_http_objects(&dir, None)?;
+        assert_eq!(files.len(), 5);
+        assert!(dir.join("index(1).html").exists());
+        assert_eq!(std::fs::read(dir.join("logo(1).png"))?, b"\x89PNG2");
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+    #[test]
+    fn test_websocket() -> Result<()> {
+        let names = ["ws_syn", "ws_synack", "ws_ack", "ws_request", "ws_switch", "ws_client1", "ws_server1", "ws_server2", "ws_server3", "ws_close", "ws_close_reply"];
+        let file = pcap_file(&names);
+        let mut instance = Instance::new(1024, MemoryLoader(file.clone()));
+        instance.update(file)?;
+        let cx = &instance.ctx;
+        let info: Vec<String> = cx.list.iter().map(|f| protocol::summary(f.tail, cx, f).unwrap_or_default()).collect();
+        assert_eq!(info[4], "HTTP/1.1 101 Switching Protocols, WebSocket Text [FIN]");
+        // a ping between the fragments of a message
+        assert_eq!(info[5], "WebSocket Text [FIN] [MASKED], WebSocket Text [MASKED], WebSocket Ping [FIN] [MASKED], WebSocket Continuation [FIN] [MASKED]");
+        assert_eq!(info[6], "WebSocket Text [FIN]");
+        assert_eq!(info[7], "WebSocket Text [FIN], WebSocket Pong [FIN]");
+        assert_eq!(info[8], "WebSocket Binary [FIN]");
+        assert!(matches!(cx.list[9].tail, Protocol::WEBSOCKET));
+
+        let connections = instance.websocket_connections();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].url, "ws://ws.example/chat");
+        assert_eq!(connections[0].protocol.as_deref(), Some("chat"));
+        assert_eq!(connections[0].close, Some((1000, true)));
+        let messages = instance.websocket_messages(0);
+        let view: Vec<(bool, &str, Option<&str>)> = messages.iter().map(|m| (m.from_client, m.opcode.as_str(), m.text.as_deref())).collect();
+        assert_eq!(
+            view,
+            [
+                (false, "Text", Some("welcome")),
+                (true, "Text", Some("hello")),
+                (true, "Ping", None),
+                (true, "Text", Some("a fragmented message")),
+                (false, "Text", Some("hello hello hello")),
+                // the server keeps its compression window, the second message refers back to the first
+                (false, "Text", Some("hello hello hello again")),
+                (false, "Pong", None),
+                (false, "Binary", None),
+                (true, "Connection Close", Some("bye")),
+                (false, "Connection Close", Some("")),
+            ]
+        );
+        assert_eq!(messages[3].fragments, 2);
+        assert!(messages[3].compressed && !messages[0].compressed);
+        assert_eq!(messages[7].length, 200);
+        assert_eq!(messages[9].close_code, Some(1000));
+        assert!(messages.iter().all(|m| !m.truncated));
+
+        let (fields, _) = instance.select_frame(5).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Masking-Key: 0x37fa213d").is_some());
+        assert!(find_summary(&fields, &|s| s == "[Decompressed Text message: 20 bytes in 2 frames]").is_some());
+        assert!(find_summary(&fields, &|s| s == "Text: a fragmented message").is_some());
+        let (fields, datasources) = instance.select_frame(7).unwrap();
+        assert_eq!(datasources.len(), 2);
+        assert!(find_summary(&fields, &|s| s.starts_with("WebSocket Text [FIN], Length") && s.ends_with("(reassembled)")).is_some());
+        assert!(find_summary(&fields, &|s| s == "Text: hello hello hello again").is_some());
+        let (fields, _) = instance.select_frame(8).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Extended Payload length (16 bits): 200").is_some());
+        let (fields, _) = instance.select_frame(9).unwrap();
+        assert!(find_summary(&fields, &|s| s == "Status code: Normal Closure (1000)").is_some());
+        assert!(find_summary(&fields, &|s| s == "Reason: bye").is_some());
+        Ok(())
+    }
+    #[test]
     fn test_ipv6() -> Result<()> {
         let (ds, mut cx, mut frame) = init("ipv6");
         {
@@ -336,6 +1760,133 @@ mod unit {
         Ok(())
     }
 
+    #[test]
+    fn test_ipv4_checksum() -> Result<()> {
+        let (ds, mut cx, mut frame) = init("ipv4");
+        cx.config.verify_checksum = true;
+        {
+            let mut reader = Reader::new(&ds);
+            protocol::network::ip4::Visitor::parse(&mut cx, &mut frame, &mut reader)?;
+            assert!(matches!(frame.info.status, PacketStatus::NORNAL));
+        }
+        {
+            let mut reader = Reader::new(&ds);
+            let mut f = Field::children();
+            protocol::network::ip4::Visitor::detail(&mut f, &cx, &frame, &mut reader)?;
+            let children = f.children.as_ref().unwrap();
+            assert!(children.iter().any(|c| c.summary == "Header Checksum: 0xc548 [correct]"));
+        }
+        let mut data = build_reader("ipv4");
+        data[8] = 0x3f; // ttl changed, checksum left untouched
+        let broken = DataSource::create(data, 0..20);
+        let mut frame = Frame::default();
+        let mut reader = Reader::new(&broken);
+        protocol::network::ip4::Visitor::parse(&mut cx, &mut frame, &mut reader)?;
+        assert!(matches!(frame.info.status, PacketStatus::ERROR));
+        assert_eq!(frame.info.reason, Some("Bad IPv4 header checksum"));
+        Ok(())
+    }
+    #[test]
+    fn test_esp_reassembly() -> Result<()> {
+        let names = ["esp_http_syn", "esp_http_synack", "esp_http_ack", "esp_http_req", "esp_http_res"];
+        let file = pcap_file(&names);
+        let mut instance = Instance::new(1024, MemoryLoader(file.clone()));
+        instance.update(file)?;
+        assert!(matches!(instance.ctx.list[4].tail, Protocol::ESP));
+        // frames already loaded are decrypted once the keys are known
+        let count = instance.set_esp_sa("\"IPv4\",\"*\",\"*\",\"0x00001000\",\"2\",\"0x000102030405060708090a0b0c0d0e0f\",\"0\",\"\"\n\"IPv4\",\"*\",\"*\",\"0x00001001\",\"2\",\"0x000102030405060708090a0b0c0d0e0f\",\"0\",\"\"")?;
+        assert_eq!(count, 2);
+        assert_eq!(instance.ctx.list.len(), 5);
+        assert!(matches!(instance.ctx.list[4].tail, Protocol::HTTP));
+
+        // ranges of the inner segments are read from the plain text, not from the capture file
+        let detail = instance.http_detail(0).unwrap();
+        assert_eq!(detail[0].headers[0], "GET /secret.txt HTTP/1.1");
+        assert_eq!(detail[1].content, b"hidden");
+        let stream = instance.follow_tcp_stream(0, 0, Criteria { start: 0, size: 10 });
+        assert_eq!(stream.items.len(), 2);
+        assert!(stream.items[0].data.starts_with(b"GET /secret.txt"));
+        assert!(stream.items[1].data.ends_with(b"hidden"));
+
+        let (fields, datasources) = instance.select_frame(4).unwrap();
+        let status = find_summary(&fields, &|s| s == "HTTP/1.1 200 OK").unwrap();
+        let ds = &datasources[status.source as usize];
+        let start = status.start - ds.range.start;
+        assert_eq!(&ds.data[start..start + 8], b"HTTP/1.1");
+        Ok(())
+    }
+    #[test]
+    fn test_set_verify_checksum() -> Result<()> {
+        let mut file = pcap_file(&["ipv4"]);
+        // ttl of the ip header behind the file, record and ethernet headers
+        file[24 + 16 + 14 + 8] = 0x3f;
+        let mut instance = Instance::new(1024, MemoryLoader(file.clone()));
+        instance.update(file)?;
+        assert_eq!(instance.ctx.list[0].info.reason, None);
+        // frames already loaded are checked once it is turned on
+        instance.set_verify_checksum(true)?;
+        assert_eq!(instance.ctx.list.len(), 1);
+        assert_eq!(instance.ctx.list[0].info.reason, Some("Bad IPv4 header checksum"));
+        instance.set_verify_checksum(false)?;
+        assert_eq!(instance.ctx.list[0].info.reason, None);
+        Ok(())
+    }
+    #[test]
+    fn test_icmp_checksum() -> Result<()> {
+        let (ds, mut cx, mut frame) = init("icmp");
+        cx.config.verify_checksum = true;
+        frame.iplen = 64;
+        let reader = Reader::new(&ds);
+        assert_eq!(checksum::verify_transport(&cx, &frame, &reader, checksum::IP_PROTO_ICMP, 64, 2), ChecksumStatus::Correct);
+
+        let mut data = build_reader("icmp");
+        data[8] ^= 0xff;
+        let broken = DataSource::create(data, 0..64);
+        let reader = Reader::new(&broken);
+        let status = checksum::verify_transport(&cx, &frame, &reader, checksum::IP_PROTO_ICMP, 64, 2);
+        assert_eq!(status, ChecksumStatus::Incorrect(0x302b));
+        Ok(())
+    }
+    #[test]
+    fn test_transport_checksum() -> Result<()> {
+        let mut cx = Context::default();
+        let mut frame = Frame {
+            address_field: AddressField::IPv4(Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)),
+            ..Default::default()
+        };
+        let mut udp = vec![0x30, 0x39, 0x00, 0x35, 0x00, 0x0c, 0x00, 0x00, 0x61, 0x62, 0x63, 0x64];
+        {
+            let ds = DataSource::create(udp.clone(), 0..12);
+            let reader = Reader::new(&ds);
+            let status = checksum::verify_transport(&cx, &frame, &reader, checksum::IP_PROTO_UDP, 12, 6);
+            assert_eq!(status, ChecksumStatus::Absent);
+        }
+        let mut pseudo = vec![10, 0, 0, 1, 10, 0, 0, 2, 0, 17, 0, 12];
+        pseudo.extend_from_slice(&udp);
+        let sum = checksum::inet_checksum(&pseudo);
+        udp[6..8].copy_from_slice(&sum.to_be_bytes());
+        {
+            let ds = DataSource::create(udp.clone(), 0..12);
+            let reader = Reader::new(&ds);
+            let status = checksum::verify_transport(&cx, &frame, &reader, checksum::IP_PROTO_UDP, 12, 6);
+            assert_eq!(status, ChecksumStatus::Correct);
+        }
+        {
+            // tcp segment captured on the sending host before the NIC fills in the checksum
+            let mut data = build_reader("tcp");
+            data[16] = 0;
+            data[17] = 0;
+            let ds = DataSource::create(data, 0..20);
+            cx.config.verify_checksum = true;
+            frame.iplen = 20;
+            let mut reader = Reader::new(&ds);
+            assert_eq!(checksum::verify_transport(&cx, &frame, &reader, checksum::IP_PROTO_TCP, 20, 16), ChecksumStatus::Offloaded);
+            protocol::transport::tcp::Visitor::parse(&mut cx, &mut frame, &mut reader)?;
+            assert!(frame.info.reason.is_none());
+        }
+        Ok(())
+    }
+
     #[test]
     fn funcs() -> Result<()> {
         let range = 4..8;
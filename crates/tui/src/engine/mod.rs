@@ -2,6 +2,7 @@ use std::fs::File;
 use std::io::BufReader;
 use std::io::Read;
 use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::Receiver;
 use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -10,6 +11,7 @@ use pcap::common::concept::{
     ConversationCriteria, Criteria, Field, FrameIndex, FrameInfo, HttpMessageDetail, Language, ListResult, ProgressStatus, VConnection, VConversation, VHttpConnection,
 };
 use pcap::common::io::DataSource;
+use pcap::common::stream::StreamChunk;
 use pcap::common::{Instance, ResourceLoader};
 use std::sync::mpsc::Sender;
 use util::{file_seek, file_seeks};
@@ -26,6 +28,11 @@ pub enum PcapEvent {
     ConnectionList(ListResult<VConnection>),
     HttpConnectionList(ListResult<VHttpConnection>),
     HttpContent(Option<HttpMessageWrap>, Option<HttpMessageWrap>),
+    StreamData(ListResult<StreamChunk>),
+    // outcome of an export, shown under the list
+    Notice(String),
+    // outcome of new decode as rules, the frames were dissected again
+    DecodeAs(String),
 }
 
 pub enum PcapUICommand {
@@ -39,6 +46,11 @@ pub enum PcapUICommand {
     HttpConnectionList(usize, usize),
     HttpContent(VHttpConnection),
     HttpDetail(usize),
+    FollowStream(usize, usize, usize, usize),
+    // the response body of one http connection, or all of them
+    ExportObjects(Option<usize>),
+    // decode as rules replacing the current ones
+    DecodeAs(String),
 }
 
 pub struct LocalResource {
@@ -60,6 +72,41 @@ impl LocalResource {
     }
 }
 
+/**
+ * where the objects of a capture are exported, next to the file
+ */
+pub fn objects_dir(fname: &str) -> PathBuf {
+    let path = Path::new(fname);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!("{stem}_objects"))
+}
+
+/**
+ * parses the whole capture without the ui and writes its http objects into dir
+ */
+pub fn export_objects(fname: &str, decode_as: Option<&str>, esp_sa: Option<&str>, verify_checksum: bool, dir: &Path) -> anyhow::Result<Vec<String>> {
+    let batch_size = 1024 * 256;
+    let mut ins = Instance::new(batch_size, LocalResource::new(fname.to_string()));
+    if let Some(content) = decode_as {
+        ins.set_decode_as(content)?;
+    }
+    if let Some(content) = esp_sa {
+        ins.set_esp_sa(content)?;
+    }
+    ins.set_verify_checksum(verify_checksum)?;
+    let mut reader = BufReader::new(File::open(fname)?);
+    let mut buffer = vec![0; batch_size];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        ins.update(buffer[..n].to_vec())?;
+    }
+    ins.finish();
+    ins.export_http_objects(dir, None)
+}
+
 pub struct HttpMessageWrap {
     pub headers: Vec<String>,
     pub mime: Language,
@@ -86,17 +133,34 @@ pub struct Service {
     fname: String,
     sender: Sender<PcapEvent>,
     receiver: Receiver<PcapUICommand>,
+    // decode as rules applied before the first frame
+    pub decode_as: Option<String>,
+    pub verify_checksum: bool,
+    // esp_sa file content, the keys to decrypt esp
+    pub esp_sa: Option<String>,
+    // content of the .mmdb files given on the command line
+    pub geoip: Vec<Vec<u8>>,
 }
 
 impl Service {
     pub fn new(fname: String, sender: Sender<PcapEvent>, receiver: Receiver<PcapUICommand>) -> Self {
         let file = File::open(fname.clone()).unwrap();
-        Self { fname, file, sender, receiver }
+        Self { fname, file, sender, receiver, decode_as: None, verify_checksum: false, esp_sa: None, geoip: vec![] }
     }
     pub fn run(&mut self) -> anyhow::Result<()> {
         let batch_size = 1024 * 256;
         let loader = LocalResource::new(self.fname.clone());
         let mut ins = Instance::new(batch_size, loader);
+        if let Some(content) = &self.decode_as {
+            ins.set_decode_as(content)?;
+        }
+        if let Some(content) = &self.esp_sa {
+            ins.set_esp_sa(content)?;
+        }
+        ins.set_verify_checksum(self.verify_checksum)?;
+        for data in self.geoip.drain(..) {
+            ins.add_geoip_database(data)?;
+        }
         let mut reader = BufReader::new(&mut self.file);
         let mut pos = 0;
         let mut buffer = vec![0; batch_size];
@@ -136,6 +200,11 @@ impl Service {
                         let result_list = ins.connections(key, cri);
                         self.sender.send(PcapEvent::ConnectionList(result_list)).unwrap();
                     }
+                    PcapUICommand::FollowStream(key, connection, start, size) => {
+                        let cri = Criteria { start, size };
+                        let result_list = ins.follow_tcp_stream(key, connection, cri);
+                        self.sender.send(PcapEvent::StreamData(result_list)).unwrap();
+                    }
                     PcapUICommand::HttpConnectionList(start, size) => {
                         let cri = Criteria { start, size };
                         let result_list = ins.http_connections(cri, None, true);
@@ -166,6 +235,26 @@ impl Service {
                             self.sender.send(PcapEvent::HttpContent(request, response)).unwrap();
                         }
                     }
+                    PcapUICommand::DecodeAs(content) => {
+                        let notice = match ins.set_decode_as(&content) {
+                            Ok(count) => {
+                                self.decode_as = Some(content);
+                                format!("{count} decode as rule(s) applied")
+                            }
+                            Err(e) => format!("Invalid decode as rules: {e}"),
+                        };
+                        self.sender.send(PcapEvent::DecodeAs(notice)).unwrap();
+                    }
+                    PcapUICommand::ExportObjects(index) => {
+                        let dir = objects_dir(&self.fname);
+                        let indexes = index.map(|index| vec![index]);
+                        let notice = match ins.export_http_objects(&dir, indexes.as_deref()) {
+                            Ok(files) if files.is_empty() => "No object to export".to_string(),
+                            Ok(files) => format!("{} object(s) exported to {}", files.len(), dir.display()),
+                            Err(e) => format!("Export failed: {e}"),
+                        };
+                        self.sender.send(PcapEvent::Notice(notice)).unwrap();
+                    }
 
                     // PcapUICommand::HttpContent(http_connection) => {
                     //     if let Ok(mut file) = File::open(&self.fname) {
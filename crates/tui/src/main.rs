@@ -1,7 +1,8 @@
 use std::{fs::{self}, sync::mpsc};
 
 use clap::Parser;
-use pcapviewer_tui::{engine::{PcapUICommand, PcapEvent, Service}, ui};
+use pcap::common::{decode::parse_decode_as, geoip::GeoIp, ipsec::parse_esp_sa};
+use pcapviewer_tui::{engine::{export_objects, PcapUICommand, PcapEvent, Service}, ui};
 
 
 
@@ -12,6 +13,21 @@ use pcapviewer_tui::{engine::{PcapUICommand, PcapEvent, Service}, ui};
 struct Args {
     #[arg(short, long)]
     file: String,
+    /// decode as rules file, one rule per line like "udp 5300 dns"
+    #[arg(short, long)]
+    decode_as: Option<String>,
+    /// write every http response body into this directory and exit
+    #[arg(long)]
+    export_objects: Option<String>,
+    /// verify ip, transport and icmp checksums
+    #[arg(long, default_value_t = false)]
+    verify_checksum: bool,
+    /// maxmind .mmdb country, city or asn database, may be repeated
+    #[arg(long)]
+    geoip: Vec<String>,
+    /// wireshark esp_sa file with the keys to decrypt esp
+    #[arg(long)]
+    esp_sa: Option<String>,
 }
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
@@ -20,14 +36,57 @@ fn main() -> anyhow::Result<()> {
         eprintln!("File [{}] not exists", fname);
         std::process::exit(1);
     }
-    start(&fname)
+    let decode_as = match args.decode_as {
+        Some(path) => {
+            let content = fs::read_to_string(&path)?;
+            if let Err(e) = parse_decode_as(&content) {
+                eprintln!("Invalid decode as rules [{}]: {}", path, e);
+                std::process::exit(1);
+            }
+            Some(content)
+        }
+        None => None,
+    };
+    let esp_sa = match args.esp_sa {
+        Some(path) => {
+            let content = fs::read_to_string(&path)?;
+            if let Err(e) = parse_esp_sa(&content) {
+                eprintln!("Invalid esp_sa file [{}]: {}", path, e);
+                std::process::exit(1);
+            }
+            Some(content)
+        }
+        None => None,
+    };
+    let mut geoip = vec![];
+    for path in &args.geoip {
+        let data = fs::read(path)?;
+        if let Err(e) = GeoIp::default().load(data.clone()) {
+            eprintln!("Invalid geoip database [{}]: {}", path, e);
+            std::process::exit(1);
+        }
+        geoip.push(data);
+    }
+    if let Some(dir) = args.export_objects {
+        let files = export_objects(&fname, decode_as.as_deref(), esp_sa.as_deref(), args.verify_checksum, std::path::Path::new(&dir))?;
+        for file in &files {
+            println!("{}", file);
+        }
+        eprintln!("{} object(s) exported to {}", files.len(), dir);
+        return Ok(());
+    }
+    start(&fname, decode_as, esp_sa, args.verify_checksum, geoip)
 }
 
-fn start(_fname: &str) -> anyhow::Result<()> {
+fn start(_fname: &str, decode_as: Option<String>, esp_sa: Option<String>, verify_checksum: bool, geoip: Vec<Vec<u8>>) -> anyhow::Result<()> {
     let (etx, erx) = mpsc::channel::<PcapEvent>();
     let (ptx, prx) = mpsc::channel::<PcapUICommand>();
     let ui = ui::UI::new(ptx, erx);
     let mut engine = Service::new(_fname.to_string(), etx, prx);
+    engine.decode_as = decode_as;
+    engine.verify_checksum = verify_checksum;
+    engine.esp_sa = esp_sa;
+    engine.geoip = geoip;
     let logic_handle = std::thread::spawn(move || {
         engine.run().unwrap();
     });
@@ -58,7 +58,7 @@ pub fn get_protocol_color(protocol: &str) -> Style {
         "udp" => Style::new().fg(GRUVBOX_BG_H_0).bg(GRUVBOX_D_YELLOW),
         "ssdp" => Style::new().fg(GRUVBOX_FG_0).bg(SSDP_C),
         "error" => Style::new().fg(ERROR_FG).bg(ERROR_BG),
-        "http" => Style::new().fg(HTTP_FG).bg(HTTP_BG),
+        "http" | "http2" | "websocket" => Style::new().fg(HTTP_FG).bg(HTTP_BG),
         "deactive" => Style::new().fg(DEACTIVE_FG).bg(DEACTIVE_BG),
         "dns" => Style::new().fg(DNS_FG).bg(DNS_BG),
         "icmp" => Style::new().fg(ICMP_FG).bg(ICMP_BG),
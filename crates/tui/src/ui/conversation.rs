@@ -1,10 +1,12 @@
 use crossterm::event::{KeyCode, KeyEvent};
 use pcap::common::{
-    concept::{VConnection, VConversation},
+    concept::{ListResult, VConnection, VConversation},
+    geoip::GeoInfo,
+    stream::{StreamChunk, StreamDirection},
     util::format_bytes_single_unit_int,
 };
 use ratatui::{
-    buffer::Buffer, layout::{Constraint, Rect}, widgets::{Block, Widget}
+    buffer::Buffer, layout::{Constraint, Rect}, style::Style, text::Line, widgets::{Block, Paragraph, Widget}
 };
 
 use crate::{
@@ -15,7 +17,41 @@ use crate::{
 pub struct Conversation {
     state: CustomTableState<VConversation>,
     detail: Option<(usize, String, CustomTableState<VConnection>)>,
+    stream: Option<StreamView>,
 }
+
+// payload of the selected connection, both directions in stream order
+struct StreamView {
+    connection: usize,
+    list: ListResult<StreamChunk>,
+    scroll: u16,
+}
+
+impl Widget for &StreamView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut lines = vec![];
+        for chunk in &self.list.items {
+            let color = match chunk.direction {
+                StreamDirection::Forward => crate::theme::GRUVBOX_D_RED,
+                StreamDirection::Backward => crate::theme::GRUVBOX_D_BLUE,
+            };
+            let style = Style::default().fg(color);
+            lines.push(Line::styled(format!("#{} ({} bytes)", chunk.index + 1, chunk.data.len()), crate::theme::BLANK_FROZEN));
+            for line in String::from_utf8_lossy(&chunk.data).lines() {
+                lines.push(Line::styled(line.to_string(), style));
+            }
+        }
+        Paragraph::new(lines).block(super::block::content_border_low()).scroll((self.scroll, 0)).render(area, buf);
+    }
+}
+// the address followed by its country code when a geoip database is loaded
+fn with_country(address: &str, geo: &Option<GeoInfo>) -> String {
+    match geo.as_ref().and_then(|geo| geo.country.as_ref()) {
+        Some(country) => format!("{address} [{country}]"),
+        None => address.to_string(),
+    }
+}
+
 pub struct ConversationStyle;
 impl TableStyle<VConversation> for ConversationStyle {
     fn get_header_style(&self) -> ratatui::prelude::Style {
@@ -45,8 +81,8 @@ impl TableStyle<VConversation> for ConversationStyle {
         vec![
             if selected { "⏎".into() } else { "".into() },
             format!("{}", data.connects),
-            data.sender.clone(),
-            data.receiver.clone(),
+            with_country(&data.sender, &data.sender_geo),
+            with_country(&data.receiver, &data.receiver_geo),
             format!("{}", tx_p + rx_p),
             format_bytes_single_unit_int((tx_b + rx_b) as usize),
             format!("{}", tx_p),
@@ -90,7 +126,7 @@ impl TableStyle<VConnection> for ConnectionStyle {
     }
 
     fn get_cols(&self) -> Vec<&str> {
-        vec!["", "Protocol", "S-port", "R-port", "TX-Packets", "TX-Bytes", "TX-Used", "RX-Packets", "RX-Bytes", "RX-Used"]
+        vec!["", "Protocol", "S-port", "R-port", "TX-Packets", "TX-Bytes", "TX-Used", "RX-Packets", "RX-Bytes", "RX-Used", "Handshake", "End"]
     }
 
     fn get_row(&self, data: &VConnection, selected: bool) -> Vec<String> {
@@ -105,6 +141,8 @@ impl TableStyle<VConnection> for ConnectionStyle {
             format!("{}", data.second.statistic.count),
             format_bytes_single_unit_int(data.second.statistic.throughput as usize),
             format_bytes_single_unit_int(data.second.statistic.clean_throughput as usize),
+            data.metrics.irtt.map(|v| format!("{:.3}ms", v as f64 / 1000.0)).unwrap_or_default(),
+            format!("{:?}", data.metrics.close),
         ]
     }
 
@@ -120,6 +158,8 @@ impl TableStyle<VConnection> for ConnectionStyle {
             Constraint::Min(10),
             Constraint::Min(12),
             Constraint::Min(12),
+            Constraint::Min(10),
+            Constraint::Min(12),
         ]
     }
     fn get_block(&self) -> Option<ratatui::widgets::Block<'_>> {
@@ -136,6 +176,10 @@ impl Widget for &mut Conversation {
         if let Some((_,_, state)) = &self.detail {
             let rects = ratatui::layout::Layout::vertical([Constraint::Length(8), Constraint::Min(10)]).split(area);
             render_table(ConversationStyle, &self.state, rects[0], buf,1);
+            if let Some(stream) = &self.stream {
+                stream.render(rects[1], buf);
+                return;
+            }
             render_table(ConnectionStyle, state, rects[1], buf, 0);
             return;
         }
@@ -144,6 +188,7 @@ impl Widget for &mut Conversation {
 }
 
 const PAGE_SIZE: usize = 100;
+const STREAM_PAGE_SIZE: usize = 50;
 
 impl ControlState for Conversation {
     fn control(&mut self, _: bool, event: KeyEvent) -> PcapUICommand {
@@ -151,12 +196,53 @@ impl ControlState for Conversation {
             return PcapUICommand::None;
         }
         
+        if let (Some((key, _, _)), Some(stream)) = (&self.detail, &mut self.stream) {
+            let index = *key;
+            match event.code {
+                KeyCode::Backspace => {
+                    self.stream = None;
+                }
+                KeyCode::Down => {
+                    stream.scroll = stream.scroll.saturating_add(1);
+                }
+                KeyCode::Up => {
+                    stream.scroll = stream.scroll.saturating_sub(1);
+                }
+                KeyCode::Right => {
+                    let start = stream.list.start + stream.list.items.len();
+                    if start < stream.list.total {
+                        return PcapUICommand::FollowStream(index, stream.connection, start, STREAM_PAGE_SIZE);
+                    }
+                    return PcapUICommand::None;
+                }
+                KeyCode::Left => {
+                    if stream.list.start == 0 {
+                        return PcapUICommand::None;
+                    }
+                    let start = stream.list.start.saturating_sub(STREAM_PAGE_SIZE);
+                    return PcapUICommand::FollowStream(index, stream.connection, start, STREAM_PAGE_SIZE);
+                }
+                _ => {
+                    return PcapUICommand::None;
+                }
+            }
+            return PcapUICommand::Refresh;
+        }
+
         if let Some((key,_, state)) = &mut self.detail {
             let index = *key;
             match event.code {
                 KeyCode::Backspace => {
                     self.detail = None;
                 }
+                KeyCode::Enter => {
+                    if let Some(item) = state.list.items.get(state.select) {
+                        let connection = item.index;
+                        self.stream = Some(StreamView { connection, list: ListResult::empty(), scroll: 0 });
+                        return PcapUICommand::FollowStream(index, connection, 0, STREAM_PAGE_SIZE);
+                    }
+                    return PcapUICommand::None;
+                }
                 KeyCode::Down => {
                     state.to_next();
                 }
@@ -258,6 +344,14 @@ impl ControlState for Conversation {
                 // self.detail.as_mut().unwrap().1.update(list);
                 // PcapUICommand::Refresh
             }
+            PcapEvent::StreamData(list) => {
+                if let Some(stream) = &mut self.stream {
+                    stream.list = list;
+                    stream.scroll = 0;
+                    return PcapUICommand::Refresh;
+                }
+                PcapUICommand::None
+            }
             _ => PcapUICommand::None,
         }
     }
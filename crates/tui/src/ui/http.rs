@@ -4,7 +4,8 @@ use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Rect},
     style::Modifier,
-    widgets::{Block, Scrollbar, ScrollbarOrientation, StatefulWidget, Widget},
+    text::Line,
+    widgets::{Block, Paragraph, Scrollbar, ScrollbarOrientation, StatefulWidget, Widget},
 };
 use tui_tree_widget::{Tree, TreeItem, TreeState};
 
@@ -23,6 +24,7 @@ use crate::{
 pub struct Page {
     state: CustomTableState<VHttpConnection>,
     detail: Option<HttpHeadersView>,
+    notice: Option<String>,
 }
 pub struct ConversationStyle;
 impl TableStyle<VHttpConnection> for ConversationStyle {
@@ -86,7 +88,15 @@ impl Widget for &mut Page {
             view.render(rects[1], buf);
             return;
         }
-        render_table(ConversationStyle, &self.state, area, buf, 0);
+        match &self.notice {
+            Some(notice) => {
+                let vertical = ratatui::layout::Layout::vertical([Constraint::Min(5), Constraint::Length(1)]);
+                let rects = vertical.split(area);
+                render_table(ConversationStyle, &self.state, rects[0], buf, 0);
+                Paragraph::new(Line::styled(notice.as_str(), crate::theme::BLANK_FROZEN)).render(rects[1], buf);
+            }
+            None => render_table(ConversationStyle, &self.state, area, buf, 0),
+        }
     }
 }
 
@@ -115,6 +125,15 @@ impl ControlState for Page {
                     // return PcapUICommand::HttpContent(item.clone());
                 }
             }
+            // e exports the body of the selected response, a all of them
+            KeyCode::Char('e') => {
+                if let Some(item) = self.state.list.items.get(self.state.select) {
+                    return PcapUICommand::ExportObjects(Some(item.index));
+                }
+            }
+            KeyCode::Char('a') => {
+                return PcapUICommand::ExportObjects(None);
+            }
             KeyCode::Down => {
                 self.state.to_next();
             }
@@ -172,6 +191,10 @@ impl ControlState for Page {
                 self.detail = Some(HttpHeadersView::new(rs));
                 PcapUICommand::Refresh
             }
+            PcapEvent::Notice(notice) => {
+                self.notice = Some(notice);
+                PcapUICommand::Refresh
+            }
 
             // PcapEvent::ConnectionList(list) => {
             //     if let Some((key, title, mut state)) = self.detail.take() {
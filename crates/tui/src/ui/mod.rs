@@ -42,7 +42,8 @@ fn try_handle_event(app: &mut MainUI) -> PcapUICommand {
     if event::poll(Duration::from_millis(10)).unwrap() {
         if let Ok(Event::Key(key)) = event::read() {
             let shift_pressed = key.modifiers.contains(KeyModifiers::SHIFT);
-            if key.kind == KeyEventKind::Press {
+            // keys go to the decode as line while it is edited
+            if key.kind == KeyEventKind::Press && !app.editing() {
                 match key.code {
                     KeyCode::Char('q') | KeyCode::Esc => {
                         return PcapUICommand::Quit;
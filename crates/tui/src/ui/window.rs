@@ -27,6 +27,10 @@ pub struct MainUI {
     progress: Option<ProgressStatus>,
     container: TabContainer,
     active_tab: usize,
+    // decode as rules being typed, separated by ';'
+    input: Option<String>,
+    // outcome of the last decode as rules
+    notice: Option<String>,
 }
 
 impl Widget for &mut MainUI {
@@ -48,8 +52,31 @@ impl MainUI {
             container: TabContainer::Frame(frames::App::default()),
             active_tab: 0,
             progress: None,
+            input: None,
+            notice: None,
         }
     }
+    pub fn editing(&self) -> bool {
+        self.input.is_some()
+    }
+    fn edit(&mut self, event: KeyEvent) -> PcapUICommand {
+        let Some(input) = self.input.as_mut() else {
+            return PcapUICommand::None;
+        };
+        match event.code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.input = None,
+            KeyCode::Enter => {
+                let rules = self.input.take().unwrap_or_default().replace(';', "\n");
+                return PcapUICommand::DecodeAs(rules);
+            }
+            _ => return PcapUICommand::None,
+        }
+        PcapUICommand::Refresh
+    }
     fn render_tab_view(&mut self, area: Rect, buf: &mut Buffer) {
         let titles = TAB_NAMES.iter().map(create_tab_title);
         let selected_tab_index = self.active_tab;
@@ -73,7 +100,11 @@ impl MainUI {
         use ratatui::layout::Constraint::{Length, Min};
 
         let mut str_len = 0;
-        let tips = "◄ ► to change page | SHIFT+(◄ ►) to change tab | Press q or ESC to quit";
+        let tips = match (&self.input, &self.notice) {
+            (Some(input), _) => format!("Decode as (e.g. udp 5300 dns; tcp 8443 tls): {input}▏ Enter to apply, ESC to cancel"),
+            (None, Some(notice)) => format!("{notice} | : decode as | Press q or ESC to quit"),
+            (None, None) => "◄ ► to change page | SHIFT+(◄ ►) to change tab | : decode as | Press q or ESC to quit".to_string(),
+        };
         let left_text = vec![Span::styled(tips, Style::default().fg(Color::Green))];
         let left_paragraph = Paragraph::new(Line::from(left_text).bold())
             .block(Block::default())
@@ -135,6 +166,13 @@ impl Default for MainUI {
 
 impl ControlState for MainUI {
     fn control(&mut self, shift_pressed: bool, event: KeyEvent) -> PcapUICommand {
+        if self.editing() {
+            return self.edit(event);
+        }
+        if let KeyCode::Char(':') = event.code {
+            self.input = Some(String::new());
+            return PcapUICommand::Refresh;
+        }
         if shift_pressed {
             match event.code {
                 KeyCode::Left => {
@@ -172,6 +210,11 @@ impl ControlState for MainUI {
                     PcapUICommand::None
                 }
             }
+            PcapEvent::DecodeAs(notice) => {
+                self.notice = Some(notice);
+                // the lists changed, the page starts over
+                self.tab_select(self.active_tab)
+            }
             _ => self.container.update(event),
         }
     }
@@ -1,11 +1,11 @@
-use std::{ops::Range, path::Path, sync::Arc, time::Duration};
+use std::{ops::Range, path::{Path, PathBuf}, sync::Arc, time::Duration};
 
 use anyhow::bail;
 use pcap::common::{
     Instance, ResourceLoader, concept::{
-        ConversationCriteria, Criteria, DNSRecord, DNSResponse, Field, FrameIndex, FrameInfo, HttpCriteria, HttpMessageDetail, ListResult, ProgressStatus, TLSConversation,
+        ConnectionOrder, ConversationCriteria, Criteria, DNSRecord, DNSResponse, Field, FrameIndex, FrameInfo, HttpCriteria, HttpMessageDetail, ListResult, ProgressStatus, TLSConversation,
         TLSItem, UDPConversation, VConnection, VConversation, VHttpConnection,
-    }, file::Metadata, io::DataSource
+    }, decode::parse_decode_as, dhcp::DhcpTransaction, ipsec::parse_esp_sa, export::HttpObject, file::Metadata, geoip::GeoIp, graph::ConnectionGraph, io::DataSource, mptcp::MptcpMapping, stream::StreamChunk, websocket::WebSocketMessage
 };
 use serde::{Deserialize, Serialize};
 use tokio::{
@@ -29,14 +29,27 @@ pub enum UICommand {
     List(oneshot::Sender<String>, String),
     Stat(oneshot::Sender<String>, String),
     TCPList(oneshot::Sender<ListResult<VConversation>>, Criteria, ConversationCriteria),
-    TCPConvList(oneshot::Sender<ListResult<VConnection>>, usize, Criteria),
+    TCPConvList(oneshot::Sender<ListResult<VConnection>>, usize, Criteria, ConnectionOrder),
     UDPList(oneshot::Sender<ListResult<UDPConversation>>, Criteria, Option<String>, bool),
     TLSList(oneshot::Sender<ListResult<TLSConversation>>, Criteria),
     TLSDetail(oneshot::Sender<ListResult<TLSItem>>, usize, Criteria),
     DNSRecords(oneshot::Sender<ListResult<DNSResponse>>, Criteria, bool),
     DNSRecord(oneshot::Sender<ListResult<DNSRecord>>, usize, Criteria),
+    DHCPFailures(oneshot::Sender<ListResult<DhcpTransaction>>, Criteria),
     HTTPList(oneshot::Sender<ListResult<VHttpConnection>>, Criteria, Option<HttpCriteria>, bool),
     HTTPDetail(oneshot::Sender<Option<Vec<HttpMessageDetail>>>, usize),
+    HTTPObjects(oneshot::Sender<Vec<HttpObject>>),
+    HTTPObject(oneshot::Sender<Option<Vec<u8>>>, usize),
+    HTTPExport(oneshot::Sender<Result<Vec<String>, String>>, PathBuf, Option<Vec<usize>>),
+    LoadGeoIp(oneshot::Sender<Result<String, String>>, String),
+    DecodeAs(oneshot::Sender<Result<usize, String>>, String),
+    EspSa(oneshot::Sender<Result<usize, String>>, String),
+    VerifyChecksum(oneshot::Sender<Result<(), String>>, bool),
+    FollowTcp(oneshot::Sender<ListResult<StreamChunk>>, usize, usize, Criteria),
+    FollowUdp(oneshot::Sender<ListResult<StreamChunk>>, usize, Criteria),
+    TCPGraph(oneshot::Sender<ConnectionGraph>, usize, usize),
+    MptcpSequence(oneshot::Sender<Vec<MptcpMapping>>, usize),
+    WebSocketMessages(oneshot::Sender<Vec<WebSocketMessage>>, usize),
 }
 
 pub enum EngineCommand {
@@ -117,6 +130,54 @@ async fn stat_str(tp: &str, instance: &Arc<Mutex<Instance<LocalResource>>>) -> O
             let rs = instance.lock().await.stat_frame();
             return jsonlize(&rs);
         }
+        "multicast" => {
+            let rs = instance.lock().await.multicast_report();
+            return jsonlize(&rs);
+        }
+        "ipsec" => {
+            let rs = instance.lock().await.ipsec_sa_list();
+            return jsonlize(&rs);
+        }
+        "icmp_error" => {
+            let rs = instance.lock().await.icmp_errors();
+            return jsonlize(&rs);
+        }
+        "arp" => {
+            let rs = instance.lock().await.arp_report();
+            return jsonlize(&rs);
+        }
+        "dhcp" => {
+            let rs = instance.lock().await.dhcp_clients();
+            return jsonlize(&rs);
+        }
+        "ospf" => {
+            let rs = instance.lock().await.ospf_adjacencies();
+            return jsonlize(&rs);
+        }
+        "fhrp" => {
+            let rs = instance.lock().await.fhrp_groups();
+            return jsonlize(&rs);
+        }
+        "sctp" => {
+            let rs = instance.lock().await.sctp_associations();
+            return jsonlize(&rs);
+        }
+        "quic" => {
+            let rs = instance.lock().await.quic_connections();
+            return jsonlize(&rs);
+        }
+        "mptcp" => {
+            let rs = instance.lock().await.mptcp_connections();
+            return jsonlize(&rs);
+        }
+        "websocket" => {
+            let rs = instance.lock().await.websocket_connections();
+            return jsonlize(&rs);
+        }
+        "geo" => {
+            let rs = instance.lock().await.stat_geo();
+            return jsonlize(&rs);
+        }
         "ip_address" => instance.lock().await.stat_ipaddress_distribute(),
         _ => {
             return None;
@@ -131,6 +192,14 @@ pub struct Engine {
     engine_tx: mpsc::Sender<EngineCommand>,
     handler: Option<AbortHandle>,
     watch: bool,
+    // mmdb files applied to every opened capture
+    geoip: Vec<String>,
+    // decode as rules applied to every opened capture
+    decode_as: Option<String>,
+    // checksum verification applied to every opened capture
+    verify_checksum: bool,
+    // esp_sa file content applied to every opened capture
+    esp_sa: Option<String>,
 }
 
 impl Engine {
@@ -141,6 +210,10 @@ impl Engine {
             ins: None,
             handler: None,
             watch: true,
+            geoip: vec![],
+            decode_as: None,
+            verify_checksum: false,
+            esp_sa: None,
         }
     }
 }
@@ -159,6 +232,21 @@ impl Engine {
             bail!("instance exists")
         }
         let instance = create_instance(filepath.to_string(), buf_size);
+        for geoip in &self.geoip {
+            // a database moved or broken since it was loaded only costs the lookups
+            if let Ok(data) = std::fs::read(geoip) {
+                let _ = instance.lock().await.add_geoip_database(data);
+            }
+        }
+        if let Some(content) = &self.decode_as {
+            instance.lock().await.set_decode_as(content)?;
+        }
+        if self.verify_checksum {
+            instance.lock().await.set_verify_checksum(true)?;
+        }
+        if let Some(content) = &self.esp_sa {
+            instance.lock().await.set_esp_sa(content)?;
+        }
         let instance_clone = Arc::downgrade(&instance);
         self.ins = Some(instance);
         let watch = self.watch;
@@ -219,6 +307,8 @@ impl Engine {
                             if watch {
                                 sleep(Duration::from_millis(1000)).await;
                             } else {
+                                // a watched file may still grow, only a plain read reaches the end of the capture
+                                ins_arc.lock().await.finish();
                                 break;
                             }
                         }
@@ -236,7 +326,57 @@ impl Engine {
         self.handler = Some(handle.abort_handle());
         Ok(())
     }
+    async fn load_geoip(&mut self, filepath: String) -> anyhow::Result<String> {
+        let data = std::fs::read(&filepath)?;
+        let kind = match &self.ins {
+            Some(instance) => instance.lock().await.add_geoip_database(data)?,
+            None => GeoIp::default().load(data)?,
+        };
+        if !self.geoip.contains(&filepath) {
+            self.geoip.push(filepath);
+        }
+        Ok(kind)
+    }
+    async fn set_decode_as(&mut self, content: String) -> anyhow::Result<usize> {
+        let count = match &self.ins {
+            Some(instance) => instance.lock().await.set_decode_as(&content)?,
+            None => parse_decode_as(&content)?.len(),
+        };
+        self.decode_as = Some(content);
+        Ok(count)
+    }
+    async fn set_esp_sa(&mut self, content: String) -> anyhow::Result<usize> {
+        let count = match &self.ins {
+            Some(instance) => instance.lock().await.set_esp_sa(&content)?,
+            None => parse_esp_sa(&content)?.len(),
+        };
+        self.esp_sa = Some(content);
+        Ok(count)
+    }
+    async fn set_verify_checksum(&mut self, verify: bool) -> anyhow::Result<()> {
+        if let Some(instance) = &self.ins {
+            instance.lock().await.set_verify_checksum(verify)?;
+        }
+        self.verify_checksum = verify;
+        Ok(())
+    }
     async fn handle_gui(&mut self, cmd: UICommand) {
+        if let UICommand::LoadGeoIp(tx, filepath) = cmd {
+            let _ = tx.send(self.load_geoip(filepath).await.map_err(|e| e.to_string()));
+            return;
+        }
+        if let UICommand::DecodeAs(tx, content) = cmd {
+            let _ = tx.send(self.set_decode_as(content).await.map_err(|e| e.to_string()));
+            return;
+        }
+        if let UICommand::EspSa(tx, content) = cmd {
+            let _ = tx.send(self.set_esp_sa(content).await.map_err(|e| e.to_string()));
+            return;
+        }
+        if let UICommand::VerifyChecksum(tx, verify) = cmd {
+            let _ = tx.send(self.set_verify_checksum(verify).await.map_err(|e| e.to_string()));
+            return;
+        }
         if let Some(instance) = &self.ins {
             match cmd {
                 UICommand::CloseFile(tx) => {
@@ -284,8 +424,8 @@ impl Engine {
                     let rs = instance.lock().await.conversations(criteria, filter);
                     let _ = tx.send(rs);
                 }
-                UICommand::TCPConvList(tx, index, criteria) => {
-                    let rs = instance.lock().await.connections(index, criteria);
+                UICommand::TCPConvList(tx, index, criteria, order) => {
+                    let rs = instance.lock().await.connections_by(index, criteria, order);
                     let _ = tx.send(rs);
                 }
                 UICommand::UDPList(tx, cri, filter, asc) => {
@@ -308,6 +448,10 @@ impl Engine {
                     let rs = { instance.lock().await.dns_record(index, cri) };
                     let _ = tx.send(rs);
                 }
+                UICommand::DHCPFailures(tx, cri) => {
+                    let rs = { instance.lock().await.dhcp_failures(cri) };
+                    let _ = tx.send(rs);
+                }
                 UICommand::HTTPList(tx, cri, filter, asc) => {
                     let rs = { instance.lock().await.http_connections(cri, filter, asc) };
                     let _ = tx.send(rs);
@@ -316,6 +460,38 @@ impl Engine {
                     let rs = { instance.lock().await.http_detail(index) };
                     let _ = tx.send(rs);
                 }
+                UICommand::HTTPObjects(tx) => {
+                    let rs = { instance.lock().await.http_objects() };
+                    let _ = tx.send(rs);
+                }
+                UICommand::HTTPObject(tx, index) => {
+                    let rs = { instance.lock().await.http_object_content(index) };
+                    let _ = tx.send(rs);
+                }
+                UICommand::HTTPExport(tx, dir, indexes) => {
+                    let rs = { instance.lock().await.export_http_objects(&dir, indexes.as_deref()) };
+                    let _ = tx.send(rs.map_err(|e| e.to_string()));
+                }
+                UICommand::FollowTcp(tx, conversation_index, connection_index, cri) => {
+                    let rs = { instance.lock().await.follow_tcp_stream(conversation_index, connection_index, cri) };
+                    let _ = tx.send(rs);
+                }
+                UICommand::TCPGraph(tx, conversation_index, connection_index) => {
+                    let rs = { instance.lock().await.connection_graph(conversation_index, connection_index) };
+                    let _ = tx.send(rs);
+                }
+                UICommand::MptcpSequence(tx, index) => {
+                    let rs = { instance.lock().await.mptcp_data_sequence(index) };
+                    let _ = tx.send(rs);
+                }
+                UICommand::WebSocketMessages(tx, index) => {
+                    let rs = { instance.lock().await.websocket_messages(index) };
+                    let _ = tx.send(rs);
+                }
+                UICommand::FollowUdp(tx, index, cri) => {
+                    let rs = { instance.lock().await.follow_udp_stream(index, cri) };
+                    let _ = tx.send(rs);
+                }
 
                 _ => {}
             }
@@ -402,9 +578,39 @@ impl UIEngine {
         let _ = self.gui_tx.send(UICommand::TCPList(tx, cri, filter)).await;
         rx.await.unwrap()
     }
-    pub async fn connections(&self, index: usize, cri: Criteria) -> ListResult<VConnection> {
+    pub async fn connections(&self, index: usize, cri: Criteria, order: ConnectionOrder) -> ListResult<VConnection> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::TCPConvList(tx, index, cri, order)).await;
+        rx.await.unwrap()
+    }
+
+    pub async fn follow_tcp(&self, conversation_index: usize, connection_index: usize, cri: Criteria) -> ListResult<StreamChunk> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::FollowTcp(tx, conversation_index, connection_index, cri)).await;
+        rx.await.unwrap()
+    }
+
+    pub async fn connection_graph(&self, conversation_index: usize, connection_index: usize) -> ConnectionGraph {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::TCPGraph(tx, conversation_index, connection_index)).await;
+        rx.await.unwrap()
+    }
+
+    pub async fn mptcp_data_sequence(&self, index: usize) -> Vec<MptcpMapping> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::MptcpSequence(tx, index)).await;
+        rx.await.unwrap()
+    }
+
+    pub async fn websocket_messages(&self, index: usize) -> Vec<WebSocketMessage> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::WebSocketMessages(tx, index)).await;
+        rx.await.unwrap()
+    }
+
+    pub async fn follow_udp(&self, index: usize, cri: Criteria) -> ListResult<StreamChunk> {
         let (tx, rx) = oneshot::channel();
-        let _ = self.gui_tx.send(UICommand::TCPConvList(tx, index, cri)).await;
+        let _ = self.gui_tx.send(UICommand::FollowUdp(tx, index, cri)).await;
         rx.await.unwrap()
     }
 
@@ -426,6 +632,42 @@ impl UIEngine {
         rx.await.unwrap()
     }
 
+    /**
+     * local country, city or asn .mmdb file, returns its database type
+     */
+    pub async fn load_geoip(&self, filepath: String) -> Result<String, String> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::LoadGeoIp(tx, filepath)).await;
+        rx.await.map_err(|e| e.to_string())?
+    }
+
+    /**
+     * decode as rules, one per line, returns the number of rules. Frames already loaded are dissected again
+     */
+    pub async fn set_decode_as(&self, content: String) -> Result<usize, String> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::DecodeAs(tx, content)).await;
+        rx.await.map_err(|e| e.to_string())?
+    }
+
+    /**
+     * content of a wireshark esp_sa file, returns the number of SAs. Frames already loaded are decrypted again
+     */
+    pub async fn set_esp_sa(&self, content: String) -> Result<usize, String> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::EspSa(tx, content)).await;
+        rx.await.map_err(|e| e.to_string())?
+    }
+
+    /**
+     * turns checksum verification on or off. Frames already loaded are dissected again
+     */
+    pub async fn set_verify_checksum(&self, verify: bool) -> Result<(), String> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::VerifyChecksum(tx, verify)).await;
+        rx.await.map_err(|e| e.to_string())?
+    }
+
     pub async fn dns_records(&self, cri: Criteria, asc: bool) -> ListResult<DNSResponse> {
         let (tx, rx) = oneshot::channel();
         let _ = self.gui_tx.send(UICommand::DNSRecords(tx, cri, asc)).await;
@@ -438,6 +680,12 @@ impl UIEngine {
         rx.await.unwrap()
     }
 
+    pub async fn dhcp_failures(&self, cri: Criteria) -> ListResult<DhcpTransaction> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::DHCPFailures(tx, cri)).await;
+        rx.await.unwrap()
+    }
+
     pub async fn http_list(&self, cri: Criteria, filter: Option<HttpCriteria>, asc: bool) -> ListResult<VHttpConnection> {
         let (tx, rx) = oneshot::channel();
         let _ = self.gui_tx.send(UICommand::HTTPList(tx, cri, filter, asc)).await;
@@ -449,6 +697,24 @@ impl UIEngine {
         let _ = self.gui_tx.send(UICommand::HTTPDetail(tx, index)).await;
         rx.await.unwrap()
     }
+
+    pub async fn http_objects(&self) -> Vec<HttpObject> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::HTTPObjects(tx)).await;
+        rx.await.unwrap()
+    }
+
+    pub async fn http_object(&self, index: usize) -> Option<Vec<u8>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::HTTPObject(tx, index)).await;
+        rx.await.unwrap()
+    }
+
+    pub async fn export_http_objects(&self, dir: PathBuf, indexes: Option<Vec<usize>>) -> Result<Vec<String>, String> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.gui_tx.send(UICommand::HTTPExport(tx, dir, indexes)).await;
+        rx.await.unwrap()
+    }
 }
 
 pub fn build_engine() -> (UIEngine, Engine, mpsc::Receiver<EngineCommand>) {
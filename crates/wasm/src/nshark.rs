@@ -55,6 +55,46 @@ impl WContext {
         // self.ctx.update_slice(s).unwrap().to_json()
     }
 
+    /**
+     * the whole capture was passed to update, tcp segments still waiting for a gap are released
+     */
+    #[wasm_bindgen]
+    pub fn finish(&mut self) {
+        self.ctx.finish();
+    }
+
+    /**
+     * content of a local country, city or asn .mmdb file, returns its database type
+     */
+    #[wasm_bindgen]
+    pub fn load_geoip(&mut self, s: &Uint8Array) -> Option<String> {
+        self.ctx.add_geoip_database(s.to_vec()).ok()
+    }
+
+    /**
+     * decode as rules, one per line, returns the number of rules. Frames already loaded are dissected again
+     */
+    #[wasm_bindgen]
+    pub fn set_decode_as(&mut self, content: String) -> Option<usize> {
+        self.ctx.set_decode_as(&content).ok()
+    }
+
+    /**
+     * content of a wireshark esp_sa file, returns the number of SAs. Frames already loaded are decrypted again
+     */
+    #[wasm_bindgen]
+    pub fn set_esp_sa(&mut self, content: String) -> Option<usize> {
+        self.ctx.set_esp_sa(&content).ok()
+    }
+
+    /**
+     * turns checksum verification on or off. Frames already loaded are dissected again
+     */
+    #[wasm_bindgen]
+    pub fn set_verify_checksum(&mut self, verify: bool) -> bool {
+        self.ctx.set_verify_checksum(verify).is_ok()
+    }
+
     #[wasm_bindgen]
     pub fn metadata(&self) -> Option<String> {
         let meta = self.ctx.context().get_metadata();
@@ -100,6 +140,37 @@ impl WContext {
         jsonlize(&rs)
     }
     #[wasm_bindgen]
+    pub fn list_connections_by(&self, index: usize, start: usize, size: usize, order: String) -> Option<String> {
+        let order = order.parse().unwrap_or_default();
+        let rs = self.ctx.connections_by(index, Criteria { start, size }, order);
+        jsonlize(&rs)
+    }
+    #[wasm_bindgen]
+    pub fn follow_tcp(&self, index: usize, connection: usize, start: usize, size: usize) -> Option<String> {
+        let rs = self.ctx.follow_tcp_stream(index, connection, Criteria { start, size });
+        jsonlize(&rs)
+    }
+    #[wasm_bindgen]
+    pub fn connection_graph(&self, index: usize, connection: usize) -> Option<String> {
+        let rs = self.ctx.connection_graph(index, connection);
+        jsonlize(&rs)
+    }
+    #[wasm_bindgen]
+    pub fn mptcp_data_sequence(&self, index: usize) -> Option<String> {
+        let rs = self.ctx.mptcp_data_sequence(index);
+        jsonlize(&rs)
+    }
+    #[wasm_bindgen]
+    pub fn websocket_messages(&self, index: usize) -> Option<String> {
+        let rs = self.ctx.websocket_messages(index);
+        jsonlize(&rs)
+    }
+    #[wasm_bindgen]
+    pub fn follow_udp(&self, index: usize, start: usize, size: usize) -> Option<String> {
+        let rs = self.ctx.follow_udp_stream(index, Criteria { start, size });
+        jsonlize(&rs)
+    }
+    #[wasm_bindgen]
     pub fn list_http(&self, start: usize, size: usize, hostname: String, _method: String, asc: bool) -> Option<String> {
         let filter = if hostname.is_empty() { None } else { Some(HttpCriteria::hostname(hostname)) };
         let rs = self.ctx.http_connections(Criteria { start, size }, filter, asc);
@@ -132,9 +203,24 @@ impl WContext {
         jsonlize(&list)
     }
     #[wasm_bindgen]
+    pub fn list_dhcp_failures(&self, start: usize, size: usize) -> Option<String> {
+        let list = self.ctx.dhcp_failures(Criteria { start, size });
+        jsonlize(&list)
+    }
+    #[wasm_bindgen]
     pub fn http_detail(&self, index: usize) -> Option<Vec<HttpDetail>> {
         self.ctx.http_detail(index).map(|data| data.into_iter().map(HttpDetail::from).collect())
     }
+    #[wasm_bindgen]
+    pub fn http_objects(&self) -> Option<String> {
+        let rs = self.ctx.http_objects();
+        jsonlize(&rs)
+    }
+    // the browser saves it, a directory is out of reach here
+    #[wasm_bindgen]
+    pub fn http_object(&self, index: usize) -> Option<Vec<u8>> {
+        self.ctx.http_object_content(index)
+    }
 
     #[wasm_bindgen]
     pub fn stat(&self, field: String) -> Option<String> {
@@ -151,6 +237,54 @@ impl WContext {
                 let rs = self.ctx.stat_frame();
                 return jsonlize(&rs);
             }
+            "multicast" => {
+                let rs = self.ctx.multicast_report();
+                return jsonlize(&rs);
+            }
+            "ipsec" => {
+                let rs = self.ctx.ipsec_sa_list();
+                return jsonlize(&rs);
+            }
+            "icmp_error" => {
+                let rs = self.ctx.icmp_errors();
+                return jsonlize(&rs);
+            }
+            "arp" => {
+                let rs = self.ctx.arp_report();
+                return jsonlize(&rs);
+            }
+            "dhcp" => {
+                let rs = self.ctx.dhcp_clients();
+                return jsonlize(&rs);
+            }
+            "ospf" => {
+                let rs = self.ctx.ospf_adjacencies();
+                return jsonlize(&rs);
+            }
+            "fhrp" => {
+                let rs = self.ctx.fhrp_groups();
+                return jsonlize(&rs);
+            }
+            "sctp" => {
+                let rs = self.ctx.sctp_associations();
+                return jsonlize(&rs);
+            }
+            "quic" => {
+                let rs = self.ctx.quic_connections();
+                return jsonlize(&rs);
+            }
+            "mptcp" => {
+                let rs = self.ctx.mptcp_connections();
+                return jsonlize(&rs);
+            }
+            "websocket" => {
+                let rs = self.ctx.websocket_connections();
+                return jsonlize(&rs);
+            }
+            "geo" => {
+                let rs = self.ctx.stat_geo();
+                return jsonlize(&rs);
+            }
             "ip_address" => self.ctx.stat_ipaddress_distribute(),
             _ => {
                 return None;
@@ -1,6 +1,6 @@
 use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener},
-    path::Path,
+    path::{Path, PathBuf},
     process::exit,
     sync::Arc,
     thread,
@@ -11,7 +11,8 @@ use clap::Parser;
 use pcapviewer_web::{control, routes::init_routes, web::WebApplication};
 use tokio::runtime::Runtime;
 
-async fn start(address: IpAddr, port: u16, target: String) -> std::io::Result<()> {
+async fn start(address: IpAddr, port: u16, args: Args) -> std::io::Result<()> {
+    let target = args.file;
     let folder = include_dir::include_dir!("dist/socket"); 
     let (ui, mut engine, _) = util::core::build_engine();
     thread::spawn(move || {
@@ -20,7 +21,30 @@ async fn start(address: IpAddr, port: u16, target: String) -> std::io::Result<()
             engine.run().await;
         });
     });
+    for path in &args.geoip {
+        if let Err(e) = ui.load_geoip(path.clone()).await {
+            eprintln!("Error loading geoip database [{path}]: {e}");
+            exit(1);
+        }
+    }
+    if args.verify_checksum {
+        let _ = ui.set_verify_checksum(true).await;
+    }
+    if let Some(path) = &args.esp_sa {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Error reading esp_sa file [{path}]: {e}");
+                exit(1);
+            }
+        };
+        if let Err(e) = ui.set_esp_sa(content).await {
+            eprintln!("Invalid esp_sa file [{path}]: {e}");
+            exit(1);
+        }
+    }
     let mut _app = WebApplication::new(folder, address, port, ui);
+    _app.export_dir = args.export_dir.map(PathBuf::from);
     if let Err(str) = _app.open(target.clone()).await {
         eprintln!("Error {str}: [{target}]");
         exit(1);
@@ -60,6 +84,14 @@ struct Args {
     port: Option<u16>,
     #[arg(short, long, default_value_t = false, help = "Listen on localhost only")]
     local: bool,
+    #[arg(long, default_value_t = false, help = "Verify ip, transport and icmp checksums")]
+    verify_checksum: bool,
+    #[arg(long, help = "MaxMind .mmdb country, city or asn database, may be repeated")]
+    geoip: Vec<String>,
+    #[arg(long, help = "Wireshark esp_sa file with the keys to decrypt ESP")]
+    esp_sa: Option<String>,
+    #[arg(long, help = "Directory http objects may be exported to from the browser")]
+    export_dir: Option<String>,
 }
 
 const DEFAULT_PORT: u16 = 6400;
@@ -116,5 +148,5 @@ async fn main() -> std::io::Result<()> {
     } else {
         get_port().unwrap()
     };
-    start(address, port, fname.clone()).await
+    start(address, port, args).await
 }
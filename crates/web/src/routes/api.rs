@@ -1,6 +1,6 @@
 use std::{fs, path::Path, sync::Arc};
 
-use actix_web::{get, web, HttpResponse};
+use actix_web::{get, post, web, HttpResponse};
 use pcap::common::concept::{Criteria, FrameIndex};
 use serde::Serialize;
 
@@ -65,6 +65,24 @@ async fn metadata(app: web::Data<Arc<WebApplication>>) -> HttpResponse {
     }
 }
 
+// body holds the rules, one per line
+#[post("/decode_as")]
+async fn decode_as(app: web::Data<Arc<WebApplication>>, body: String) -> HttpResponse {
+    match app.engine().set_decode_as(body).await {
+        Ok(count) => HttpResponse::Ok().json(count),
+        Err(e) => HttpResponse::BadRequest().body(e),
+    }
+}
+
+// body holds a wireshark esp_sa file
+#[post("/esp_sa")]
+async fn esp_sa(app: web::Data<Arc<WebApplication>>, body: String) -> HttpResponse {
+    match app.engine().set_esp_sa(body).await {
+        Ok(count) => HttpResponse::Ok().json(count),
+        Err(e) => HttpResponse::BadRequest().body(e),
+    }
+}
+
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/api").service(frame).service(frames).service(ready).service(metadata));
+    cfg.service(web::scope("/api").service(frame).service(frames).service(ready).service(metadata).service(decode_as).service(esp_sa));
 }
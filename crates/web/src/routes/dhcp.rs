@@ -0,0 +1,16 @@
+use std::sync::Arc;
+
+use actix_web::{get, web, HttpResponse};
+use pcap::common::concept::Criteria;
+
+use crate::web::WebApplication;
+
+#[get("/failures")]
+async fn failures(app: web::Data<Arc<WebApplication>>, query: web::Query<Criteria>) -> HttpResponse {
+    let rs = app.engine().dhcp_failures(query.into_inner()).await;
+    HttpResponse::Ok().json(rs)
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/api/dhcp").service(failures));
+}
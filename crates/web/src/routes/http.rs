@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use actix_web::{get, web, HttpResponse};
+use actix_web::{get, post, web, HttpResponse};
 use pcap::common::concept::{Criteria, HttpCriteria, HttpMessageDetail};
 use serde::{Deserialize, Serialize};
 
@@ -72,6 +72,53 @@ async fn detail(app: web::Data<Arc<WebApplication>>, path: web::Path<String>) ->
     }
 }
 
+#[get("/objects")]
+async fn objects(app: web::Data<Arc<WebApplication>>) -> HttpResponse {
+    let rs = app.engine().http_objects().await;
+    HttpResponse::Ok().json(rs)
+}
+
+// the body as an attachment, under the name the object list gives it
+#[get("/object/{index}")]
+async fn object(app: web::Data<Arc<WebApplication>>, path: web::Path<String>) -> HttpResponse {
+    let Ok(index) = path.into_inner().parse::<usize>() else {
+        return HttpResponse::BadRequest().finish();
+    };
+    let engine = app.engine();
+    let Some(item) = engine.http_objects().await.into_iter().find(|item| item.index == index) else {
+        return HttpResponse::NotFound().finish();
+    };
+    match engine.http_object(index).await {
+        Some(content) => {
+            let content_type = if item.content_type.is_empty() { "application/octet-stream".to_string() } else { item.content_type };
+            HttpResponse::Ok()
+                .content_type(content_type)
+                .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", item.filename)))
+                .body(content)
+        }
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+#[derive(Deserialize)]
+struct PExport {
+    // every object when absent
+    pub indexes: Option<Vec<usize>>,
+}
+
+// writes into the directory given by --export-dir only, clients download single objects through /object/{index}
+#[post("/export")]
+async fn export(app: web::Data<Arc<WebApplication>>, body: web::Json<PExport>) -> HttpResponse {
+    let Some(dir) = app.export_dir.clone() else {
+        return HttpResponse::Forbidden().body("export is disabled, start the server with --export-dir");
+    };
+    let PExport { indexes } = body.into_inner();
+    match app.engine().export_http_objects(dir, indexes).await {
+        Ok(files) => HttpResponse::Ok().json(files),
+        Err(e) => HttpResponse::BadRequest().body(e),
+    }
+}
+
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/api/http").service(list).service(detail));
+    cfg.service(web::scope("/api/http").service(list).service(detail).service(objects).service(object).service(export));
 }
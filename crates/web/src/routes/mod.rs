@@ -6,11 +6,13 @@ mod tcp;
 mod udp;
 mod tls;
 mod dns;
+mod dhcp;
 mod http;
 
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.configure(http::init);
     cfg.configure(dns::init);
+    cfg.configure(dhcp::init);
     cfg.configure(udp::init);
     cfg.configure(tcp::init);
     cfg.configure(stat::init);
@@ -1,7 +1,7 @@
 use std::{sync::Arc};
 
 use actix_web::{get, web, HttpResponse};
-use pcap::common::concept::{ConversationCriteria, Criteria};
+use pcap::common::concept::{ConnectionOrder, ConversationCriteria, Criteria};
 use serde::{Deserialize, Serialize};
 
 use crate::web::WebApplication;
@@ -30,6 +30,14 @@ impl From<&TCPCriteria> for ConversationCriteria {
 }
 
 
+#[derive(Deserialize)]
+struct ConnectionCriteria {
+    size: usize,
+    start: usize,
+    #[serde(default)]
+    order: ConnectionOrder,
+}
+
 #[get("/list")]
 async fn conversations(app: web::Data<Arc<WebApplication>>, query: web::Query<TCPCriteria>) -> HttpResponse {
     let cri: TCPCriteria = query.into_inner();
@@ -39,12 +47,12 @@ async fn conversations(app: web::Data<Arc<WebApplication>>, query: web::Query<TC
 }
 
 #[get("/conv/{index}/list")]
-async fn connections(app: web::Data<Arc<WebApplication>>, path: web::Path<String>, query: web::Query<Criteria>) -> HttpResponse {
+async fn connections(app: web::Data<Arc<WebApplication>>, path: web::Path<String>, query: web::Query<ConnectionCriteria>) -> HttpResponse {
     let index = path.into_inner();
     match index.parse::<usize>() {
         Ok(num) => {
-            let cri = query.into_inner();
-            let rs = app.engine().connections(num, cri).await;
+            let ConnectionCriteria { size, start, order } = query.into_inner();
+            let rs = app.engine().connections(num, Criteria { size, start }, order).await;
             HttpResponse::Ok().json(rs)
         },
         Err(_) => {
@@ -53,6 +61,20 @@ async fn connections(app: web::Data<Arc<WebApplication>>, path: web::Path<String
     }
 }
 
+#[get("/conv/{index}/{connection}/stream")]
+async fn stream(app: web::Data<Arc<WebApplication>>, path: web::Path<(usize, usize)>, query: web::Query<Criteria>) -> HttpResponse {
+    let (index, connection) = path.into_inner();
+    let rs = app.engine().follow_tcp(index, connection, query.into_inner()).await;
+    HttpResponse::Ok().json(rs)
+}
+
+#[get("/conv/{index}/{connection}/graph")]
+async fn graph(app: web::Data<Arc<WebApplication>>, path: web::Path<(usize, usize)>) -> HttpResponse {
+    let (index, connection) = path.into_inner();
+    let rs = app.engine().connection_graph(index, connection).await;
+    HttpResponse::Ok().json(rs)
+}
+
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/api/tcp").service(conversations).service(connections));
+    cfg.service(web::scope("/api/tcp").service(conversations).service(connections).service(stream).service(graph));
 }
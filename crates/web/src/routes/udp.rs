@@ -36,6 +36,12 @@ async fn list(app: web::Data<Arc<WebApplication>>, query: web::Query<TCPCriteria
     HttpResponse::Ok().json(rs)
 }
 
+#[get("/{index}/stream")]
+async fn stream(app: web::Data<Arc<WebApplication>>, path: web::Path<usize>, query: web::Query<Criteria>) -> HttpResponse {
+    let rs = app.engine().follow_udp(path.into_inner(), query.into_inner()).await;
+    HttpResponse::Ok().json(rs)
+}
+
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/api/udp").service(list));
+    cfg.service(web::scope("/api/udp").service(list).service(stream));
 }
@@ -4,7 +4,7 @@ use actix_web::{web, Error, HttpRequest, HttpResponse, Responder};
 use actix_web_actors::ws;
 use include_dir::Dir;
 use mime_guess;
-use std::net::IpAddr;
+use std::{net::IpAddr, path::PathBuf};
 use util::core::UIEngine;
 
 pub struct WebApplication {
@@ -12,6 +12,8 @@ pub struct WebApplication {
     pub ip: IpAddr,
     pub port: u16,
     pub target: Option<String>,
+    // the only directory http objects are exported to, chosen at startup
+    pub export_dir: Option<PathBuf>,
     engine: UIEngine,
 }
 
@@ -23,6 +25,7 @@ impl WebApplication {
             port,
             engine,
             target: None,
+            export_dir: None,
         }
     }
     pub async fn open(&mut self, target: String) -> Result<(), String>{
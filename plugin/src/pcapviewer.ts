@@ -105,6 +105,7 @@ export class Client extends PCAPClient {
   }
   constructor(private view: vscode.Webview, private output: vscode.LogOutputChannel, private watcher: FileTailWatcher) {
     super();
+    this.watching = true;
   }
   printLog(log: ComLog): void {
     switch (log.level) {
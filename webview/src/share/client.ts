@@ -31,6 +31,8 @@ export abstract class PCAPClient {
   level: string = "trace";
   isPendding: boolean = false;
   ready: boolean = false;
+  // the file is tailed for new data, the end of what was read is not the end of the capture
+  watching: boolean = false;
   ctx?: WContext;
   info?: PcapFile;
   resourceId?: string;
@@ -54,6 +56,9 @@ export abstract class PCAPClient {
               progress.total = Math.max(progress.total, rt.total);
               progress.cursor = rt.cursor;
             }
+            if (!this.watching && progress.total > 0 && progress.cursor >= progress.total) {
+              this.ctx.finish();
+            }
           } catch(e) {
             console.error(e);
           }